@@ -0,0 +1,263 @@
+//! 本地 HTTP API：供其它程序在本机启动项目中的流程、获取事件与结果
+//!
+//! 服务只监听 127.0.0.1，请求须携带 `Authorization: Bearer <token>`。令牌随机生成并跨重启保留，
+//! 与服务地址一起写入 `<app_config_dir>/api.json`（仅当前用户可读），调用方从该文件读取地址与令牌。
+//! 应用启动时按 app.db 中的配置（key = `api`，值形如 `{ "enabled": true, "port": 7869 }`）开启，
+//! 也可以用 `vlogi serve` 在命令行中运行。路由见 [`routes`]，项目的 OpenAPI 文档见 [`openapi`]。
+
+pub mod openapi;
+pub mod routes;
+pub mod runs;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use tokio_util::sync::CancellationToken;
+
+use self::routes::ApiState;
+use self::runs::RunLogs;
+use crate::state::GlobalState;
+use crate::utils::appdb::{app_config_dir, AppDb};
+use crate::utils::file::write_private;
+
+/// config 表中 API 配置的 key
+pub const CONFIG_KEY: &str = "api";
+pub const DEFAULT_PORT: u16 = 7869;
+/// 地址与令牌文件
+const API_FILE: &str = "api.json";
+
+/// API 配置
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiConfig {
+    /// 应用启动时开启服务
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_port")]
+    pub port: u16,
+}
+
+fn default_port() -> u16 {
+    DEFAULT_PORT
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: DEFAULT_PORT,
+        }
+    }
+}
+
+impl ApiConfig {
+    pub async fn load(app_db: &AppDb) -> Result<Self, String> {
+        let items = app_db.get_configs_by_key(CONFIG_KEY).await?;
+        for item in items {
+            match serde_json::from_value(item.value) {
+                Ok(config) => return Ok(config),
+                Err(e) => tracing::warn!("⚠️  API 配置 {} 格式错误，已忽略: {}", item.id, e),
+            }
+        }
+        Ok(Self::default())
+    }
+}
+
+/// `api.json` 的内容
+#[derive(Serialize, Deserialize)]
+struct ApiFile {
+    token: String,
+    /// 服务地址，服务停止后移除
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pid: Option<u32>,
+}
+
+/// 服务状态
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiStatus {
+    pub running: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// 保存地址与令牌的文件
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_file: Option<String>,
+}
+
+struct Running {
+    addr: SocketAddr,
+    shutdown: CancellationToken,
+}
+
+/// 本地 HTTP 服务
+pub struct ApiServer {
+    path: Option<PathBuf>,
+    running: tokio::sync::Mutex<Option<Running>>,
+    token: Arc<RwLock<String>>,
+    logs: Arc<RunLogs>,
+}
+
+impl std::fmt::Debug for ApiServer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApiServer")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for ApiServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ApiServer {
+    pub fn new() -> Self {
+        Self {
+            path: app_config_dir().map(|dir| dir.join(API_FILE)),
+            running: tokio::sync::Mutex::new(None),
+            token: Arc::new(RwLock::new(String::new())),
+            logs: Arc::new(RunLogs::default()),
+        }
+    }
+
+    fn path(&self) -> Result<&Path, String> {
+        self.path
+            .as_deref()
+            .ok_or_else(|| "无法确定应用配置目录".to_string())
+    }
+
+    pub async fn status(&self) -> ApiStatus {
+        let running = self.running.lock().await;
+        self.status_of(running.as_ref())
+    }
+
+    fn status_of(&self, running: Option<&Running>) -> ApiStatus {
+        ApiStatus {
+            running: running.is_some(),
+            url: running.map(|r| format!("http://{}", r.addr)),
+            token_file: (self.path.as_ref()).map(|p| p.to_string_lossy().into_owned()),
+        }
+    }
+
+    /// 开启服务；已开启时直接返回当前状态。`port` 为 0 时由系统分配
+    pub async fn start(&self, port: u16) -> Result<ApiStatus, String> {
+        let mut running = self.running.lock().await;
+        if running.is_some() {
+            return Ok(self.status_of(running.as_ref()));
+        }
+        let path = self.path()?;
+        let token = match read_file(path) {
+            Some(file) if !file.token.is_empty() => file.token,
+            _ => new_token(),
+        };
+
+        let listener = tokio::net::TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+            .await
+            .map_err(|e| format!("监听 127.0.0.1:{} 失败: {}", port, e))?;
+        let addr = listener.local_addr().map_err(|e| e.to_string())?;
+        write_file(
+            path,
+            &ApiFile {
+                token: token.clone(),
+                url: Some(format!("http://{}", addr)),
+                pid: Some(std::process::id()),
+            },
+        )?;
+        *self.token.write().unwrap_or_else(|e| e.into_inner()) = token;
+
+        let router = routes::router(ApiState {
+            token: self.token.clone(),
+            logs: self.logs.clone(),
+        });
+        let shutdown = CancellationToken::new();
+        let signal = shutdown.clone();
+        tokio::spawn(async move {
+            let server = axum::serve(listener, router)
+                .with_graceful_shutdown(async move { signal.cancelled().await });
+            if let Err(e) = server.await {
+                tracing::error!("❌ 本地 API 服务异常退出: {}", e);
+            }
+        });
+        tracing::info!("✅ 本地 API 已开启: http://{}", addr);
+        *running = Some(Running { addr, shutdown });
+        Ok(self.status_of(running.as_ref()))
+    }
+
+    /// 停止服务，返回服务之前是否在运行
+    pub async fn stop(&self) -> bool {
+        let Some(running) = self.running.lock().await.take() else {
+            return false;
+        };
+        running.shutdown.cancel();
+        let token = self.token.read().unwrap_or_else(|e| e.into_inner()).clone();
+        if let Ok(path) = self.path() {
+            let file = ApiFile {
+                token,
+                url: None,
+                pid: None,
+            };
+            if let Err(e) = write_file(path, &file) {
+                tracing::warn!("⚠️  {}", e);
+            }
+        }
+        tracing::info!("⏹️  本地 API 已停止");
+        true
+    }
+
+    /// 更换令牌，立即生效（服务未运行时在下次开启时使用）
+    pub async fn rotate_token(&self) -> Result<(), String> {
+        let running = self.running.lock().await;
+        let path = self.path()?;
+        let token = new_token();
+        let mut file = read_file(path).unwrap_or(ApiFile {
+            token: String::new(),
+            url: None,
+            pid: None,
+        });
+        file.token = token.clone();
+        if running.is_none() {
+            file.url = None;
+            file.pid = None;
+        }
+        write_file(path, &file)?;
+        *self.token.write().unwrap_or_else(|e| e.into_inner()) = token;
+        Ok(())
+    }
+}
+
+/// 按配置在应用启动时开启服务
+pub async fn start_from_config() {
+    let state = GlobalState::get();
+    let config = match ApiConfig::load(&state.app_db).await {
+        Ok(config) => config,
+        Err(e) => {
+            tracing::warn!("⚠️  读取 API 配置失败: {}", e);
+            return;
+        }
+    };
+    if !config.enabled {
+        return;
+    }
+    if let Err(e) = state.api.start(config.port).await {
+        tracing::warn!("⚠️  开启本地 API 失败: {}", e);
+    }
+}
+
+fn new_token() -> String {
+    BASE64.encode(rand::random::<[u8; 32]>())
+}
+
+fn read_file(path: &Path) -> Option<ApiFile> {
+    let content = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// 原子地写入，仅当前用户可读
+fn write_file(path: &Path, file: &ApiFile) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(file).map_err(|e| e.to_string())?;
+    write_private(path, json.as_bytes())
+}
@@ -0,0 +1,303 @@
+//! OpenAPI 3 文档
+//!
+//! 项目中的每个流程对应一个操作 `POST /v1/projects/{项目id}/flows/{流程id}/runs`：
+//! 请求体为 [`FlowDef::input_schema`]，`?wait=true` 时的 200 响应为 [`FlowDef::output_schema`]。
+
+use serde_json::{json, Map, Value};
+use std::collections::HashSet;
+
+use super::ApiConfig;
+use crate::flow::model::FlowDef;
+use crate::flow::store::FlowStore;
+use crate::flow::trace::{ErrorKind, TraceStatus};
+use crate::project::registry::{Registry, Repository};
+use crate::state::GlobalState;
+
+const OPENAPI_VERSION: &str = "3.0.3";
+
+/// 为项目生成文档，`target` 为项目 id 或目录
+pub async fn generate(target: &str) -> Result<Value, String> {
+    let state = GlobalState::get();
+    let project = Registry::new(&state.app_db).open(target).await?;
+    let repo = project.meta()?;
+    let store = FlowStore::new(project);
+    let mut flows = Vec::new();
+    for id in store.list_local()? {
+        match store.load_local(&id) {
+            Ok(flow) => flows.push(flow),
+            Err(e) => tracing::warn!("⚠️  跳过无法读取的流程 {}: {}", id, e),
+        }
+    }
+    let server = match state.api.status().await.url {
+        Some(url) => url,
+        None => format!(
+            "http://127.0.0.1:{}",
+            ApiConfig::load(&state.app_db).await?.port
+        ),
+    };
+    Ok(document(&repo, &flows, &server))
+}
+
+/// 由项目中的流程生成文档
+pub fn document(repo: &Repository, flows: &[FlowDef], server: &str) -> Value {
+    let mut paths = Map::new();
+    let mut schemas = Map::new();
+    let mut used = HashSet::new();
+    for flow in flows {
+        let name = unique_name(&flow.id, &mut used);
+        let inputs = format!("{}Inputs", name);
+        let outputs = format!("{}Outputs", name);
+        schemas.insert(inputs.clone(), flow.input_schema());
+        schemas.insert(outputs.clone(), flow.output_schema());
+        let path = format!("/v1/projects/{}/flows/{}/runs", repo.id, flow.id);
+        paths.insert(
+            path,
+            json!({ "post": flow_operation(flow, &name, &inputs, &outputs) }),
+        );
+    }
+    add_run_paths(&mut paths);
+    schemas.insert("RunView".to_string(), run_view_schema());
+    schemas.insert(
+        "Error".to_string(),
+        json!({
+            "type": "object",
+            "required": ["error"],
+            "properties": { "error": { "type": "string" } }
+        }),
+    );
+
+    let title = if repo.name.is_empty() {
+        "vlogi flows".to_string()
+    } else {
+        format!("{} flows", repo.name)
+    };
+    json!({
+        "openapi": OPENAPI_VERSION,
+        "info": {
+            "title": title,
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "servers": [{ "url": server }],
+        "security": [{ "bearer": [] }],
+        "paths": paths,
+        "components": {
+            "securitySchemes": {
+                "bearer": { "type": "http", "scheme": "bearer" }
+            },
+            "schemas": schemas,
+        },
+    })
+}
+
+fn schema_ref(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+fn json_content(schema: Value) -> Value {
+    json!({ "application/json": { "schema": schema } })
+}
+
+fn flow_operation(flow: &FlowDef, name: &str, inputs: &str, outputs: &str) -> Value {
+    let summary = if flow.name.is_empty() {
+        &flow.id
+    } else {
+        &flow.name
+    };
+    let mut operation = json!({
+        "operationId": format!("run{}", name),
+        "summary": summary,
+        "parameters": [
+            {
+                "name": "wait",
+                "in": "query",
+                "description": "Wait for the run to finish and return the flow outputs",
+                "schema": { "type": "boolean", "default": false }
+            },
+            {
+                "name": "max_depth",
+                "in": "query",
+                "description": "Maximum subflow nesting depth",
+                "schema": { "type": "integer", "minimum": 0 }
+            }
+        ],
+        "requestBody": {
+            "required": true,
+            "content": json_content(schema_ref(inputs)),
+        },
+        "responses": {
+            "200": {
+                "description": "Flow outputs (only with wait=true)",
+                "content": json_content(schema_ref(outputs)),
+            },
+            "202": {
+                "description": "Run started, or waiting for human input",
+                "content": json_content(schema_ref("RunView")),
+            },
+            "400": error_response("Invalid project, flow or inputs"),
+            "401": error_response("Missing or invalid bearer token"),
+            "422": {
+                "description": "Run failed or was cancelled (only with wait=true)",
+                "content": json_content(schema_ref("RunView")),
+            },
+        },
+    });
+    if !flow.description.is_empty() {
+        operation["description"] = json!(flow.description);
+    }
+    operation
+}
+
+fn error_response(description: &str) -> Value {
+    json!({
+        "description": description,
+        "content": json_content(schema_ref("Error")),
+    })
+}
+
+/// 与流程无关的运行查询与取消
+fn add_run_paths(paths: &mut Map<String, Value>) {
+    let run_id = json!({
+        "name": "run_id",
+        "in": "path",
+        "required": true,
+        "schema": { "type": "string" }
+    });
+    paths.insert(
+        "/v1/runs/{run_id}".to_string(),
+        json!({
+            "get": {
+                "operationId": "getRun",
+                "summary": "Run status and outputs",
+                "parameters": [run_id.clone()],
+                "responses": {
+                    "200": {
+                        "description": "Run status",
+                        "content": json_content(schema_ref("RunView")),
+                    },
+                    "404": error_response("Unknown run"),
+                },
+            }
+        }),
+    );
+    paths.insert(
+        "/v1/runs/{run_id}/cancel".to_string(),
+        json!({
+            "post": {
+                "operationId": "cancelRun",
+                "summary": "Cancel a run",
+                "parameters": [run_id],
+                "responses": {
+                    "200": {
+                        "description": "Whether the run was cancelled",
+                        "content": json_content(json!({
+                            "type": "object",
+                            "properties": { "cancelled": { "type": "boolean" } }
+                        })),
+                    },
+                },
+            }
+        }),
+    );
+}
+
+fn run_view_schema() -> Value {
+    let statuses: Vec<&str> = [
+        TraceStatus::Pending,
+        TraceStatus::Running,
+        TraceStatus::Succeeded,
+        TraceStatus::Failed,
+        TraceStatus::Skipped,
+        TraceStatus::Cancelled,
+        TraceStatus::Paused,
+        TraceStatus::Waiting,
+    ]
+    .iter()
+    .map(TraceStatus::as_str)
+    .collect();
+    let kinds: Vec<Value> = [
+        ErrorKind::Invalid,
+        ErrorKind::Node,
+        ErrorKind::Provider,
+        ErrorKind::Budget,
+    ]
+    .iter()
+    .map(|kind| json!(kind))
+    .collect();
+    json!({
+        "type": "object",
+        "required": ["run_id", "status"],
+        "properties": {
+            "run_id": { "type": "string" },
+            "status": { "type": "string", "enum": statuses },
+            "outputs": { "type": "object", "additionalProperties": true },
+            "error": { "type": "string" },
+            "error_kind": { "type": "string", "enum": kinds },
+        },
+    })
+}
+
+/// 由流程 id 生成类型名（`daily-report` → `DailyReport`），重名时加序号
+fn unique_name(flow_id: &str, used: &mut HashSet<String>) -> String {
+    let mut base: String = flow_id
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect();
+    if !base.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        base.insert_str(0, "Flow");
+    }
+    let mut name = base.clone();
+    let mut n = 2;
+    while !used.insert(name.clone()) {
+        name = format!("{}{}", base, n);
+        n += 1;
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_from_flow_declarations() {
+        let flow: FlowDef = serde_json::from_value(json!({
+            "id": "daily-report",
+            "name": "Daily report",
+            "inputs": [
+                { "name": "topic", "description": "主题", "schema": { "type": "string" } },
+                { "name": "limit", "default": 3 }
+            ],
+            "outputs": [
+                { "name": "summary", "value": "{{ nodes.w }}", "schema": { "type": "string" } }
+            ]
+        }))
+        .unwrap();
+        let other: FlowDef = serde_json::from_value(json!({ "id": "daily_report" })).unwrap();
+        let repo = Repository::new("demo".to_string(), std::path::Path::new("/tmp/demo"));
+        let doc = document(&repo, &[flow, other], "http://127.0.0.1:7869");
+
+        let path = format!("/v1/projects/{}/flows/daily-report/runs", repo.id);
+        assert_eq!(doc["paths"][&path]["post"]["operationId"], "runDailyReport");
+        let path = format!("/v1/projects/{}/flows/daily_report/runs", repo.id);
+        assert_eq!(
+            doc["paths"][&path]["post"]["operationId"],
+            "runDailyReport2"
+        );
+
+        let inputs = &doc["components"]["schemas"]["DailyReportInputs"];
+        assert_eq!(inputs["required"], json!(["topic"]));
+        assert_eq!(inputs["properties"]["topic"]["type"], "string");
+        assert_eq!(inputs["properties"]["topic"]["description"], "主题");
+        assert_eq!(inputs["properties"]["limit"]["default"], 3);
+        let outputs = &doc["components"]["schemas"]["DailyReportOutputs"];
+        assert_eq!(outputs["required"], json!(["summary"]));
+        assert!(doc["paths"]["/v1/runs/{run_id}"]["get"].is_object());
+    }
+}
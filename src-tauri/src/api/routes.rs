@@ -0,0 +1,450 @@
+//! HTTP 路由
+//!
+//! 除 `/health` 外都要求 `Authorization: Bearer <token>`。错误以 `{ "error": "..." }` 返回。
+//!
+//! | 方法 | 路径 | 说明 |
+//! |------|------|------|
+//! | GET  | `/v1/projects` | 已登记的项目 |
+//! | GET  | `/v1/flows?project=<id或目录>` | 项目中的流程及其输入输出声明 |
+//! | GET  | `/openapi.json?project=<id或目录>` | 项目的 OpenAPI 文档（见 [`super::openapi`]） |
+//! | POST | `/v1/runs` | 开始运行：`{ project, flow, inputs?, max_depth? }`，返回 `{ run_id }` |
+//! | POST | `/v1/projects/{project}/flows/{flow}/runs?wait=<bool>` | 以请求体为输入开始运行，`wait` 时等待结束并返回输出 |
+//! | GET  | `/v1/runs/{id}` | 运行状态与结果 |
+//! | GET  | `/v1/runs/{id}/trace` | 结束后的完整轨迹 |
+//! | GET  | `/v1/runs/{id}/events?after=<n>` | 轮询第 n 条起的事件 |
+//! | GET  | `/v1/runs/{id}/stream?after=<n>` | 以 SSE 推送事件（事件 id 为序号，支持 `Last-Event-ID`） |
+//! | POST | `/v1/runs/{id}/cancel` | 取消运行 |
+//!
+//! 事件与应用中的 `tauri//flow` 事件相同，只有通过 API 启动的运行可以获取事件。
+
+use axum::extract::{Path, Query, Request, State};
+use axum::http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use axum::http::{HeaderMap, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use futures_util::Stream;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::convert::Infallible;
+use std::sync::{Arc, RwLock};
+
+use super::openapi;
+use super::runs::{LogSink, RunLog, RunLogs, RunView};
+use crate::commands::flow::{exec_options, record_start, run_cancel, run_external, Start, Target};
+use crate::flow::history::RunHistory;
+use crate::flow::model::{InputDef, OutputDef};
+use crate::flow::store::FlowStore;
+use crate::flow::trace::{FlowTrace, TraceStatus};
+use crate::project::registry::{Registry, Repository};
+use crate::state::GlobalState;
+
+/// 路由共享的状态
+#[derive(Clone)]
+pub struct ApiState {
+    pub token: Arc<RwLock<String>>,
+    pub logs: Arc<RunLogs>,
+}
+
+impl ApiState {
+    /// 比较摘要，避免按字节比较泄露令牌前缀
+    fn check_token(&self, provided: &str) -> bool {
+        let token = self.token.read().unwrap_or_else(|e| e.into_inner());
+        let expected = Sha256::digest(token.as_bytes());
+        let provided = Sha256::digest(provided.as_bytes());
+        expected
+            .iter()
+            .zip(provided.iter())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+    }
+}
+
+/// 错误响应
+pub struct ApiError(StatusCode, String);
+
+impl ApiError {
+    fn bad_request(message: String) -> Self {
+        Self(StatusCode::BAD_REQUEST, message)
+    }
+
+    fn not_found(id: &str) -> Self {
+        Self(StatusCode::NOT_FOUND, format!("运行 {} 不存在", id))
+    }
+
+    fn internal(message: String) -> Self {
+        Self(StatusCode::INTERNAL_SERVER_ERROR, message)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+type ApiResult<T> = Result<T, ApiError>;
+
+pub fn router(state: ApiState) -> Router {
+    let api = Router::new()
+        .route("/v1/projects", get(list_projects))
+        .route("/v1/flows", get(list_flows))
+        .route("/openapi.json", get(openapi_document))
+        .route("/v1/runs", post(start_run))
+        .route("/v1/projects/{project}/flows/{flow}/runs", post(run_flow))
+        .route("/v1/runs/{id}", get(get_run))
+        .route("/v1/runs/{id}/trace", get(get_trace))
+        .route("/v1/runs/{id}/events", get(poll_events))
+        .route("/v1/runs/{id}/stream", get(stream_events))
+        .route("/v1/runs/{id}/cancel", post(cancel_run))
+        .route_layer(middleware::from_fn_with_state(state.clone(), auth))
+        .with_state(state);
+    Router::new().route("/health", get(health)).merge(api)
+}
+
+async fn auth(State(state): State<ApiState>, request: Request, next: Next) -> Response {
+    let valid = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| state.check_token(token.trim()));
+    if !valid {
+        let body = Json(json!({ "error": "缺少或无效的访问令牌" }));
+        return (
+            StatusCode::UNAUTHORIZED,
+            [(WWW_AUTHENTICATE, "Bearer")],
+            body,
+        )
+            .into_response();
+    }
+    next.run(request).await
+}
+
+async fn health() -> Json<Value> {
+    Json(json!({ "ok": true, "version": env!("CARGO_PKG_VERSION") }))
+}
+
+async fn list_projects() -> ApiResult<Json<Vec<Repository>>> {
+    let registry = Registry::new(&GlobalState::get().app_db);
+    registry.list().await.map(Json).map_err(ApiError::internal)
+}
+
+#[derive(Deserialize)]
+struct ProjectQuery {
+    project: String,
+}
+
+/// 流程列表中的一项
+#[derive(Serialize)]
+struct FlowSummary {
+    id: String,
+    name: String,
+    version: String,
+    description: String,
+    inputs: Vec<InputDef>,
+    outputs: Vec<OutputDef>,
+}
+
+async fn list_flows(Query(query): Query<ProjectQuery>) -> ApiResult<Json<Vec<FlowSummary>>> {
+    let registry = Registry::new(&GlobalState::get().app_db);
+    let project = registry
+        .open(&query.project)
+        .await
+        .map_err(ApiError::bad_request)?;
+    let store = FlowStore::new(project);
+    let mut flows = Vec::new();
+    for id in store.list_local().map_err(ApiError::internal)? {
+        match store.load_local(&id) {
+            Ok(flow) => flows.push(FlowSummary {
+                id: flow.id,
+                name: flow.name,
+                version: flow.version,
+                description: flow.description,
+                inputs: flow.inputs,
+                outputs: flow.outputs,
+            }),
+            Err(e) => tracing::warn!("⚠️  跳过无法读取的流程 {}: {}", id, e),
+        }
+    }
+    Ok(Json(flows))
+}
+
+#[derive(Deserialize)]
+struct StartRun {
+    project: String,
+    flow: String,
+    #[serde(default)]
+    inputs: Option<Value>,
+    #[serde(default)]
+    max_depth: Option<usize>,
+}
+
+async fn openapi_document(Query(query): Query<ProjectQuery>) -> ApiResult<Json<Value>> {
+    openapi::generate(&query.project)
+        .await
+        .map(Json)
+        .map_err(ApiError::bad_request)
+}
+
+/// 开始运行，返回运行 id 与事件记录
+async fn spawn_run(
+    state: &ApiState,
+    project: &str,
+    flow: &str,
+    inputs: Option<Value>,
+    max_depth: Option<usize>,
+) -> ApiResult<(String, Arc<RunLog>)> {
+    let registry = Registry::new(&GlobalState::get().app_db);
+    let project = registry
+        .open(project)
+        .await
+        .map_err(ApiError::bad_request)?;
+    let project_root = project.root().to_string_lossy().into_owned();
+    let store = FlowStore::new(project);
+    let def = store.load_local(flow).map_err(ApiError::bad_request)?;
+    let inputs = inputs.unwrap_or_else(|| json!({}));
+
+    let run_id = uuid::Uuid::new_v4().to_string();
+    let recorded = record_start(&run_id, &project_root, &def, &inputs).await;
+    let log = state.logs.create(&run_id);
+    let target = Target {
+        run_id: run_id.clone(),
+        project: project_root,
+        store,
+        def,
+    };
+    let options = exec_options(max_depth);
+    let run_log = log.clone();
+    tokio::spawn(async move {
+        let events = Arc::new(LogSink(run_log.clone()));
+        match run_external(target, options, Start::New(inputs), events, recorded).await {
+            Ok(trace) => run_log.finish(trace),
+            Err(e) => run_log.fail(e),
+        }
+    });
+    Ok((run_id, log))
+}
+
+async fn start_run(
+    State(state): State<ApiState>,
+    Json(request): Json<StartRun>,
+) -> ApiResult<(StatusCode, Json<Value>)> {
+    let (run_id, _) = spawn_run(
+        &state,
+        &request.project,
+        &request.flow,
+        request.inputs,
+        request.max_depth,
+    )
+    .await?;
+    Ok((StatusCode::ACCEPTED, Json(json!({ "run_id": run_id }))))
+}
+
+#[derive(Deserialize)]
+struct RunFlowQuery {
+    #[serde(default)]
+    wait: bool,
+    #[serde(default)]
+    max_depth: Option<usize>,
+}
+
+/// OpenAPI 文档中每个流程对应的操作：请求体即流程输入
+///
+/// 等待期间客户端断开不影响运行。
+async fn run_flow(
+    State(state): State<ApiState>,
+    Path((project, flow)): Path<(String, String)>,
+    Query(query): Query<RunFlowQuery>,
+    Json(inputs): Json<Value>,
+) -> ApiResult<Response> {
+    let (_, log) = spawn_run(&state, &project, &flow, Some(inputs), query.max_depth).await?;
+    if !query.wait {
+        return Ok((StatusCode::ACCEPTED, Json(log.view())).into_response());
+    }
+    log.wait().await;
+    let view = log.view();
+    let response = match view.status {
+        TraceStatus::Succeeded => Json(view.outputs.unwrap_or_else(|| json!({}))).into_response(),
+        TraceStatus::Waiting | TraceStatus::Paused => {
+            (StatusCode::ACCEPTED, Json(view)).into_response()
+        }
+        _ => (StatusCode::UNPROCESSABLE_ENTITY, Json(view)).into_response(),
+    };
+    Ok(response)
+}
+
+/// 运行历史中的轨迹（未通过 API 启动或已被淘汰的运行）
+async fn saved_trace(id: &str) -> ApiResult<Option<FlowTrace>> {
+    let history = RunHistory::new(&GlobalState::get().engine_db);
+    history.get(id).await.map_err(ApiError::internal)
+}
+
+async fn get_run(
+    State(state): State<ApiState>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<RunView>> {
+    if let Some(log) = state.logs.get(&id) {
+        return Ok(Json(log.view()));
+    }
+    match saved_trace(&id).await? {
+        // 其它途径启动且仍在执行的运行，历史中为开始时的状态
+        Some(trace) if GlobalState::get().runs.get(&id).is_some() => Ok(Json(RunView {
+            status: TraceStatus::Running,
+            ..RunView::of(&id, &trace)
+        })),
+        Some(trace) => Ok(Json(RunView::of(&id, &trace))),
+        None => Err(ApiError::not_found(&id)),
+    }
+}
+
+async fn get_trace(
+    State(state): State<ApiState>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<FlowTrace>> {
+    if let Some(log) = state.logs.get(&id) {
+        return match log.trace() {
+            Some(trace) => Ok(Json(trace)),
+            None if log.is_done() => Err(ApiError(
+                StatusCode::NOT_FOUND,
+                log.view().error.unwrap_or_default(),
+            )),
+            None => Err(ApiError(
+                StatusCode::CONFLICT,
+                format!("运行 {} 尚未结束", id),
+            )),
+        };
+    }
+    saved_trace(&id)
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(&id))
+}
+
+#[derive(Deserialize)]
+struct AfterQuery {
+    #[serde(default)]
+    after: usize,
+}
+
+#[derive(Serialize)]
+struct EventPage {
+    events: Vec<crate::flow::events::FlowEvent>,
+    /// 下次轮询使用的 `after`
+    next: usize,
+    /// 运行已结束，之后不会再有事件
+    done: bool,
+}
+
+async fn poll_events(
+    State(state): State<ApiState>,
+    Path(id): Path<String>,
+    Query(query): Query<AfterQuery>,
+) -> ApiResult<Json<EventPage>> {
+    let log = state
+        .logs
+        .get(&id)
+        .ok_or_else(|| ApiError::not_found(&id))?;
+    let (events, done) = log.events(query.after);
+    Ok(Json(EventPage {
+        next: query.after + events.len(),
+        events,
+        done,
+    }))
+}
+
+async fn stream_events(
+    State(state): State<ApiState>,
+    Path(id): Path<String>,
+    Query(query): Query<AfterQuery>,
+    headers: HeaderMap,
+) -> ApiResult<Sse<impl Stream<Item = Result<Event, Infallible>>>> {
+    let log = state
+        .logs
+        .get(&id)
+        .ok_or_else(|| ApiError::not_found(&id))?;
+    // 断线重连时从上次收到的事件之后继续
+    let after = headers
+        .get("last-event-id")
+        .and_then(|value| value.to_str().ok()?.parse::<usize>().ok())
+        .map_or(query.after, |last| last + 1);
+    let stream = futures_util::stream::unfold((log, after), |(log, index)| async move {
+        let event = log.next(index).await?;
+        let sse = Event::default()
+            .id(index.to_string())
+            .json_data(&event)
+            .unwrap_or_else(|e| Event::default().comment(format!("序列化事件失败: {}", e)));
+        Some((Ok(sse), (log, index + 1)))
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+async fn cancel_run(Path(id): Path<String>) -> ApiResult<Json<Value>> {
+    let cancelled = run_cancel(id).await.map_err(ApiError::bad_request)?;
+    Ok(Json(json!({ "cancelled": cancelled })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow::events::{EventSink, FlowEvent};
+
+    #[tokio::test]
+    async fn test_auth_poll_and_stream() {
+        let state = ApiState {
+            token: Arc::new(RwLock::new("secret".to_string())),
+            logs: Arc::new(RunLogs::default()),
+        };
+        let log = state.logs.create("r1");
+        let sink = LogSink(log.clone());
+        for node in ["a", "b"] {
+            sink.emit(FlowEvent::NodeStarted {
+                run_id: "r1".to_string(),
+                path: node.to_string(),
+                node_id: node.to_string(),
+            });
+        }
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router(state)).await });
+        let client = reqwest::Client::new();
+
+        let health = client.get(format!("{}/health", base)).send().await.unwrap();
+        assert_eq!(health.status(), 200);
+        let denied = client
+            .get(format!("{}/v1/runs/r1/events", base))
+            .bearer_auth("wrong")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(denied.status(), 401);
+
+        let page: Value = client
+            .get(format!("{}/v1/runs/r1/events?after=1", base))
+            .bearer_auth("secret")
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(page["events"][0]["node_id"], "b");
+        assert_eq!(page["next"], 2);
+        assert_eq!(page["done"], false);
+
+        // 运行结束后 SSE 在发完全部事件后关闭
+        let stream = client
+            .get(format!("{}/v1/runs/r1/stream", base))
+            .bearer_auth("secret")
+            .header("Last-Event-ID", "0")
+            .send();
+        log.finish(FlowTrace::new("f", "1", json!({})));
+        let body = stream.await.unwrap().text().await.unwrap();
+        assert!(!body.contains("\"node_id\":\"a\""));
+        assert!(body.contains("id: 1") && body.contains("\"node_id\":\"b\""));
+    }
+}
@@ -0,0 +1,210 @@
+//! 通过 HTTP API 启动的运行：保存执行事件供轮询与 SSE 订阅，运行结束后保存结果
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+use crate::flow::events::{EventSink, FlowEvent};
+use crate::flow::trace::{ErrorKind, FlowTrace, TraceStatus};
+
+/// 保留的已结束运行数，超出后丢弃最早结束的运行（结果仍可从运行历史读取）
+const KEEP_FINISHED: usize = 64;
+
+/// 运行结果摘要
+#[derive(Debug, Clone, Serialize)]
+pub struct RunView {
+    pub run_id: String,
+    pub status: TraceStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outputs: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_kind: Option<ErrorKind>,
+}
+
+impl RunView {
+    pub fn of(run_id: &str, trace: &FlowTrace) -> Self {
+        Self {
+            run_id: run_id.to_string(),
+            status: trace.status,
+            outputs: trace.outputs.clone(),
+            error: trace.error.clone(),
+            error_kind: trace.error_kind,
+        }
+    }
+}
+
+/// 运行的结束状态
+enum Outcome {
+    Running,
+    Finished(Box<FlowTrace>),
+    /// 未能开始执行（如配置无效）
+    Failed(String),
+}
+
+/// 一次运行的事件记录
+pub struct RunLog {
+    run_id: String,
+    state: Mutex<(Vec<FlowEvent>, Outcome)>,
+    changed: Notify,
+}
+
+impl RunLog {
+    fn new(run_id: &str) -> Self {
+        Self {
+            run_id: run_id.to_string(),
+            state: Mutex::new((Vec::new(), Outcome::Running)),
+            changed: Notify::new(),
+        }
+    }
+
+    fn update<T>(&self, f: impl FnOnce(&mut Vec<FlowEvent>, &mut Outcome) -> T) -> T {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let (events, outcome) = &mut *state;
+        let result = f(events, outcome);
+        drop(state);
+        self.changed.notify_waiters();
+        result
+    }
+
+    fn read<T>(&self, f: impl FnOnce(&[FlowEvent], &Outcome) -> T) -> T {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        f(&state.0, &state.1)
+    }
+
+    pub fn finish(&self, trace: FlowTrace) {
+        self.update(|_, outcome| *outcome = Outcome::Finished(Box::new(trace)));
+    }
+
+    pub fn fail(&self, error: String) {
+        self.update(|_, outcome| *outcome = Outcome::Failed(error));
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.read(|_, outcome| !matches!(outcome, Outcome::Running))
+    }
+
+    /// 从第 `after` 条开始的事件，以及运行是否已结束
+    pub fn events(&self, after: usize) -> (Vec<FlowEvent>, bool) {
+        self.read(|events, outcome| {
+            let page = events.get(after..).unwrap_or_default().to_vec();
+            (page, !matches!(outcome, Outcome::Running))
+        })
+    }
+
+    /// 等待第 `index` 条事件；运行已结束且没有更多事件时返回 `None`
+    pub async fn next(&self, index: usize) -> Option<FlowEvent> {
+        loop {
+            let changed = self.changed.notified();
+            tokio::pin!(changed);
+            changed.as_mut().enable();
+            let (event, done) = self.read(|events, outcome| {
+                (
+                    events.get(index).cloned(),
+                    !matches!(outcome, Outcome::Running),
+                )
+            });
+            match event {
+                Some(event) => return Some(event),
+                None if done => return None,
+                None => changed.await,
+            }
+        }
+    }
+
+    /// 等待运行结束
+    pub async fn wait(&self) {
+        loop {
+            let changed = self.changed.notified();
+            tokio::pin!(changed);
+            changed.as_mut().enable();
+            if self.is_done() {
+                return;
+            }
+            changed.await;
+        }
+    }
+
+    pub fn view(&self) -> RunView {
+        self.read(|_, outcome| match outcome {
+            Outcome::Running => RunView {
+                run_id: self.run_id.clone(),
+                status: TraceStatus::Running,
+                outputs: None,
+                error: None,
+                error_kind: None,
+            },
+            Outcome::Finished(trace) => RunView::of(&self.run_id, trace),
+            Outcome::Failed(error) => RunView {
+                run_id: self.run_id.clone(),
+                status: TraceStatus::Failed,
+                outputs: None,
+                error: Some(error.clone()),
+                error_kind: Some(ErrorKind::Invalid),
+            },
+        })
+    }
+
+    pub fn trace(&self) -> Option<FlowTrace> {
+        self.read(|_, outcome| match outcome {
+            Outcome::Finished(trace) => Some((**trace).clone()),
+            _ => None,
+        })
+    }
+}
+
+/// 记录事件的接收器
+pub struct LogSink(pub Arc<RunLog>);
+
+impl EventSink for LogSink {
+    fn emit(&self, event: FlowEvent) {
+        self.0.update(|events, _| events.push(event));
+    }
+}
+
+/// 按 id 索引的运行及其登记顺序
+type Logs = (HashMap<String, Arc<RunLog>>, VecDeque<String>);
+
+/// 全部通过 API 启动的运行
+#[derive(Default)]
+pub struct RunLogs {
+    logs: Mutex<Logs>,
+}
+
+impl RunLogs {
+    /// 登记新的运行
+    pub fn create(&self, run_id: &str) -> Arc<RunLog> {
+        let log = Arc::new(RunLog::new(run_id));
+        let mut logs = self.logs.lock().unwrap_or_else(|e| e.into_inner());
+        let (map, order) = &mut *logs;
+        map.insert(run_id.to_string(), log.clone());
+        order.push_back(run_id.to_string());
+
+        // 只淘汰已结束的运行
+        let finished = order
+            .iter()
+            .filter(|id| map.get(*id).is_some_and(|log| log.is_done()))
+            .count();
+        if finished > KEEP_FINISHED {
+            let mut excess = finished - KEEP_FINISHED;
+            order.retain(|id| {
+                if excess > 0 && map.get(id).is_some_and(|log| log.is_done()) {
+                    map.remove(id);
+                    excess -= 1;
+                    false
+                } else {
+                    true
+                }
+            });
+        }
+        log
+    }
+
+    pub fn get(&self, run_id: &str) -> Option<Arc<RunLog>> {
+        let logs = self.logs.lock().unwrap_or_else(|e| e.into_inner());
+        logs.0.get(run_id).cloned()
+    }
+}
@@ -0,0 +1,87 @@
+//! `vlogi mcp`：通过 stdio 提供 MCP 服务，把项目中的流程作为工具
+//!
+//! stdout 只用于协议消息，日志输出到 stderr。
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use super::exit;
+use super::run::unlock_vault;
+use crate::commands::flow::exec_options;
+use crate::flow::store::FlowStore;
+use crate::mcp::server::McpServer;
+use crate::project::Project;
+
+#[derive(clap::Args, Debug, Clone)]
+#[command(after_help = "\
+Speaks the Model Context Protocol over stdin/stdout. Each flow becomes a tool
+whose arguments are the flow inputs; the tool result carries the flow outputs.
+
+Set VLOGI_VAULT_PASSPHRASE to unlock the secret vault before serving.")]
+pub struct McpArgs {
+    /// Project directory path
+    ///
+    /// If not specified, uses the current working directory
+    #[arg(short, long, value_name = "DIR")]
+    pub project: Option<PathBuf>,
+
+    /// Flow to expose as a tool (repeatable); all flows by default
+    #[arg(short, long = "flow", value_name = "FLOW")]
+    pub flows: Vec<String>,
+
+    /// Maximum subflow nesting depth
+    #[arg(long, value_name = "N")]
+    pub max_depth: Option<usize>,
+
+    /// Do not record tool calls in run history
+    #[arg(long)]
+    pub no_history: bool,
+}
+
+/// 执行 `vlogi mcp`，返回退出码
+pub async fn run(args: McpArgs) -> i32 {
+    match serve(args).await {
+        Ok(()) => exit::OK,
+        Err((code, message)) => {
+            eprintln!("❌ {}", message);
+            code
+        }
+    }
+}
+
+async fn serve(args: McpArgs) -> Result<(), (i32, String)> {
+    let invalid = |message: String| (exit::INVALID, message);
+    let root = match args.project {
+        Some(root) => root,
+        None => std::env::current_dir().map_err(|e| (exit::USAGE, e.to_string()))?,
+    };
+    let store = FlowStore::new(Project::open(&root).map_err(invalid)?);
+    let mut flows = Vec::new();
+    if args.flows.is_empty() {
+        for id in store.list_local().map_err(invalid)? {
+            match store.load_local(&id) {
+                Ok(flow) => flows.push(flow),
+                Err(e) => tracing::warn!("⚠️  跳过无法读取的流程 {}: {}", id, e),
+            }
+        }
+    } else {
+        for id in &args.flows {
+            flows.push(store.load_local(id).map_err(invalid)?);
+        }
+    }
+    unlock_vault().await.map_err(invalid)?;
+
+    let server = Arc::new(McpServer::new(
+        store,
+        flows,
+        exec_options(args.max_depth),
+        !args.no_history,
+    ));
+    let names: Vec<&str> = server.tools().map(|tool| tool.name.as_str()).collect();
+    tracing::info!("✅ MCP 服务已就绪，工具: {}", names.join(", "));
+    server
+        .clone()
+        .serve(tokio::io::stdin(), tokio::io::stdout())
+        .await
+        .map_err(|e| (exit::FAILED, e))
+}
@@ -0,0 +1,121 @@
+//! 命令行模式：带子命令启动时不创建窗口，执行完子命令后以退出码结束进程
+//!
+//! 日志与进度输出到 stderr，结果（JSON）输出到 stdout，便于在 CI 与定时任务中使用。
+
+pub mod mcp;
+pub mod openapi;
+pub mod project;
+pub mod run;
+pub mod serve;
+
+use clap::Subcommand;
+
+use crate::state::{self, GlobalState};
+use crate::utils::appdb::app_config_dir;
+
+/// 命令行子命令
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Run a flow without opening a window
+    Run(run::RunArgs),
+    /// Serve the local HTTP API without opening a window
+    Serve(serve::ServeArgs),
+    /// Export the OpenAPI document for a project's flows
+    Openapi(openapi::OpenapiArgs),
+    /// Serve a project's flows as MCP tools over stdio
+    Mcp(mcp::McpArgs),
+    /// Create, list, open, check, export and import projects
+    Project {
+        #[command(subcommand)]
+        command: project::ProjectCommand,
+    },
+}
+
+/// 退出码
+pub mod exit {
+    pub const OK: i32 = 0;
+    /// 节点执行失败
+    pub const FAILED: i32 = 1;
+    /// 命令行参数或输入文件有误（与 clap 的参数错误一致）
+    pub const USAGE: i32 = 2;
+    /// 项目、流程定义、流程输入或应用配置无效
+    pub const INVALID: i32 = 3;
+    /// 调用模型失败
+    pub const PROVIDER: i32 = 4;
+    /// 超出预算
+    pub const BUDGET: i32 = 5;
+    /// 运行未完成（暂停或等待人工输入）
+    pub const INCOMPLETE: i32 = 6;
+    /// 被中断（Ctrl-C）
+    pub const CANCELLED: i32 = 130;
+}
+
+/// 命令行入口，返回退出码
+pub fn main(command: Command) -> i32 {
+    if state::set_global_state(GlobalState::new()).is_err() {
+        eprintln!("GLOBAL_STATE already initialized");
+        return exit::FAILED;
+    }
+    crate::utils::init();
+    // 与应用共用信号文件，修改配置后通知正在运行的实例
+    if let Some(dir) = app_config_dir() {
+        let state = GlobalState::get();
+        state.app_states.set_config_watch_path(dir.join("db.sig"));
+    }
+
+    let runtime = match tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("❌ 创建运行时失败: {}", e);
+            return exit::FAILED;
+        }
+    };
+    runtime.block_on(async move {
+        match command {
+            Command::Run(args) => run::run(args).await,
+            Command::Serve(args) => serve::run(args).await,
+            Command::Openapi(args) => openapi::run(args).await,
+            Command::Mcp(args) => mcp::run(args).await,
+            Command::Project { command } => project::run(command).await,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::args::Args;
+    use clap::Parser;
+
+    #[test]
+    fn test_subcommands_and_gui_project_argument() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_str().unwrap();
+
+        let args = Args::try_parse_from(["vlogi", root]).unwrap();
+        assert!(args.project.is_some());
+        assert!(args.command.is_none());
+
+        let args = Args::try_parse_from([
+            "vlogi",
+            "run",
+            "--project",
+            root,
+            "--flow",
+            "daily",
+            "-l",
+            "warn",
+        ])
+        .unwrap();
+        let Some(Command::Run(run)) = args.command else {
+            panic!("应解析为 run 子命令");
+        };
+        assert_eq!(run.flow, "daily");
+        assert_eq!(args.log_level, tracing::Level::WARN);
+
+        assert!(Args::try_parse_from(["vlogi", "run", "--project", root]).is_err());
+    }
+}
@@ -0,0 +1,63 @@
+//! `vlogi openapi`：导出项目的 OpenAPI 文档，供生成本地 HTTP API 的客户端代码
+
+use std::path::PathBuf;
+
+use super::exit;
+use crate::api::openapi;
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct OpenapiArgs {
+    /// Project id or directory path
+    ///
+    /// If not specified, uses the current working directory
+    #[arg(short, long, value_name = "PROJECT")]
+    pub project: Option<String>,
+
+    /// Write the document to this file instead of stdout
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+}
+
+/// 执行 `vlogi openapi`，返回退出码
+pub async fn run(args: OpenapiArgs) -> i32 {
+    let target = match args.project {
+        Some(target) => target,
+        None => match std::env::current_dir() {
+            Ok(dir) => dir.to_string_lossy().into_owned(),
+            Err(e) => {
+                eprintln!("❌ {}", e);
+                return exit::USAGE;
+            }
+        },
+    };
+    let document = match openapi::generate(&target).await {
+        Ok(document) => document,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            return exit::INVALID;
+        }
+    };
+    let json = match serde_json::to_string_pretty(&document) {
+        Ok(json) => json,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            return exit::FAILED;
+        }
+    };
+    match args.output {
+        Some(path) => match std::fs::write(&path, json + "\n") {
+            Ok(()) => {
+                eprintln!("✅ 已导出到 {}", path.display());
+                exit::OK
+            }
+            Err(e) => {
+                eprintln!("❌ 写入 {:?} 失败: {}", path, e);
+                exit::FAILED
+            }
+        },
+        None => {
+            println!("{}", json);
+            exit::OK
+        }
+    }
+}
@@ -0,0 +1,424 @@
+//! `vlogi project`：在命令行中创建、登记、检查、打开与迁移项目
+//!
+//! 与应用共用 app.db 中的项目登记和项目目录布局；修改登记后触发变更信号，正在运行的实例会重新加载。
+
+use clap::Subcommand;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use super::exit;
+use crate::commands::store::{touch_db_config, touch_focus_config, validate_pid};
+use crate::flow::graph;
+use crate::flow::store::FlowStore;
+use crate::project::registry::{Registry, Repository};
+use crate::project::{archive, Project};
+use crate::state::GlobalState;
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum ProjectCommand {
+    /// Create a project in a directory and register it
+    Init {
+        /// Project directory path (created if missing)
+        ///
+        /// If not specified, uses the current working directory
+        dir: Option<PathBuf>,
+        /// Project name (defaults to the directory name)
+        #[arg(long)]
+        name: Option<String>,
+        /// Create the project even if the directory is not empty
+        #[arg(long)]
+        force: bool,
+    },
+    /// List registered projects with their owner process and liveness
+    List {
+        /// Print as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Open a project in the app, focusing the instance that already has it open
+    Open {
+        /// Project directory path or project id
+        target: String,
+    },
+    /// Check project layout, registration and flows
+    Doctor {
+        /// Project directory path
+        ///
+        /// If not specified, uses the current working directory
+        dir: Option<PathBuf>,
+        /// Repair what can be repaired (missing directories, registration, stale owner)
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Export a project to a .tar.gz archive
+    Export {
+        /// Project directory path or project id
+        target: String,
+        /// Archive file to write
+        #[arg(short, long, value_name = "FILE")]
+        output: PathBuf,
+    },
+    /// Restore a project from an archive into an empty directory and register it
+    Import {
+        /// Archive created by `vlogi project export`
+        archive: PathBuf,
+        /// Directory to restore into (must not exist or be empty)
+        dir: PathBuf,
+    },
+}
+
+/// 项目的打开状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Liveness {
+    /// 被正在运行的实例打开
+    Open,
+    /// 登记的打开进程已退出（应用异常结束）
+    Stale,
+    Closed,
+    /// 项目目录已不存在或不再是项目
+    Missing,
+}
+
+impl Liveness {
+    fn of(repo: &Repository) -> Self {
+        if Project::open(&repo.path).is_err() {
+            Liveness::Missing
+        } else if repo.owner == 0 {
+            Liveness::Closed
+        } else if validate_pid(repo.owner) {
+            Liveness::Open
+        } else {
+            Liveness::Stale
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Liveness::Open => "open",
+            Liveness::Stale => "stale",
+            Liveness::Closed => "closed",
+            Liveness::Missing => "missing",
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ListItem {
+    #[serde(flatten)]
+    repo: Repository,
+    liveness: Liveness,
+}
+
+/// 执行 `vlogi project` 子命令，返回退出码
+pub async fn run(command: ProjectCommand) -> i32 {
+    let result = match command {
+        ProjectCommand::Init { dir, name, force } => init(dir, name, force).await,
+        ProjectCommand::List { json } => list(json).await,
+        ProjectCommand::Open { target } => open(&target).await,
+        ProjectCommand::Doctor { dir, fix } => doctor(dir, fix).await,
+        ProjectCommand::Export { target, output } => export(&target, &output).await,
+        ProjectCommand::Import { archive, dir } => import(&archive, &dir).await,
+    };
+    match result {
+        Ok(code) => code,
+        Err(message) => {
+            eprintln!("❌ {}", message);
+            exit::INVALID
+        }
+    }
+}
+
+fn registry() -> Registry<'static> {
+    Registry::new(&GlobalState::get().app_db)
+}
+
+/// 修改登记后通知正在运行的实例（没有实例时信号文件无人监听，忽略失败）
+fn notify() {
+    if let Err(e) = touch_db_config() {
+        tracing::debug!("触发配置变更信号失败: {}", e);
+    }
+}
+
+fn dir_or_current(dir: Option<PathBuf>) -> Result<PathBuf, String> {
+    match dir {
+        Some(dir) => Ok(dir),
+        None => std::env::current_dir().map_err(|e| e.to_string()),
+    }
+}
+
+/// 按目录或 id 找到项目
+async fn resolve(target: &str) -> Result<(Project, Option<Repository>), String> {
+    let registered = registry().find(target).await?;
+    let path = match &registered {
+        Some(repo) => PathBuf::from(&repo.path),
+        None => PathBuf::from(target),
+    };
+    Ok((Project::open(path)?, registered))
+}
+
+/// 保存登记并通知正在运行的实例
+async fn save(registry: &Registry<'_>, repo: &Repository) -> Result<(), String> {
+    registry.save(repo).await?;
+    notify();
+    Ok(())
+}
+
+/// 登记项目；已以其它 id 登记过同一目录时沿用原记录
+async fn register(project: &Project, meta: &Repository) -> Result<Repository, String> {
+    let registry = registry();
+    let existing = registry.find(&project.root().to_string_lossy()).await?;
+    let mut repo = match existing {
+        Some(existing) if existing.id != meta.id => {
+            tracing::warn!(
+                "⚠️  目录已以 id {} 登记，元信息中的 id 为 {}",
+                existing.id,
+                meta.id
+            );
+            existing
+        }
+        Some(existing) => existing,
+        None => meta.clone(),
+    };
+    repo.name = meta.name.clone();
+    repo.path = project.root().to_string_lossy().into_owned();
+    save(&registry, &repo).await?;
+    Ok(repo)
+}
+
+async fn init(dir: Option<PathBuf>, name: Option<String>, force: bool) -> Result<i32, String> {
+    let dir = dir_or_current(dir)?;
+    let (project, meta) = Project::init(&dir, name.as_deref(), force)?;
+    let repo = register(&project, &meta).await?;
+    println!("✅ 项目 {} ({}) 位于 {}", repo.name, repo.id, repo.path);
+    Ok(exit::OK)
+}
+
+async fn list(json: bool) -> Result<i32, String> {
+    let items: Vec<ListItem> = (registry().list().await?.into_iter())
+        .map(|repo| ListItem {
+            liveness: Liveness::of(&repo),
+            repo,
+        })
+        .collect();
+    if json {
+        let text = serde_json::to_string_pretty(&items).map_err(|e| e.to_string())?;
+        println!("{}", text);
+        return Ok(exit::OK);
+    }
+    println!(
+        "{:<36}  {:<8}  {:<8}  {:<20}  PATH",
+        "ID", "STATUS", "OWNER", "NAME"
+    );
+    for item in &items {
+        let owner = match item.repo.owner {
+            0 => "-".to_string(),
+            pid => pid.to_string(),
+        };
+        println!(
+            "{:<36}  {:<8}  {:<8}  {:<20}  {}",
+            item.repo.id,
+            item.liveness.as_str(),
+            owner,
+            item.repo.name,
+            item.repo.path
+        );
+    }
+    Ok(exit::OK)
+}
+
+async fn open(target: &str) -> Result<i32, String> {
+    let (project, registered) = resolve(target).await?;
+    let repo = match registered {
+        Some(repo) => repo,
+        None => register(&project, &project.meta()?).await?,
+    };
+    if repo.owner != 0 && validate_pid(repo.owner) {
+        touch_focus_config(repo.owner)?;
+        println!("✅ 项目已在进程 {} 中打开，已切换到该窗口", repo.owner);
+        return Ok(exit::OK);
+    }
+
+    let exe = std::env::current_exe().map_err(|e| format!("无法确定程序路径: {}", e))?;
+    let child = std::process::Command::new(exe)
+        .arg(project.root())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("启动应用失败: {}", e))?;
+    println!("✅ 已在新窗口中打开项目（进程 {}）", child.id());
+    Ok(exit::OK)
+}
+
+/// 检查结果
+struct Report {
+    errors: usize,
+    warnings: usize,
+    fix: bool,
+}
+
+impl Report {
+    fn ok(&self, message: impl AsRef<str>) {
+        println!("✅ {}", message.as_ref());
+    }
+
+    fn warn(&mut self, message: impl AsRef<str>) {
+        self.warnings += 1;
+        println!("⚠️  {}", message.as_ref());
+    }
+
+    fn error(&mut self, message: impl AsRef<str>) {
+        self.errors += 1;
+        println!("❌ {}", message.as_ref());
+    }
+
+    /// 可修复的问题：未指定 --fix 时记为警告，返回是否需要修复
+    fn fixable(&mut self, message: &str) -> bool {
+        if !self.fix {
+            self.warn(format!("{}（可用 --fix 修复）", message));
+        }
+        self.fix
+    }
+
+    fn fixed(&mut self, message: &str, result: Result<(), String>) {
+        match result {
+            Ok(()) => println!("🔧 {}（已修复）", message),
+            Err(e) => self.error(format!("{}，修复失败: {}", message, e)),
+        }
+    }
+}
+
+async fn doctor(dir: Option<PathBuf>, fix: bool) -> Result<i32, String> {
+    let dir = dir_or_current(dir)?;
+    let project = Project::open(&dir)?;
+    let mut report = Report {
+        errors: 0,
+        warnings: 0,
+        fix,
+    };
+    let root = project.root().to_string_lossy().into_owned();
+
+    // 目录布局
+    for dir in [project.gitdata_dir(), project.flows_dir()] {
+        let message = format!("缺少目录 {:?}", dir);
+        if !dir.is_dir() && report.fixable(&message) {
+            let result = std::fs::create_dir_all(&dir).map_err(|e| e.to_string());
+            report.fixed(&message, result);
+        }
+    }
+
+    // 元信息
+    let mut meta = match project.meta() {
+        Ok(meta) => {
+            report.ok(format!("元信息: {} ({})", meta.name, meta.id));
+            meta
+        }
+        Err(e) => {
+            report.error(e);
+            return Ok(finish(&report));
+        }
+    };
+    let message = format!("元信息中的目录 {} 与实际目录 {} 不一致", meta.path, root);
+    if meta.path != root && report.fixable(&message) {
+        meta.path = root.clone();
+        report.fixed(&message, project.write_meta(&meta));
+    }
+
+    // 登记
+    let registry = registry();
+    match registry.find(&meta.id).await? {
+        None => match registry.find(&root).await? {
+            Some(other) => report.warn(format!(
+                "目录以 id {} 登记，与元信息中的 id {} 不一致",
+                other.id, meta.id
+            )),
+            None => {
+                let message = "项目未登记";
+                if report.fixable(message) {
+                    let repo = Repository {
+                        path: root.clone(),
+                        owner: 0,
+                        ..meta.clone()
+                    };
+                    report.fixed(message, save(&registry, &repo).await);
+                }
+            }
+        },
+        Some(mut repo) => {
+            let liveness = Liveness::of(&repo);
+            if repo.path != root {
+                let message = format!("登记的目录 {} 与实际目录 {} 不一致", repo.path, root);
+                if report.fixable(&message) {
+                    repo.path = root.clone();
+                    report.fixed(&message, save(&registry, &repo).await);
+                }
+            } else if liveness == Liveness::Stale {
+                let message = format!("登记的打开进程 {} 已退出", repo.owner);
+                if report.fixable(&message) {
+                    repo.owner = 0;
+                    report.fixed(&message, save(&registry, &repo).await);
+                }
+            } else {
+                report.ok(format!("已登记（{}）", liveness.as_str()));
+            }
+        }
+    }
+
+    // 流程
+    let store = FlowStore::new(project.clone());
+    for id in store.list_local()? {
+        match store.load_local(&id) {
+            Ok(flow) => match graph::build(&flow) {
+                Ok(_) => report.ok(format!("流程 {}", id)),
+                Err(issues) => {
+                    for issue in issues {
+                        report.error(format!("流程 {}: {}", id, issue));
+                    }
+                }
+            },
+            Err(e) => report.error(e),
+        }
+    }
+    Ok(finish(&report))
+}
+
+fn finish(report: &Report) -> i32 {
+    println!("{} 个错误，{} 个警告", report.errors, report.warnings);
+    if report.errors > 0 {
+        exit::INVALID
+    } else {
+        exit::OK
+    }
+}
+
+async fn export(target: &str, output: &Path) -> Result<i32, String> {
+    let (project, _) = resolve(target).await?;
+    archive::export(&project, output)?;
+    println!("✅ 已导出到 {:?}", output);
+    Ok(exit::OK)
+}
+
+async fn import(archive_path: &Path, dir: &Path) -> Result<i32, String> {
+    let project = archive::import(archive_path, dir)?;
+    let mut meta = project.meta()?;
+    meta.path = project.root().to_string_lossy().into_owned();
+    meta.owner = 0;
+    // 同一项目已在本机其它目录登记时，以新 id 导入为副本
+    if let Some(existing) = registry().find(&meta.id).await? {
+        if Path::new(&existing.path) != project.root() {
+            let copy = Repository::new(meta.name.clone(), project.root());
+            println!(
+                "⚠️  项目 {} 已登记于 {}，以新 id {} 导入",
+                meta.id, existing.path, copy.id
+            );
+            meta.id = copy.id;
+            meta.ctime = copy.ctime;
+        }
+    }
+    project.write_meta(&meta)?;
+    let repo = register(&project, &meta).await?;
+    println!("✅ 项目 {} ({}) 已导入到 {}", repo.name, repo.id, repo.path);
+    Ok(exit::OK)
+}
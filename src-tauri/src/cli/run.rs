@@ -0,0 +1,288 @@
+//! `vlogi run`：不创建窗口，在当前进程中执行流程
+//!
+//! 结果以 JSON 输出到 stdout，进度输出到 stderr，运行照常登记到运行历史（可在应用中查看或继续）。
+
+use clap::ValueEnum;
+use serde_json::Value;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::sync::Arc;
+use zeroize::Zeroizing;
+
+use super::exit;
+use crate::commands::flow::{
+    exec_options, record_start, run_target, Start, Target, DELTA_INTERVAL,
+};
+use crate::flow::events::{EventSink, FlowEvent, Throttled};
+use crate::flow::store::FlowStore;
+use crate::flow::trace::{ErrorKind, FlowTrace, TraceStatus};
+use crate::project::Project;
+use crate::state::GlobalState;
+
+/// 从该环境变量读取密钥库口令并在运行前解锁
+const PASSPHRASE_ENV: &str = "VLOGI_VAULT_PASSPHRASE";
+
+#[derive(clap::Args, Debug, Clone)]
+#[command(after_help = "\
+Exit codes:
+  0    run succeeded
+  1    a node failed
+  2    invalid arguments or unreadable input file
+  3    invalid project, flow definition, flow inputs or configuration
+  4    model provider error
+  5    budget exceeded
+  6    run paused or waiting for human input
+  130  cancelled (Ctrl-C)
+
+Set VLOGI_VAULT_PASSPHRASE to unlock the secret vault before running.")]
+pub struct RunArgs {
+    /// Project directory path
+    ///
+    /// If not specified, uses the current working directory
+    #[arg(short, long, value_name = "DIR")]
+    pub project: Option<PathBuf>,
+
+    /// Flow id (file name under vlogi/flows, without extension)
+    #[arg(short, long)]
+    pub flow: String,
+
+    /// Flow inputs as a JSON/JSON5 file, or '-' to read from stdin
+    ///
+    /// If not specified, the flow runs with empty inputs.
+    #[arg(short, long, value_name = "FILE")]
+    pub input: Option<PathBuf>,
+
+    /// Maximum subflow nesting depth
+    #[arg(long, value_name = "N")]
+    pub max_depth: Option<usize>,
+
+    /// Progress output written to stderr
+    #[arg(long, value_enum, default_value_t = ProgressFormat::Text, value_name = "FORMAT")]
+    pub progress: ProgressFormat,
+
+    /// Print the full run trace instead of the flow outputs
+    #[arg(long)]
+    pub trace: bool,
+
+    /// Do not record the run in run history
+    #[arg(long)]
+    pub no_history: bool,
+}
+
+/// 进度输出格式
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressFormat {
+    /// One line per node event
+    Text,
+    /// One JSON event per line (same as the app's flow events)
+    Json,
+    /// No progress output
+    None,
+}
+
+/// 把执行事件输出到 stderr
+struct Progress(ProgressFormat);
+
+impl EventSink for Progress {
+    fn emit(&self, event: FlowEvent) {
+        match self.0 {
+            ProgressFormat::None => {}
+            ProgressFormat::Json => match serde_json::to_string(&event) {
+                Ok(line) => eprintln!("{}", line),
+                Err(e) => tracing::warn!("⚠️  序列化执行事件失败: {}", e),
+            },
+            ProgressFormat::Text => {
+                if let Some(line) = describe(&event) {
+                    eprintln!("{}", line);
+                }
+            }
+        }
+    }
+}
+
+/// 文本进度中的一行，流式文本等细粒度事件不输出
+fn describe(event: &FlowEvent) -> Option<String> {
+    let line = match event {
+        FlowEvent::RunStarted {
+            run_id,
+            flow_id,
+            flow_version,
+        } => format!("▶️  运行 {} 开始: {}@{}", run_id, flow_id, flow_version),
+        FlowEvent::NodeStarted { path, .. } => format!("⏳ {}", path),
+        FlowEvent::NodeFinished { path, cached, .. } => {
+            format!("✅ {}{}", path, if *cached { "（缓存）" } else { "" })
+        }
+        FlowEvent::NodeFailed { path, error, .. } => format!("❌ {}: {}", path, error),
+        FlowEvent::MapProgress {
+            path,
+            completed,
+            failed,
+            total,
+            ..
+        } => format!("⏳ {} {}/{}（失败 {}）", path, completed, total, failed),
+        FlowEvent::InputRequested { path, prompt, .. } => {
+            format!("⏸️  {} 等待人工输入: {}", path, prompt)
+        }
+        FlowEvent::NodeSkipped { path, .. } => format!("⏭️  {}", path),
+        FlowEvent::NodeCancelled { path, .. } => format!("⏹️  {}", path),
+        FlowEvent::RunFinished { status, error, .. } => match error {
+            Some(error) => format!("🏁 运行结束: {}（{}）", status.as_str(), error),
+            None => format!("🏁 运行结束: {}", status.as_str()),
+        },
+        FlowEvent::NodeQueued { .. } | FlowEvent::TokenDelta { .. } => return None,
+    };
+    Some(line)
+}
+
+/// 执行 `vlogi run`，返回退出码
+pub async fn run(args: RunArgs) -> i32 {
+    match execute(args).await {
+        Ok(code) => code,
+        Err((code, message)) => {
+            eprintln!("❌ {}", message);
+            code
+        }
+    }
+}
+
+async fn execute(args: RunArgs) -> Result<i32, (i32, String)> {
+    let invalid = |message: String| (exit::INVALID, message);
+    let root = match &args.project {
+        Some(root) => root.clone(),
+        None => std::env::current_dir().map_err(|e| (exit::USAGE, e.to_string()))?,
+    };
+    let project = Project::open(&root).map_err(invalid)?;
+    let project_root = project.root().to_string_lossy().into_owned();
+    let store = FlowStore::new(project);
+    let def = store.load_local(&args.flow).map_err(invalid)?;
+    let inputs = read_inputs(args.input.as_ref()).map_err(|e| (exit::USAGE, e))?;
+    unlock_vault().await.map_err(invalid)?;
+
+    let run_id = uuid::Uuid::new_v4().to_string();
+    let recorded = !args.no_history && record_start(&run_id, &project_root, &def, &inputs).await;
+    cancel_on_ctrl_c(run_id.clone());
+
+    let target = Target {
+        run_id,
+        project: project_root,
+        store,
+        def,
+    };
+    let events = Arc::new(Throttled::new(Progress(args.progress), DELTA_INTERVAL));
+    let trace = run_target(
+        target,
+        exec_options(args.max_depth),
+        Start::New(inputs),
+        events,
+        recorded,
+    )
+    .await
+    .map_err(invalid)?;
+
+    // 失败时只输出轨迹（指定了 --trace）或什么都不输出，错误已在进度中给出
+    let output = if args.trace {
+        Some(serde_json::to_string_pretty(&trace))
+    } else if trace.status == TraceStatus::Succeeded {
+        Some(serde_json::to_string_pretty(
+            trace.outputs.as_ref().unwrap_or(&Value::Null),
+        ))
+    } else {
+        None
+    };
+    if let Some(output) = output {
+        println!("{}", output.map_err(|e| (exit::FAILED, e.to_string()))?);
+    }
+    if trace.status == TraceStatus::Waiting && recorded {
+        if let Some(run_id) = &trace.run_id {
+            eprintln!("⏸️  运行 {} 等待人工输入，可在应用中答复后继续", run_id);
+        }
+    }
+    Ok(exit_code(&trace))
+}
+
+/// 读取流程输入（JSON5），未指定时为空对象
+fn read_inputs(path: Option<&PathBuf>) -> Result<Value, String> {
+    let Some(path) = path else {
+        return Ok(Value::Object(Default::default()));
+    };
+    let text = if path.as_os_str() == "-" {
+        let mut text = String::new();
+        io::stdin()
+            .read_to_string(&mut text)
+            .map_err(|e| format!("读取标准输入失败: {}", e))?;
+        text
+    } else {
+        std::fs::read_to_string(path).map_err(|e| format!("读取输入文件 {:?} 失败: {}", path, e))?
+    };
+    json5::from_str(&text).map_err(|e| format!("解析流程输入失败: {}", e))
+}
+
+/// 设置了口令环境变量时解锁密钥库
+pub(super) async fn unlock_vault() -> Result<(), String> {
+    let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) else {
+        return Ok(());
+    };
+    let passphrase = Zeroizing::new(passphrase);
+    tokio::task::spawn_blocking(move || GlobalState::get().vault.unlock(&passphrase))
+        .await
+        .map_err(|e| format!("密钥库操作失败: {}", e))?
+        .map_err(|e| format!("解锁密钥库失败: {}", e))
+}
+
+/// Ctrl-C 时取消运行；运行尚未开始时直接退出
+fn cancel_on_ctrl_c(run_id: String) {
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_err() {
+            return;
+        }
+        match GlobalState::get().runs.get(&run_id) {
+            Some(control) => {
+                eprintln!("⏹️  正在取消运行 {}", run_id);
+                control.cancel();
+            }
+            None => std::process::exit(exit::CANCELLED),
+        }
+    });
+}
+
+/// 按运行结果确定退出码
+fn exit_code(trace: &FlowTrace) -> i32 {
+    match trace.status {
+        TraceStatus::Succeeded => exit::OK,
+        TraceStatus::Paused | TraceStatus::Waiting => exit::INCOMPLETE,
+        TraceStatus::Cancelled => exit::CANCELLED,
+        _ => match trace.error_kind {
+            Some(ErrorKind::Invalid) => exit::INVALID,
+            Some(ErrorKind::Provider) => exit::PROVIDER,
+            Some(ErrorKind::Budget) => exit::BUDGET,
+            Some(ErrorKind::Node) | None => exit::FAILED,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_exit_code_by_status_and_error_kind() {
+        let mut trace = FlowTrace::new("f", "1", json!({}));
+        trace.succeed(json!({"ok": true}));
+        assert_eq!(exit_code(&trace), exit::OK);
+
+        let mut trace = FlowTrace::new("f", "1", json!({}));
+        trace.fail("节点失败");
+        assert_eq!(exit_code(&trace), exit::FAILED);
+        trace.error_kind = Some(ErrorKind::Provider);
+        assert_eq!(exit_code(&trace), exit::PROVIDER);
+        trace.error_kind = Some(ErrorKind::Budget);
+        assert_eq!(exit_code(&trace), exit::BUDGET);
+
+        let mut trace = FlowTrace::new("f", "1", json!({}));
+        trace.wait();
+        assert_eq!(exit_code(&trace), exit::INCOMPLETE);
+        trace.cancel();
+        assert_eq!(exit_code(&trace), exit::CANCELLED);
+    }
+}
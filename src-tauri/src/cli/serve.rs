@@ -0,0 +1,44 @@
+//! `vlogi serve`：不创建窗口，只运行本地 HTTP API，直到 Ctrl-C
+
+use super::exit;
+use super::run::unlock_vault;
+use crate::api::DEFAULT_PORT;
+use crate::state::GlobalState;
+
+#[derive(clap::Args, Debug, Clone)]
+#[command(after_help = "\
+The server listens on 127.0.0.1 only. Its URL and bearer token are written to
+api.json in the app config directory.
+
+Set VLOGI_VAULT_PASSPHRASE to unlock the secret vault before serving.")]
+pub struct ServeArgs {
+    /// Port to listen on (0 picks a free port)
+    #[arg(long, default_value_t = DEFAULT_PORT)]
+    pub port: u16,
+}
+
+/// 执行 `vlogi serve`，返回退出码
+pub async fn run(args: ServeArgs) -> i32 {
+    if let Err(e) = unlock_vault().await {
+        eprintln!("❌ {}", e);
+        return exit::INVALID;
+    }
+    let api = &GlobalState::get().api;
+    let status = match api.start(args.port).await {
+        Ok(status) => status,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            return exit::FAILED;
+        }
+    };
+    eprintln!(
+        "✅ 本地 API 已开启: {}（令牌见 {}）",
+        status.url.unwrap_or_default(),
+        status.token_file.unwrap_or_default()
+    );
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        eprintln!("❌ 等待退出信号失败: {}", e);
+    }
+    api.stop().await;
+    exit::OK
+}
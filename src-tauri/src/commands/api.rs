@@ -0,0 +1,44 @@
+// src/commands/api.rs
+use serde_json::Value;
+
+use crate::api::{openapi, ApiConfig, ApiStatus};
+use crate::state::GlobalState;
+
+/// Tauri Command: 查询本地 API 服务状态
+#[tauri::command]
+pub async fn api_status() -> ApiStatus {
+    GlobalState::get().api.status().await
+}
+
+/// Tauri Command: 开启本地 API 服务
+///
+/// 未指定端口时使用配置中的端口。服务已开启时直接返回当前状态。
+#[tauri::command]
+pub async fn api_start(port: Option<u16>) -> Result<ApiStatus, String> {
+    let state = GlobalState::get();
+    let port = match port {
+        Some(port) => port,
+        None => ApiConfig::load(&state.app_db).await?.port,
+    };
+    state.api.start(port).await
+}
+
+/// Tauri Command: 停止本地 API 服务，返回服务之前是否在运行
+#[tauri::command]
+pub async fn api_stop() -> bool {
+    GlobalState::get().api.stop().await
+}
+
+/// Tauri Command: 更换本地 API 的访问令牌，原令牌立即失效
+#[tauri::command]
+pub async fn api_rotate_token() -> Result<(), String> {
+    GlobalState::get().api.rotate_token().await
+}
+
+/// Tauri Command: 生成项目的 OpenAPI 文档
+///
+/// 每个流程对应一个操作，请求与响应结构由流程的输入输出声明生成。
+#[tauri::command]
+pub async fn api_openapi(project: String) -> Result<Value, String> {
+    openapi::generate(&project).await
+}
@@ -0,0 +1,21 @@
+// src/commands/cache.rs
+use crate::flow::cache;
+use crate::project::Project;
+use crate::state::GlobalState;
+
+/// Tauri Command: 清空节点输出缓存
+///
+/// 指定 `project` 时只清空该项目的缓存，否则清空全部；返回删除的条目数。
+#[tauri::command]
+pub async fn cache_clear(project: Option<String>) -> Result<u64, String> {
+    let project = match project {
+        Some(root) => Some(cache_project(&Project::open(root)?)),
+        None => None,
+    };
+    cache::clear(&GlobalState::get().engine_db, project.as_deref()).await
+}
+
+/// 缓存中标识项目的键（规范化后的根目录）
+pub(crate) fn cache_project(project: &Project) -> String {
+    project.root().to_string_lossy().into_owned()
+}
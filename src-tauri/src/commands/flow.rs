@@ -0,0 +1,488 @@
+// src/commands/flow.rs
+use super::cache::cache_project;
+use crate::flow::budget::{self, Budget, BudgetConfig};
+use crate::flow::cache::NodeCache;
+use crate::flow::events::{EventSink, Fanout, FlowEvent, Throttled};
+use crate::flow::exec::{ExecOptions, Executor};
+use crate::flow::history::{RunFilter, RunHistory};
+use crate::flow::human::HumanResponse;
+use crate::flow::model::FlowDef;
+use crate::flow::replay::{self, ReplayReport};
+use crate::flow::store::FlowStore;
+use crate::flow::trace::{now_ms, FlowTrace, TraceStatus};
+use crate::llm::pricing::PriceTable;
+use crate::llm::registry::ProviderRegistry;
+use crate::memory::{MemoryConfig, ScopedMemory};
+use crate::project::Project;
+use crate::state::GlobalState;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+/// 执行事件名
+pub const FLOW_EVENT: &str = "tauri//flow";
+
+/// 流式文本片段的最小发送间隔
+pub(crate) const DELTA_INTERVAL: Duration = Duration::from_millis(50);
+
+/// 把执行事件转发给前端
+struct TauriSink(AppHandle);
+
+impl EventSink for TauriSink {
+    fn emit(&self, event: FlowEvent) {
+        if let Err(e) = self.0.emit(FLOW_EVENT, event) {
+            tracing::warn!("⚠️  发送执行事件失败: {}", e);
+        }
+    }
+}
+
+/// 运行的起点
+pub(crate) enum Start {
+    /// 新运行，携带流程输入
+    New(Value),
+    /// 从保存的轨迹继续
+    Resume(FlowTrace),
+}
+
+/// 要执行的运行
+pub(crate) struct Target {
+    pub run_id: String,
+    /// 项目根目录（与运行历史中一致）
+    pub project: String,
+    pub store: FlowStore,
+    pub def: FlowDef,
+}
+
+/// 在应用中执行一次运行：事件转发给前端，等待人工输入时安排超时
+async fn execute(
+    app: AppHandle,
+    target: Target,
+    options: ExecOptions,
+    start: Start,
+    recorded: bool,
+) -> Result<FlowTrace, String> {
+    let events = Arc::new(Throttled::new(TauriSink(app.clone()), DELTA_INTERVAL));
+    let trace = run_target(target, options, start, events, recorded).await?;
+    if recorded {
+        if let Some(run_id) = &trace.run_id {
+            schedule_input_timeout(app, run_id.clone(), &trace);
+        }
+    }
+    Ok(trace)
+}
+
+/// 执行通过本地 HTTP API 发起的运行：事件同时转发给前端（应用运行时），等待人工输入时安排超时
+pub(crate) async fn run_external(
+    target: Target,
+    options: ExecOptions,
+    start: Start,
+    events: Arc<dyn EventSink>,
+    recorded: bool,
+) -> Result<FlowTrace, String> {
+    let app = GlobalState::get().app_handle.get().await;
+    let mut sinks = vec![events];
+    if let Some(app) = &app {
+        sinks.push(Arc::new(TauriSink(app.clone())));
+    }
+    let events = Arc::new(Throttled::new(Fanout(sinks), DELTA_INTERVAL));
+    let trace = run_target(target, options, start, events, recorded).await?;
+    if let (true, Some(app), Some(run_id)) = (recorded, app, &trace.run_id) {
+        schedule_input_timeout(app, run_id.clone(), &trace);
+    }
+    Ok(trace)
+}
+
+/// 执行一次运行：登记控制句柄、发出事件、启用节点输出缓存、工具（含流程允许的 MCP 服务）、密钥、用户记忆与预算，
+/// 并（在已登记历史时）写入检查点与最终轨迹。命令行与应用共用
+pub(crate) async fn run_target(
+    target: Target,
+    options: ExecOptions,
+    start: Start,
+    events: Arc<dyn EventSink>,
+    recorded: bool,
+) -> Result<FlowTrace, String> {
+    let state = GlobalState::get();
+    let Target {
+        run_id,
+        project,
+        store,
+        def,
+    } = target;
+    let run_id = run_id.as_str();
+    let providers =
+        ProviderRegistry::load(&state.app_db, &state.provider_guards, &state.vault).await?;
+    let prices = PriceTable::load(&state.app_db).await?;
+    let active = state.runs.register(run_id)?;
+    let history = RunHistory::new(&state.engine_db);
+
+    let cache = NodeCache::new(&state.engine_db, cache_project(store.project()));
+    let profile = MemoryConfig::load(&state.app_db).await?.profile;
+    let memory = ScopedMemory::new(&state.engine_db, profile, &project);
+    let tools = (state.mcp)
+        .tools(
+            store.project(),
+            &def.permissions.mcp,
+            &state.tools,
+            &state.vault,
+        )
+        .await;
+    let mut executor = Executor::new(store, providers, options)
+        .with_events(events)
+        .with_control(active.control.clone())
+        .with_cache(Arc::new(cache))
+        .with_prices(prices)
+        .with_tools(tools)
+        .with_secrets(state.vault.clone())
+        .with_memory(Arc::new(memory));
+    if recorded {
+        executor = executor.with_checkpoint(Arc::new(history));
+    }
+    if let Some(config) = BudgetConfig::load(&state.app_db, &project).await? {
+        let spent = match &start {
+            Start::New(_) => 0.0,
+            Start::Resume(previous) => previous.cost(),
+        };
+        let today = history
+            .spent_since(&project, budget::day_start(now_ms()), run_id)
+            .await?;
+        executor = executor.with_budget(Arc::new(Budget::new(config, spent, today)));
+    }
+
+    let trace = match start {
+        Start::New(inputs) => executor.run(run_id, def, inputs).await,
+        Start::Resume(previous) => executor.resume(run_id, def, previous).await,
+    };
+    if recorded {
+        if let Err(e) = history.save(run_id, &trace).await {
+            tracing::warn!("⚠️  保存运行 {} 失败: {}", run_id, e);
+        }
+    }
+    Ok(trace)
+}
+
+/// 运行在等待有期限的人工输入时，到期后自动继续执行（由执行器按默认动作处理）
+fn schedule_input_timeout(app: AppHandle, run_id: String, trace: &FlowTrace) {
+    if trace.status != TraceStatus::Waiting {
+        return;
+    }
+    let Some(deadline) = trace
+        .nodes
+        .iter()
+        .filter_map(|n| n.input.as_ref())
+        .filter(|input| input.response.is_none())
+        .filter_map(|input| input.deadline)
+        .min()
+    else {
+        return;
+    };
+    let delay = Duration::from_millis((deadline - now_ms()).max(0) as u64);
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(delay).await;
+        expire_inputs(app, run_id).await;
+    });
+}
+
+async fn expire_inputs(app: AppHandle, run_id: String) {
+    let state = GlobalState::get();
+    if state.runs.get(&run_id).is_some() {
+        return;
+    }
+    // 期间已答复、取消或再次继续的运行不需要处理
+    let trace = match RunHistory::new(&state.engine_db).get(&run_id).await {
+        Ok(Some(trace)) => trace,
+        Ok(None) => return,
+        Err(e) => {
+            tracing::warn!("⚠️  读取运行 {} 失败: {}", run_id, e);
+            return;
+        }
+    };
+    let now = now_ms();
+    let expired = trace.status == TraceStatus::Waiting
+        && (trace.nodes.iter()).any(|n| n.input.as_ref().is_some_and(|i| i.expired(now)));
+    if !expired {
+        return;
+    }
+    if let Err(e) = resume(app, run_id.clone(), None).await {
+        tracing::warn!("⚠️  运行 {} 的人工输入超时后继续执行失败: {}", run_id, e);
+    }
+}
+
+/// 应用启动时为等待中的运行重新安排超时
+pub async fn restore_waiting_runs(app: AppHandle) {
+    let history = RunHistory::new(&GlobalState::get().engine_db);
+    let filter = RunFilter {
+        status: Some(TraceStatus::Waiting),
+        limit: Some(i64::MAX),
+        ..Default::default()
+    };
+    let runs = match history.list(&filter).await {
+        Ok(runs) => runs,
+        Err(e) => {
+            tracing::warn!("⚠️  读取等待中的运行失败: {}", e);
+            return;
+        }
+    };
+    for run in runs {
+        match history.get(&run.id).await {
+            Ok(Some(trace)) => schedule_input_timeout(app.clone(), run.id, &trace),
+            Ok(None) => {}
+            Err(e) => tracing::warn!("⚠️  读取运行 {} 失败: {}", run.id, e),
+        }
+    }
+}
+
+/// 在运行历史中登记新的运行，返回是否登记成功（失败不影响执行）
+pub(crate) async fn record_start(
+    run_id: &str,
+    project: &str,
+    def: &FlowDef,
+    inputs: &Value,
+) -> bool {
+    let history = RunHistory::new(&GlobalState::get().engine_db);
+    match history.start(run_id, project, def, inputs).await {
+        Ok(()) => true,
+        Err(e) => {
+            tracing::warn!("⚠️  记录运行历史失败: {}", e);
+            false
+        }
+    }
+}
+
+pub(crate) fn exec_options(max_depth: Option<usize>) -> ExecOptions {
+    let mut options = ExecOptions::default();
+    if let Some(depth) = max_depth {
+        options.max_depth = depth;
+    }
+    options
+}
+
+/// Tauri Command: 执行项目中的流程
+///
+/// 返回完整的运行轨迹（包含嵌套子流程），执行失败时错误记录在轨迹的 `error` 字段中。
+/// 执行过程中通过 `tauri//flow` 事件推送进度，事件与轨迹中的 `run_id` 一致，
+/// 轨迹同时写入运行历史，可之后通过 `run_get` 查看；记录失败不影响执行。
+/// `max_depth` 为子流程最大嵌套深度，未指定时使用默认值。
+/// 节点费用按价格表计算；配置了预算时，超出后按配置终止或暂停运行。
+#[tauri::command]
+pub async fn flow_run(
+    app: AppHandle,
+    project: String,
+    flow: String,
+    inputs: Value,
+    max_depth: Option<usize>,
+) -> Result<FlowTrace, String> {
+    let store = FlowStore::new(Project::open(&project)?);
+    let def = store.load_local(&flow)?;
+
+    let run_id = uuid::Uuid::new_v4().to_string();
+    let recorded = record_start(&run_id, &project, &def, &inputs).await;
+
+    let target = Target {
+        run_id,
+        project,
+        store,
+        def,
+    };
+    execute(
+        app,
+        target,
+        exec_options(max_depth),
+        Start::New(inputs),
+        recorded,
+    )
+    .await
+}
+
+/// Tauri Command: 取消运行
+///
+/// 正在执行的运行会中断进行中的模型请求，未完成的节点标记为 `cancelled`；
+/// 已暂停、等待人工输入或因应用退出而中断的运行直接在历史中标记为已取消。返回是否有运行被取消。
+#[tauri::command]
+pub async fn run_cancel(id: String) -> Result<bool, String> {
+    let state = GlobalState::get();
+    if let Some(control) = state.runs.get(&id) {
+        control.cancel();
+        return Ok(true);
+    }
+
+    let history = RunHistory::new(&state.engine_db);
+    let Some(mut trace) = history.get(&id).await? else {
+        return Ok(false);
+    };
+    if !matches!(
+        trace.status,
+        TraceStatus::Paused | TraceStatus::Running | TraceStatus::Waiting
+    ) {
+        return Ok(false);
+    }
+    for node in trace.nodes.iter_mut() {
+        if matches!(
+            node.status,
+            TraceStatus::Pending | TraceStatus::Running | TraceStatus::Waiting
+        ) {
+            node.status = TraceStatus::Cancelled;
+        }
+    }
+    trace.cancel();
+    history.save(&id, &trace).await?;
+    Ok(true)
+}
+
+/// Tauri Command: 暂停运行
+///
+/// 暂停后不再调度新节点，正在执行的节点完成后运行以 `paused` 状态结束。
+/// 返回运行是否正在执行。
+#[tauri::command]
+pub fn run_pause(id: String) -> Result<bool, String> {
+    match GlobalState::get().runs.get(&id) {
+        Some(control) => {
+            control.pause();
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Tauri Command: 继续执行运行
+///
+/// 从运行历史中保存的节点状态继续：已成功的节点不再执行，其余节点重新执行。
+/// 适用于已暂停、已取消、失败以及因应用退出而中断的运行；流程版本变化后不能继续。
+/// 等待人工输入的运行继续后仍会等待（已超时的按默认动作处理）。
+#[tauri::command]
+pub async fn run_resume(
+    app: AppHandle,
+    id: String,
+    max_depth: Option<usize>,
+) -> Result<FlowTrace, String> {
+    resume(app, id, max_depth).await
+}
+
+async fn resume(app: AppHandle, id: String, max_depth: Option<usize>) -> Result<FlowTrace, String> {
+    let state = GlobalState::get();
+    if state.runs.get(&id).is_some() {
+        return Err(format!("运行 {} 仍在执行中，请等待暂停生效后再继续", id));
+    }
+
+    let history = RunHistory::new(&state.engine_db);
+    let summary = history
+        .summary(&id)
+        .await?
+        .ok_or_else(|| format!("运行 {} 不存在", id))?;
+    if summary.status == TraceStatus::Succeeded {
+        return Err(format!("运行 {} 已成功完成", id));
+    }
+    let previous = history
+        .get(&id)
+        .await?
+        .ok_or_else(|| format!("运行 {} 没有保存可继续的节点状态", id))?;
+
+    let store = FlowStore::new(Project::open(&summary.project)?);
+    let def = store.load_local(&summary.flow_id)?;
+    if def.version != summary.flow_version {
+        return Err(format!(
+            "流程 {} 的版本已从 {} 变为 {}，无法继续",
+            def.id, summary.flow_version, def.version
+        ));
+    }
+
+    let target = Target {
+        run_id: id,
+        project: summary.project,
+        store,
+        def,
+    };
+    let options = exec_options(max_depth);
+    execute(app, target, options, Start::Resume(previous), true).await
+}
+
+/// Tauri Command: 提交人工输入
+///
+/// 答复运行中等待的 input 节点：`approve` 通过待审内容，`edit` 以 `value` 作为节点输出
+/// （设置了表单时需符合表单），`reject` 使节点失败。答复写入运行历史后立即继续执行运行，
+/// 返回继续执行后的轨迹。
+#[tauri::command]
+pub async fn run_submit_input(
+    app: AppHandle,
+    id: String,
+    node_id: String,
+    response: HumanResponse,
+) -> Result<FlowTrace, String> {
+    let state = GlobalState::get();
+    if state.runs.get(&id).is_some() {
+        return Err(format!(
+            "运行 {} 仍在执行其它节点，请在其进入等待状态后再提交",
+            id
+        ));
+    }
+
+    let history = RunHistory::new(&state.engine_db);
+    let mut trace = history
+        .get(&id)
+        .await?
+        .ok_or_else(|| format!("运行 {} 不存在", id))?;
+    if trace.status != TraceStatus::Waiting {
+        return Err(format!("运行 {} 没有在等待人工输入", id));
+    }
+    let request = trace
+        .nodes
+        .iter_mut()
+        .filter(|n| n.node_id == node_id && n.status == TraceStatus::Waiting)
+        .find_map(|n| n.input.as_mut())
+        .filter(|input| input.response.is_none())
+        .ok_or_else(|| format!("节点 `{}` 没有等待中的输入", node_id))?;
+    request.check(&response)?;
+    request.response = Some(HumanResponse {
+        responded_at: now_ms(),
+        timed_out: false,
+        ..response
+    });
+    history.save(&id, &trace).await?;
+    tracing::info!("✅ 运行 {} 的节点 {} 已收到人工输入", id, node_id);
+
+    resume(app, id, None).await
+}
+
+/// Tauri Command: 回放运行
+///
+/// 用当前的流程定义重新执行历史运行，模型请求按哈希从录制的响应中应答，不访问真实模型；
+/// 工具声明与调用结果取自录制，不执行工具、不启动 MCP 服务。
+/// 返回回放轨迹及逐节点的差异（提示词变化、状态或输出不同等），差异为空表示行为一致。
+/// 回放通过 `tauri//flow` 事件推送进度（使用新的运行 id），不写入运行历史；用户记忆只读。
+#[tauri::command]
+pub async fn run_replay(app: AppHandle, id: String) -> Result<ReplayReport, String> {
+    let state = GlobalState::get();
+    let history = RunHistory::new(&state.engine_db);
+    let summary = history
+        .summary(&id)
+        .await?
+        .ok_or_else(|| format!("运行 {} 不存在", id))?;
+    let recorded = history
+        .get(&id)
+        .await?
+        .ok_or_else(|| format!("运行 {} 没有保存轨迹", id))?;
+
+    let store = FlowStore::new(Project::open(&summary.project)?);
+    let def = store.load_local(&summary.flow_id)?;
+    let providers = ProviderRegistry::single(Arc::new(replay::cassette(&recorded)));
+
+    let profile = MemoryConfig::load(&state.app_db).await?.profile;
+    let memory = ScopedMemory::new(&state.engine_db, profile, &summary.project).read_only();
+
+    let replay_id = uuid::Uuid::new_v4().to_string();
+    let active = state.runs.register(&replay_id)?;
+    let events = Arc::new(Throttled::new(TauriSink(app), DELTA_INTERVAL));
+    let trace = Executor::new(store, providers, ExecOptions::default())
+        .with_events(events)
+        .with_control(active.control.clone())
+        .with_tool_recording(Arc::new(replay::tool_recording(&recorded)))
+        .with_memory(Arc::new(memory))
+        .run(&replay_id, def, summary.inputs)
+        .await;
+
+    Ok(ReplayReport {
+        source_run_id: id,
+        divergences: replay::compare(&recorded, &trace),
+        trace,
+    })
+}
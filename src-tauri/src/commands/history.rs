@@ -0,0 +1,39 @@
+// src/commands/history.rs
+use crate::flow::history::{RunFilter, RunHistory, RunSummary, SpendFilter, SpendRow};
+use crate::flow::trace::FlowTrace;
+use crate::state::GlobalState;
+
+/// Tauri Command: 按条件列出运行历史（按开始时间倒序）
+#[tauri::command]
+pub async fn run_list(filter: Option<RunFilter>) -> Result<Vec<RunSummary>, String> {
+    let history = RunHistory::new(&GlobalState::get().engine_db);
+    history.list(&filter.unwrap_or_default()).await
+}
+
+/// Tauri Command: 获取一次运行的完整轨迹
+///
+/// 运行不存在或仍在执行中时返回 `null`。
+#[tauri::command]
+pub async fn run_get(id: String) -> Result<Option<FlowTrace>, String> {
+    RunHistory::new(&GlobalState::get().engine_db)
+        .get(&id)
+        .await
+}
+
+/// Tauri Command: 删除运行历史，返回实际删除的条数
+#[tauri::command]
+pub async fn run_delete(ids: Vec<String>) -> Result<u64, String> {
+    RunHistory::new(&GlobalState::get().engine_db)
+        .delete(&ids)
+        .await
+}
+
+/// Tauri Command: 按时间段汇总 token 与费用
+///
+/// 可按项目或流程分组，时间段按 UTC 划分。
+#[tauri::command]
+pub async fn spend_report(filter: Option<SpendFilter>) -> Result<Vec<SpendRow>, String> {
+    RunHistory::new(&GlobalState::get().engine_db)
+        .spend(&filter.unwrap_or_default())
+        .await
+}
@@ -0,0 +1,21 @@
+// src/commands/mcp.rs
+use crate::project::Project;
+use crate::state::GlobalState;
+use crate::tools::mcp::ServerStatus;
+
+/// Tauri Command: 列出项目 `vlogi/mcp.json5` 中声明的 MCP 服务及其运行状态
+#[tauri::command]
+pub async fn mcp_status(project: String) -> Result<Vec<ServerStatus>, String> {
+    let project = Project::open(&project)?;
+    GlobalState::get().mcp.status(&project).await
+}
+
+/// Tauri Command: 重新启动 MCP 服务，返回其提供的工具（agent 节点中引用的名称）
+///
+/// 也用于修改声明后检查服务能否正常启动。
+#[tauri::command]
+pub async fn mcp_restart(project: String, server: String) -> Result<Vec<String>, String> {
+    let project = Project::open(&project)?;
+    let state = GlobalState::get();
+    state.mcp.restart(&project, &server, &state.vault).await
+}
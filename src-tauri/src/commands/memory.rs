@@ -0,0 +1,79 @@
+// src/commands/memory.rs
+use crate::memory::store::{ChangeFilter, MemoryChange, MemoryFilter, MemoryStore};
+use crate::memory::{Memory, MemoryConfig, Origin};
+use crate::state::GlobalState;
+
+async fn current_profile() -> Result<String, String> {
+    Ok(MemoryConfig::load(&GlobalState::get().app_db)
+        .await?
+        .profile)
+}
+
+/// Tauri Command: 按条件列出用户记忆，未指定档案时列出当前档案的记忆
+#[tauri::command]
+pub async fn memory_list(mut filter: MemoryFilter) -> Result<Vec<Memory>, String> {
+    if filter.profile.is_none() {
+        filter.profile = Some(current_profile().await?);
+    }
+    MemoryStore::new(&GlobalState::get().engine_db)
+        .list(&filter)
+        .await
+}
+
+/// Tauri Command: 新增或修改用户记忆，返回保存后的记忆
+///
+/// `id` 为空时新增，`profile` 为空时保存到当前档案。修改已遗忘的记忆会恢复它。
+#[tauri::command]
+pub async fn memory_save(mut memory: Memory) -> Result<Memory, String> {
+    if memory.profile.is_empty() {
+        memory.profile = current_profile().await?;
+    }
+    MemoryStore::new(&GlobalState::get().engine_db)
+        .save(&memory, &Origin::default())
+        .await
+}
+
+/// Tauri Command: 遗忘用户记忆（可撤销），返回记忆是否存在
+#[tauri::command]
+pub async fn memory_forget(id: String) -> Result<bool, String> {
+    MemoryStore::new(&GlobalState::get().engine_db)
+        .forget(&id, &Origin::default())
+        .await
+}
+
+/// Tauri Command: 彻底删除用户记忆及其修改记录（不可撤销），返回记忆是否存在
+#[tauri::command]
+pub async fn memory_purge(id: String) -> Result<bool, String> {
+    MemoryStore::new(&GlobalState::get().engine_db)
+        .purge(&id)
+        .await
+}
+
+/// Tauri Command: 按条件列出用户记忆的修改记录，未指定档案时列出当前档案的修改
+#[tauri::command]
+pub async fn memory_changes(mut filter: ChangeFilter) -> Result<Vec<MemoryChange>, String> {
+    if filter.profile.is_none() && filter.memory_id.is_none() && filter.run_id.is_none() {
+        filter.profile = Some(current_profile().await?);
+    }
+    MemoryStore::new(&GlobalState::get().engine_db)
+        .changes(&filter)
+        .await
+}
+
+/// Tauri Command: 撤销一次修改，恢复修改之前的记忆
+///
+/// 记忆在这之后还有未撤销的修改时报错，需先撤销之后的修改。
+#[tauri::command]
+pub async fn memory_revert(change_id: i64) -> Result<MemoryChange, String> {
+    MemoryStore::new(&GlobalState::get().engine_db)
+        .revert(change_id)
+        .await
+}
+
+/// Tauri Command: 撤销一次运行中 agent 对用户记忆的全部修改，返回撤销的修改数
+#[tauri::command]
+pub async fn memory_revert_run(run_id: String) -> Result<u64, String> {
+    MemoryStore::new(&GlobalState::get().engine_db)
+        .revert_run(&run_id)
+        .await
+}
@@ -1,5 +1,16 @@
-pub mod store;
+pub mod api;
+pub mod cache;
+pub mod flow;
+pub mod history;
 pub mod info;
+pub mod mcp;
+pub mod memory;
+pub mod schedule;
+pub mod store;
+pub mod template;
+pub mod tools;
+pub mod vault;
+pub mod watch;
 
 #[macro_export]
 macro_rules! register_all_commands {
@@ -11,6 +22,47 @@ macro_rules! register_all_commands {
             crate::commands::store::emit_cfg_changed,
             crate::commands::store::emit_focus,
             crate::commands::store::is_pid_valid,
+            crate::commands::template::template_preview,
+            crate::commands::flow::flow_run,
+            crate::commands::flow::run_cancel,
+            crate::commands::flow::run_pause,
+            crate::commands::flow::run_resume,
+            crate::commands::flow::run_submit_input,
+            crate::commands::flow::run_replay,
+            crate::commands::history::run_list,
+            crate::commands::history::run_get,
+            crate::commands::history::run_delete,
+            crate::commands::history::spend_report,
+            crate::commands::cache::cache_clear,
+            crate::commands::tools::tool_list,
+            crate::commands::vault::vault_status,
+            crate::commands::vault::vault_unlock,
+            crate::commands::vault::vault_lock,
+            crate::commands::vault::vault_set,
+            crate::commands::vault::vault_list,
+            crate::commands::vault::vault_get_masked,
+            crate::commands::vault::vault_delete,
+            crate::commands::vault::vault_rotate,
+            crate::commands::api::api_status,
+            crate::commands::api::api_start,
+            crate::commands::api::api_stop,
+            crate::commands::api::api_rotate_token,
+            crate::commands::api::api_openapi,
+            crate::commands::mcp::mcp_status,
+            crate::commands::mcp::mcp_restart,
+            crate::commands::schedule::schedule_list,
+            crate::commands::schedule::schedule_save,
+            crate::commands::schedule::schedule_delete,
+            crate::commands::watch::watch_list,
+            crate::commands::watch::watch_save,
+            crate::commands::watch::watch_delete,
+            crate::commands::memory::memory_list,
+            crate::commands::memory::memory_save,
+            crate::commands::memory::memory_forget,
+            crate::commands::memory::memory_purge,
+            crate::commands::memory::memory_changes,
+            crate::commands::memory::memory_revert,
+            crate::commands::memory::memory_revert_run,
         ]
     };
 }
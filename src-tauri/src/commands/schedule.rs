@@ -0,0 +1,31 @@
+// src/commands/schedule.rs
+use crate::state::GlobalState;
+use crate::trigger::schedule::{validate, Schedule, ScheduleStore};
+
+/// Tauri Command: 列出定时计划（可按项目过滤），包含上一次与下一次执行时间
+#[tauri::command]
+pub async fn schedule_list(project: Option<String>) -> Result<Vec<Schedule>, String> {
+    let store = ScheduleStore::new(&GlobalState::get().engine_db);
+    store.list(project.as_deref()).await
+}
+
+/// Tauri Command: 新增或修改定时计划，返回保存后的计划
+///
+/// `id` 为空时新增。下一次执行时间从现在起按 cron 表达式重新计算，停用的计划不会执行。
+#[tauri::command]
+pub async fn schedule_save(schedule: Schedule) -> Result<Schedule, String> {
+    validate(&schedule)?;
+    let state = GlobalState::get();
+    let saved = ScheduleStore::new(&state.engine_db).save(&schedule).await?;
+    state.scheduler.wake();
+    Ok(saved)
+}
+
+/// Tauri Command: 删除定时计划，已触发的运行不受影响。返回计划是否存在
+#[tauri::command]
+pub async fn schedule_delete(id: String) -> Result<bool, String> {
+    let state = GlobalState::get();
+    let deleted = ScheduleStore::new(&state.engine_db).delete(&id).await?;
+    state.scheduler.wake();
+    Ok(deleted)
+}
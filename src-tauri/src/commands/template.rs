@@ -0,0 +1,33 @@
+// src/commands/template.rs
+use crate::flow::template::{self, TemplateError};
+use serde::Serialize;
+use serde_json::Value;
+
+#[derive(Serialize)]
+pub struct TemplatePreviewResponse {
+    /// 渲染结果，出错时为 None
+    pub output: Option<String>,
+    /// 解析或渲染错误（带行列位置），成功时为 None
+    pub error: Option<TemplateError>,
+}
+
+/// Tauri Command: 预览提示词模板
+///
+/// 使用前端提供的示例数据渲染模板，不调用任何模型。
+/// 示例数据应遵循执行时的约定：`{ "inputs": {...}, "nodes": { "<node_id>": ... } }`
+#[tauri::command]
+pub fn template_preview(template: String, data: Value) -> TemplatePreviewResponse {
+    match template::render(&template, &data) {
+        Ok(output) => TemplatePreviewResponse {
+            output: Some(output),
+            error: None,
+        },
+        Err(e) => {
+            tracing::debug!("模板预览失败: {}", e);
+            TemplatePreviewResponse {
+                output: None,
+                error: Some(e),
+            }
+        }
+    }
+}
@@ -0,0 +1,9 @@
+// src/commands/tools.rs
+use crate::llm::ToolSpec;
+use crate::state::GlobalState;
+
+/// Tauri Command: 列出 agent 节点可用的后端工具
+#[tauri::command]
+pub async fn tool_list() -> Result<Vec<ToolSpec>, String> {
+    Ok(GlobalState::get().tools.list())
+}
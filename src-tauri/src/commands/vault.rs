@@ -0,0 +1,79 @@
+// src/commands/vault.rs
+use crate::state::GlobalState;
+use crate::vault::{SecretInfo, VaultStatus};
+use zeroize::Zeroizing;
+
+/// 在阻塞线程中执行（口令派生密钥较耗时）
+async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| format!("密钥库操作失败: {}", e))?
+}
+
+/// Tauri Command: 查询密钥库状态
+#[tauri::command]
+pub fn vault_status() -> VaultStatus {
+    GlobalState::get().vault.status()
+}
+
+/// Tauri Command: 解锁密钥库
+///
+/// 密钥库尚不存在时以该口令创建。返回全部密钥（值已打码）。
+#[tauri::command]
+pub async fn vault_unlock(passphrase: String) -> Result<Vec<SecretInfo>, String> {
+    let passphrase = Zeroizing::new(passphrase);
+    blocking(move || {
+        let vault = &GlobalState::get().vault;
+        vault.unlock(&passphrase)?;
+        vault.list()
+    })
+    .await
+}
+
+/// Tauri Command: 锁定密钥库，清除内存中的明文
+#[tauri::command]
+pub fn vault_lock() {
+    GlobalState::get().vault.lock();
+}
+
+/// Tauri Command: 新增或替换密钥，返回打码后的值
+#[tauri::command]
+pub fn vault_set(name: String, value: String) -> Result<SecretInfo, String> {
+    let value = Zeroizing::new(value);
+    GlobalState::get().vault.set(&name, &value)
+}
+
+/// Tauri Command: 列出全部密钥（值已打码）
+#[tauri::command]
+pub fn vault_list() -> Result<Vec<SecretInfo>, String> {
+    GlobalState::get().vault.list()
+}
+
+/// Tauri Command: 获取打码后的密钥，明文不会返回给前端
+#[tauri::command]
+pub fn vault_get_masked(name: String) -> Result<SecretInfo, String> {
+    GlobalState::get().vault.masked(&name)
+}
+
+/// Tauri Command: 删除密钥，返回密钥是否存在
+#[tauri::command]
+pub fn vault_delete(name: String) -> Result<bool, String> {
+    GlobalState::get().vault.delete(&name)
+}
+
+/// Tauri Command: 更换密钥库口令
+///
+/// 需要密钥库已解锁，并再次输入当前口令；全部密钥以新口令派生的密钥重新加密。
+#[tauri::command]
+pub async fn vault_rotate(passphrase: String, new_passphrase: String) -> Result<(), String> {
+    let passphrase = Zeroizing::new(passphrase);
+    let new_passphrase = Zeroizing::new(new_passphrase);
+    blocking(move || {
+        GlobalState::get()
+            .vault
+            .rotate(&passphrase, &new_passphrase)
+    })
+    .await
+}
@@ -0,0 +1,30 @@
+// src/commands/watch.rs
+use crate::state::GlobalState;
+use crate::trigger::watch::{validate, FolderWatch, WatchStore};
+
+/// Tauri Command: 列出目录监听（可按项目过滤），包含上一次执行的运行与错误
+#[tauri::command]
+pub async fn watch_list(project: Option<String>) -> Result<Vec<FolderWatch>, String> {
+    let store = WatchStore::new(&GlobalState::get().engine_db);
+    store.list(project.as_deref()).await
+}
+
+/// Tauri Command: 新增或修改目录监听，返回保存后的监听
+///
+/// `id` 为空时新增。保存后立即按新的设置重新监听；目录中已有的文件不会处理。
+#[tauri::command]
+pub async fn watch_save(watch: FolderWatch) -> Result<FolderWatch, String> {
+    validate(&watch)?;
+    let state = GlobalState::get();
+    let saved = WatchStore::new(&state.engine_db).save(&watch).await?;
+    state.watches.reload(&saved.id).await?;
+    Ok(saved)
+}
+
+/// Tauri Command: 停止并删除目录监听，已触发的运行与写入的输出不受影响。返回监听是否存在
+#[tauri::command]
+pub async fn watch_delete(id: String) -> Result<bool, String> {
+    let state = GlobalState::get();
+    state.watches.stop(&id);
+    WatchStore::new(&state.engine_db).delete(&id).await
+}
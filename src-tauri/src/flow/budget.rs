@@ -0,0 +1,145 @@
+//! 运行预算：限制单次运行与项目每日的费用
+//!
+//! 预算配置保存在 app.db 的 config 表中（key = `budget`），值形如：
+//! `{ "project": "/path/to/project", "per_run": 0.5, "per_day": 5, "action": "pause" }`。
+//! 省略 `project` 的配置对所有项目生效，项目自己的配置优先。每日按 UTC 自然日统计。
+//! 执行器每调度一批节点前检查一次，超出后按 `action` 终止或暂停运行；agent 节点每次调用模型前也检查，
+//! 节点内超出时（工具调用或结构化输出重试中）同样按 `action` 处理：暂停时该节点保持待执行，继续后重新执行。
+
+use serde::Deserialize;
+use std::sync::Mutex;
+
+use crate::utils::appdb::AppDb;
+
+/// config 表中预算配置的 key
+pub const CONFIG_KEY: &str = "budget";
+
+/// 超出预算后的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetAction {
+    /// 终止运行（运行失败）
+    #[default]
+    Abort,
+    /// 暂停运行，提高预算后可继续
+    Pause,
+}
+
+/// 预算配置
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct BudgetConfig {
+    #[serde(default)]
+    pub project: Option<String>,
+    /// 单次运行的费用上限
+    #[serde(default)]
+    pub per_run: Option<f64>,
+    /// 项目每日的费用上限
+    #[serde(default)]
+    pub per_day: Option<f64>,
+    #[serde(default)]
+    pub action: BudgetAction,
+}
+
+impl BudgetConfig {
+    /// 读取对项目生效的预算配置
+    pub async fn load(app_db: &AppDb, project: &str) -> Result<Option<Self>, String> {
+        let items = app_db.get_configs_by_key(CONFIG_KEY).await?;
+        let mut global = None;
+        for item in items {
+            let config: BudgetConfig = match serde_json::from_value(item.value) {
+                Ok(c) => c,
+                Err(e) => {
+                    tracing::warn!("⚠️  预算配置 {} 格式错误，已忽略: {}", item.id, e);
+                    continue;
+                }
+            };
+            match config.project.as_deref() {
+                Some(p) if p == project => return Ok(Some(config)),
+                Some(_) => {}
+                None => global = global.or(Some(config)),
+            }
+        }
+        Ok(global)
+    }
+}
+
+/// 一次运行的预算状态
+#[derive(Debug)]
+pub struct Budget {
+    config: BudgetConfig,
+    /// 项目当天其它运行已花费的费用
+    spent_today: f64,
+    /// 本次运行已花费的费用
+    spent: Mutex<f64>,
+}
+
+impl Budget {
+    /// `spent` 为本次运行之前已花费的费用（继续执行时不为零）
+    pub fn new(config: BudgetConfig, spent: f64, spent_today: f64) -> Self {
+        Self {
+            config,
+            spent_today,
+            spent: Mutex::new(spent),
+        }
+    }
+
+    pub fn action(&self) -> BudgetAction {
+        self.config.action
+    }
+
+    /// 记录一次调用的费用
+    pub fn charge(&self, cost: f64) {
+        *self.spent.lock().unwrap_or_else(|e| e.into_inner()) += cost;
+    }
+
+    pub fn spent(&self) -> f64 {
+        *self.spent.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 超出预算时返回原因
+    pub fn exceeded(&self) -> Option<String> {
+        let spent = self.spent();
+        if let Some(limit) = self.config.per_run.filter(|limit| spent > *limit) {
+            return Some(format!(
+                "本次运行已花费 {:.4}，超过单次上限 {}",
+                spent, limit
+            ));
+        }
+        let today = self.spent_today + spent;
+        if let Some(limit) = self.config.per_day.filter(|limit| today > *limit) {
+            return Some(format!(
+                "项目今日已花费 {:.4}，超过每日上限 {}",
+                today, limit
+            ));
+        }
+        None
+    }
+}
+
+/// 当天（UTC）零点的时间戳（毫秒）
+pub fn day_start(now_ms: i64) -> i64 {
+    now_ms - now_ms.rem_euclid(24 * 3600 * 1000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_limits() {
+        let config = BudgetConfig {
+            per_run: Some(1.0),
+            per_day: Some(5.0),
+            ..Default::default()
+        };
+        let budget = Budget::new(config.clone(), 0.5, 3.0);
+        assert_eq!(budget.exceeded(), None);
+        budget.charge(0.6);
+        assert!(budget.exceeded().unwrap().contains("单次上限"));
+
+        let budget = Budget::new(config, 0.0, 4.5);
+        budget.charge(0.8);
+        assert!(budget.exceeded().unwrap().contains("每日上限"));
+        assert_eq!(day_start(86_400_000 + 5), 86_400_000);
+    }
+}
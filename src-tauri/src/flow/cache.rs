@@ -0,0 +1,287 @@
+//! 节点输出缓存：按内容寻址，保存在 engine.db 的 `node_cache` 表
+//!
+//! 缓存键是 provider 名称、完整请求（渲染后的提示词、模型与参数）以及输出 Schema 的哈希，
+//! 任一项变化都会得到新的键。缓存按项目隔离，条目到期后失效，总大小超过上限时
+//! 按最近命中时间淘汰。
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use sqlx::Row;
+
+use super::exec::OutputCache;
+use super::trace::{now_ms, ProviderCall};
+use crate::llm::ChatRequest;
+use crate::utils::enginedb::EngineDb;
+
+/// 默认有效期（秒）
+pub const DEFAULT_TTL: u64 = 7 * 24 * 3600;
+
+/// 缓存总大小上限（字节）
+pub const DEFAULT_MAX_BYTES: i64 = 64 * 1024 * 1024;
+
+/// 缓存的节点结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedOutput {
+    pub output: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attempts: Option<u32>,
+    /// 产生该结果的模型调用，命中时写入轨迹以便回放
+    #[serde(default)]
+    pub calls: Vec<ProviderCall>,
+}
+
+/// 计算缓存键
+pub fn cache_key(provider: &str, request: &ChatRequest, schema: Option<&Value>) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(provider.as_bytes());
+    hasher.update([0]);
+    hasher.update(request.hash().as_bytes());
+    hasher.update([0]);
+    if let Some(schema) = schema {
+        hasher.update(schema.to_string().as_bytes());
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// 某个项目的节点输出缓存
+#[derive(Debug, Clone)]
+pub struct NodeCache<'a> {
+    db: &'a EngineDb,
+    project: String,
+    max_bytes: i64,
+}
+
+impl<'a> NodeCache<'a> {
+    pub fn new(db: &'a EngineDb, project: impl Into<String>) -> Self {
+        Self {
+            db,
+            project: project.into(),
+            max_bytes: DEFAULT_MAX_BYTES,
+        }
+    }
+
+    /// 设置缓存总大小上限（所有项目合计）
+    #[cfg(test)]
+    pub fn with_max_bytes(mut self, max_bytes: i64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// 读取未过期的条目，命中时更新命中时间
+    pub async fn get(&self, key: &str) -> Result<Option<CachedOutput>, String> {
+        let pool = self.db.pool().await?;
+        let now = now_ms();
+        let row = sqlx::query(
+            "SELECT entry FROM node_cache WHERE project = ? AND key = ? AND expires_at > ?",
+        )
+        .bind(&self.project)
+        .bind(key)
+        .bind(now)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("读取节点缓存失败: {}", e))?;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        sqlx::query(
+            "UPDATE node_cache SET last_hit_at = ?, hits = hits + 1 WHERE project = ? AND key = ?",
+        )
+        .bind(now)
+        .bind(&self.project)
+        .bind(key)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("更新节点缓存失败: {}", e))?;
+        let entry: String = row.get("entry");
+        serde_json::from_str(&entry)
+            .map(Some)
+            .map_err(|e| format!("解析节点缓存失败: {}", e))
+    }
+
+    /// 写入条目（`ttl` 为秒），随后清理过期条目并按大小上限淘汰
+    pub async fn put(
+        &self,
+        key: &str,
+        node_id: &str,
+        entry: &CachedOutput,
+        ttl: u64,
+    ) -> Result<(), String> {
+        let entry = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+        let now = now_ms();
+        let pool = self.db.pool().await?;
+        sqlx::query(
+            "INSERT OR REPLACE INTO node_cache
+                 (project, key, node_id, entry, size, created_at, expires_at, last_hit_at, hits)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, 0)",
+        )
+        .bind(&self.project)
+        .bind(key)
+        .bind(node_id)
+        .bind(&entry)
+        .bind(entry.len() as i64)
+        .bind(now)
+        .bind(now.saturating_add(ttl.saturating_mul(1000) as i64))
+        .bind(now)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("写入节点缓存失败: {}", e))?;
+
+        sqlx::query(
+            "DELETE FROM node_cache WHERE expires_at <= ?;
+             DELETE FROM node_cache WHERE rowid IN (
+                 SELECT rowid FROM (
+                     SELECT rowid, SUM(size) OVER (ORDER BY last_hit_at DESC, rowid DESC) AS total
+                     FROM node_cache
+                 ) WHERE total > ?
+             );",
+        )
+        .bind(now)
+        .bind(self.max_bytes)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("清理节点缓存失败: {}", e))?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl OutputCache for NodeCache<'static> {
+    async fn get(&self, key: &str) -> Option<CachedOutput> {
+        match NodeCache::get(self, key).await {
+            Ok(entry) => entry,
+            Err(e) => {
+                tracing::warn!("⚠️  {}", e);
+                None
+            }
+        }
+    }
+
+    async fn put(&self, key: &str, node_id: &str, entry: &CachedOutput, ttl: u64) {
+        if let Err(e) = NodeCache::put(self, key, node_id, entry, ttl).await {
+            tracing::warn!("⚠️  {}", e);
+        }
+    }
+}
+
+/// 清空缓存，`project` 为空时清空所有项目；返回删除的条目数
+pub async fn clear(db: &EngineDb, project: Option<&str>) -> Result<u64, String> {
+    let pool = db.pool().await?;
+    let result = match project {
+        Some(project) => {
+            sqlx::query("DELETE FROM node_cache WHERE project = ?")
+                .bind(project)
+                .execute(pool)
+                .await
+        }
+        None => sqlx::query("DELETE FROM node_cache").execute(pool).await,
+    };
+    result
+        .map(|r| r.rows_affected())
+        .map_err(|e| format!("清空节点缓存失败: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow::exec::{ExecOptions, Executor};
+    use crate::flow::store::FlowStore;
+    use crate::llm::registry::ProviderRegistry;
+    use crate::llm::{ChatResponse, LlmError, Provider};
+    use crate::project::Project;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[derive(Default)]
+    struct Counting(AtomicUsize);
+
+    #[async_trait]
+    impl Provider for Counting {
+        fn name(&self) -> &str {
+            "counting"
+        }
+
+        async fn chat(&self, _request: &ChatRequest) -> Result<ChatResponse, LlmError> {
+            let n = self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(ChatResponse {
+                content: format!("answer {}", n),
+                ..Default::default()
+            })
+        }
+    }
+
+    fn entry(text: &str) -> CachedOutput {
+        CachedOutput {
+            output: json!(text),
+            response: Some(text.to_string()),
+            attempts: None,
+            calls: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cache_ttl_eviction_and_clear() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = EngineDb::with_path(dir.path().join("engine.db"));
+        let size = serde_json::to_string(&entry("aaaa")).unwrap().len() as i64;
+        let cache = NodeCache::new(&db, "/p").with_max_bytes(size * 2);
+
+        cache.put("k1", "a", &entry("aaaa"), 60).await.unwrap();
+        cache.put("expired", "a", &entry("aaaa"), 0).await.unwrap();
+        assert_eq!(cache.get("k1").await.unwrap(), Some(entry("aaaa")));
+        assert_eq!(cache.get("expired").await.unwrap(), None);
+        assert_eq!(NodeCache::new(&db, "/other").get("k1").await.unwrap(), None);
+
+        // 超过大小上限时淘汰最久未命中的条目
+        cache.put("k2", "b", &entry("bbbb"), 60).await.unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        cache.get("k1").await.unwrap();
+        cache.put("k3", "c", &entry("cccc"), 60).await.unwrap();
+        assert!(cache.get("k1").await.unwrap().is_some());
+        assert_eq!(cache.get("k2").await.unwrap(), None);
+
+        assert_eq!(clear(&db, Some("/p")).await.unwrap(), 2);
+        assert_eq!(cache.get("k3").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_cached_nodes_skip_provider() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("vlogi")).unwrap();
+        std::fs::write(dir.path().join("vlogi/meta.json5"), "{}").unwrap();
+        let db: &'static EngineDb =
+            Box::leak(Box::new(EngineDb::with_path(dir.path().join("engine.db"))));
+        let provider = Arc::new(Counting::default());
+        let flow = |last: &str| {
+            serde_json::from_value(json!({
+                "id": "f",
+                "nodes": [
+                    { "id": "a", "type": "agent", "model": "m", "prompt": "first", "cache": true },
+                    { "id": "b", "type": "agent", "model": "m", "prompt": last, "cache": true }
+                ],
+                "edges": [{ "from": "a", "to": "b" }]
+            }))
+            .unwrap()
+        };
+        let run = |last: &'static str| {
+            let store = FlowStore::new(Project::open(dir.path()).unwrap());
+            let providers = ProviderRegistry::single(provider.clone());
+            let exec = Executor::new(store, providers, ExecOptions::default())
+                .with_cache(Arc::new(NodeCache::new(db, "/p")));
+            async move { exec.run("r", flow(last), json!({})).await }
+        };
+
+        run("second {{ nodes.a }}").await;
+        let trace = run("changed {{ nodes.a }}").await;
+        assert_eq!(provider.0.load(Ordering::SeqCst), 3);
+        assert!(trace.nodes[0].cached);
+        assert_eq!(trace.nodes[0].output, Some(json!("answer 0")));
+        assert!(!trace.nodes[1].cached);
+        assert_eq!(trace.nodes[1].output, Some(json!("answer 2")));
+    }
+}
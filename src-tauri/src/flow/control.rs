@@ -0,0 +1,95 @@
+//! 运行控制：取消与暂停
+//!
+//! 取消会中断正在进行的模型请求；暂停只阻止调度新的节点，已在执行的节点完成后运行以
+//! `paused` 状态结束，之后可以从运行历史中保存的节点状态继续执行。
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio_util::sync::CancellationToken;
+
+/// 单次运行的控制句柄（可廉价克隆）
+#[derive(Debug, Clone, Default)]
+pub struct RunControl {
+    cancel: CancellationToken,
+    paused: Arc<AtomicBool>,
+}
+
+impl RunControl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    /// 取消时完成
+    pub async fn cancelled(&self) {
+        self.cancel.cancelled().await
+    }
+
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+}
+
+/// 正在执行的运行（run_id → 控制句柄）
+#[derive(Debug, Default)]
+pub struct ActiveRuns {
+    runs: Mutex<HashMap<String, RunControl>>,
+}
+
+impl ActiveRuns {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 登记运行，返回的守卫在运行结束（drop）时自动注销
+    pub fn register(&self, run_id: &str) -> Result<ActiveRun<'_>, String> {
+        let mut runs = self.runs.lock().unwrap_or_else(|e| e.into_inner());
+        if runs.contains_key(run_id) {
+            return Err(format!("运行 {} 正在执行中", run_id));
+        }
+        let control = RunControl::new();
+        runs.insert(run_id.to_string(), control.clone());
+        Ok(ActiveRun {
+            runs: self,
+            run_id: run_id.to_string(),
+            control,
+        })
+    }
+
+    pub fn get(&self, run_id: &str) -> Option<RunControl> {
+        self.runs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(run_id)
+            .cloned()
+    }
+}
+
+/// 已登记的运行
+pub struct ActiveRun<'a> {
+    runs: &'a ActiveRuns,
+    run_id: String,
+    pub control: RunControl,
+}
+
+impl Drop for ActiveRun<'_> {
+    fn drop(&mut self) {
+        self.runs
+            .runs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&self.run_id);
+    }
+}
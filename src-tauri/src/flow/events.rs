@@ -0,0 +1,295 @@
+//! 执行事件：执行器在运行过程中发出的进度事件
+//!
+//! 执行器只依赖 [`EventSink`]，由调用方决定事件去向（commands 层转发为 Tauri 事件）。
+//! 节点以路径标识：根流程中为节点 id，子流程中的节点为 `<子流程节点id>/<节点id>`，
+//! 循环第 n 轮中的节点为 `<循环节点id>/<n>/<节点id>`，map 的元素为 `<map节点id>/<序号>/<节点id>`。
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::trace::TraceStatus;
+use crate::llm::Usage;
+
+/// 执行事件，以 `type` 字段区分
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FlowEvent {
+    RunStarted {
+        run_id: String,
+        flow_id: String,
+        flow_version: String,
+    },
+    /// 上游均已完成，节点进入执行队列
+    NodeQueued {
+        run_id: String,
+        path: String,
+        node_id: String,
+        kind: String,
+    },
+    NodeStarted {
+        run_id: String,
+        path: String,
+        node_id: String,
+    },
+    /// 模型流式输出的文本片段（经节流合并）
+    TokenDelta {
+        run_id: String,
+        path: String,
+        node_id: String,
+        delta: String,
+    },
+    NodeFinished {
+        run_id: String,
+        path: String,
+        node_id: String,
+        output: Value,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        usage: Option<Usage>,
+        /// 输出来自节点缓存
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        cached: bool,
+    },
+    NodeFailed {
+        run_id: String,
+        path: String,
+        node_id: String,
+        error: String,
+    },
+    /// map 节点的一个元素执行结束
+    MapProgress {
+        run_id: String,
+        path: String,
+        node_id: String,
+        index: usize,
+        completed: usize,
+        failed: usize,
+        total: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    /// input 节点等待人工答复，运行随后以 `waiting` 状态结束
+    InputRequested {
+        run_id: String,
+        path: String,
+        node_id: String,
+        prompt: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        value: Option<Value>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        form: Option<Value>,
+        /// 答复期限（毫秒）
+        #[serde(skip_serializing_if = "Option::is_none")]
+        deadline: Option<i64>,
+    },
+    /// 因上游失败或分支未选中而未执行
+    NodeSkipped {
+        run_id: String,
+        path: String,
+        node_id: String,
+    },
+    /// 运行取消时正在执行或尚未执行的节点
+    NodeCancelled {
+        run_id: String,
+        path: String,
+        node_id: String,
+    },
+    RunFinished {
+        run_id: String,
+        status: TraceStatus,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        outputs: Option<Value>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+}
+
+impl FlowEvent {
+    fn run_id(&self) -> &str {
+        match self {
+            FlowEvent::RunStarted { run_id, .. }
+            | FlowEvent::NodeQueued { run_id, .. }
+            | FlowEvent::NodeStarted { run_id, .. }
+            | FlowEvent::TokenDelta { run_id, .. }
+            | FlowEvent::NodeFinished { run_id, .. }
+            | FlowEvent::NodeFailed { run_id, .. }
+            | FlowEvent::MapProgress { run_id, .. }
+            | FlowEvent::InputRequested { run_id, .. }
+            | FlowEvent::NodeSkipped { run_id, .. }
+            | FlowEvent::NodeCancelled { run_id, .. }
+            | FlowEvent::RunFinished { run_id, .. } => run_id,
+        }
+    }
+
+    fn path(&self) -> Option<&str> {
+        match self {
+            FlowEvent::NodeQueued { path, .. }
+            | FlowEvent::NodeStarted { path, .. }
+            | FlowEvent::TokenDelta { path, .. }
+            | FlowEvent::NodeFinished { path, .. }
+            | FlowEvent::NodeFailed { path, .. }
+            | FlowEvent::MapProgress { path, .. }
+            | FlowEvent::InputRequested { path, .. }
+            | FlowEvent::NodeSkipped { path, .. }
+            | FlowEvent::NodeCancelled { path, .. } => Some(path),
+            FlowEvent::RunStarted { .. } | FlowEvent::RunFinished { .. } => None,
+        }
+    }
+}
+
+/// 事件接收器
+pub trait EventSink: Send + Sync {
+    fn emit(&self, event: FlowEvent);
+}
+
+/// 丢弃全部事件
+pub struct NoopSink;
+
+impl EventSink for NoopSink {
+    fn emit(&self, _event: FlowEvent) {}
+}
+
+/// 把事件发给多个接收器
+pub struct Fanout(pub Vec<Arc<dyn EventSink>>);
+
+impl EventSink for Fanout {
+    fn emit(&self, event: FlowEvent) {
+        for sink in &self.0 {
+            sink.emit(event.clone());
+        }
+    }
+}
+
+/// 尚未发出的文本片段
+struct Pending {
+    node_id: String,
+    text: String,
+    last_flush: Option<Instant>,
+}
+
+/// 对 `token_delta` 节流：同一节点的片段在间隔内合并为一条事件，
+/// 节点结束或运行结束前先发出剩余片段，保证顺序不变
+pub struct Throttled<S> {
+    inner: S,
+    interval: Duration,
+    pending: Mutex<HashMap<(String, String), Pending>>,
+}
+
+impl<S: EventSink> Throttled<S> {
+    pub fn new(inner: S, interval: Duration) -> Self {
+        Self {
+            inner,
+            interval,
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn delta(run_id: String, path: String, pending: &mut Pending) -> FlowEvent {
+        FlowEvent::TokenDelta {
+            run_id,
+            path,
+            node_id: pending.node_id.clone(),
+            delta: std::mem::take(&mut pending.text),
+        }
+    }
+}
+
+impl<S: EventSink> EventSink for Throttled<S> {
+    fn emit(&self, event: FlowEvent) {
+        let mut flush = Vec::new();
+        {
+            let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+            if let FlowEvent::TokenDelta {
+                run_id,
+                path,
+                node_id,
+                delta,
+            } = event
+            {
+                let key = (run_id, path);
+                let entry = pending.entry(key.clone()).or_insert_with(|| Pending {
+                    node_id,
+                    text: String::new(),
+                    last_flush: None,
+                });
+                entry.text.push_str(&delta);
+                let due = entry
+                    .last_flush
+                    .is_none_or(|last| last.elapsed() >= self.interval);
+                if due {
+                    entry.last_flush = Some(Instant::now());
+                    flush.push(Self::delta(key.0, key.1, entry));
+                }
+            } else {
+                // 其它事件之前先发出该节点（运行结束时为整个运行）剩余的片段
+                let run_id = event.run_id().to_string();
+                let path = event.path().map(str::to_string);
+                let keys: Vec<_> = pending
+                    .keys()
+                    .filter(|(r, p)| *r == run_id && path.as_ref().is_none_or(|path| p == path))
+                    .cloned()
+                    .collect();
+                for key in keys {
+                    if let Some(mut entry) = pending.remove(&key) {
+                        if !entry.text.is_empty() {
+                            flush.push(Self::delta(key.0, key.1, &mut entry));
+                        }
+                    }
+                }
+                flush.push(event);
+            }
+        }
+        for event in flush {
+            self.inner.emit(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[derive(Clone, Default)]
+    struct Collect(Arc<Mutex<Vec<FlowEvent>>>);
+
+    impl EventSink for Collect {
+        fn emit(&self, event: FlowEvent) {
+            self.0.lock().unwrap().push(event);
+        }
+    }
+
+    fn delta(text: &str) -> FlowEvent {
+        FlowEvent::TokenDelta {
+            run_id: "r".to_string(),
+            path: "a".to_string(),
+            node_id: "a".to_string(),
+            delta: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_throttle_merges_deltas_and_flushes_before_finish() {
+        let collect = Collect::default();
+        let sink = Throttled::new(collect.clone(), Duration::from_secs(60));
+        for text in ["He", "llo", ", ", "world"] {
+            sink.emit(delta(text));
+        }
+        sink.emit(FlowEvent::NodeFinished {
+            run_id: "r".to_string(),
+            path: "a".to_string(),
+            node_id: "a".to_string(),
+            output: Value::String("Hello, world".to_string()),
+            usage: None,
+            cached: false,
+        });
+
+        let events = collect.0.lock().unwrap().clone();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0], delta("He"));
+        assert_eq!(events[1], delta("llo, world"));
+        assert!(matches!(events[2], FlowEvent::NodeFinished { .. }));
+    }
+}
@@ -0,0 +1,296 @@
+//! Agent 节点：渲染提示词并调用模型
+//!
+//! 声明了 `output_schema` 时开启 JSON mode，输出经宽松解析与 Schema 校验后以 JSON 值传给下游；
+//! 校验失败会把错误反馈给模型重试，最多 `max_attempts` 次。
+//! 声明了 `cache` 时先按请求查找节点输出缓存，命中则不调用模型。
+//! 声明了 `tools` 时模型可以调用已注册的后端工具，工具结果反馈给模型后继续对话，
+//! 最多 `max_tool_iterations` 轮；调用工具的节点不使用缓存。回放时工具声明与调用结果取自录制。
+//! 声明了 `memory` 且运行启用了用户记忆时，按提示词（或 `memory.query`）检索相关记忆，
+//! 在 token 上限内加入系统提示词，注入的记忆 id 记录在轨迹中。
+
+use serde_json::Value;
+
+use super::{ExecError, Executor, Frame};
+use crate::flow::cache::{self, CachedOutput};
+use crate::flow::events::FlowEvent;
+use crate::flow::model::AgentNode;
+use crate::flow::structured::{self, OutputSchema};
+use crate::flow::template;
+use crate::flow::trace::{now_ms, NodeTrace, ProviderCall, ToolCallTrace};
+use crate::llm::{ChatMessage, ChatRequest, ResponseFormat, Usage};
+use crate::tools::ToolContext;
+
+pub(super) async fn run(
+    exec: &Executor,
+    node_id: &str,
+    agent: &AgentNode,
+    scope: &Value,
+    frame: &Frame,
+    trace: &mut NodeTrace,
+) -> Result<Value, ExecError> {
+    let render = |source: &str| {
+        template::render(source, scope).map_err(|error| ExecError::Template {
+            node: node_id.to_string(),
+            error,
+        })
+    };
+
+    let schema = agent
+        .output_schema
+        .as_ref()
+        .map(OutputSchema::compile)
+        .transpose()
+        .map_err(|message| ExecError::Node {
+            node: node_id.to_string(),
+            message,
+        })?;
+
+    let prompt = render(&agent.prompt)?;
+    let mut system = agent.system.as_deref().map(render).transpose()?;
+    if let (Some(options), Some(memory)) = (&agent.memory, exec.memory()) {
+        let query = match &options.query {
+            Some(query) => render(query)?,
+            None => prompt.clone(),
+        };
+        let memories = crate::memory::recall(memory, &query, options.budget).await;
+        if !memories.is_empty() {
+            let injected = crate::memory::render(&memories);
+            system = Some(match system {
+                Some(s) => format!("{}\n\n{}", s, injected),
+                None => injected,
+            });
+        }
+        trace.memories = memories.into_iter().map(|m| m.id).collect();
+    }
+    if let Some(schema) = &agent.output_schema {
+        let instruction = format!(
+            "只输出一个符合以下 JSON Schema 的 JSON 值，不要包含任何其它内容：\n{}",
+            serde_json::to_string_pretty(schema).unwrap_or_default()
+        );
+        system = Some(match system {
+            Some(s) => format!("{}\n\n{}", s, instruction),
+            None => instruction,
+        });
+    }
+
+    let mut messages = Vec::new();
+    if let Some(system) = system {
+        messages.push(ChatMessage::system(system));
+    }
+    trace.rendered_prompt = Some(prompt.clone());
+    messages.push(ChatMessage::user(prompt));
+
+    let node_err = |message: String| ExecError::Node {
+        node: node_id.to_string(),
+        message,
+    };
+    // 回放（有工具录制）时 provider 固定为录制，不需要读取密钥
+    let provider = match &agent.api_key_secret {
+        Some(secret) if exec.tool_recording().is_none() => {
+            let api_key = exec.secret(secret).map_err(node_err)?;
+            exec.providers()
+                .with_api_key(agent.provider.as_deref(), &api_key)
+                .transpose()
+                .map_err(node_err)?
+        }
+        _ => exec.providers().get(agent.provider.as_deref()),
+    }
+    .ok_or_else(|| {
+        node_err(format!(
+            "未找到 provider `{}`",
+            agent.provider.as_deref().unwrap_or("<默认>")
+        ))
+    })?;
+
+    tracing::debug!(
+        "节点 {} 调用 provider {} ({})",
+        node_id,
+        provider.name(),
+        agent.model
+    );
+    let path = frame.path(node_id);
+    let tool_ctx = ToolContext {
+        project: exec.store().project(),
+        run_id: &frame.run_id,
+        allow_write: frame.fs_write,
+        mcp: &frame.mcp,
+        node_id,
+        memory: exec.memory(),
+    };
+    let mut request = ChatRequest {
+        model: agent.model.clone(),
+        messages,
+        temperature: agent.temperature,
+        max_tokens: agent.max_tokens,
+        response_format: schema.as_ref().map(|_| ResponseFormat::Json),
+        tools: match exec.tool_recording() {
+            Some(recording) => recording.specs(&path, &agent.tools),
+            None => exec.tools().specs(&agent.tools, &tool_ctx),
+        }
+        .map_err(node_err)?,
+    };
+    trace.tool_specs = request.tools.clone();
+
+    let cache = match exec.cache() {
+        Some(cache) if agent.cache && agent.tools.is_empty() => {
+            let key = cache::cache_key(provider.name(), &request, agent.output_schema.as_ref());
+            if let Some(hit) = cache.get(&key).await {
+                tracing::debug!("节点 {} 命中输出缓存", node_id);
+                trace.cached = true;
+                trace.response = hit.response;
+                trace.attempts = hit.attempts;
+                trace.calls = hit.calls;
+                return Ok(hit.output);
+            }
+            Some((cache, key))
+        }
+        _ => None,
+    };
+
+    let max_attempts = agent.max_attempts.max(1);
+    let mut usage = Usage::default();
+    let mut attempt = 0;
+    let mut tool_iterations = 0;
+    let on_delta = |delta: &str| {
+        exec.emit(FlowEvent::TokenDelta {
+            run_id: frame.run_id.to_string(),
+            path: path.clone(),
+            node_id: node_id.to_string(),
+            delta: delta.to_string(),
+        })
+    };
+
+    let output = loop {
+        exec.check_budget()?;
+        let mut result = provider.chat_stream(&request, &on_delta).await;
+        let guard = match &mut result {
+            Ok(response) => response.guard.take(),
+            Err(error) => error.guard.take(),
+        };
+        trace.calls.push(ProviderCall {
+            request_hash: request.hash(),
+            response: result.as_ref().ok().cloned(),
+            guard,
+        });
+        let response = result.map_err(|error| ExecError::Provider {
+            node: node_id.to_string(),
+            error,
+        })?;
+        usage += response.usage;
+        trace.usage = Some(usage);
+        if let Some(cost) = exec
+            .prices()
+            .cost(provider.name(), &agent.model, &response.usage)
+        {
+            trace.cost = Some(trace.cost.unwrap_or_default() + cost);
+            exec.charge(cost);
+        }
+        trace.response = Some(response.content.clone());
+
+        if !response.tool_calls.is_empty() {
+            tool_iterations += 1;
+            if tool_iterations > agent.max_tool_iterations {
+                return Err(ExecError::Node {
+                    node: node_id.to_string(),
+                    message: format!("工具调用超过 {} 轮", agent.max_tool_iterations),
+                });
+            }
+            request.messages.push(ChatMessage::assistant_tool_calls(
+                response.content,
+                response.tool_calls.clone(),
+            ));
+            for call in response.tool_calls {
+                let started_at = now_ms();
+                tracing::debug!("节点 {} 调用工具 {}", node_id, call.name);
+                let result = match exec.tool_recording() {
+                    Some(recording) => {
+                        let recorded = recording.take(&path, &call.name).map_err(node_err)?;
+                        match recorded.error {
+                            Some(error) => Err(error),
+                            None => Ok(recorded.output.unwrap_or(Value::Null)),
+                        }
+                    }
+                    // 只能调用节点声明的工具，模型请求其它工具时作为工具错误反馈
+                    None if !agent.tools.contains(&call.name) => Err(format!(
+                        "工具 `{}` 未在节点的 tools 中声明，不能调用",
+                        call.name
+                    )),
+                    None => {
+                        exec.tools()
+                            .invoke(&call.name, &call.arguments, &tool_ctx)
+                            .await
+                    }
+                };
+                let content = match &result {
+                    Ok(value) => value.to_string(),
+                    Err(error) => serde_json::json!({ "error": error }).to_string(),
+                };
+                request.messages.push(ChatMessage::tool(&call.id, content));
+                let (output, error) = match result {
+                    Ok(value) => (Some(value), None),
+                    Err(error) => (None, Some(error)),
+                };
+                trace.tool_calls.push(ToolCallTrace {
+                    id: call.id,
+                    name: call.name,
+                    arguments: call.arguments,
+                    output,
+                    error,
+                    started_at,
+                    finished_at: now_ms(),
+                });
+            }
+            continue;
+        }
+
+        attempt += 1;
+        let Some(schema) = &schema else {
+            break Value::String(response.content);
+        };
+        trace.attempts = Some(attempt);
+        let errors = match structured::parse_lenient(&response.content) {
+            Ok(value) => {
+                let errors = schema.check(&value);
+                if errors.is_empty() {
+                    break value;
+                }
+                errors
+            }
+            Err(e) => vec![e],
+        };
+
+        if attempt >= max_attempts {
+            return Err(ExecError::Schema {
+                node: node_id.to_string(),
+                attempts: attempt,
+                errors,
+            });
+        }
+        tracing::debug!(
+            "节点 {} 第 {} 次输出未通过校验，重试: {}",
+            node_id,
+            attempt,
+            errors.join("; ")
+        );
+        // 把上一轮输出与校验错误反馈给模型
+        request
+            .messages
+            .push(ChatMessage::assistant(response.content));
+        request.messages.push(ChatMessage::user(format!(
+            "上面的输出未通过 JSON Schema 校验：\n- {}\n请修正后重新输出完整的 JSON，不要包含其它内容。",
+            errors.join("\n- ")
+        )));
+    };
+
+    if let Some((cache, key)) = cache {
+        let entry = CachedOutput {
+            output: output.clone(),
+            response: trace.response.clone(),
+            attempts: trace.attempts,
+            calls: trace.calls.clone(),
+        };
+        let ttl = agent.cache_ttl.unwrap_or(cache::DEFAULT_TTL);
+        cache.put(&key, node_id, &entry, ttl).await;
+    }
+    Ok(output)
+}
@@ -0,0 +1,44 @@
+//! 分支节点：`if` 输出条件的真值，`switch` 输出命中的 case（未命中为 `default`）
+//!
+//! 输出即选中的分支，执行器据此判断出边是否有效，见 [`NodeKind::selected_branch`]。
+//!
+//! [`NodeKind::selected_branch`]: crate::flow::model::NodeKind::selected_branch
+
+use serde_json::Value;
+
+use super::ExecError;
+use crate::flow::model::{IfNode, SwitchNode, DEFAULT_BRANCH};
+use crate::flow::template::Expression;
+
+pub(super) fn run_if(node_id: &str, node: &IfNode, scope: &Value) -> Result<Value, ExecError> {
+    let template_err = |error| ExecError::Template {
+        node: node_id.to_string(),
+        error,
+    };
+    let condition = Expression::parse(&node.condition).map_err(template_err)?;
+    Ok(Value::Bool(condition.test(scope).map_err(template_err)?))
+}
+
+pub(super) fn run_switch(
+    node_id: &str,
+    node: &SwitchNode,
+    scope: &Value,
+) -> Result<Value, ExecError> {
+    let template_err = |error| ExecError::Template {
+        node: node_id.to_string(),
+        error,
+    };
+    let value = Expression::parse(&node.value)
+        .and_then(|expr| expr.eval(scope))
+        .map_err(template_err)?;
+    let text = match value {
+        Value::String(s) => s,
+        other => other.to_string(),
+    };
+    let branch = node
+        .cases
+        .iter()
+        .find(|case| **case == text)
+        .map_or(DEFAULT_BRANCH, String::as_str);
+    Ok(Value::String(branch.to_string()))
+}
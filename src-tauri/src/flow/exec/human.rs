@@ -0,0 +1,93 @@
+//! input 节点：首次执行时渲染请求并挂起，继续执行时按保存的答复（或超时默认动作）得到输出
+
+use serde_json::Value;
+
+use super::{ExecError, Executor, Frame};
+use crate::flow::events::FlowEvent;
+use crate::flow::human::{HumanAction, HumanInput, HumanResponse};
+use crate::flow::model::{InputNode, InputTimeout};
+use crate::flow::template;
+use crate::flow::trace::{now_ms, NodeTrace};
+
+/// `request` 为之前运行中保存的请求（可能已附带答复）
+pub(super) fn run(
+    exec: &Executor,
+    node_id: &str,
+    input: &InputNode,
+    scope: &Value,
+    frame: &Frame,
+    request: Option<HumanInput>,
+    trace: &mut NodeTrace,
+) -> Result<Value, ExecError> {
+    let node_err = |message: String| ExecError::Node {
+        node: node_id.to_string(),
+        message,
+    };
+    if frame.depth() > 0 || !frame.prefix.is_empty() {
+        return Err(node_err(
+            "input 节点只能用于根流程，不能位于子流程、循环体或 map 中".to_string(),
+        ));
+    }
+
+    let mut request = match request {
+        Some(request) => request,
+        None => new_request(node_id, input, scope)?,
+    };
+    let now = now_ms();
+    if request.expired(now) {
+        let action = match input.on_timeout {
+            InputTimeout::Reject => HumanAction::Reject,
+            InputTimeout::Approve => HumanAction::Approve,
+        };
+        tracing::info!(
+            "⏳ 运行 {} 的节点 {} 等待超时，按 {:?} 处理",
+            frame.run_id,
+            node_id,
+            action
+        );
+        request.response = Some(HumanResponse::timeout(action, now));
+    }
+
+    let outcome = request.outcome();
+    if outcome.is_none() {
+        exec.emit(FlowEvent::InputRequested {
+            run_id: frame.run_id.to_string(),
+            path: frame.path(node_id),
+            node_id: node_id.to_string(),
+            prompt: request.prompt.clone(),
+            value: request.value.clone(),
+            form: request.form.clone(),
+            deadline: request.deadline,
+        });
+    }
+    trace.input = Some(request);
+    match outcome {
+        None => Err(ExecError::Waiting),
+        Some(result) => result.map_err(node_err),
+    }
+}
+
+fn new_request(node_id: &str, input: &InputNode, scope: &Value) -> Result<HumanInput, ExecError> {
+    let template_err = |error| ExecError::Template {
+        node: node_id.to_string(),
+        error,
+    };
+    let prompt = template::render(&input.prompt, scope).map_err(template_err)?;
+    let value = input
+        .value
+        .as_ref()
+        .map(|value| template::render_value(value, scope))
+        .transpose()
+        .map_err(template_err)?;
+    let requested_at = now_ms();
+    Ok(HumanInput {
+        prompt,
+        value,
+        form: input.form.clone(),
+        requested_at,
+        deadline: input
+            .timeout_secs
+            .map(|secs| requested_at + (secs as i64).saturating_mul(1000)),
+        response: None,
+    })
+}
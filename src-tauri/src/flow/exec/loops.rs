@@ -0,0 +1,123 @@
+//! 循环节点：以 `state` 为输入重复执行循环体，直到条件结束或达到最大轮数
+//!
+//! 每一轮都是一次独立的子图执行，轨迹按轮次记录在节点的 `iterations` 中，
+//! 节点路径为 `<循环节点id>/<轮次>/<节点id>`。
+
+use serde_json::{Map, Value};
+use std::sync::Arc;
+
+use super::{ExecError, Executor, Frame};
+use crate::flow::model::{LoopLimit, LoopMode, LoopNode};
+use crate::flow::template::{self, Expression};
+use crate::flow::trace::{NodeTrace, TraceStatus};
+
+pub(super) async fn run(
+    exec: &Executor,
+    node_id: &str,
+    node: &LoopNode,
+    scope: &Value,
+    frame: &Frame,
+    trace: &mut NodeTrace,
+) -> Result<Value, ExecError> {
+    let node_err = |message: String| ExecError::Node {
+        node: node_id.to_string(),
+        message,
+    };
+    let template_err = |error| ExecError::Template {
+        node: node_id.to_string(),
+        error,
+    };
+
+    let max_depth = exec.options().max_depth;
+    if frame.depth() + 1 > max_depth {
+        return Err(ExecError::DepthExceeded {
+            node: node_id.to_string(),
+            max_depth,
+        });
+    }
+
+    let condition = Expression::parse(&node.condition).map_err(template_err)?;
+    let mut state = Map::new();
+    for (name, binding) in &node.state {
+        let value = match binding {
+            Value::String(source) => template::render_value(source, scope).map_err(template_err)?,
+            literal => literal.clone(),
+        };
+        state.insert(name.clone(), value);
+    }
+
+    let body = Arc::new(node.body_flow(node_id));
+    let key = format!(
+        "{}/{}",
+        frame.stack.last().map_or("", String::as_str),
+        node_id
+    );
+    let check = |state: &Map<String, Value>, iteration: u32| {
+        let mut data = scope.clone();
+        data["state"] = Value::Object(state.clone());
+        data["iteration"] = Value::from(iteration);
+        condition.test(&data).map_err(template_err)
+    };
+
+    let mut iteration = 0;
+    loop {
+        if node.mode == LoopMode::While && !check(&state, iteration)? {
+            break;
+        }
+        if iteration >= node.max_iterations {
+            match node.on_max_iterations {
+                LoopLimit::Fail => {
+                    return Err(node_err(format!(
+                        "循环达到最大轮数 {} 仍未结束",
+                        node.max_iterations
+                    )))
+                }
+                LoopLimit::Exit => {
+                    tracing::warn!(
+                        "⚠️  循环 {} 达到最大轮数 {}，结束循环",
+                        node_id,
+                        node.max_iterations
+                    );
+                    break;
+                }
+            }
+        }
+        if exec.inner.control.is_cancelled() {
+            return Err(ExecError::Cancelled);
+        }
+
+        iteration += 1;
+        let mut inputs = state.clone();
+        inputs.insert("iteration".to_string(), Value::from(iteration));
+        let child_frame = frame.child(key.clone(), &format!("{}/{}", node_id, iteration), &body);
+        let child_trace = exec
+            .run_flow(body.clone(), Value::Object(inputs), child_frame)
+            .await;
+        let status = child_trace.status;
+        let error = child_trace.error.clone();
+        let outputs = child_trace.outputs.clone();
+        trace.iterations.push(child_trace);
+        match status {
+            TraceStatus::Succeeded => {
+                if let Some(Value::Object(outputs)) = outputs {
+                    state.extend(outputs);
+                }
+            }
+            TraceStatus::Cancelled => return Err(ExecError::Cancelled),
+            TraceStatus::Paused => return Err(ExecError::Paused),
+            _ => {
+                return Err(node_err(format!(
+                    "循环第 {} 轮执行失败: {}",
+                    iteration,
+                    error.unwrap_or_default()
+                )))
+            }
+        }
+
+        if node.mode == LoopMode::Until && check(&state, iteration)? {
+            break;
+        }
+    }
+    tracing::debug!("循环 {} 共执行 {} 轮", node_id, iteration);
+    Ok(Value::Object(state))
+}
@@ -0,0 +1,134 @@
+//! map 节点：对列表中的每个元素执行一次 `each` 节点，按输入顺序汇总结果
+//!
+//! 元素按 `concurrency` 并发执行，每个元素结束时发出 `map_progress` 事件。
+//! `fail_fast` 时首个失败之后不再启动新的元素（已在执行的元素照常完成），其余元素标记为 `skipped`。
+
+use futures_util::future::BoxFuture;
+use futures_util::stream::{self, StreamExt};
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use super::{ExecError, Executor, Frame};
+use crate::flow::events::FlowEvent;
+use crate::flow::model::{MapErrorPolicy, MapNode};
+use crate::flow::template;
+use crate::flow::trace::NodeTrace;
+
+/// map 可以嵌套（`each` 也是 map），返回装箱的 future 以打断递归类型
+pub(super) fn run<'a>(
+    exec: &'a Executor,
+    node_id: &'a str,
+    map: &'a MapNode,
+    scope: &'a Value,
+    frame: &'a Frame,
+    trace: &'a mut NodeTrace,
+) -> BoxFuture<'a, Result<Value, ExecError>> {
+    Box::pin(run_elements(exec, node_id, map, scope, frame, trace))
+}
+
+async fn run_elements(
+    exec: &Executor,
+    node_id: &str,
+    map: &MapNode,
+    scope: &Value,
+    frame: &Frame,
+    trace: &mut NodeTrace,
+) -> Result<Value, ExecError> {
+    let node_err = |message: String| ExecError::Node {
+        node: node_id.to_string(),
+        message,
+    };
+
+    let items = template::render_value(&map.items, scope).map_err(|error| ExecError::Template {
+        node: node_id.to_string(),
+        error,
+    })?;
+    let Value::Array(items) = items else {
+        return Err(node_err(format!("items 应为数组，实际为 {}", items)));
+    };
+    let total = items.len();
+    let each = &*map.each;
+    let stop = AtomicBool::new(false);
+
+    let mut elements = stream::iter(items.into_iter().enumerate())
+        .map(|(index, item)| {
+            let frame = frame.element(node_id, index);
+            let stop = &stop;
+            async move {
+                if stop.load(Ordering::SeqCst) {
+                    return (index, frame, None);
+                }
+                let mut data = scope.clone();
+                data["item"] = item;
+                data["index"] = Value::from(index);
+                let element = exec.run_node(each, &data, &frame, None).await;
+                (index, frame, Some(element))
+            }
+        })
+        .buffer_unordered(map.concurrency.max(1));
+
+    let mut results: Vec<Option<Result<Value, ExecError>>> = (0..total).map(|_| None).collect();
+    let mut traces: Vec<Option<NodeTrace>> = (0..total).map(|_| None).collect();
+    let (mut completed, mut failed) = (0, 0);
+    let mut failure = None;
+    let path = frame.path(node_id);
+    while let Some((index, element_frame, element)) = elements.next().await {
+        let Some((result, element_trace)) = element else {
+            let mut skipped = NodeTrace::new(&each.id, each.kind.name());
+            skipped.skip();
+            traces[index] = Some(skipped);
+            exec.emit(FlowEvent::NodeSkipped {
+                run_id: frame.run_id.to_string(),
+                path: element_frame.path(&each.id),
+                node_id: each.id.clone(),
+            });
+            continue;
+        };
+        traces[index] = Some(element_trace);
+        completed += 1;
+        let error = result.as_ref().err().map(ToString::to_string);
+        if error.is_some() {
+            failed += 1;
+        }
+        exec.emit(FlowEvent::MapProgress {
+            run_id: frame.run_id.to_string(),
+            path: path.clone(),
+            node_id: node_id.to_string(),
+            index,
+            completed,
+            failed,
+            total,
+            error,
+        });
+        match result {
+            Err(ExecError::Cancelled) => {
+                stop.store(true, Ordering::SeqCst);
+                failure.get_or_insert(ExecError::Cancelled);
+            }
+            Err(ExecError::Paused) => {
+                stop.store(true, Ordering::SeqCst);
+                failure.get_or_insert(ExecError::Paused);
+            }
+            Err(e) if map.on_error == MapErrorPolicy::FailFast => {
+                stop.store(true, Ordering::SeqCst);
+                failure.get_or_insert(node_err(format!("第 {} 个元素执行失败: {}", index, e)));
+            }
+            result => results[index] = Some(result),
+        }
+    }
+    drop(elements);
+    trace.items = traces.into_iter().flatten().collect();
+    if let Some(e) = failure {
+        return Err(e);
+    }
+
+    let mut output = Vec::with_capacity(total);
+    for result in results.into_iter().flatten() {
+        match result {
+            Ok(value) => output.push(value),
+            Err(_) if map.on_error == MapErrorPolicy::Skip => {}
+            Err(e) => output.push(json!({ "error": e.to_string() })),
+        }
+    }
+    Ok(Value::Array(output))
+}
@@ -0,0 +1,1279 @@
+//! 流程执行器
+//!
+//! 按依赖图分批调度：每一批取出所有上游均已完成的节点并发执行，任一节点失败则终止流程，
+//! 尚未执行的节点标记为 `skipped`。入边全部无效（上游被跳过或分支未选中）的节点同样跳过。
+//! 子流程与循环节点递归调用执行器，并受最大嵌套深度与循环检测约束；map 节点对每个元素执行一次
+//! 内嵌节点，受并发上限约束。
+//! 执行过程中的进度通过 [`EventSink`] 发出；[`RunControl`] 用于取消与暂停，
+//! 暂停后可从 [`Checkpoint`] 保存的轨迹继续执行。input 节点等待人工答复时不阻塞其它节点，
+//! 没有其它可执行的节点后运行以 `waiting` 状态结束，答复后同样从保存的轨迹继续。
+
+mod agent;
+mod branch;
+mod human;
+mod loops;
+mod map;
+mod subflow;
+
+use async_trait::async_trait;
+use futures_util::future::{join_all, BoxFuture};
+use serde_json::{json, Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+
+use super::budget::{Budget, BudgetAction};
+use super::cache::CachedOutput;
+use super::control::RunControl;
+use super::events::{EventSink, FlowEvent, NoopSink};
+use super::graph;
+use super::human::HumanInput;
+use super::model::{FlowDef, NodeDef, NodeKind};
+use super::replay::ToolRecording;
+use super::store::FlowStore;
+use super::template::{self, TemplateError};
+use super::trace::{ErrorKind, FlowTrace, NodeTrace, TraceStatus};
+use crate::llm::pricing::PriceTable;
+use crate::llm::registry::ProviderRegistry;
+use crate::llm::LlmError;
+use crate::memory::AgentMemory;
+use crate::tools::ToolRegistry;
+use crate::vault::Vault;
+
+/// 执行选项
+#[derive(Debug, Clone)]
+pub struct ExecOptions {
+    /// 子流程最大嵌套深度（根流程深度为 0）
+    pub max_depth: usize,
+}
+
+impl Default for ExecOptions {
+    fn default() -> Self {
+        Self { max_depth: 8 }
+    }
+}
+
+/// 执行错误
+#[derive(Debug, Clone)]
+pub enum ExecError {
+    /// 流程定义无效
+    Invalid(String),
+    /// 流程输入不合法
+    Input(String),
+    /// 模板渲染失败
+    Template { node: String, error: TemplateError },
+    /// 调用模型失败
+    Provider { node: String, error: LlmError },
+    /// 结构化输出多次重试后仍未通过校验
+    Schema {
+        node: String,
+        attempts: u32,
+        errors: Vec<String>,
+    },
+    /// 子流程嵌套过深
+    DepthExceeded { node: String, max_depth: usize },
+    /// 子流程循环引用
+    Cycle { node: String, path: String },
+    /// 其它节点级错误
+    Node { node: String, message: String },
+    /// 超出预算而终止
+    Budget(String),
+    /// 运行被取消
+    Cancelled,
+    /// 运行被暂停
+    Paused,
+    /// 等待人工输入
+    Waiting,
+}
+
+impl fmt::Display for ExecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecError::Invalid(msg) => write!(f, "流程定义无效: {}", msg),
+            ExecError::Input(msg) => write!(f, "流程输入无效: {}", msg),
+            ExecError::Template { node, error } => write!(f, "节点 `{}` {}", node, error),
+            ExecError::Provider { node, error } => {
+                write!(f, "节点 `{}` 调用模型失败: {}", node, error)
+            }
+            ExecError::Schema {
+                node,
+                attempts,
+                errors,
+            } => write!(
+                f,
+                "节点 `{}` 的输出经 {} 次尝试仍不符合 Schema: {}",
+                node,
+                attempts,
+                errors.join("; ")
+            ),
+            ExecError::DepthExceeded { node, max_depth } => {
+                write!(f, "节点 `{}` 的子流程嵌套超过最大深度 {}", node, max_depth)
+            }
+            ExecError::Cycle { node, path } => {
+                write!(f, "节点 `{}` 形成子流程循环引用: {}", node, path)
+            }
+            ExecError::Node { node, message } => write!(f, "节点 `{}`: {}", node, message),
+            ExecError::Budget(msg) => write!(f, "超出预算: {}", msg),
+            ExecError::Cancelled => write!(f, "运行已取消"),
+            ExecError::Paused => write!(f, "运行已暂停"),
+            ExecError::Waiting => write!(f, "等待人工输入"),
+        }
+    }
+}
+
+impl std::error::Error for ExecError {}
+
+impl ExecError {
+    /// 失败类别（取消、暂停与等待不算失败，归入节点错误）
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            ExecError::Invalid(_) | ExecError::Input(_) => ErrorKind::Invalid,
+            ExecError::Provider { .. } => ErrorKind::Provider,
+            ExecError::Budget(_) => ErrorKind::Budget,
+            _ => ErrorKind::Node,
+        }
+    }
+}
+
+/// 子流程调用栈
+#[derive(Debug, Clone)]
+pub(crate) struct Frame {
+    pub run_id: Arc<str>,
+    /// 从根流程到当前流程的 `id@version` 列表
+    pub stack: Vec<String>,
+    /// 当前流程中节点路径的前缀（根流程为空，子流程为 `<节点id>/`）
+    pub prefix: String,
+    /// 当前流程是否允许工具写入项目文件
+    pub fs_write: bool,
+    /// 当前流程允许的 MCP 服务
+    pub mcp: Arc<[String]>,
+}
+
+impl Frame {
+    fn root(run_id: &str, flow: &FlowDef) -> Self {
+        Self {
+            run_id: Arc::from(run_id),
+            stack: vec![flow.key()],
+            prefix: String::new(),
+            fs_write: flow.permissions.fs_write,
+            mcp: flow.permissions.mcp.clone().into(),
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.stack.len().saturating_sub(1)
+    }
+
+    /// 子流程需要自己声明写入权限与 MCP 服务，且调用方流程也允许
+    pub fn child(&self, key: String, node_id: &str, flow: &FlowDef) -> Self {
+        let mut stack = self.stack.clone();
+        stack.push(key);
+        Self {
+            run_id: self.run_id.clone(),
+            stack,
+            prefix: self.path(node_id) + "/",
+            fs_write: self.fs_write && flow.permissions.fs_write,
+            mcp: flow.permissions.mcp_within(&self.mcp).into(),
+        }
+    }
+
+    /// map 节点中第 `index` 个元素所在的位置
+    pub fn element(&self, node_id: &str, index: usize) -> Self {
+        Self {
+            prefix: format!("{}/{}/", self.path(node_id), index),
+            ..self.clone()
+        }
+    }
+
+    /// 节点在整个运行中的路径
+    pub fn path(&self, node_id: &str) -> String {
+        format!("{}{}", self.prefix, node_id)
+    }
+}
+
+/// 运行检查点：根流程每调度完一批节点保存一次轨迹，中断后可据此继续执行
+#[async_trait]
+pub trait Checkpoint: Send + Sync {
+    async fn save(&self, trace: &FlowTrace);
+}
+
+/// 节点输出缓存，只对声明了 `cache` 的 agent 节点生效；读写失败不影响执行
+#[async_trait]
+pub trait OutputCache: Send + Sync {
+    async fn get(&self, key: &str) -> Option<CachedOutput>;
+    /// `ttl` 为有效期（秒）
+    async fn put(&self, key: &str, node_id: &str, entry: &CachedOutput, ttl: u64);
+}
+
+struct ExecutorInner {
+    store: FlowStore,
+    providers: ProviderRegistry,
+    options: ExecOptions,
+    events: Arc<dyn EventSink>,
+    control: RunControl,
+    checkpoint: Option<Arc<dyn Checkpoint>>,
+    cache: Option<Arc<dyn OutputCache>>,
+    prices: PriceTable,
+    budget: Option<Arc<Budget>>,
+    tools: ToolRegistry,
+    secrets: Option<Arc<Vault>>,
+    memory: Option<Arc<dyn AgentMemory>>,
+    tool_recording: Option<Arc<ToolRecording>>,
+}
+
+/// 流程执行器（可廉价克隆）
+#[derive(Clone)]
+pub struct Executor {
+    inner: Arc<ExecutorInner>,
+}
+
+impl Executor {
+    pub fn new(store: FlowStore, providers: ProviderRegistry, options: ExecOptions) -> Self {
+        Self {
+            inner: Arc::new(ExecutorInner {
+                store,
+                providers,
+                options,
+                events: Arc::new(NoopSink),
+                control: RunControl::new(),
+                checkpoint: None,
+                cache: None,
+                prices: PriceTable::default(),
+                budget: None,
+                tools: ToolRegistry::new(),
+                secrets: None,
+                memory: None,
+                tool_recording: None,
+            }),
+        }
+    }
+
+    fn inner_mut(&mut self) -> &mut ExecutorInner {
+        Arc::get_mut(&mut self.inner).expect("执行器需在克隆之前完成配置")
+    }
+
+    /// 设置事件接收器
+    pub fn with_events(mut self, events: Arc<dyn EventSink>) -> Self {
+        self.inner_mut().events = events;
+        self
+    }
+
+    /// 设置运行控制句柄（取消 / 暂停）
+    pub fn with_control(mut self, control: RunControl) -> Self {
+        self.inner_mut().control = control;
+        self
+    }
+
+    /// 设置检查点
+    pub fn with_checkpoint(mut self, checkpoint: Arc<dyn Checkpoint>) -> Self {
+        self.inner_mut().checkpoint = Some(checkpoint);
+        self
+    }
+
+    /// 设置节点输出缓存
+    pub fn with_cache(mut self, cache: Arc<dyn OutputCache>) -> Self {
+        self.inner_mut().cache = Some(cache);
+        self
+    }
+
+    /// 设置价格表，用于计算节点费用
+    pub fn with_prices(mut self, prices: PriceTable) -> Self {
+        self.inner_mut().prices = prices;
+        self
+    }
+
+    /// 设置运行预算
+    pub fn with_budget(mut self, budget: Arc<Budget>) -> Self {
+        self.inner_mut().budget = Some(budget);
+        self
+    }
+
+    /// 设置 agent 节点可用的工具
+    pub fn with_tools(mut self, tools: ToolRegistry) -> Self {
+        self.inner_mut().tools = tools;
+        self
+    }
+
+    /// 设置密钥库，agent 节点通过 `api_key_secret` 引用其中的密钥
+    pub fn with_secrets(mut self, vault: Arc<Vault>) -> Self {
+        self.inner_mut().secrets = Some(vault);
+        self
+    }
+
+    /// 设置用户记忆，供声明了 `memory` 的 agent 节点与记忆工具使用
+    pub fn with_memory(mut self, memory: Arc<dyn AgentMemory>) -> Self {
+        self.inner_mut().memory = Some(memory);
+        self
+    }
+
+    /// 回放：工具调用从录制中应答，不执行真实工具
+    pub fn with_tool_recording(mut self, recording: Arc<ToolRecording>) -> Self {
+        self.inner_mut().tool_recording = Some(recording);
+        self
+    }
+
+    pub fn store(&self) -> &FlowStore {
+        &self.inner.store
+    }
+
+    pub fn providers(&self) -> &ProviderRegistry {
+        &self.inner.providers
+    }
+
+    pub fn options(&self) -> &ExecOptions {
+        &self.inner.options
+    }
+
+    pub(crate) fn cache(&self) -> Option<&dyn OutputCache> {
+        self.inner.cache.as_deref()
+    }
+
+    pub(crate) fn tool_recording(&self) -> Option<&ToolRecording> {
+        self.inner.tool_recording.as_deref()
+    }
+
+    pub(crate) fn memory(&self) -> Option<&dyn AgentMemory> {
+        self.inner.memory.as_deref()
+    }
+
+    pub fn prices(&self) -> &PriceTable {
+        &self.inner.prices
+    }
+
+    pub fn tools(&self) -> &ToolRegistry {
+        &self.inner.tools
+    }
+
+    /// 读取密钥明文（不得写入轨迹或日志）
+    pub(crate) fn secret(&self, name: &str) -> Result<zeroize::Zeroizing<String>, String> {
+        match &self.inner.secrets {
+            Some(vault) => vault.get(name),
+            None => Err("未配置密钥库".to_string()),
+        }
+    }
+
+    /// 把一次模型调用的费用计入预算
+    pub(crate) fn charge(&self, cost: f64) {
+        if let Some(budget) = &self.inner.budget {
+            budget.charge(cost);
+        }
+    }
+
+    /// 超出预算时按 `action` 返回预算错误，或暂停运行并返回暂停；
+    /// agent 节点每次调用模型前检查，避免工具调用与重试在节点内超支
+    pub(crate) fn check_budget(&self) -> Result<(), ExecError> {
+        let Some(budget) = &self.inner.budget else {
+            return Ok(());
+        };
+        let Some(reason) = budget.exceeded() else {
+            return Ok(());
+        };
+        match budget.action() {
+            BudgetAction::Abort => Err(ExecError::Budget(reason)),
+            BudgetAction::Pause => {
+                tracing::warn!("⚠️  节点内超出预算，暂停运行: {}", reason);
+                self.inner.control.pause();
+                Err(ExecError::Paused)
+            }
+        }
+    }
+
+    pub(crate) fn emit(&self, event: FlowEvent) {
+        self.inner.events.emit(event);
+    }
+
+    /// 执行流程，返回完整轨迹（失败信息记录在轨迹中）
+    pub async fn run(&self, run_id: &str, flow: FlowDef, inputs: Value) -> FlowTrace {
+        let trace = FlowTrace::new(&flow.id, &flow.version, inputs);
+        self.run_root(run_id, flow, trace).await
+    }
+
+    /// 从之前保存的轨迹继续执行：已成功的节点保留输出不再执行，等待中的 input 节点保留请求与答复，
+    /// 其余节点重新执行
+    pub async fn resume(&self, run_id: &str, flow: FlowDef, previous: FlowTrace) -> FlowTrace {
+        let mut trace = FlowTrace::new(&flow.id, &flow.version, previous.inputs);
+        trace.started_at = previous.started_at;
+        trace.nodes = previous
+            .nodes
+            .into_iter()
+            .filter(|n| match n.status {
+                TraceStatus::Succeeded => n.output.is_some(),
+                TraceStatus::Waiting => n.input.is_some(),
+                _ => false,
+            })
+            .collect();
+        self.run_root(run_id, flow, trace).await
+    }
+
+    async fn run_root(&self, run_id: &str, flow: FlowDef, mut trace: FlowTrace) -> FlowTrace {
+        self.emit(FlowEvent::RunStarted {
+            run_id: run_id.to_string(),
+            flow_id: flow.id.clone(),
+            flow_version: flow.version.clone(),
+        });
+        trace.run_id = Some(run_id.to_string());
+        let frame = Frame::root(run_id, &flow);
+        let trace = self.run_trace(Arc::new(flow), trace, frame).await;
+        self.emit(FlowEvent::RunFinished {
+            run_id: run_id.to_string(),
+            status: trace.status,
+            outputs: trace.outputs.clone(),
+            error: trace.error.clone(),
+        });
+        trace
+    }
+
+    /// 执行单个流程（子流程通过此函数递归）
+    pub(crate) fn run_flow(
+        &self,
+        flow: Arc<FlowDef>,
+        inputs: Value,
+        frame: Frame,
+    ) -> BoxFuture<'_, FlowTrace> {
+        let trace = FlowTrace::new(&flow.id, &flow.version, inputs);
+        self.run_trace(flow, trace, frame)
+    }
+
+    /// 按轨迹执行流程，轨迹中已成功的节点视为已完成
+    fn run_trace(
+        &self,
+        flow: Arc<FlowDef>,
+        mut trace: FlowTrace,
+        frame: Frame,
+    ) -> BoxFuture<'_, FlowTrace> {
+        Box::pin(async move {
+            let mut previous = std::mem::take(&mut trace.nodes);
+            trace.nodes = flow
+                .nodes
+                .iter()
+                .map(|n| match previous.iter().position(|t| t.node_id == n.id) {
+                    Some(i) => previous.swap_remove(i),
+                    None => NodeTrace::new(&n.id, n.kind.name()),
+                })
+                .collect();
+            let inputs = trace.inputs.clone();
+
+            tracing::debug!("▶️  开始执行流程 {} (深度 {})", flow.key(), frame.depth());
+            match self.execute(&flow, inputs, &frame, &mut trace).await {
+                Ok(outputs) => trace.succeed(outputs),
+                Err(ExecError::Paused) => {
+                    tracing::info!("⏸️  运行 {} 已暂停", frame.run_id);
+                    trace.pause();
+                }
+                Err(ExecError::Waiting) => {
+                    tracing::info!("⏳ 运行 {} 等待人工输入", frame.run_id);
+                    trace.wait();
+                }
+                Err(e) => {
+                    let cancelled = matches!(e, ExecError::Cancelled);
+                    for node in trace.nodes.iter_mut() {
+                        if !matches!(node.status, TraceStatus::Pending | TraceStatus::Waiting) {
+                            continue;
+                        }
+                        let run_id = frame.run_id.to_string();
+                        let path = frame.path(&node.node_id);
+                        let node_id = node.node_id.clone();
+                        if cancelled {
+                            node.status = TraceStatus::Cancelled;
+                            self.emit(FlowEvent::NodeCancelled {
+                                run_id,
+                                path,
+                                node_id,
+                            });
+                        } else {
+                            node.status = TraceStatus::Skipped;
+                            self.emit(FlowEvent::NodeSkipped {
+                                run_id,
+                                path,
+                                node_id,
+                            });
+                        }
+                    }
+                    if cancelled {
+                        tracing::info!("⏹️  流程 {} 已取消", flow.key());
+                        trace.cancel();
+                    } else {
+                        tracing::warn!("流程 {} 执行失败: {}", flow.key(), e);
+                        trace.fail(e.to_string());
+                        trace.error_kind = Some(e.kind());
+                    }
+                }
+            }
+            trace
+        })
+    }
+
+    async fn execute(
+        &self,
+        flow: &FlowDef,
+        inputs: Value,
+        frame: &Frame,
+        trace: &mut FlowTrace,
+    ) -> Result<Value, ExecError> {
+        let graph = graph::build(flow).map_err(|issues| {
+            ExecError::Invalid(
+                issues
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join("; "),
+            )
+        })?;
+        let inputs = resolve_inputs(flow, inputs)?;
+        trace.inputs = inputs.clone();
+
+        // 继续执行时，已成功的节点直接使用保存的输出，input 节点沿用之前的请求
+        let mut outputs = Map::new();
+        let mut done: HashSet<&str> = HashSet::new();
+        let mut requests: HashMap<&str, HumanInput> = HashMap::new();
+        for node in &flow.nodes {
+            let Some(t) = trace.nodes.iter().find(|t| t.node_id == node.id) else {
+                continue;
+            };
+            if let (TraceStatus::Succeeded, Some(output)) = (t.status, &t.output) {
+                outputs.insert(node.id.clone(), output.clone());
+                done.insert(node.id.as_str());
+            } else if let (TraceStatus::Waiting, Some(input)) = (t.status, &t.input) {
+                requests.insert(node.id.as_str(), input.clone());
+            }
+        }
+        let mut waiting: HashSet<&str> = HashSet::new();
+
+        let control = &self.inner.control;
+        while done.len() < flow.nodes.len() {
+            if let Some(budget) = &self.inner.budget {
+                if let Some(reason) = budget.exceeded() {
+                    match budget.action() {
+                        BudgetAction::Abort => return Err(ExecError::Budget(reason)),
+                        BudgetAction::Pause => {
+                            tracing::warn!("⚠️  运行 {} 超出预算，暂停: {}", frame.run_id, reason);
+                            control.pause();
+                        }
+                    }
+                }
+            }
+            if control.is_cancelled() {
+                return Err(ExecError::Cancelled);
+            }
+            // 暂停只作用于根流程，子流程节点会完整执行
+            if frame.depth() == 0 && control.is_paused() {
+                return Err(ExecError::Paused);
+            }
+
+            let ready: Vec<&NodeDef> = graph
+                .order()
+                .iter()
+                .filter(|id| !done.contains(id.as_str()) && !waiting.contains(id.as_str()))
+                .filter(|id| graph.upstream(id).iter().all(|u| done.contains(u.as_str())))
+                .filter_map(|id| flow.node(id))
+                .collect();
+            if ready.is_empty() && !waiting.is_empty() {
+                return Err(ExecError::Waiting);
+            }
+            if ready.is_empty() {
+                // 校验已保证无环，正常不会到达此处
+                return Err(ExecError::Invalid("存在无法调度的节点".to_string()));
+            }
+            let (ready, inactive): (Vec<&NodeDef>, Vec<&NodeDef>) = ready
+                .into_iter()
+                .partition(|node| is_active(flow, &node.id, &outputs));
+            for node in inactive {
+                done.insert(node.id.as_str());
+                if let Some(slot) = trace.nodes.iter_mut().find(|t| t.node_id == node.id) {
+                    *slot = NodeTrace::new(&node.id, node.kind.name());
+                    slot.skip();
+                }
+                self.emit(FlowEvent::NodeSkipped {
+                    run_id: frame.run_id.to_string(),
+                    path: frame.path(&node.id),
+                    node_id: node.id.clone(),
+                });
+            }
+
+            for node in &ready {
+                self.emit(FlowEvent::NodeQueued {
+                    run_id: frame.run_id.to_string(),
+                    path: frame.path(&node.id),
+                    node_id: node.id.clone(),
+                    kind: node.kind.name().to_string(),
+                });
+            }
+            let scope = json!({ "inputs": inputs, "nodes": outputs });
+            let results = join_all(ready.iter().map(|node| {
+                let request = requests.remove(node.id.as_str());
+                self.run_node(node, &scope, frame, request)
+            }))
+            .await;
+
+            let mut failure = None;
+            for (node, (result, node_trace)) in ready.iter().zip(results) {
+                if let Some(slot) = trace.nodes.iter_mut().find(|t| t.node_id == node.id) {
+                    *slot = node_trace;
+                }
+                if matches!(result, Err(ExecError::Waiting)) {
+                    waiting.insert(node.id.as_str());
+                    continue;
+                }
+                done.insert(node.id.as_str());
+                match result {
+                    Ok(value) => {
+                        outputs.insert(node.id.clone(), value);
+                    }
+                    Err(e) => {
+                        failure.get_or_insert(e);
+                    }
+                }
+            }
+            if control.is_cancelled() {
+                return Err(ExecError::Cancelled);
+            }
+            if let Some(e) = failure {
+                return Err(e);
+            }
+            if frame.depth() == 0 {
+                if let Some(checkpoint) = &self.inner.checkpoint {
+                    checkpoint.save(trace).await;
+                }
+            }
+        }
+
+        let scope = json!({ "inputs": inputs, "nodes": outputs });
+        let mut result = Map::new();
+        for output in &flow.outputs {
+            let value = template::render_value(&output.value, &scope).map_err(|error| {
+                ExecError::Template {
+                    node: format!("<output:{}>", output.name),
+                    error,
+                }
+            })?;
+            result.insert(output.name.clone(), value);
+        }
+        Ok(Value::Object(result))
+    }
+
+    /// 执行单个节点，返回结果及节点轨迹；`request` 为 input 节点之前保存的请求
+    async fn run_node(
+        &self,
+        node: &NodeDef,
+        scope: &Value,
+        frame: &Frame,
+        request: Option<HumanInput>,
+    ) -> (Result<Value, ExecError>, NodeTrace) {
+        let mut trace = NodeTrace::new(&node.id, node.kind.name());
+        trace.start();
+        let run_id = frame.run_id.to_string();
+        let path = frame.path(&node.id);
+        self.emit(FlowEvent::NodeStarted {
+            run_id: run_id.clone(),
+            path: path.clone(),
+            node_id: node.id.clone(),
+        });
+
+        let result = match &node.kind {
+            NodeKind::Agent(agent) => tokio::select! {
+                result = agent::run(self, &node.id, agent, scope, frame, &mut trace) => result,
+                // 取消时丢弃进行中的模型请求
+                _ = self.inner.control.cancelled() => Err(ExecError::Cancelled),
+            },
+            NodeKind::Template(t) => {
+                template::render_value(&t.template, scope).map_err(|error| ExecError::Template {
+                    node: node.id.clone(),
+                    error,
+                })
+            }
+            // 子流程共用同一个控制句柄，自行响应取消
+            NodeKind::Subflow(sub) => {
+                subflow::run(self, &node.id, sub, scope, frame, &mut trace).await
+            }
+            NodeKind::If(node_if) => branch::run_if(&node.id, node_if, scope),
+            NodeKind::Switch(switch) => branch::run_switch(&node.id, switch, scope),
+            NodeKind::Loop(looping) => {
+                loops::run(self, &node.id, looping, scope, frame, &mut trace).await
+            }
+            NodeKind::Map(map) => map::run(self, &node.id, map, scope, frame, &mut trace).await,
+            NodeKind::Input(input) => {
+                human::run(self, &node.id, input, scope, frame, request, &mut trace)
+            }
+        };
+
+        match &result {
+            Ok(value) => {
+                trace.succeed(value.clone());
+                self.emit(FlowEvent::NodeFinished {
+                    run_id,
+                    path,
+                    node_id: node.id.clone(),
+                    output: value.clone(),
+                    usage: trace.usage,
+                    cached: trace.cached,
+                });
+            }
+            // 请求事件已由 input 节点发出
+            Err(ExecError::Waiting) => trace.wait(),
+            // 节点内暂停（超出预算）：节点保持待执行，继续时重新执行
+            Err(ExecError::Paused) => trace.pause(),
+            Err(ExecError::Cancelled) => {
+                trace.cancel();
+                self.emit(FlowEvent::NodeCancelled {
+                    run_id,
+                    path,
+                    node_id: node.id.clone(),
+                });
+            }
+            Err(e) => {
+                trace.fail(e.to_string());
+                self.emit(FlowEvent::NodeFailed {
+                    run_id,
+                    path,
+                    node_id: node.id.clone(),
+                    error: e.to_string(),
+                });
+            }
+        }
+        (result, trace)
+    }
+}
+
+/// 没有入边，或至少一条入边的上游已执行且（分支连线）选中了该分支
+fn is_active(flow: &FlowDef, node_id: &str, outputs: &Map<String, Value>) -> bool {
+    let mut incoming = flow.edges.iter().filter(|e| e.to == node_id).peekable();
+    if incoming.peek().is_none() {
+        return true;
+    }
+    incoming.any(|edge| {
+        let Some(output) = outputs.get(&edge.from) else {
+            return false;
+        };
+        match &edge.branch {
+            None => true,
+            Some(branch) => flow
+                .node(&edge.from)
+                .and_then(|from| from.kind.selected_branch(output))
+                .is_some_and(|selected| selected == *branch),
+        }
+    })
+}
+
+/// 按流程输入声明补全默认值并检查必填项
+fn resolve_inputs(flow: &FlowDef, inputs: Value) -> Result<Value, ExecError> {
+    let mut given = match inputs {
+        Value::Object(map) => map,
+        Value::Null => Map::new(),
+        other => {
+            return Err(ExecError::Input(format!(
+                "流程输入应为对象，实际为 {}",
+                other
+            )))
+        }
+    };
+    for input in &flow.inputs {
+        if given.contains_key(&input.name) {
+            continue;
+        }
+        match &input.default {
+            Some(default) => {
+                given.insert(input.name.clone(), default.clone());
+            }
+            None => {
+                return Err(ExecError::Input(format!("缺少必填输入 `{}`", input.name)));
+            }
+        }
+    }
+    Ok(Value::Object(given))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow::budget::BudgetConfig;
+    use crate::llm::{ChatRequest, ChatResponse, Provider, ToolCall, Usage};
+    use crate::project::Project;
+    use serde_json::json;
+    use std::path::Path;
+
+    fn write(root: &Path, rel: &str, content: Value) {
+        let path = root.join(rel);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content.to_string()).unwrap();
+    }
+
+    fn executor(root: &Path, max_depth: usize) -> Executor {
+        let store = FlowStore::new(Project::open(root).unwrap());
+        Executor::new(store, ProviderRegistry::new(), ExecOptions { max_depth })
+    }
+
+    fn project() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "vlogi/meta.json5", json!({ "name": "test" }));
+        dir
+    }
+
+    #[tokio::test]
+    async fn test_subflow_maps_inputs_and_outputs() {
+        let dir = project();
+        write(
+            dir.path(),
+            "vlogi/packages/text/1.2.0/shout.json",
+            json!({
+                "id": "shout", "version": "1.2.0",
+                "inputs": [{ "name": "text" }],
+                "outputs": [{ "name": "loud", "value": "{{ nodes.up }}!" }],
+                "nodes": [{ "id": "up", "type": "template", "template": "{{ inputs.text | upper }}" }]
+            }),
+        );
+        write(
+            dir.path(),
+            "vlogi/flows/parent.json5",
+            json!({
+                "id": "parent",
+                "inputs": [{ "name": "topic" }],
+                "outputs": [{ "name": "result", "value": "{{ nodes.call.title }}" }],
+                "nodes": [
+                    { "id": "make", "type": "template", "template": "about {{ inputs.topic }}" },
+                    {
+                        "id": "call", "type": "subflow", "flow": "shout", "package": "text", "version": "^1.0",
+                        "inputs": { "text": "{{ nodes.make }}" },
+                        "outputs": { "title": "loud" }
+                    }
+                ],
+                "edges": [{ "from": "make", "to": "call" }]
+            }),
+        );
+
+        let exec = executor(dir.path(), 4);
+        let flow = exec.store().load_local("parent").unwrap();
+        let trace = exec.run("r1", flow, json!({ "topic": "rust" })).await;
+
+        assert_eq!(trace.status, TraceStatus::Succeeded, "{:?}", trace.error);
+        assert_eq!(trace.outputs, Some(json!({ "result": "ABOUT RUST!" })));
+        let call = trace.nodes.iter().find(|n| n.node_id == "call").unwrap();
+        let child = call.subflow.as_ref().expect("子流程轨迹应嵌套在节点中");
+        assert_eq!(child.flow_id, "shout");
+        assert_eq!(child.nodes[0].output, Some(json!("ABOUT RUST")));
+    }
+
+    #[tokio::test]
+    async fn test_subflow_cycle_and_depth() {
+        let dir = project();
+        for (id, next) in [("a", "b"), ("b", "a")] {
+            write(
+                dir.path(),
+                &format!("vlogi/flows/{}.json", id),
+                json!({
+                    "id": id,
+                    "nodes": [{ "id": "call", "type": "subflow", "flow": next }]
+                }),
+            );
+        }
+
+        let exec = executor(dir.path(), 8);
+        let trace = exec
+            .run("r1", exec.store().load_local("a").unwrap(), json!({}))
+            .await;
+        assert_eq!(trace.status, TraceStatus::Failed);
+        assert!(trace.error.unwrap().contains("a@0.1.0 → b@0.1.0 → a@0.1.0"));
+
+        let exec = executor(dir.path(), 0);
+        let trace = exec
+            .run("r1", exec.store().load_local("a").unwrap(), json!({}))
+            .await;
+        assert!(trace.error.unwrap().contains("最大深度 0"));
+    }
+
+    #[tokio::test]
+    async fn test_branches_and_loops() {
+        let dir = project();
+        write(
+            dir.path(),
+            "vlogi/flows/branch.json",
+            json!({
+                "id": "branch",
+                "outputs": [{ "name": "result", "value": "{{ nodes.merge }}" }],
+                "nodes": [
+                    { "id": "check", "type": "if", "condition": "inputs.score >= 60" },
+                    { "id": "pass", "type": "template", "template": "pass" },
+                    { "id": "fail", "type": "template", "template": "fail" },
+                    {
+                        "id": "merge", "type": "template",
+                        "template": "{% if nodes.pass is defined %}{{ nodes.pass }}{% else %}{{ nodes.fail }}{% endif %}"
+                    },
+                    { "id": "kind", "type": "switch", "value": "inputs.kind", "cases": ["a", "b"] },
+                    { "id": "on_a", "type": "template", "template": "A" },
+                    { "id": "after_a", "type": "template", "template": "{{ nodes.on_a }}!" },
+                    { "id": "other", "type": "template", "template": "other" }
+                ],
+                "edges": [
+                    { "from": "check", "to": "pass", "branch": "true" },
+                    { "from": "check", "to": "fail", "branch": "false" },
+                    { "from": "pass", "to": "merge" },
+                    { "from": "fail", "to": "merge" },
+                    { "from": "kind", "to": "on_a", "branch": "a" },
+                    { "from": "on_a", "to": "after_a" },
+                    { "from": "kind", "to": "other", "branch": "default" }
+                ]
+            }),
+        );
+        let exec = executor(dir.path(), 4);
+        let flow = exec.store().load_local("branch").unwrap();
+        let trace = exec
+            .run("r1", flow, json!({ "score": 80, "kind": "z" }))
+            .await;
+        assert_eq!(trace.status, TraceStatus::Succeeded, "{:?}", trace.error);
+        assert_eq!(trace.outputs, Some(json!({ "result": "pass" })));
+        let status = |id: &str| trace.nodes.iter().find(|n| n.node_id == id).unwrap().status;
+        assert_eq!(status("fail"), TraceStatus::Skipped);
+        assert_eq!(status("after_a"), TraceStatus::Skipped);
+        assert_eq!(status("other"), TraceStatus::Succeeded);
+
+        let refine = |max: u32| {
+            serde_json::from_value::<FlowDef>(json!({
+                "id": "refine",
+                "outputs": [{ "name": "text", "value": "{{ nodes.grow.text }}" }],
+                "nodes": [{
+                    "id": "grow", "type": "loop", "mode": "until",
+                    "condition": "state.text | length >= 4", "max_iterations": max,
+                    "state": { "text": "a" },
+                    "body": {
+                        "nodes": [{ "id": "add", "type": "template", "template": "{{ inputs.text }}{{ inputs.iteration }}" }],
+                        "outputs": [{ "name": "text", "value": "{{ nodes.add }}" }]
+                    }
+                }]
+            }))
+            .unwrap()
+        };
+        let trace = exec.run("r2", refine(5), json!({})).await;
+        assert_eq!(trace.outputs, Some(json!({ "text": "a123" })));
+        assert_eq!(trace.nodes[0].iterations.len(), 3);
+        let trace = exec.run("r3", refine(2), json!({})).await;
+        assert!(trace.error.unwrap().contains("最大轮数 2"));
+
+        let cyclic: FlowDef = serde_json::from_value(json!({
+            "id": "cyclic",
+            "nodes": [
+                { "id": "a", "type": "template", "template": "x" },
+                { "id": "b", "type": "if", "condition": "true" }
+            ],
+            "edges": [
+                { "from": "a", "to": "b", "branch": "yes" },
+                { "from": "b", "to": "a" }
+            ]
+        }))
+        .unwrap();
+        let issues: Vec<String> = graph::build(&cyclic)
+            .unwrap_err()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert!(issues[0].contains("只有 if / switch"), "{:?}", issues);
+        assert!(issues[1].contains("loop 节点"), "{:?}", issues);
+    }
+
+    #[tokio::test]
+    async fn test_map_orders_results_and_applies_error_policy() {
+        let dir = project();
+        let exec = executor(dir.path(), 4);
+        let flow = |template: &str, on_error: &str| {
+            serde_json::from_value::<FlowDef>(json!({
+                "id": "fan",
+                "outputs": [{ "name": "out", "value": "{{ nodes.each }}" }],
+                "nodes": [
+                    { "id": "list", "type": "template", "template": "{{ inputs.items }}" },
+                    {
+                        "id": "each", "type": "map", "items": "{{ nodes.list }}",
+                        "concurrency": 1, "on_error": on_error,
+                        "each": { "id": "write", "type": "template", "template": template }
+                    }
+                ],
+                "edges": [{ "from": "list", "to": "each" }]
+            }))
+            .unwrap()
+        };
+
+        let def = flow("{{ item | upper }}-{{ index }}", "fail_fast");
+        let trace = exec
+            .run("r1", def, json!({ "items": ["a", "bb", "ccc"] }))
+            .await;
+        assert_eq!(
+            trace.outputs,
+            Some(json!({ "out": ["A-0", "BB-1", "CCC-2"] }))
+        );
+
+        let items = json!({ "items": [{ "name": "x" }, {}, { "name": "z" }] });
+        let trace = exec
+            .run("r2", flow("{{ item.name }}", "skip"), items.clone())
+            .await;
+        assert_eq!(trace.outputs, Some(json!({ "out": ["x", "z"] })));
+
+        let trace = exec
+            .run("r3", flow("{{ item.name }}", "collect"), items.clone())
+            .await;
+        let out = &trace.outputs.unwrap()["out"];
+        assert_eq!(out[2], json!("z"));
+        assert!(out[1]["error"].as_str().unwrap().contains("item.name"));
+
+        let trace = exec
+            .run("r4", flow("{{ item.name }}", "fail_fast"), items)
+            .await;
+        assert!(trace.error.unwrap().contains("第 1 个元素执行失败"));
+        let statuses: Vec<TraceStatus> = trace.nodes[1].items.iter().map(|n| n.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                TraceStatus::Succeeded,
+                TraceStatus::Failed,
+                TraceStatus::Skipped
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_input_waits_for_response_and_times_out() {
+        use crate::flow::human::{HumanAction, HumanResponse};
+
+        let dir = project();
+        let exec = executor(dir.path(), 4);
+        let flow = |timeout: Value| {
+            serde_json::from_value::<FlowDef>(json!({
+                "id": "review",
+                "outputs": [{ "name": "out", "value": "{{ nodes.write }}" }],
+                "nodes": [
+                    { "id": "outline", "type": "template", "template": "{{ inputs.topic }} 大纲" },
+                    {
+                        "id": "approve", "type": "input", "prompt": "请审阅 {{ inputs.topic }}",
+                        "value": "{{ nodes.outline }}", "timeout_secs": timeout,
+                        "form": { "type": "string", "minLength": 3 }
+                    },
+                    { "id": "side", "type": "template", "template": "side" },
+                    { "id": "write", "type": "template", "template": "按 {{ nodes.approve }} 写作" }
+                ],
+                "edges": [
+                    { "from": "outline", "to": "approve" },
+                    { "from": "approve", "to": "write" }
+                ]
+            }))
+            .unwrap()
+        };
+        let def = flow(Value::Null);
+        let waiting = exec
+            .run("r1", def.clone(), json!({ "topic": "rust" }))
+            .await;
+        assert_eq!(waiting.status, TraceStatus::Waiting, "{:?}", waiting.error);
+        let statuses: Vec<TraceStatus> = waiting.nodes.iter().map(|n| n.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                TraceStatus::Succeeded,
+                TraceStatus::Waiting,
+                TraceStatus::Succeeded,
+                TraceStatus::Pending
+            ]
+        );
+        let request = waiting.nodes[1].input.clone().unwrap();
+        assert_eq!(request.prompt, "请审阅 rust");
+        assert_eq!(request.value, Some(json!("rust 大纲")));
+
+        let respond = |action, value: Option<Value>| {
+            let response = HumanResponse {
+                action,
+                value,
+                comment: Some("太短".to_string()),
+                responded_at: 0,
+                timed_out: false,
+            };
+            request.check(&response).map(|_| response)
+        };
+        assert!(respond(HumanAction::Edit, None).is_err());
+        assert!(respond(HumanAction::Edit, Some(json!("ab")))
+            .unwrap_err()
+            .contains("不符合表单"));
+
+        // 答复保存在轨迹中，从保存的轨迹继续执行
+        let with_response = |response: HumanResponse| {
+            let mut saved: FlowTrace =
+                serde_json::from_str(&serde_json::to_string(&waiting).unwrap()).unwrap();
+            saved.nodes[1].input.as_mut().unwrap().response = Some(response);
+            saved
+        };
+        let edited = with_response(respond(HumanAction::Edit, Some(json!("新大纲"))).unwrap());
+        let trace = exec.resume("r1", def.clone(), edited).await;
+        assert_eq!(trace.outputs, Some(json!({ "out": "按 新大纲 写作" })));
+        let approved = with_response(respond(HumanAction::Approve, None).unwrap());
+        let trace = exec.resume("r1", def.clone(), approved).await;
+        assert_eq!(trace.outputs, Some(json!({ "out": "按 rust 大纲 写作" })));
+        let rejected = with_response(respond(HumanAction::Reject, None).unwrap());
+        let trace = exec.resume("r1", def, rejected).await;
+        assert!(trace.error.unwrap().contains("已被拒绝: 太短"));
+
+        // 超过期限后按默认动作（拒绝）处理
+        let trace = exec
+            .run("r2", flow(json!(0)), json!({ "topic": "rust" }))
+            .await;
+        assert!(trace.error.unwrap().contains("等待人工输入超时"));
+        let timeout = trace.nodes[1].input.as_ref().unwrap().response.as_ref();
+        assert!(timeout.unwrap().timed_out);
+    }
+
+    /// 第一个节点完成时请求暂停
+    struct PauseAfter(RunControl, &'static str);
+
+    impl EventSink for PauseAfter {
+        fn emit(&self, event: FlowEvent) {
+            if let FlowEvent::NodeFinished { node_id, .. } = &event {
+                if node_id == self.1 {
+                    self.0.pause();
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pause_resume_and_cancel() {
+        let dir = project();
+        write(
+            dir.path(),
+            "vlogi/flows/two.json",
+            json!({
+                "id": "two",
+                "outputs": [{ "name": "out", "value": "{{ nodes.b }}" }],
+                "nodes": [
+                    { "id": "a", "type": "template", "template": "A" },
+                    { "id": "b", "type": "template", "template": "{{ nodes.a }}B" }
+                ],
+                "edges": [{ "from": "a", "to": "b" }]
+            }),
+        );
+
+        let control = RunControl::new();
+        let exec = executor(dir.path(), 4)
+            .with_control(control.clone())
+            .with_events(Arc::new(PauseAfter(control, "a")));
+        let flow = exec.store().load_local("two").unwrap();
+        let paused = exec.run("r1", flow.clone(), json!({})).await;
+        assert_eq!(paused.status, TraceStatus::Paused);
+        assert_eq!(paused.nodes[0].status, TraceStatus::Succeeded);
+        assert_eq!(paused.nodes[1].status, TraceStatus::Pending);
+
+        // 模拟重启后从保存的轨迹继续：a 不再执行
+        let saved: FlowTrace =
+            serde_json::from_str(&serde_json::to_string(&paused).unwrap()).unwrap();
+        let a_finished = paused.nodes[0].finished_at;
+        let resumed = executor(dir.path(), 4)
+            .resume("r1", flow.clone(), saved)
+            .await;
+        assert_eq!(
+            resumed.status,
+            TraceStatus::Succeeded,
+            "{:?}",
+            resumed.error
+        );
+        assert_eq!(resumed.outputs, Some(json!({ "out": "AB" })));
+        assert_eq!(resumed.nodes[0].finished_at, a_finished);
+
+        let control = RunControl::new();
+        control.cancel();
+        let exec = executor(dir.path(), 4).with_control(control);
+        let cancelled = exec.run("r2", flow, json!({})).await;
+        assert_eq!(cancelled.status, TraceStatus::Cancelled);
+        assert!(cancelled
+            .nodes
+            .iter()
+            .all(|n| n.status == TraceStatus::Cancelled));
+    }
+
+    /// 每次都请求调用 `list_dir`，每次调用用掉 100 万输入 token
+    struct Looper;
+
+    #[async_trait]
+    impl Provider for Looper {
+        fn name(&self) -> &str {
+            "looper"
+        }
+
+        async fn chat(&self, _request: &ChatRequest) -> Result<ChatResponse, LlmError> {
+            Ok(ChatResponse {
+                tool_calls: vec![ToolCall {
+                    id: "c".to_string(),
+                    name: "list_dir".to_string(),
+                    arguments: "{}".to_string(),
+                }],
+                usage: Usage {
+                    prompt_tokens: 1_000_000,
+                    completion_tokens: 0,
+                },
+                ..Default::default()
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_budget_stops_agent_between_tool_calls() {
+        let dir = project();
+        let prices =
+            serde_json::from_value(json!([{ "model": "m", "prompt": 1, "completion": 0 }]));
+        let config = BudgetConfig {
+            per_run: Some(1.5),
+            ..Default::default()
+        };
+        let exec = Executor::new(
+            FlowStore::new(Project::open(dir.path()).unwrap()),
+            ProviderRegistry::single(Arc::new(Looper)),
+            ExecOptions::default(),
+        )
+        .with_tools(crate::tools::builtin())
+        .with_prices(PriceTable::new(prices.unwrap()))
+        .with_budget(Arc::new(Budget::new(config, 0.0, 0.0)));
+        let flow = serde_json::from_value(json!({
+            "id": "f",
+            "nodes": [{ "id": "ask", "type": "agent", "model": "m", "prompt": "看看", "tools": ["list_dir"] }]
+        }))
+        .unwrap();
+
+        let trace = exec.run("r", flow, json!({})).await;
+        assert_eq!(trace.status, TraceStatus::Failed);
+        assert_eq!(trace.error_kind, Some(ErrorKind::Budget));
+        // 第二次调用后超出单次上限，不再发起第三次调用
+        assert_eq!(trace.nodes[0].calls.len(), 2);
+        assert!(trace.error.unwrap().contains("超过单次上限"));
+    }
+
+    #[tokio::test]
+    async fn test_budget_pauses_agent_between_tool_calls() {
+        let dir = project();
+        let prices: Vec<_> =
+            serde_json::from_value(json!([{ "model": "m", "prompt": 1, "completion": 0 }]))
+                .unwrap();
+        let exec = |per_run, spent| {
+            let config = BudgetConfig {
+                per_run: Some(per_run),
+                action: BudgetAction::Pause,
+                ..Default::default()
+            };
+            Executor::new(
+                FlowStore::new(Project::open(dir.path()).unwrap()),
+                ProviderRegistry::single(Arc::new(Looper)),
+                ExecOptions::default(),
+            )
+            .with_tools(crate::tools::builtin())
+            .with_prices(PriceTable::new(prices.clone()))
+            .with_budget(Arc::new(Budget::new(config, spent, 0.0)))
+        };
+        let flow: FlowDef = serde_json::from_value(json!({
+            "id": "f",
+            "nodes": [{ "id": "ask", "type": "agent", "model": "m", "prompt": "看看", "tools": ["list_dir"] }]
+        }))
+        .unwrap();
+
+        let paused = exec(1.5, 0.0).run("r", flow.clone(), json!({})).await;
+        assert_eq!(paused.status, TraceStatus::Paused, "{:?}", paused.error);
+        assert_eq!(paused.error, None);
+        // 节点保持待执行，已产生的调用与费用保留
+        assert_eq!(paused.nodes[0].status, TraceStatus::Pending);
+        assert_eq!(paused.nodes[0].calls.len(), 2);
+        assert_eq!(paused.cost(), 2.0);
+
+        // 提高预算后继续：节点重新执行，之前的费用计入本次运行
+        let resumed = exec(2.5, paused.cost()).resume("r", flow, paused).await;
+        assert_eq!(resumed.status, TraceStatus::Paused);
+        assert_eq!(resumed.nodes[0].calls.len(), 1);
+    }
+}
@@ -0,0 +1,95 @@
+//! 子流程节点：把父流程的值映射为子流程输入，递归执行后再把子流程输出映射回来
+
+use serde_json::{Map, Value};
+use std::sync::Arc;
+
+use super::{ExecError, Executor, Frame};
+use crate::flow::model::SubflowNode;
+use crate::flow::template;
+use crate::flow::trace::{NodeTrace, TraceStatus};
+
+pub(super) async fn run(
+    exec: &Executor,
+    node_id: &str,
+    sub: &SubflowNode,
+    scope: &Value,
+    frame: &Frame,
+    trace: &mut NodeTrace,
+) -> Result<Value, ExecError> {
+    let node_err = |message: String| ExecError::Node {
+        node: node_id.to_string(),
+        message,
+    };
+
+    let max_depth = exec.options().max_depth;
+    if frame.depth() + 1 > max_depth {
+        return Err(ExecError::DepthExceeded {
+            node: node_id.to_string(),
+            max_depth,
+        });
+    }
+
+    let child = exec.store().resolve(&sub.target).map_err(node_err)?;
+    let key = match &sub.target.package {
+        Some(pkg) => format!("{}:{}", pkg, child.key()),
+        None => child.key(),
+    };
+    if frame.stack.contains(&key) {
+        let mut path = frame.stack.clone();
+        path.push(key);
+        return Err(ExecError::Cycle {
+            node: node_id.to_string(),
+            path: path.join(" → "),
+        });
+    }
+
+    let mut inputs = Map::new();
+    for (name, binding) in &sub.inputs {
+        let value = match binding {
+            Value::String(source) => {
+                template::render_value(source, scope).map_err(|error| ExecError::Template {
+                    node: node_id.to_string(),
+                    error,
+                })?
+            }
+            literal => literal.clone(),
+        };
+        inputs.insert(name.clone(), value);
+    }
+
+    let child_frame = frame.child(key, node_id, &child);
+    let child_trace = exec
+        .run_flow(Arc::new(child), Value::Object(inputs), child_frame)
+        .await;
+    let result = match child_trace.status {
+        TraceStatus::Succeeded => {
+            let outputs = child_trace.outputs.clone().unwrap_or(Value::Null);
+            map_outputs(sub, outputs).map_err(node_err)
+        }
+        TraceStatus::Cancelled => Err(ExecError::Cancelled),
+        TraceStatus::Paused => Err(ExecError::Paused),
+        _ => Err(node_err(format!(
+            "子流程 {} 执行失败: {}",
+            sub.target,
+            child_trace.error.as_deref().unwrap_or("未知错误")
+        ))),
+    };
+    trace.subflow = Some(Box::new(child_trace));
+    result
+}
+
+/// 按 `outputs` 映射重命名子流程输出；未配置映射时原样返回
+fn map_outputs(sub: &SubflowNode, outputs: Value) -> Result<Value, String> {
+    if sub.outputs.is_empty() {
+        return Ok(outputs);
+    }
+    let mut mapped = Map::new();
+    for (name, child_name) in &sub.outputs {
+        let value = outputs
+            .get(child_name)
+            .cloned()
+            .ok_or_else(|| format!("子流程 {} 没有输出 `{}`", sub.target, child_name))?;
+        mapped.insert(name.clone(), value);
+    }
+    Ok(Value::Object(mapped))
+}
@@ -0,0 +1,308 @@
+//! 流程校验与依赖图
+//!
+//! 执行前统一检查：节点 id 唯一、连线端点存在、不存在环、所有模板、表达式与输出 Schema 可以解析，
+//! 分支标签与分支节点匹配；循环体作为独立的子图递归校验。
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use serde_json::Value;
+
+use super::model::{FlowDef, NodeKind, DEFAULT_BRANCH};
+use super::structured::OutputSchema;
+use super::template::{Expression, Template};
+
+/// 校验发现的问题
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Issue {
+    /// 相关节点，为空表示流程级问题
+    pub node: Option<String>,
+    pub message: String,
+}
+
+impl std::fmt::Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.node {
+            Some(node) => write!(f, "节点 `{}`: {}", node, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// 校验通过后的依赖图
+#[derive(Debug, Clone)]
+pub struct FlowGraph {
+    /// 节点 → 上游节点
+    upstream: HashMap<String, Vec<String>>,
+    /// 拓扑序
+    order: Vec<String>,
+}
+
+impl FlowGraph {
+    pub fn upstream(&self, node: &str) -> &[String] {
+        self.upstream.get(node).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn order(&self) -> &[String] {
+        &self.order
+    }
+}
+
+/// 校验流程并构建依赖图，出错时返回全部问题
+pub fn build(flow: &FlowDef) -> Result<FlowGraph, Vec<Issue>> {
+    let mut issues = Vec::new();
+    let mut issue = |node: Option<&str>, message: String| {
+        issues.push(Issue {
+            node: node.map(str::to_string),
+            message,
+        })
+    };
+
+    let mut ids = HashSet::new();
+    for node in &flow.nodes {
+        if node.id.is_empty() {
+            issue(None, "存在 id 为空的节点".to_string());
+        } else if !ids.insert(node.id.as_str()) {
+            issue(Some(&node.id), "节点 id 重复".to_string());
+        }
+        for (field, source) in templates_of(&node.kind) {
+            if let Err(e) = Template::parse(source) {
+                issue(Some(&node.id), format!("{} {}", field, e));
+            }
+        }
+        for (field, source) in expressions_of(&node.kind) {
+            if let Err(e) = Expression::parse(source) {
+                issue(Some(&node.id), format!("{} {}", field, e));
+            }
+        }
+        match &node.kind {
+            NodeKind::Agent(agent) => {
+                if let Some(Err(e)) = agent.output_schema.as_ref().map(OutputSchema::compile) {
+                    issue(Some(&node.id), e);
+                }
+            }
+            NodeKind::Switch(switch) => {
+                let mut cases = HashSet::new();
+                for case in &switch.cases {
+                    if case == DEFAULT_BRANCH {
+                        issue(
+                            Some(&node.id),
+                            format!("`{}` 是保留的分支名", DEFAULT_BRANCH),
+                        );
+                    } else if !cases.insert(case) {
+                        issue(Some(&node.id), format!("case `{}` 重复", case));
+                    }
+                }
+            }
+            NodeKind::Loop(looping) => {
+                if looping.max_iterations == 0 {
+                    issue(Some(&node.id), "max_iterations 至少为 1".to_string());
+                }
+                if looping.state.contains_key("iteration") {
+                    issue(Some(&node.id), "`iteration` 是保留的状态名".to_string());
+                }
+                if let Err(body_issues) = build(&looping.body_flow(&node.id)) {
+                    for e in body_issues {
+                        issue(Some(&node.id), format!("循环体中 {}", e));
+                    }
+                }
+            }
+            NodeKind::Input(input) => {
+                if let Some(Err(e)) = input.form.as_ref().map(OutputSchema::compile) {
+                    issue(Some(&node.id), format!("form {}", e));
+                }
+            }
+            NodeKind::Map(map) => {
+                if map.concurrency == 0 {
+                    issue(Some(&node.id), "concurrency 至少为 1".to_string());
+                }
+                let each = FlowDef::inline(
+                    format!("{}.each", node.id),
+                    vec![(*map.each).clone()],
+                    Vec::new(),
+                    Vec::new(),
+                );
+                if let Err(each_issues) = build(&each) {
+                    for e in each_issues {
+                        issue(Some(&node.id), format!("each 中 {}", e));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    for output in &flow.outputs {
+        if let Err(e) = Template::parse(&output.value) {
+            issue(None, format!("输出 `{}` {}", output.name, e));
+        }
+    }
+
+    let mut upstream: HashMap<String, Vec<String>> = flow
+        .nodes
+        .iter()
+        .map(|n| (n.id.clone(), Vec::new()))
+        .collect();
+    for edge in &flow.edges {
+        let mut ok = true;
+        for end in [&edge.from, &edge.to] {
+            if !ids.contains(end.as_str()) {
+                issue(
+                    None,
+                    format!(
+                        "连线 {} → {} 引用了不存在的节点 `{}`",
+                        edge.from, edge.to, end
+                    ),
+                );
+                ok = false;
+            }
+        }
+        if let (Some(branch), Some(from)) = (&edge.branch, flow.node(&edge.from)) {
+            let valid = match &from.kind {
+                NodeKind::If(_) => branch == "true" || branch == "false",
+                NodeKind::Switch(switch) => {
+                    branch == DEFAULT_BRANCH || switch.cases.contains(branch)
+                }
+                _ => {
+                    issue(
+                        None,
+                        format!(
+                            "连线 {} → {} 指定了分支，但只有 if / switch 节点的出边可以指定分支",
+                            edge.from, edge.to
+                        ),
+                    );
+                    true
+                }
+            };
+            if !valid {
+                issue(
+                    None,
+                    format!(
+                        "连线 {} → {} 的分支 `{}` 不存在",
+                        edge.from, edge.to, branch
+                    ),
+                );
+            }
+        }
+        if ok {
+            upstream
+                .entry(edge.to.clone())
+                .or_default()
+                .push(edge.from.clone());
+        }
+    }
+
+    let order = match topo_order(flow, &upstream) {
+        Ok(order) => order,
+        Err(cyclic) => {
+            issue(
+                None,
+                format!(
+                    "流程中存在环（需要重复执行时请使用 loop 节点）: {}",
+                    cyclic.join(", ")
+                ),
+            );
+            Vec::new()
+        }
+    };
+
+    if issues.is_empty() {
+        Ok(FlowGraph { upstream, order })
+    } else {
+        Err(issues)
+    }
+}
+
+/// Kahn 算法求拓扑序；存在环时返回环上（及其下游）的节点
+fn topo_order(
+    flow: &FlowDef,
+    upstream: &HashMap<String, Vec<String>>,
+) -> Result<Vec<String>, Vec<String>> {
+    let mut indegree: HashMap<&str, usize> = flow
+        .nodes
+        .iter()
+        .map(|n| (n.id.as_str(), upstream.get(&n.id).map_or(0, Vec::len)))
+        .collect();
+    let mut downstream: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (to, froms) in upstream {
+        for from in froms {
+            downstream
+                .entry(from.as_str())
+                .or_default()
+                .push(to.as_str());
+        }
+    }
+
+    // 按定义顺序出队，使结果稳定
+    let mut queue: VecDeque<&str> = flow
+        .nodes
+        .iter()
+        .map(|n| n.id.as_str())
+        .filter(|id| indegree.get(id) == Some(&0))
+        .collect();
+    let mut order = Vec::with_capacity(flow.nodes.len());
+    while let Some(id) = queue.pop_front() {
+        order.push(id.to_string());
+        for next in downstream.get(id).into_iter().flatten() {
+            let d = indegree.get_mut(next).expect("节点必然存在");
+            *d -= 1;
+            if *d == 0 {
+                queue.push_back(next);
+            }
+        }
+    }
+
+    if order.len() == flow.nodes.len() {
+        Ok(order)
+    } else {
+        Err(flow
+            .nodes
+            .iter()
+            .filter(|n| indegree.get(n.id.as_str()).is_some_and(|d| *d > 0))
+            .map(|n| n.id.clone())
+            .collect())
+    }
+}
+
+/// 节点中所有模板字段（字段名, 模板源码）
+fn templates_of(kind: &NodeKind) -> Vec<(&'static str, &str)> {
+    match kind {
+        NodeKind::Agent(agent) => {
+            let mut out = vec![("prompt", agent.prompt.as_str())];
+            if let Some(system) = &agent.system {
+                out.push(("system", system.as_str()));
+            }
+            out
+        }
+        NodeKind::Template(t) => vec![("template", t.template.as_str())],
+        NodeKind::Subflow(sub) => sub
+            .inputs
+            .values()
+            .filter_map(Value::as_str)
+            .map(|s| ("inputs", s))
+            .collect(),
+        NodeKind::Loop(looping) => looping
+            .state
+            .values()
+            .filter_map(Value::as_str)
+            .map(|s| ("state", s))
+            .collect(),
+        NodeKind::Map(map) => vec![("items", map.items.as_str())],
+        NodeKind::Input(input) => {
+            let mut out = vec![("prompt", input.prompt.as_str())];
+            if let Some(value) = &input.value {
+                out.push(("value", value.as_str()));
+            }
+            out
+        }
+        NodeKind::If(_) | NodeKind::Switch(_) => Vec::new(),
+    }
+}
+
+/// 节点中所有表达式字段（字段名, 表达式源码）
+fn expressions_of(kind: &NodeKind) -> Vec<(&'static str, &str)> {
+    match kind {
+        NodeKind::If(node) => vec![("condition", node.condition.as_str())],
+        NodeKind::Switch(node) => vec![("value", node.value.as_str())],
+        NodeKind::Loop(node) => vec![("condition", node.condition.as_str())],
+        _ => Vec::new(),
+    }
+}
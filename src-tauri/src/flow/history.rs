@@ -0,0 +1,542 @@
+//! 运行历史：每次执行的轨迹保存在 engine.db
+//!
+//! `runs` 表保存运行摘要与完整轨迹（JSON），`run_nodes` 表把嵌套轨迹展开成一行一个节点，
+//! 子流程与循环体中的节点以路径区分（如 `call/up`、`retry/2/draft`），便于按节点查询与排查。
+//! token 与费用按节点记录、按运行汇总，按流程与项目的统计见 [`RunHistory::spend`]。
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::sqlite::SqliteRow;
+use sqlx::{QueryBuilder, Row, Sqlite};
+
+use super::exec::Checkpoint;
+use super::model::FlowDef;
+use super::trace::{now_ms, FlowTrace, NodeTrace, TraceStatus};
+use crate::llm::Usage;
+use crate::utils::enginedb::EngineDb;
+
+/// 运行列表中的一条记录（不含轨迹）
+#[derive(Debug, Clone, Serialize)]
+pub struct RunSummary {
+    pub id: String,
+    pub project: String,
+    pub flow_id: String,
+    pub flow_version: String,
+    pub status: TraceStatus,
+    pub inputs: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub started_at: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<i64>,
+    pub usage: Usage,
+    pub cost: f64,
+}
+
+/// 运行列表过滤条件，字段均可省略
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct RunFilter {
+    /// 项目根目录
+    pub project: Option<String>,
+    pub flow_id: Option<String>,
+    pub status: Option<TraceStatus>,
+    /// 开始时间下限（毫秒，含）
+    pub since: Option<i64>,
+    /// 开始时间上限（毫秒，不含）
+    pub until: Option<i64>,
+    /// 默认 50
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// 费用统计的时间粒度（UTC）
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpendPeriod {
+    Hour,
+    #[default]
+    Day,
+    Week,
+    Month,
+}
+
+impl SpendPeriod {
+    fn format(&self) -> &'static str {
+        match self {
+            SpendPeriod::Hour => "%Y-%m-%dT%H:00",
+            SpendPeriod::Day => "%Y-%m-%d",
+            SpendPeriod::Week => "%Y-W%W",
+            SpendPeriod::Month => "%Y-%m",
+        }
+    }
+}
+
+/// 费用统计的分组维度
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpendGroup {
+    Project,
+    /// 按项目与流程
+    Flow,
+}
+
+/// 费用统计条件，字段均可省略
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SpendFilter {
+    pub project: Option<String>,
+    pub flow_id: Option<String>,
+    /// 开始时间下限（毫秒，含）
+    pub since: Option<i64>,
+    /// 开始时间上限（毫秒，不含）
+    pub until: Option<i64>,
+    pub period: SpendPeriod,
+    /// 为空时只按时间汇总
+    pub group_by: Option<SpendGroup>,
+}
+
+/// 一个时间段（及分组）内的费用汇总
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SpendRow {
+    /// 时间段，如 `2024-05-01`、`2024-W18`
+    pub period: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flow_id: Option<String>,
+    pub runs: i64,
+    pub usage: Usage,
+    pub cost: f64,
+}
+
+/// 运行历史
+#[derive(Clone, Copy)]
+pub struct RunHistory<'a> {
+    db: &'a EngineDb,
+}
+
+impl<'a> RunHistory<'a> {
+    pub fn new(db: &'a EngineDb) -> Self {
+        Self { db }
+    }
+
+    /// 记录一次新的运行（状态为 running）
+    pub async fn start(
+        &self,
+        id: &str,
+        project: &str,
+        flow: &FlowDef,
+        inputs: &Value,
+    ) -> Result<(), String> {
+        sqlx::query(
+            "INSERT INTO runs (id, project, flow_id, flow_version, status, inputs, started_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(id)
+        .bind(project)
+        .bind(&flow.id)
+        .bind(&flow.version)
+        .bind(TraceStatus::Running.as_str())
+        .bind(inputs.to_string())
+        .bind(now_ms())
+        .execute(self.db.pool().await?)
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("记录运行失败: {}", e))
+    }
+
+    /// 保存轨迹（覆盖之前保存的节点记录）；执行中作为检查点定期保存，结束时再保存一次
+    pub async fn save(&self, id: &str, trace: &FlowTrace) -> Result<(), String> {
+        let mut rows = Vec::new();
+        flatten(&trace.nodes, "", &mut rows);
+        let usage = rows.iter().fold(Usage::default(), |mut acc, row| {
+            acc += row.usage;
+            acc
+        });
+        let cost: f64 = rows.iter().filter_map(|row| row.cost).sum();
+
+        let pool = self.db.pool().await?;
+        let mut tx = pool
+            .begin()
+            .await
+            .map_err(|e| format!("保存运行失败: {}", e))?;
+        sqlx::query(
+            "UPDATE runs SET status = ?, inputs = ?, outputs = ?, error = ?, finished_at = ?,
+                 prompt_tokens = ?, completion_tokens = ?, cost = ?, trace = ?
+             WHERE id = ?",
+        )
+        .bind(trace.status.as_str())
+        .bind(trace.inputs.to_string())
+        .bind(trace.outputs.as_ref().map(Value::to_string))
+        .bind(trace.error.clone())
+        .bind(trace.finished_at)
+        .bind(usage.prompt_tokens as i64)
+        .bind(usage.completion_tokens as i64)
+        .bind(cost)
+        .bind(serde_json::to_string(trace).map_err(|e| e.to_string())?)
+        .bind(id.to_string())
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("保存运行失败: {}", e))?;
+
+        sqlx::query("DELETE FROM run_nodes WHERE run_id = ?")
+            .bind(id.to_string())
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("保存节点记录失败: {}", e))?;
+        if !rows.is_empty() {
+            let mut insert: QueryBuilder<Sqlite> = QueryBuilder::new(
+                "INSERT INTO run_nodes (run_id, path, seq, node_id, kind, status, rendered_prompt,
+                     response, output, error, started_at, finished_at, prompt_tokens,
+                     completion_tokens, cost, attempts) ",
+            );
+            insert.push_values(rows, |mut b, row| {
+                b.push_bind(id.to_string())
+                    .push_bind(row.path)
+                    .push_bind(row.seq)
+                    .push_bind(row.node_id)
+                    .push_bind(row.kind)
+                    .push_bind(row.status)
+                    .push_bind(row.rendered_prompt)
+                    .push_bind(row.response)
+                    .push_bind(row.output)
+                    .push_bind(row.error)
+                    .push_bind(row.started_at)
+                    .push_bind(row.finished_at)
+                    .push_bind(row.usage.prompt_tokens as i64)
+                    .push_bind(row.usage.completion_tokens as i64)
+                    .push_bind(row.cost)
+                    .push_bind(row.attempts);
+            });
+            insert
+                .build()
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| format!("保存节点记录失败: {}", e))?;
+        }
+        tx.commit()
+            .await
+            .map_err(|e| format!("保存运行失败: {}", e))
+    }
+
+    /// 按条件列出运行，按开始时间倒序
+    pub async fn list(&self, filter: &RunFilter) -> Result<Vec<RunSummary>, String> {
+        let mut query: QueryBuilder<Sqlite> =
+            QueryBuilder::new(format!("{} WHERE 1 = 1", SUMMARY_SELECT));
+        if let Some(project) = &filter.project {
+            query.push(" AND project = ").push_bind(project);
+        }
+        if let Some(flow_id) = &filter.flow_id {
+            query.push(" AND flow_id = ").push_bind(flow_id);
+        }
+        if let Some(status) = filter.status {
+            query.push(" AND status = ").push_bind(status.as_str());
+        }
+        if let Some(since) = filter.since {
+            query.push(" AND started_at >= ").push_bind(since);
+        }
+        if let Some(until) = filter.until {
+            query.push(" AND started_at < ").push_bind(until);
+        }
+        query
+            .push(" ORDER BY started_at DESC LIMIT ")
+            .push_bind(filter.limit.unwrap_or(50))
+            .push(" OFFSET ")
+            .push_bind(filter.offset.unwrap_or(0));
+
+        let rows = query
+            .build()
+            .fetch_all(self.db.pool().await?)
+            .await
+            .map_err(|e| format!("查询运行历史失败: {}", e))?;
+        rows.iter().map(summary_from_row).collect()
+    }
+
+    /// 读取一次运行的摘要
+    pub async fn summary(&self, id: &str) -> Result<Option<RunSummary>, String> {
+        let row = sqlx::query(&format!("{} WHERE id = ?", SUMMARY_SELECT))
+            .bind(id)
+            .fetch_optional(self.db.pool().await?)
+            .await
+            .map_err(|e| format!("读取运行 {} 失败: {}", id, e))?;
+        row.as_ref().map(summary_from_row).transpose()
+    }
+
+    /// 读取一次运行的完整轨迹；仍在执行中的运行没有轨迹
+    pub async fn get(&self, id: &str) -> Result<Option<FlowTrace>, String> {
+        let raw: Option<Option<String>> = sqlx::query_scalar("SELECT trace FROM runs WHERE id = ?")
+            .bind(id)
+            .fetch_optional(self.db.pool().await?)
+            .await
+            .map_err(|e| format!("读取运行 {} 失败: {}", id, e))?;
+        match raw.flatten() {
+            Some(raw) => serde_json::from_str(&raw)
+                .map(Some)
+                .map_err(|e| format!("运行 {} 的轨迹已损坏: {}", id, e)),
+            None => Ok(None),
+        }
+    }
+
+    /// 项目在 `since` 之后开始的其它运行的费用合计
+    pub async fn spent_since(
+        &self,
+        project: &str,
+        since: i64,
+        exclude: &str,
+    ) -> Result<f64, String> {
+        sqlx::query_scalar(
+            "SELECT TOTAL(cost) FROM runs WHERE project = ? AND started_at >= ? AND id != ?",
+        )
+        .bind(project)
+        .bind(since)
+        .bind(exclude)
+        .fetch_one(self.db.pool().await?)
+        .await
+        .map_err(|e| format!("统计项目费用失败: {}", e))
+    }
+
+    /// 按时间段（及项目 / 流程）汇总 token 与费用，按时间段升序
+    pub async fn spend(&self, filter: &SpendFilter) -> Result<Vec<SpendRow>, String> {
+        let (columns, group) = match filter.group_by {
+            None => ("NULL AS project, NULL AS flow_id", ""),
+            Some(SpendGroup::Project) => ("project, NULL AS flow_id", ", project"),
+            Some(SpendGroup::Flow) => ("project, flow_id", ", project, flow_id"),
+        };
+        let mut query: QueryBuilder<Sqlite> = QueryBuilder::new(format!(
+            "SELECT strftime('{}', started_at / 1000, 'unixepoch') AS period, {},
+                 COUNT(*) AS runs, SUM(prompt_tokens) AS prompt_tokens,
+                 SUM(completion_tokens) AS completion_tokens, TOTAL(cost) AS cost
+             FROM runs WHERE 1 = 1",
+            filter.period.format(),
+            columns
+        ));
+        if let Some(project) = &filter.project {
+            query.push(" AND project = ").push_bind(project);
+        }
+        if let Some(flow_id) = &filter.flow_id {
+            query.push(" AND flow_id = ").push_bind(flow_id);
+        }
+        if let Some(since) = filter.since {
+            query.push(" AND started_at >= ").push_bind(since);
+        }
+        if let Some(until) = filter.until {
+            query.push(" AND started_at < ").push_bind(until);
+        }
+        query.push(format!(
+            " GROUP BY period{} ORDER BY period{}",
+            group, group
+        ));
+
+        let rows = query
+            .build()
+            .fetch_all(self.db.pool().await?)
+            .await
+            .map_err(|e| format!("统计费用失败: {}", e))?;
+        Ok(rows
+            .iter()
+            .map(|row| SpendRow {
+                period: row.get("period"),
+                project: row.get("project"),
+                flow_id: row.get("flow_id"),
+                runs: row.get("runs"),
+                usage: Usage {
+                    prompt_tokens: row.get::<i64, _>("prompt_tokens") as u64,
+                    completion_tokens: row.get::<i64, _>("completion_tokens") as u64,
+                },
+                cost: row.get("cost"),
+            })
+            .collect())
+    }
+
+    /// 删除运行（节点记录级联删除），返回删除条数
+    pub async fn delete(&self, ids: &[String]) -> Result<u64, String> {
+        if ids.is_empty() {
+            return Ok(0);
+        }
+        let mut query: QueryBuilder<Sqlite> = QueryBuilder::new("DELETE FROM runs WHERE id IN (");
+        let mut list = query.separated(", ");
+        for id in ids {
+            list.push_bind(id);
+        }
+        query.push(")");
+        query
+            .build()
+            .execute(self.db.pool().await?)
+            .await
+            .map(|r| r.rows_affected())
+            .map_err(|e| format!("删除运行失败: {}", e))
+    }
+}
+
+#[async_trait]
+impl Checkpoint for RunHistory<'static> {
+    async fn save(&self, trace: &FlowTrace) {
+        let Some(id) = &trace.run_id else {
+            return;
+        };
+        if let Err(e) = RunHistory::save(self, id, trace).await {
+            tracing::warn!("⚠️  保存运行 {} 的检查点失败: {}", id, e);
+        }
+    }
+}
+
+const SUMMARY_SELECT: &str = "SELECT id, project, flow_id, flow_version, status, inputs, error,
+     started_at, finished_at, prompt_tokens, completion_tokens, cost
+ FROM runs";
+
+fn summary_from_row(row: &SqliteRow) -> Result<RunSummary, String> {
+    Ok(RunSummary {
+        id: row.get("id"),
+        project: row.get("project"),
+        flow_id: row.get("flow_id"),
+        flow_version: row.get("flow_version"),
+        status: parse_status(row.get("status"))?,
+        inputs: serde_json::from_str(row.get("inputs")).unwrap_or(Value::Null),
+        error: row.get("error"),
+        started_at: row.get("started_at"),
+        finished_at: row.get("finished_at"),
+        usage: Usage {
+            prompt_tokens: row.get::<i64, _>("prompt_tokens") as u64,
+            completion_tokens: row.get::<i64, _>("completion_tokens") as u64,
+        },
+        cost: row.get("cost"),
+    })
+}
+
+/// run_nodes 表中的一行
+struct NodeRow {
+    path: String,
+    seq: i64,
+    node_id: String,
+    kind: String,
+    status: &'static str,
+    rendered_prompt: Option<String>,
+    response: Option<String>,
+    output: Option<String>,
+    error: Option<String>,
+    started_at: Option<i64>,
+    finished_at: Option<i64>,
+    usage: Usage,
+    cost: Option<f64>,
+    attempts: Option<i64>,
+}
+
+/// 把嵌套轨迹展开为节点行，子流程中的节点排在子流程节点之前
+fn flatten(nodes: &[NodeTrace], prefix: &str, out: &mut Vec<NodeRow>) {
+    for node in nodes {
+        let path = format!("{}{}", prefix, node.node_id);
+        for (suffix, nested) in node.nested() {
+            flatten(&nested.nodes, &format!("{}/{}", path, suffix), out);
+        }
+        for (index, item) in node.items.iter().enumerate() {
+            flatten(
+                std::slice::from_ref(item),
+                &format!("{}/{}/", path, index),
+                out,
+            );
+        }
+        out.push(NodeRow {
+            path,
+            seq: out.len() as i64,
+            node_id: node.node_id.clone(),
+            kind: node.kind.clone(),
+            status: node.status.as_str(),
+            rendered_prompt: node.rendered_prompt.clone(),
+            response: node.response.clone(),
+            output: node.output.as_ref().map(Value::to_string),
+            error: node.error.clone(),
+            started_at: node.started_at,
+            finished_at: node.finished_at,
+            usage: node.usage.unwrap_or_default(),
+            cost: node.cost,
+            attempts: node.attempts.map(i64::from),
+        });
+    }
+}
+
+fn parse_status(raw: &str) -> Result<TraceStatus, String> {
+    serde_json::from_value(Value::String(raw.to_string()))
+        .map_err(|_| format!("未知的运行状态 `{}`", raw))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_history_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = EngineDb::with_path(dir.path().join("engine.db"));
+        let history = RunHistory::new(&db);
+
+        let flow: FlowDef = serde_json::from_value(json!({ "id": "parent" })).unwrap();
+        let inputs = json!({ "topic": "rust" });
+        let id = "run-1".to_string();
+        history.start(&id, "/p", &flow, &inputs).await.unwrap();
+        assert_eq!(
+            history.list(&RunFilter::default()).await.unwrap()[0].status,
+            TraceStatus::Running
+        );
+        assert!(history.get(&id).await.unwrap().is_none());
+
+        let mut trace = FlowTrace::new("parent", "0.1.0", inputs);
+
+        let mut child = FlowTrace::new("child", "1.0.0", json!({}));
+        let mut leaf = NodeTrace::new("up", "agent");
+        leaf.usage = Some(Usage {
+            prompt_tokens: 10,
+            completion_tokens: 5,
+        });
+        leaf.cost = Some(0.25);
+        leaf.succeed(json!("X"));
+        child.nodes.push(leaf);
+        let mut call = NodeTrace::new("call", "subflow");
+        call.subflow = Some(Box::new(child));
+        call.succeed(json!({}));
+        trace.nodes.push(call);
+        trace.succeed(json!({ "result": "X" }));
+        history.save(&id, &trace).await.unwrap();
+
+        let runs = history
+            .list(&RunFilter {
+                project: Some("/p".to_string()),
+                status: Some(TraceStatus::Succeeded),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].usage.total(), 15);
+        assert_eq!(runs[0].cost, 0.25);
+        let spend = history
+            .spend(&SpendFilter {
+                group_by: Some(SpendGroup::Project),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(spend.len(), 1);
+        assert_eq!(spend[0].project.as_deref(), Some("/p"));
+        assert_eq!((spend[0].runs, spend[0].cost), (1, 0.25));
+
+        let paths: Vec<String> =
+            sqlx::query_scalar("SELECT path FROM run_nodes WHERE run_id = ? ORDER BY seq")
+                .bind(&id)
+                .fetch_all(db.pool().await.unwrap())
+                .await
+                .unwrap();
+        assert_eq!(paths, ["call/up", "call"]);
+
+        let saved = history.get(&id).await.unwrap().unwrap();
+        assert_eq!(saved.outputs, Some(json!({ "result": "X" })));
+        assert_eq!(history.delete(std::slice::from_ref(&id)).await.unwrap(), 1);
+        let left: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM run_nodes")
+            .fetch_one(db.pool().await.unwrap())
+            .await
+            .unwrap();
+        assert_eq!(left, 0);
+    }
+}
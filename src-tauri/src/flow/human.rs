@@ -0,0 +1,121 @@
+//! 人工审批与输入：`input` 节点挂起运行，等待用户答复
+//!
+//! 请求与答复都保存在节点轨迹中，运行以 `waiting` 状态结束并写入运行历史；
+//! 用户提交答复后运行从历史继续执行，因此等待期间重启应用不会丢失状态。
+//! 超过期限仍未答复时，继续执行时按节点配置的默认动作处理。
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::structured::OutputSchema;
+
+/// 用户的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HumanAction {
+    /// 通过待审内容
+    Approve,
+    /// 以修改后的内容（或填写的表单）作为节点输出
+    Edit,
+    /// 拒绝，节点失败
+    Reject,
+}
+
+/// 用户对 `input` 节点的答复
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HumanResponse {
+    pub action: HumanAction,
+    /// `edit` 时的新内容
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    /// 答复时间（毫秒），由后端记录
+    #[serde(default)]
+    pub responded_at: i64,
+    /// 超时后按默认动作自动答复
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub timed_out: bool,
+}
+
+impl HumanResponse {
+    /// 超时后的自动答复
+    pub fn timeout(action: HumanAction, now: i64) -> Self {
+        Self {
+            action,
+            value: None,
+            comment: None,
+            responded_at: now,
+            timed_out: true,
+        }
+    }
+}
+
+/// `input` 节点发出的请求及用户的答复
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HumanInput {
+    /// 渲染后的说明
+    pub prompt: String,
+    /// 渲染后的待审内容
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<Value>,
+    /// 表单的 JSON Schema
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub form: Option<Value>,
+    pub requested_at: i64,
+    /// 答复期限（毫秒），为空表示一直等待
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deadline: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<HumanResponse>,
+}
+
+impl HumanInput {
+    /// 尚未答复且已超过期限
+    pub fn expired(&self, now: i64) -> bool {
+        self.response.is_none() && self.deadline.is_some_and(|deadline| deadline <= now)
+    }
+
+    /// 检查答复是否可以接受：`edit` 必须提供内容，设置了表单时内容需符合表单；
+    /// 只有表单、没有待审内容的请求不能直接 `approve`
+    pub fn check(&self, response: &HumanResponse) -> Result<(), String> {
+        match response.action {
+            HumanAction::Reject => Ok(()),
+            HumanAction::Approve => {
+                if self.value.is_none() && self.form.is_some() {
+                    return Err("该节点需要填写表单，请以 edit 提交".to_string());
+                }
+                Ok(())
+            }
+            HumanAction::Edit => {
+                let value = response
+                    .value
+                    .as_ref()
+                    .ok_or_else(|| "edit 需要提供 value".to_string())?;
+                let Some(form) = &self.form else {
+                    return Ok(());
+                };
+                let errors = OutputSchema::compile(form)?.check(value);
+                if errors.is_empty() {
+                    Ok(())
+                } else {
+                    Err(format!("内容不符合表单: {}", errors.join("; ")))
+                }
+            }
+        }
+    }
+
+    /// 按答复得到节点输出，尚未答复时为空；拒绝时返回失败原因
+    pub fn outcome(&self) -> Option<Result<Value, String>> {
+        let response = self.response.as_ref()?;
+        Some(match response.action {
+            HumanAction::Approve => Ok(self.value.clone().unwrap_or(Value::Null)),
+            HumanAction::Edit => Ok(response.value.clone().unwrap_or(Value::Null)),
+            HumanAction::Reject if response.timed_out => Err("等待人工输入超时".to_string()),
+            HumanAction::Reject => Err(match &response.comment {
+                Some(comment) => format!("已被拒绝: {}", comment),
+                None => "已被拒绝".to_string(),
+            }),
+        })
+    }
+}
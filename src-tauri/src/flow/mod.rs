@@ -0,0 +1,18 @@
+//! PromptFlow 引擎
+//!
+//! 负责流程定义的解析与执行，与 Tauri 解耦，commands 层只做参数转换。
+
+pub mod budget;
+pub mod cache;
+pub mod control;
+pub mod events;
+pub mod exec;
+pub mod graph;
+pub mod history;
+pub mod human;
+pub mod model;
+pub mod replay;
+pub mod store;
+pub mod structured;
+pub mod template;
+pub mod trace;
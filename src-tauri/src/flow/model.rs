@@ -0,0 +1,506 @@
+//! PromptFlow 定义（与前端编辑器保存的 JSON5 文件一一对应）
+//!
+//! 节点之间的数据通过模板引用：节点提示词、子流程输入映射、流程输出都是模板字符串，
+//! 渲染上下文为 `{ "inputs": <流程输入>, "nodes": { <节点id>: <节点输出> } }`。
+//!
+//! 控制流：`if` / `switch` 节点选择分支，出边上的 `branch` 标明所属分支，未选中的分支被跳过；
+//! 节点只要有一条入边有效就会执行（未执行的上游不出现在 `nodes` 中）。`loop` 节点重复执行内嵌的
+//! 循环体，是流程中唯一允许重复执行的地方，流程图本身必须无环。
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// 流程定义
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlowDef {
+    pub id: String,
+    #[serde(default = "default_version")]
+    pub version: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// 流程输入声明
+    #[serde(default)]
+    pub inputs: Vec<InputDef>,
+    /// 流程输出声明
+    #[serde(default)]
+    pub outputs: Vec<OutputDef>,
+    #[serde(default)]
+    pub nodes: Vec<NodeDef>,
+    #[serde(default)]
+    pub edges: Vec<EdgeDef>,
+    /// 流程中 agent 调用工具的权限
+    #[serde(default)]
+    pub permissions: Permissions,
+}
+
+/// 流程权限（默认全部关闭）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Permissions {
+    /// 允许工具写入项目文件
+    #[serde(default)]
+    pub fs_write: bool,
+    /// 允许 agent 调用的 MCP 服务（`vlogi/mcp.json5` 中的服务名），`*` 表示全部
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mcp: Vec<String>,
+}
+
+impl Permissions {
+    /// 子流程可用的 MCP 服务：调用方与子流程都允许的服务
+    pub fn mcp_within(&self, parent: &[String]) -> Vec<String> {
+        if self.mcp.iter().any(|s| s == "*") {
+            return parent.to_vec();
+        }
+        (self.mcp.iter())
+            .filter(|server| mcp_allowed(parent, server))
+            .cloned()
+            .collect()
+    }
+}
+
+/// MCP 服务是否在白名单中
+pub fn mcp_allowed(list: &[String], server: &str) -> bool {
+    list.iter().any(|s| s == "*" || s == server)
+}
+
+fn default_version() -> String {
+    "0.1.0".to_string()
+}
+
+impl FlowDef {
+    /// 内嵌在节点中的子图（循环体、map 的元素节点），写入权限由所在流程决定
+    pub fn inline(
+        id: String,
+        nodes: Vec<NodeDef>,
+        edges: Vec<EdgeDef>,
+        outputs: Vec<OutputDef>,
+    ) -> Self {
+        Self {
+            id,
+            version: default_version(),
+            name: String::new(),
+            description: String::new(),
+            inputs: Vec::new(),
+            outputs,
+            nodes,
+            edges,
+            permissions: Permissions {
+                fs_write: true,
+                mcp: vec!["*".to_string()],
+            },
+        }
+    }
+
+    pub fn node(&self, id: &str) -> Option<&NodeDef> {
+        self.nodes.iter().find(|n| n.id == id)
+    }
+
+    /// 流程的唯一标识（`id@version`），用于循环检测与日志
+    pub fn key(&self) -> String {
+        format!("{}@{}", self.id, self.version)
+    }
+
+    /// 流程输入的 JSON Schema：以输入名为属性的对象，无默认值的输入为必填
+    ///
+    /// 声明中的 `schema` 原样使用，未声明时为任意值。供本地 API 与 MCP 对外描述流程。
+    pub fn input_schema(&self) -> Value {
+        let mut properties = Map::new();
+        let mut required = Vec::new();
+        for input in &self.inputs {
+            let mut schema = declared_schema(input.schema.as_ref(), &input.description);
+            if let Some(default) = &input.default {
+                schema["default"] = default.clone();
+            } else {
+                required.push(input.name.clone());
+            }
+            properties.insert(input.name.clone(), schema);
+        }
+        object_schema(properties, required)
+    }
+
+    /// 流程输出的 JSON Schema：以输出名为属性的对象，输出全部必有
+    pub fn output_schema(&self) -> Value {
+        let mut properties = Map::new();
+        for output in &self.outputs {
+            let schema = declared_schema(output.schema.as_ref(), &output.description);
+            properties.insert(output.name.clone(), schema);
+        }
+        let required = properties.keys().cloned().collect();
+        object_schema(properties, required)
+    }
+}
+
+fn object_schema(properties: Map<String, Value>, required: Vec<String>) -> Value {
+    let mut schema = json!({ "type": "object", "properties": properties });
+    if !required.is_empty() {
+        schema["required"] = json!(required);
+    }
+    schema
+}
+
+/// 声明的 schema，没有描述时补上声明中的描述；非对象的 schema 视为未声明
+fn declared_schema(schema: Option<&Value>, description: &str) -> Value {
+    let mut schema = match schema {
+        Some(Value::Object(map)) => Value::Object(map.clone()),
+        _ => json!({}),
+    };
+    if !description.is_empty() && schema.get("description").is_none() {
+        schema["description"] = json!(description);
+    }
+    schema
+}
+
+/// 流程输入声明
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputDef {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// JSON Schema（可选）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<Value>,
+    /// 未提供时的默认值；无默认值的输入为必填
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<Value>,
+}
+
+/// 流程输出声明
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputDef {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// 输出值模板，如 `{{ nodes.writer }}`
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<Value>,
+}
+
+/// 连线：`from` 执行完成后才会执行 `to`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EdgeDef {
+    pub from: String,
+    pub to: String,
+    /// 分支标签，仅用于 `if`（`true` / `false`）与 `switch`（case 或 `default`）的出边
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+}
+
+/// 节点定义
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeDef {
+    pub id: String,
+    #[serde(default)]
+    pub label: String,
+    #[serde(flatten)]
+    pub kind: NodeKind,
+}
+
+/// 节点类型，以 `type` 字段区分
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NodeKind {
+    /// 调用大模型
+    Agent(AgentNode),
+    /// 仅渲染模板，不调用模型
+    Template(TemplateNode),
+    /// 调用另一个流程
+    Subflow(SubflowNode),
+    /// 按条件选择 `true` / `false` 分支
+    If(IfNode),
+    /// 按值选择分支
+    Switch(SwitchNode),
+    /// 重复执行循环体
+    Loop(LoopNode),
+    /// 对列表中的每个元素执行一次节点
+    Map(MapNode),
+    /// 挂起运行，等待人工审批或填写
+    Input(InputNode),
+}
+
+impl NodeKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            NodeKind::Agent(_) => "agent",
+            NodeKind::Template(_) => "template",
+            NodeKind::Subflow(_) => "subflow",
+            NodeKind::If(_) => "if",
+            NodeKind::Switch(_) => "switch",
+            NodeKind::Loop(_) => "loop",
+            NodeKind::Map(_) => "map",
+            NodeKind::Input(_) => "input",
+        }
+    }
+
+    /// 分支节点按输出选中的分支标签
+    pub fn selected_branch(&self, output: &Value) -> Option<String> {
+        match self {
+            NodeKind::If(_) => output.as_bool().map(|b| b.to_string()),
+            NodeKind::Switch(_) => output.as_str().map(str::to_string),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentNode {
+    /// provider 名称，为空时使用默认 provider
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// 密钥库中的密钥名，设置后以该密钥作为 API Key 调用 provider（覆盖 provider 配置）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_secret: Option<String>,
+    pub model: String,
+    /// 系统提示词模板
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    /// 用户提示词模板
+    pub prompt: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// 输出的 JSON Schema；设置后要求模型输出 JSON，校验通过后以 JSON 值传给下游
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<Value>,
+    /// 结构化输出校验失败时的最大调用次数（含首次）
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    /// 缓存节点输出：请求与 Schema 不变时直接复用之前的结果
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cache: bool,
+    /// 缓存有效期（秒），为空时使用默认值
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_ttl: Option<u64>,
+    /// 允许模型调用的工具名称
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<String>,
+    /// 工具调用的最大轮数
+    #[serde(default = "default_max_tool_iterations")]
+    pub max_tool_iterations: u32,
+    /// 注入用户记忆：执行前检索相关记忆并加入系统提示词
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<MemoryOptions>,
+}
+
+/// agent 节点的记忆注入设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryOptions {
+    /// 注入记忆的 token 上限
+    #[serde(default = "default_memory_budget")]
+    pub budget: u32,
+    /// 检索查询模板，为空时以用户提示词检索
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+}
+
+fn default_max_attempts() -> u32 {
+    3
+}
+
+fn default_max_tool_iterations() -> u32 {
+    8
+}
+
+fn default_memory_budget() -> u32 {
+    500
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateNode {
+    /// 输出模板；单个 `{{ expr }}` 时输出原始 JSON 值
+    pub template: String,
+}
+
+/// 对其它流程的引用
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlowRef {
+    /// 流程 id
+    pub flow: String,
+    /// 版本要求；为空时取本项目中的流程或包内的最高版本
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// 包名；为空表示本项目中的流程
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+}
+
+impl fmt::Display for FlowRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(pkg) = &self.package {
+            write!(f, "{}:", pkg)?;
+        }
+        write!(f, "{}", self.flow)?;
+        if let Some(ver) = &self.version {
+            write!(f, "@{}", ver)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubflowNode {
+    #[serde(flatten)]
+    pub target: FlowRef,
+    /// 子流程输入映射：子流程输入名 → 模板（非字符串值按字面量传入）
+    #[serde(default)]
+    pub inputs: BTreeMap<String, Value>,
+    /// 子流程输出映射：本节点输出名 → 子流程输出名；为空时原样输出子流程全部输出
+    #[serde(default)]
+    pub outputs: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IfNode {
+    /// 条件表达式，输出为其真值
+    pub condition: String,
+}
+
+/// switch 未匹配任何 case 时的分支标签
+pub const DEFAULT_BRANCH: &str = "default";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwitchNode {
+    /// 取值表达式，结果按文本与 `cases` 比较
+    pub value: String,
+    pub cases: Vec<String>,
+}
+
+/// 循环条件的检查时机
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoopMode {
+    /// 每轮开始前检查，条件为真时执行
+    #[default]
+    While,
+    /// 每轮结束后检查，条件为真时结束
+    Until,
+}
+
+/// 达到最大轮数后的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoopLimit {
+    /// 节点失败
+    #[default]
+    Fail,
+    /// 结束循环，输出当前状态
+    Exit,
+}
+
+/// 循环节点
+///
+/// 循环体以 `state` 为输入执行，`iteration` 为当前轮次（从 1 开始）；循环体的输出合并回 `state`。
+/// 条件的上下文为所在流程的 `inputs` / `nodes`，加上 `state` 与已完成轮数 `iteration`。
+/// 节点输出为最终的 `state`。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoopNode {
+    #[serde(default)]
+    pub mode: LoopMode,
+    pub condition: String,
+    #[serde(default = "default_max_iterations")]
+    pub max_iterations: u32,
+    #[serde(default)]
+    pub on_max_iterations: LoopLimit,
+    /// 初始状态：状态名 → 模板（非字符串值按字面量传入）
+    #[serde(default)]
+    pub state: BTreeMap<String, Value>,
+    pub body: LoopBody,
+}
+
+fn default_max_iterations() -> u32 {
+    10
+}
+
+/// 循环体：内嵌的子图
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoopBody {
+    #[serde(default)]
+    pub nodes: Vec<NodeDef>,
+    #[serde(default)]
+    pub edges: Vec<EdgeDef>,
+    /// 每轮结束后写回 `state` 的值
+    #[serde(default)]
+    pub outputs: Vec<OutputDef>,
+}
+
+impl LoopNode {
+    /// 循环体对应的流程定义
+    pub fn body_flow(&self, node_id: &str) -> FlowDef {
+        FlowDef::inline(
+            format!("{}.body", node_id),
+            self.body.nodes.clone(),
+            self.body.edges.clone(),
+            self.body.outputs.clone(),
+        )
+    }
+}
+
+/// map 中单个元素失败时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MapErrorPolicy {
+    /// 不再启动新的元素，节点失败
+    #[default]
+    FailFast,
+    /// 从结果中去掉失败的元素
+    Skip,
+    /// 失败的元素在结果中记为 `{ "error": "..." }`
+    Collect,
+}
+
+/// map 节点
+///
+/// `each` 的模板上下文在所在流程的 `inputs` / `nodes` 之外增加 `item`（当前元素）与 `index`（从 0 开始）。
+/// 节点输出为按输入顺序排列的结果数组。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MapNode {
+    /// 列表模板，如 `{{ nodes.outline.sections }}`
+    pub items: String,
+    /// 对每个元素执行的节点（agent、subflow 等）
+    pub each: Box<NodeDef>,
+    /// 同时执行的元素数
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+    #[serde(default)]
+    pub on_error: MapErrorPolicy,
+}
+
+fn default_concurrency() -> usize {
+    4
+}
+
+/// 超时未答复时的默认动作
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputTimeout {
+    /// 节点失败
+    #[default]
+    Reject,
+    /// 通过待审内容
+    Approve,
+}
+
+/// 人工审批与输入节点
+///
+/// 执行到该节点时运行挂起，等待用户通过、修改或拒绝，见 [`crate::flow::human`]。
+/// 通过时输出 `value`，修改时输出用户提交的内容，拒绝时节点失败。只能用于根流程。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputNode {
+    /// 展示给用户的说明（模板）
+    pub prompt: String,
+    /// 待审内容模板，如 `{{ nodes.outline }}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    /// 用户填写或修改内容时使用的表单（JSON Schema）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub form: Option<Value>,
+    /// 等待期限（秒），为空表示一直等待
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    #[serde(default)]
+    pub on_timeout: InputTimeout,
+}
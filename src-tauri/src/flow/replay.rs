@@ -0,0 +1,445 @@
+//! 运行回放：用录制的模型响应重新执行流程，并逐节点比较与原运行的差异
+//!
+//! 录制来自轨迹中每个 agent 节点的 `calls`，回放时模型请求按哈希匹配录制的响应，
+//! 不会访问真实模型。提示词模板、模型参数等变化会使请求哈希不同，从而被报告为差异。
+//! 工具调用同样不会真正执行：工具声明取自录制的 `tool_specs`，调用按节点中的顺序从录制的
+//! `tool_calls` 中取出结果，录制中没有对应声明或调用时节点失败。
+
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use super::trace::{FlowTrace, NodeTrace, ToolCallTrace};
+use crate::llm::cassette::Cassette;
+use crate::llm::ToolSpec;
+
+/// 差异类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DivergenceKind {
+    /// 录制中的节点在当前流程中已不存在
+    Removed,
+    /// 当前流程新增的节点
+    Added,
+    /// 模型请求与录制不一致
+    RequestChanged,
+    /// 节点状态不同
+    StatusChanged,
+    /// 节点输出不同
+    OutputChanged,
+}
+
+/// 单个节点的差异
+#[derive(Debug, Clone, Serialize)]
+pub struct Divergence {
+    /// 节点路径（子流程中的节点为 `<子流程节点id>/<节点id>`）
+    pub path: String,
+    pub kind: DivergenceKind,
+    pub detail: String,
+}
+
+/// 回放结果
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayReport {
+    /// 被回放的运行
+    pub source_run_id: String,
+    /// 回放得到的轨迹
+    pub trace: FlowTrace,
+    /// 为空表示行为与录制一致
+    pub divergences: Vec<Divergence>,
+}
+
+/// 用轨迹（含子流程）中录制的模型响应构建 cassette
+pub fn cassette(trace: &FlowTrace) -> Cassette {
+    let cassette = Cassette::new();
+    let mut nodes = Vec::new();
+    flatten(&trace.nodes, "", &mut nodes);
+    for (_, node) in nodes {
+        for call in &node.calls {
+            if let Some(response) = &call.response {
+                cassette.insert(call.request_hash.clone(), response.clone());
+            }
+        }
+    }
+    cassette
+}
+
+/// 录制的工具声明与调用：按节点路径分组，每个节点内按调用顺序取出调用
+#[derive(Debug, Default)]
+pub struct ToolRecording {
+    specs: HashMap<String, Vec<ToolSpec>>,
+    calls: Mutex<HashMap<String, VecDeque<ToolCallTrace>>>,
+}
+
+impl ToolRecording {
+    /// 节点声明的工具在录制中的声明（按 `names` 的顺序）
+    pub fn specs(&self, path: &str, names: &[String]) -> Result<Vec<ToolSpec>, String> {
+        let recorded = self.specs.get(path).map(Vec::as_slice).unwrap_or_default();
+        names
+            .iter()
+            .map(|name| {
+                (recorded.iter().find(|spec| &spec.name == name).cloned())
+                    .ok_or_else(|| format!("录制中没有工具 `{}` 的声明", name))
+            })
+            .collect()
+    }
+
+    /// 取出节点的下一次工具调用，名称与录制不一致或录制中已没有调用时报错
+    pub fn take(&self, path: &str, name: &str) -> Result<ToolCallTrace, String> {
+        let call = (self.calls.lock().unwrap_or_else(|e| e.into_inner()))
+            .get_mut(path)
+            .and_then(VecDeque::pop_front)
+            .ok_or_else(|| format!("录制中没有工具调用 `{}`", name))?;
+        if call.name != name {
+            return Err(format!(
+                "工具调用 `{}` 与录制不一致（录制为 `{}`）",
+                name, call.name
+            ));
+        }
+        Ok(call)
+    }
+}
+
+/// 用轨迹（含子流程）中录制的工具调用构建 [`ToolRecording`]
+pub fn tool_recording(trace: &FlowTrace) -> ToolRecording {
+    let mut nodes = Vec::new();
+    flatten(&trace.nodes, "", &mut nodes);
+    let specs = (nodes.iter())
+        .filter(|(_, node)| !node.tool_specs.is_empty())
+        .map(|(path, node)| (path.clone(), node.tool_specs.clone()))
+        .collect();
+    let calls = (nodes.iter())
+        .filter(|(_, node)| !node.tool_calls.is_empty())
+        .map(|(path, node)| (path.clone(), node.tool_calls.iter().cloned().collect()))
+        .collect();
+    ToolRecording {
+        specs,
+        calls: Mutex::new(calls),
+    }
+}
+
+/// 逐节点比较录制与回放的轨迹，每个节点只报告最主要的一处差异
+pub fn compare(recorded: &FlowTrace, replayed: &FlowTrace) -> Vec<Divergence> {
+    let mut before = Vec::new();
+    flatten(&recorded.nodes, "", &mut before);
+    let mut after = Vec::new();
+    flatten(&replayed.nodes, "", &mut after);
+    let after_by_path: HashMap<&str, &NodeTrace> =
+        after.iter().map(|(p, n)| (p.as_str(), *n)).collect();
+
+    let mut divergences = Vec::new();
+    for (path, old) in &before {
+        let Some(new) = after_by_path.get(path.as_str()) else {
+            divergences.push(Divergence {
+                path: path.clone(),
+                kind: DivergenceKind::Removed,
+                detail: "节点在当前流程中已不存在".to_string(),
+            });
+            continue;
+        };
+        if let Some(divergence) = compare_node(path, old, new) {
+            divergences.push(divergence);
+        }
+    }
+    for (path, _) in &after {
+        if !before.iter().any(|(p, _)| p == path) {
+            divergences.push(Divergence {
+                path: path.clone(),
+                kind: DivergenceKind::Added,
+                detail: "录制中没有该节点".to_string(),
+            });
+        }
+    }
+    divergences
+}
+
+fn compare_node(path: &str, old: &NodeTrace, new: &NodeTrace) -> Option<Divergence> {
+    let divergence = |kind, detail: String| {
+        Some(Divergence {
+            path: path.to_string(),
+            kind,
+            detail,
+        })
+    };
+
+    let changed = old
+        .calls
+        .iter()
+        .zip(&new.calls)
+        .position(|(a, b)| a.request_hash != b.request_hash);
+    if let Some(i) = changed {
+        return divergence(
+            DivergenceKind::RequestChanged,
+            format!(
+                "第 {} 次模型请求与录制不一致（提示词、模型或参数已变化）",
+                i + 1
+            ),
+        );
+    }
+    if old.status != new.status {
+        return divergence(
+            DivergenceKind::StatusChanged,
+            match &new.error {
+                Some(error) => format!(
+                    "录制为 {}，回放为 {}: {}",
+                    old.status.as_str(),
+                    new.status.as_str(),
+                    error
+                ),
+                None => format!(
+                    "录制为 {}，回放为 {}",
+                    old.status.as_str(),
+                    new.status.as_str()
+                ),
+            },
+        );
+    }
+    if old.calls.len() != new.calls.len() {
+        return divergence(
+            DivergenceKind::RequestChanged,
+            format!(
+                "模型调用次数由 {} 变为 {}",
+                old.calls.len(),
+                new.calls.len()
+            ),
+        );
+    }
+    if old.output != new.output {
+        return divergence(
+            DivergenceKind::OutputChanged,
+            "输出与录制不一致".to_string(),
+        );
+    }
+    None
+}
+
+/// 把嵌套轨迹展开为 (路径, 节点)
+fn flatten<'t>(nodes: &'t [NodeTrace], prefix: &str, out: &mut Vec<(String, &'t NodeTrace)>) {
+    for node in nodes {
+        let path = format!("{}{}", prefix, node.node_id);
+        for (suffix, nested) in node.nested() {
+            flatten(&nested.nodes, &format!("{}/{}", path, suffix), out);
+        }
+        for (index, item) in node.items.iter().enumerate() {
+            flatten(
+                std::slice::from_ref(item),
+                &format!("{}/{}/", path, index),
+                out,
+            );
+        }
+        out.push((path, node));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow::exec::{ExecOptions, Executor};
+    use crate::flow::model::FlowDef;
+    use crate::flow::store::FlowStore;
+    use crate::flow::trace::TraceStatus;
+    use crate::llm::registry::ProviderRegistry;
+    use crate::llm::{ChatRequest, ChatResponse, LlmError, Provider, Role, ToolCall};
+    use crate::project::Project;
+    use crate::tools;
+    use crate::vault::Vault;
+    use async_trait::async_trait;
+    use serde_json::json;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    /// 把最后一条消息转成大写返回
+    struct Shout;
+
+    #[async_trait]
+    impl Provider for Shout {
+        fn name(&self) -> &str {
+            "shout"
+        }
+
+        async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, LlmError> {
+            let last = request.messages.last().map(|m| m.content.as_str());
+            Ok(ChatResponse {
+                content: last.unwrap_or_default().to_uppercase(),
+                ..Default::default()
+            })
+        }
+    }
+
+    /// 先调用 `write_file` 写入 out.txt，收到工具结果后结束
+    struct Writer;
+
+    #[async_trait]
+    impl Provider for Writer {
+        fn name(&self) -> &str {
+            "writer"
+        }
+
+        async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, LlmError> {
+            if request
+                .messages
+                .last()
+                .is_some_and(|m| m.role == Role::Tool)
+            {
+                return Ok(ChatResponse {
+                    content: "done".to_string(),
+                    ..Default::default()
+                });
+            }
+            Ok(ChatResponse {
+                tool_calls: vec![ToolCall {
+                    id: "c1".to_string(),
+                    name: "write_file".to_string(),
+                    arguments: r#"{"path": "out.txt", "content": "hi"}"#.to_string(),
+                }],
+                ..Default::default()
+            })
+        }
+    }
+
+    fn flow(prompt: &str) -> FlowDef {
+        serde_json::from_value(json!({
+            "id": "f",
+            "nodes": [
+                { "id": "ask", "type": "agent", "model": "m", "prompt": prompt },
+                { "id": "wrap", "type": "template", "template": "[{{ nodes.ask }}]" }
+            ],
+            "edges": [{ "from": "ask", "to": "wrap" }]
+        }))
+        .unwrap()
+    }
+
+    fn executor(root: &Path, providers: ProviderRegistry) -> Executor {
+        std::fs::create_dir_all(root.join("vlogi")).unwrap();
+        std::fs::write(root.join("vlogi/meta.json5"), "{}").unwrap();
+        let store = FlowStore::new(Project::open(root).unwrap());
+        Executor::new(store, providers, ExecOptions::default())
+    }
+
+    async fn run(providers: ProviderRegistry, def: FlowDef) -> FlowTrace {
+        let dir = tempfile::tempdir().unwrap();
+        executor(dir.path(), providers)
+            .run("r", def, json!({}))
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_replay_matches_and_reports_changed_prompt() {
+        let recorded = run(ProviderRegistry::single(Arc::new(Shout)), flow("hello")).await;
+        assert_eq!(recorded.nodes[1].output, Some(json!("[HELLO]")));
+
+        let replay = ProviderRegistry::single(Arc::new(cassette(&recorded)));
+        let replayed = run(replay, flow("hello")).await;
+        assert_eq!(replayed.status, TraceStatus::Succeeded);
+        assert!(compare(&recorded, &replayed).is_empty());
+
+        let replay = ProviderRegistry::single(Arc::new(cassette(&recorded)));
+        let replayed = run(replay, flow("hello again")).await;
+        let divergences = compare(&recorded, &replayed);
+        assert_eq!(divergences[0].path, "ask");
+        assert_eq!(divergences[0].kind, DivergenceKind::RequestChanged);
+        assert_eq!(divergences[1].path, "wrap");
+        assert_eq!(divergences[1].kind, DivergenceKind::StatusChanged);
+    }
+
+    #[tokio::test]
+    async fn test_replay_answers_tool_calls_from_recording() {
+        let def: FlowDef = serde_json::from_value(json!({
+            "id": "f",
+            "permissions": { "fs_write": true },
+            "nodes": [{ "id": "ask", "type": "agent", "model": "m", "prompt": "写文件", "tools": ["write_file"] }]
+        }))
+        .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out.txt");
+        let recorded = executor(dir.path(), ProviderRegistry::single(Arc::new(Writer)))
+            .with_tools(tools::builtin())
+            .run("r", def.clone(), json!({}))
+            .await;
+        assert_eq!(
+            recorded.status,
+            TraceStatus::Succeeded,
+            "{:?}",
+            recorded.error
+        );
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), "hi");
+        std::fs::remove_file(&out).unwrap();
+
+        let replay = |recording: ToolRecording| {
+            executor(
+                dir.path(),
+                ProviderRegistry::single(Arc::new(cassette(&recorded))),
+            )
+            .with_tool_recording(Arc::new(recording))
+        };
+        let replayed = (replay(tool_recording(&recorded)).run("r2", def.clone(), json!({}))).await;
+        assert_eq!(
+            replayed.status,
+            TraceStatus::Succeeded,
+            "{:?}",
+            replayed.error
+        );
+        assert!(compare(&recorded, &replayed).is_empty());
+        assert_eq!(
+            replayed.nodes[0].tool_calls[0].output,
+            recorded.nodes[0].tool_calls[0].output
+        );
+        assert!(!out.exists());
+
+        // 录制中没有的工具声明或调用使节点失败，同样不写入文件
+        let replayed = (replay(ToolRecording::default()).run("r3", def.clone(), json!({}))).await;
+        assert_eq!(replayed.status, TraceStatus::Failed);
+        assert!(replayed
+            .error
+            .unwrap()
+            .contains("录制中没有工具 `write_file` 的声明"));
+        let recording = ToolRecording {
+            specs: tool_recording(&recorded).specs,
+            calls: Default::default(),
+        };
+        let replayed = (replay(recording).run("r4", def, json!({}))).await;
+        assert_eq!(replayed.status, TraceStatus::Failed);
+        assert!(replayed
+            .error
+            .unwrap()
+            .contains("录制中没有工具调用 `write_file`"));
+        assert!(!out.exists());
+    }
+
+    #[tokio::test]
+    async fn test_replay_agent_with_api_key_secret() {
+        let def: FlowDef = serde_json::from_value(json!({
+            "id": "f",
+            "nodes": [{ "id": "ask", "type": "agent", "model": "m", "prompt": "hi", "api_key_secret": "team-key" }]
+        }))
+        .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let vault = Vault::with_path(dir.path().join("vault.json"));
+        vault.unlock("passphrase").unwrap();
+        vault.set("team-key", "sk-team").unwrap();
+        let recorded = executor(dir.path(), ProviderRegistry::single(Arc::new(Shout)))
+            .with_secrets(Arc::new(vault))
+            .run("r", def.clone(), json!({}))
+            .await;
+        assert_eq!(
+            recorded.status,
+            TraceStatus::Succeeded,
+            "{:?}",
+            recorded.error
+        );
+
+        // 回放的执行器没有密钥库
+        let replay = ProviderRegistry::single(Arc::new(cassette(&recorded)));
+        let replayed = executor(dir.path(), replay)
+            .with_tool_recording(Arc::new(tool_recording(&recorded)))
+            .run("r2", def, json!({}))
+            .await;
+        assert_eq!(
+            replayed.status,
+            TraceStatus::Succeeded,
+            "{:?}",
+            replayed.error
+        );
+        assert!(compare(&recorded, &replayed).is_empty());
+    }
+}
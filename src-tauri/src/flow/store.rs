@@ -0,0 +1,157 @@
+//! 流程文件的读取与引用解析
+
+use semver::{Version, VersionReq};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+use super::model::{FlowDef, FlowRef};
+use crate::project::Project;
+
+/// 流程文件扩展名（按优先级）
+const FLOW_EXTS: &[&str] = &["json5", "json"];
+
+/// 项目内流程与已安装包的读取入口
+#[derive(Debug, Clone)]
+pub struct FlowStore {
+    project: Project,
+}
+
+impl FlowStore {
+    pub fn new(project: Project) -> Self {
+        Self { project }
+    }
+
+    pub fn project(&self) -> &Project {
+        &self.project
+    }
+
+    /// 读取本项目中的流程
+    pub fn load_local(&self, id: &str) -> Result<FlowDef, String> {
+        check_id(id)?;
+        let path = find_flow_file(&self.project.flows_dir(), id)
+            .ok_or_else(|| format!("流程 `{}` 不存在", id))?;
+        read_flow(&path, id)
+    }
+
+    /// 本项目中全部流程的 id（按文件名排序）
+    pub fn list_local(&self) -> Result<Vec<String>, String> {
+        let dir = self.project.flows_dir();
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("读取目录 {:?} 失败: {}", dir, e)),
+        };
+        let mut ids: Vec<String> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|ext| FLOW_EXTS.iter().any(|e| ext == *e))
+            })
+            .filter_map(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+            .collect();
+        ids.sort();
+        ids.dedup();
+        Ok(ids)
+    }
+
+    /// 解析流程引用：本项目流程或已安装包中的流程
+    pub fn resolve(&self, target: &FlowRef) -> Result<FlowDef, String> {
+        let flow = match &target.package {
+            None => self.load_local(&target.flow)?,
+            Some(package) => self.load_package(package, &target.flow, target.version.as_deref())?,
+        };
+        if let Some(req) = &target.version {
+            if !version_matches(req, &flow.version)? {
+                return Err(format!(
+                    "流程 `{}` 的版本为 {}，不满足引用要求 {}",
+                    target.flow, flow.version, req
+                ));
+            }
+        }
+        Ok(flow)
+    }
+
+    /// 读取包内流程：`packages/<package>/<version>/<id>.json5`
+    fn load_package(&self, package: &str, id: &str, req: Option<&str>) -> Result<FlowDef, String> {
+        check_id(package)?;
+        check_id(id)?;
+        let pkg_dir = self.project.packages_dir().join(package);
+        let entries =
+            std::fs::read_dir(&pkg_dir).map_err(|e| format!("包 `{}` 未安装: {}", package, e))?;
+
+        // 在满足要求的已安装版本中选择最高版本
+        let mut best: Option<(Version, PathBuf)> = None;
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let Ok(version) = Version::parse(&name) else {
+                continue;
+            };
+            if let Some(req) = req {
+                if !version_matches(req, &name)? {
+                    continue;
+                }
+            }
+            if best.as_ref().is_none_or(|(b, _)| version > *b) {
+                best = Some((version, entry.path()));
+            }
+        }
+        let (_, dir) = best.ok_or_else(|| {
+            format!(
+                "包 `{}` 中没有满足版本要求 {} 的安装",
+                package,
+                req.unwrap_or("*")
+            )
+        })?;
+        let path = find_flow_file(&dir, id)
+            .ok_or_else(|| format!("包 `{}` 中不存在流程 `{}`", package, id))?;
+        read_flow(&path, id)
+    }
+}
+
+/// 版本匹配：完整版本号按精确匹配，否则按 semver 范围（如 `^1.2`）匹配
+fn version_matches(req: &str, version: &str) -> Result<bool, String> {
+    if let Ok(exact) = Version::parse(req) {
+        return Ok(Version::parse(version).is_ok_and(|v| v == exact) || req == version);
+    }
+    let req = VersionReq::parse(req).map_err(|e| format!("版本要求 `{}` 无效: {}", req, e))?;
+    Ok(Version::parse(version).is_ok_and(|v| req.matches(&v)))
+}
+
+/// 流程/包 id 只能作为单级文件名使用，避免路径穿越
+fn check_id(id: &str) -> Result<(), String> {
+    let valid = !id.is_empty()
+        && !id.starts_with('.')
+        && id
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("非法的流程标识 `{}`", id))
+    }
+}
+
+fn find_flow_file(dir: &Path, id: &str) -> Option<PathBuf> {
+    FLOW_EXTS
+        .iter()
+        .map(|ext| dir.join(format!("{}.{}", id, ext)))
+        .find(|p| p.is_file())
+}
+
+fn read_flow(path: &Path, id: &str) -> Result<FlowDef, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("读取流程文件 {:?} 失败: {}", path, e))?;
+    // 先解析为 JSON 值再转换，避免 JSON5 反序列化器对 flatten 字段的数字类型处理差异
+    let value: Value =
+        json5::from_str(&content).map_err(|e| format!("流程文件 {:?} 格式错误: {}", path, e))?;
+    let flow: FlowDef = serde_json::from_value(value)
+        .map_err(|e| format!("流程文件 {:?} 结构错误: {}", path, e))?;
+    if flow.id != id {
+        return Err(format!(
+            "流程文件 {:?} 中的 id `{}` 与文件名不一致",
+            path, flow.id
+        ));
+    }
+    Ok(flow)
+}
@@ -0,0 +1,176 @@
+//! 结构化输出：宽松解析模型返回的 JSON 并按 JSON Schema 校验
+//!
+//! 模型即使开启 JSON mode 也常常带上代码块、前后说明文字，或因截断导致括号不完整，
+//! [`parse_lenient`] 先按严格 JSON 解析，失败后再做一次修复。
+
+use serde_json::Value;
+
+/// 编译后的输出 Schema
+pub struct OutputSchema {
+    validator: jsonschema::Validator,
+}
+
+impl OutputSchema {
+    pub fn compile(schema: &Value) -> Result<Self, String> {
+        jsonschema::validator_for(schema)
+            .map(|validator| Self { validator })
+            .map_err(|e| format!("输出 Schema 无效: {}", e))
+    }
+
+    /// 校验值，返回全部错误（`路径: 原因`）
+    pub fn check(&self, value: &Value) -> Vec<String> {
+        self.validator
+            .iter_errors(value)
+            .map(|e| {
+                let path = e.instance_path().to_string();
+                let path = if path.is_empty() { "/" } else { path.as_str() };
+                format!("{}: {}", path, e)
+            })
+            .collect()
+    }
+}
+
+/// 解析模型输出；严格解析失败时去掉代码块与多余文字、补全括号后再试
+pub fn parse_lenient(text: &str) -> Result<Value, String> {
+    let text = text.trim();
+    if let Ok(value) = serde_json::from_str(text) {
+        return Ok(value);
+    }
+
+    let candidate = balance(strip_fence(text)).ok_or("响应中未找到 JSON 对象或数组")?;
+    serde_json::from_str(&candidate)
+        .or_else(|_| json5::from_str(&candidate))
+        .map_err(|e| format!("响应无法解析为 JSON: {}", e))
+}
+
+/// 取出第一个 ``` 代码块中的内容（没有代码块时原样返回）
+fn strip_fence(text: &str) -> &str {
+    let Some(start) = text.find("```") else {
+        return text;
+    };
+    let body = &text[start + 3..];
+    // 跳过语言标记，如 ```json
+    let body = body.find('\n').map_or(body, |i| &body[i + 1..]);
+    match body.find("```") {
+        Some(end) => &body[..end],
+        None => body,
+    }
+}
+
+/// 从第一个 `{` / `[` 开始截取到与之匹配的括号为止，去掉尾随逗号；
+/// 文本提前结束时补全未闭合的字符串与括号
+fn balance(text: &str) -> Option<String> {
+    let start = text.find(['{', '['])?;
+    let mut out = String::with_capacity(text.len() - start);
+    let mut stack = Vec::new();
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+
+    for c in text[start..].chars() {
+        if let Some(q) = quote {
+            out.push(c);
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' => {
+                quote = Some(c);
+                out.push(c);
+            }
+            '{' => {
+                stack.push('}');
+                out.push(c);
+            }
+            '[' => {
+                stack.push(']');
+                out.push(c);
+            }
+            '}' | ']' => {
+                // 不匹配的右括号直接丢弃
+                if stack.last() == Some(&c) {
+                    stack.pop();
+                    trim_trailing_comma(&mut out);
+                    out.push(c);
+                    if stack.is_empty() {
+                        return Some(out);
+                    }
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+
+    if let Some(q) = quote {
+        if escaped {
+            out.pop();
+        }
+        out.push(q);
+    }
+    while let Some(close) = stack.pop() {
+        trim_trailing_comma(&mut out);
+        if out.ends_with(':') {
+            out.push_str(" null");
+        }
+        out.push(close);
+    }
+    Some(out)
+}
+
+fn trim_trailing_comma(out: &mut String) {
+    out.truncate(out.trim_end().len());
+    if out.ends_with(',') {
+        out.pop();
+        out.truncate(out.trim_end().len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_lenient_repairs() {
+        let fenced = "好的，结果如下：\n```json\n{\"a\": 1, \"b\": [1, 2,],}\n```\n希望有帮助";
+        assert_eq!(
+            parse_lenient(fenced).unwrap(),
+            json!({ "a": 1, "b": [1, 2] })
+        );
+
+        let prose = "Here you go: [\"x\", \"y\"] -- let me know if you need more.";
+        assert_eq!(parse_lenient(prose).unwrap(), json!(["x", "y"]));
+
+        let truncated = "{\"title\": \"Hello\", \"tags\": [\"a\", \"b";
+        assert_eq!(
+            parse_lenient(truncated).unwrap(),
+            json!({ "title": "Hello", "tags": ["a", "b"] })
+        );
+
+        assert_eq!(parse_lenient("{\"k\":").unwrap(), json!({ "k": null }));
+        assert!(parse_lenient("没有 JSON").is_err());
+    }
+
+    #[test]
+    fn test_output_schema_check() {
+        let schema = OutputSchema::compile(&json!({
+            "type": "object",
+            "required": ["score"],
+            "properties": { "score": { "type": "integer", "maximum": 10 } }
+        }))
+        .unwrap();
+        assert!(schema.check(&json!({ "score": 7 })).is_empty());
+
+        let errors = schema.check(&json!({ "score": 11 }));
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("/score: "), "{}", errors[0]);
+        assert!(schema.check(&json!({}))[0].starts_with("/: "));
+
+        assert!(OutputSchema::compile(&json!({ "type": 5 })).is_err());
+    }
+}
@@ -0,0 +1,200 @@
+//! 内置过滤器
+//!
+//! `default` 在渲染器中特殊处理（需要感知变量是否存在），其余过滤器都在这里实现。
+
+use serde_json::Value;
+
+/// 所有可用的过滤器名称
+const KNOWN: &[&str] = &[
+    "default",
+    "json",
+    "json_pretty",
+    "escape",
+    "e",
+    "xml",
+    "quote",
+    "upper",
+    "lower",
+    "trim",
+    "length",
+    "join",
+    "first",
+    "last",
+    "indent",
+    "lines",
+];
+
+pub(crate) fn is_known(name: &str) -> bool {
+    KNOWN.contains(&name)
+}
+
+/// 将值转为插值文本：字符串原样输出，null 输出空串，对象/数组输出紧凑 JSON
+pub(crate) fn to_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// 应用过滤器，错误信息由调用方补充位置
+pub(crate) fn apply(name: &str, input: Value, args: &[Value]) -> Result<Value, String> {
+    let arity = |min: usize, max: usize| -> Result<(), String> {
+        if args.len() < min || args.len() > max {
+            Err(format!(
+                "过滤器 `{}` 需要 {} 个参数，实际传入 {} 个",
+                name,
+                if min == max {
+                    min.to_string()
+                } else {
+                    format!("{}~{}", min, max)
+                },
+                args.len()
+            ))
+        } else {
+            Ok(())
+        }
+    };
+
+    match name {
+        "json" => {
+            arity(0, 0)?;
+            Ok(Value::String(input.to_string()))
+        }
+        "json_pretty" => {
+            arity(0, 0)?;
+            serde_json::to_string_pretty(&input)
+                .map(Value::String)
+                .map_err(|e| e.to_string())
+        }
+        "escape" | "e" | "xml" => {
+            arity(0, 0)?;
+            Ok(Value::String(escape_markup(&to_text(&input))))
+        }
+        "quote" => {
+            arity(0, 0)?;
+            // 以 JSON 字符串形式转义，适合嵌入引号包围的上下文
+            Ok(Value::String(Value::String(to_text(&input)).to_string()))
+        }
+        "upper" => {
+            arity(0, 0)?;
+            Ok(Value::String(to_text(&input).to_uppercase()))
+        }
+        "lower" => {
+            arity(0, 0)?;
+            Ok(Value::String(to_text(&input).to_lowercase()))
+        }
+        "trim" => {
+            arity(0, 0)?;
+            Ok(Value::String(to_text(&input).trim().to_string()))
+        }
+        "length" => {
+            arity(0, 0)?;
+            let len = match &input {
+                Value::String(s) => s.chars().count(),
+                Value::Array(a) => a.len(),
+                Value::Object(o) => o.len(),
+                Value::Null => 0,
+                other => return Err(format!("无法对 {} 求长度", type_name(other))),
+            };
+            Ok(Value::from(len))
+        }
+        "join" => {
+            arity(0, 1)?;
+            let sep = args.first().map(to_text).unwrap_or_default();
+            match input {
+                Value::Array(items) => Ok(Value::String(
+                    items.iter().map(to_text).collect::<Vec<_>>().join(&sep),
+                )),
+                other => Err(format!("`join` 需要数组，实际为 {}", type_name(&other))),
+            }
+        }
+        "first" | "last" => {
+            arity(0, 0)?;
+            match input {
+                Value::Array(items) => {
+                    let item = if name == "first" {
+                        items.into_iter().next()
+                    } else {
+                        items.into_iter().next_back()
+                    };
+                    Ok(item.unwrap_or(Value::Null))
+                }
+                Value::String(s) => {
+                    let c = if name == "first" {
+                        s.chars().next()
+                    } else {
+                        s.chars().next_back()
+                    };
+                    Ok(c.map(|c| Value::String(c.to_string()))
+                        .unwrap_or(Value::Null))
+                }
+                other => Err(format!(
+                    "`{}` 需要数组或字符串，实际为 {}",
+                    name,
+                    type_name(&other)
+                )),
+            }
+        }
+        "indent" => {
+            arity(1, 1)?;
+            let width = args[0]
+                .as_u64()
+                .ok_or_else(|| "`indent` 的参数应为非负整数".to_string())?;
+            let pad = " ".repeat(width as usize);
+            let text = to_text(&input);
+            let indented = text
+                .lines()
+                .enumerate()
+                .map(|(i, line)| {
+                    if i == 0 || line.is_empty() {
+                        line.to_string()
+                    } else {
+                        format!("{}{}", pad, line)
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            Ok(Value::String(indented))
+        }
+        "lines" => {
+            arity(0, 0)?;
+            Ok(Value::Array(
+                to_text(&input)
+                    .lines()
+                    .map(|l| Value::String(l.to_string()))
+                    .collect(),
+            ))
+        }
+        other => Err(format!("未知的过滤器 `{}`", other)),
+    }
+}
+
+/// 转义 HTML/XML 特殊字符，防止用户数据伪造提示词中的标签边界
+fn escape_markup(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+pub(crate) fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "布尔值",
+        Value::Number(_) => "数字",
+        Value::String(_) => "字符串",
+        Value::Array(_) => "数组",
+        Value::Object(_) => "对象",
+    }
+}
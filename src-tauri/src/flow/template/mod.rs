@@ -0,0 +1,276 @@
+//! 提示词模板引擎
+//!
+//! Agent 节点的提示词通过模板引用流程输入与上游节点输出。渲染数据约定为：
+//! - `inputs.<name>`：流程输入
+//! - `nodes.<node_id>`：上游节点的输出（可继续用 `.` 或 `[]` 深入 JSON 结构）
+//!
+//! 支持的语法：
+//! - 插值：`{{ nodes.outline.sections[0].title | upper }}`
+//! - 条件：`{% if x %}...{% elif y %}...{% else %}...{% endif %}`
+//! - 循环：`{% for s in list %}{{ loop.index }}. {{ s }}{% else %}(空){% endfor %}`，
+//!   对象可用 `{% for k, v in obj %}` 遍历
+//! - 注释：`{# ... #}`；标签内侧加 `-`（如 `{%- ... -%}`）会裁剪相邻空白
+//!
+//! 变量缺失时渲染失败并给出行列位置；需要可选变量时使用 `| default("...")` 或 `is defined`。
+//!
+//! 控制流节点的条件使用同一套表达式语法（不带 `{{ }}`），见 [`Expression`]。
+
+mod filters;
+mod parser;
+mod render;
+
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+
+/// 模板中的位置（行列号均从 1 开始）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Pos {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Default for Pos {
+    fn default() -> Self {
+        Self { line: 1, column: 1 }
+    }
+}
+
+/// 模板错误类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateErrorKind {
+    /// 语法错误
+    Syntax,
+    /// 引用了不存在的变量
+    UndefinedVariable,
+    /// 使用了未知的过滤器
+    UnknownFilter,
+    /// 值类型不符合运算或过滤器的要求
+    Type,
+}
+
+/// 模板解析/渲染错误，携带出错位置，供编辑器定位
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TemplateError {
+    pub kind: TemplateErrorKind,
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl TemplateError {
+    pub(crate) fn new(kind: TemplateErrorKind, message: impl Into<String>, pos: Pos) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            line: pos.line,
+            column: pos.column,
+        }
+    }
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "模板第 {} 行第 {} 列: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// 已解析的模板，可在不同数据上重复渲染
+#[derive(Debug, Clone)]
+pub struct Template {
+    nodes: Vec<parser::Node>,
+}
+
+impl Template {
+    /// 解析模板源码
+    pub fn parse(source: &str) -> Result<Self, TemplateError> {
+        Ok(Self {
+            nodes: parser::parse(source)?,
+        })
+    }
+
+    /// 在给定数据上渲染
+    pub fn render(&self, data: &Value) -> Result<String, TemplateError> {
+        let mut out = String::new();
+        render::Renderer::new(data).render(&self.nodes, &mut out)?;
+        Ok(out)
+    }
+
+    /// 渲染为 JSON 值
+    ///
+    /// 若模板仅由单个 `{{ expr }}` 组成（允许两侧空白），直接返回表达式的值而不转为文本，
+    /// 从而可以在节点之间传递数组、对象等结构化数据；否则按文本渲染。
+    pub fn render_value(&self, data: &Value) -> Result<Value, TemplateError> {
+        let exprs: Vec<&parser::Node> = self
+            .nodes
+            .iter()
+            .filter(|n| !matches!(n, parser::Node::Text(t) if t.trim().is_empty()))
+            .collect();
+        if let [parser::Node::Output(expr)] = exprs.as_slice() {
+            return render::Renderer::new(data).eval_value(expr);
+        }
+        self.render(data).map(Value::String)
+    }
+}
+
+/// 独立表达式，如 `nodes.critique.verdict == "OK" and inputs.strict`
+#[derive(Debug, Clone)]
+pub struct Expression {
+    expr: parser::Expr,
+}
+
+impl Expression {
+    pub fn parse(source: &str) -> Result<Self, TemplateError> {
+        Ok(Self {
+            expr: parser::parse_expr(source)?,
+        })
+    }
+
+    /// 求值为 JSON 值
+    pub fn eval(&self, data: &Value) -> Result<Value, TemplateError> {
+        render::Renderer::new(data).eval_value(&self.expr)
+    }
+
+    /// 按模板 `if` 的真值规则求值
+    pub fn test(&self, data: &Value) -> Result<bool, TemplateError> {
+        self.eval(data).map(|value| render::truthy(&value))
+    }
+}
+
+/// 便捷函数：解析并按 [`Template::render_value`] 规则求值
+pub fn render_value(source: &str, data: &Value) -> Result<Value, TemplateError> {
+    Template::parse(source)?.render_value(data)
+}
+
+/// 便捷函数：解析并渲染
+pub fn render(source: &str, data: &Value) -> Result<String, TemplateError> {
+    Template::parse(source)?.render(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn data() -> Value {
+        json!({
+            "inputs": { "topic": "Rust", "tags": ["a", "b", "c"], "empty": [] },
+            "nodes": {
+                "outline": {
+                    "sections": [
+                        { "title": "Intro", "points": 2 },
+                        { "title": "Body", "points": 5 }
+                    ]
+                },
+                "section-writer": "<b>text</b>"
+            }
+        })
+    }
+
+    #[test]
+    fn test_interpolation_and_paths() {
+        let out = render(
+            "Topic: {{ inputs.topic }}, first: {{ nodes.outline.sections[0].title }}, second: {{ nodes.outline.sections.1.title }}",
+            &data(),
+        )
+        .unwrap();
+        assert_eq!(out, "Topic: Rust, first: Intro, second: Body");
+    }
+
+    #[test]
+    fn test_loops_and_loop_vars() {
+        let out = render(
+            "{% for s in nodes.outline.sections %}{{ loop.index }}.{{ s.title }}{% if not loop.last %}, {% endif %}{% endfor %}",
+            &data(),
+        )
+        .unwrap();
+        assert_eq!(out, "1.Intro, 2.Body");
+
+        let out = render(
+            "{% for x in inputs.empty %}x{% else %}empty{% endfor %}",
+            &data(),
+        )
+        .unwrap();
+        assert_eq!(out, "empty");
+
+        let out = render(
+            "{% for k, v in nodes.outline %}{{ k }}={{ v | length }}{% endfor %}",
+            &data(),
+        )
+        .unwrap();
+        assert_eq!(out, "sections=2");
+    }
+
+    #[test]
+    fn test_conditionals() {
+        let tpl = "{% if nodes.outline.sections[1].points > 3 and inputs.topic == 'Rust' %}big{% elif inputs.topic %}small{% else %}none{% endif %}";
+        assert_eq!(render(tpl, &data()).unwrap(), "big");
+        assert_eq!(
+            render(
+                "{% if inputs.extra is defined %}y{% else %}n{% endif %}",
+                &data()
+            )
+            .unwrap(),
+            "n"
+        );
+        assert_eq!(
+            render("{% if 'b' in inputs.tags %}y{% endif %}", &data()).unwrap(),
+            "y"
+        );
+    }
+
+    #[test]
+    fn test_filters() {
+        assert_eq!(
+            render("{{ nodes['section-writer'] | escape }}", &data()).unwrap(),
+            "&lt;b&gt;text&lt;/b&gt;"
+        );
+        assert_eq!(
+            render("{{ inputs.tags | join(', ') | upper }}", &data()).unwrap(),
+            "A, B, C"
+        );
+        assert_eq!(
+            render("{{ inputs.tags | json }}", &data()).unwrap(),
+            r#"["a","b","c"]"#
+        );
+        assert_eq!(
+            render("{{ inputs.none | default('n/a') }}", &data()).unwrap(),
+            "n/a"
+        );
+        assert_eq!(render("{{ inputs.tags | length }}", &data()).unwrap(), "3");
+    }
+
+    #[test]
+    fn test_whitespace_control() {
+        let out = render("a\n  {%- if true -%}\n  b\n  {%- endif -%}\n  c", &data()).unwrap();
+        assert_eq!(out, "abc");
+    }
+
+    #[test]
+    fn test_strict_missing_variable() {
+        let err = render("line1\n  {{ nodes.outline.missing }}", &data()).unwrap_err();
+        assert_eq!(err.kind, TemplateErrorKind::UndefinedVariable);
+        assert_eq!((err.line, err.column), (2, 6));
+        assert!(err.message.contains("nodes.outline.missing"));
+    }
+
+    #[test]
+    fn test_syntax_errors_have_location() {
+        let err = Template::parse("ok\n{% if x %}never closed").unwrap_err();
+        assert_eq!(err.kind, TemplateErrorKind::Syntax);
+
+        let err = Template::parse("{{ a | nope }}").unwrap_err();
+        assert_eq!(err.kind, TemplateErrorKind::UnknownFilter);
+        assert_eq!((err.line, err.column), (1, 8));
+
+        let err = Template::parse("{{ a ").unwrap_err();
+        assert_eq!(err.kind, TemplateErrorKind::Syntax);
+    }
+}
@@ -0,0 +1,903 @@
+//! 模板解析：先把源文本切分为文本/输出/语句三类片段，再把语句片段组装成语法树。
+//!
+//! 表达式语法（由低到高优先级）：
+//! - `or` / `and` / `not`
+//! - 比较：`==` `!=` `<` `<=` `>` `>=` `in`，测试：`is [not] defined`
+//! - 过滤器：`expr | name` 或 `expr | name(arg, ...)`
+//! - 基本项：字面量、`(expr)`、变量路径 `a.b.0.c` / `a["key"][1]`
+
+use serde_json::Value;
+
+use super::filters;
+use super::{Pos, TemplateError, TemplateErrorKind};
+
+/// 模板语法树节点
+#[derive(Debug, Clone)]
+pub(crate) enum Node {
+    Text(String),
+    Output(Expr),
+    If {
+        branches: Vec<(Expr, Vec<Node>)>,
+        otherwise: Option<Vec<Node>>,
+    },
+    For {
+        key_var: Option<String>,
+        var: String,
+        iter: Expr,
+        body: Vec<Node>,
+        otherwise: Option<Vec<Node>>,
+    },
+}
+
+/// 路径片段：对象键或数组下标
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BinOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    In,
+    And,
+    Or,
+}
+
+/// 表达式节点，均携带源码位置，用于报错定位
+#[derive(Debug, Clone)]
+pub(crate) enum Expr {
+    Literal(Value),
+    Path {
+        root: String,
+        segments: Vec<Segment>,
+        pos: Pos,
+    },
+    Not(Box<Expr>),
+    Binary {
+        op: BinOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+        pos: Pos,
+    },
+    Defined {
+        expr: Box<Expr>,
+        negated: bool,
+    },
+    Filter {
+        expr: Box<Expr>,
+        name: String,
+        args: Vec<Expr>,
+        pos: Pos,
+    },
+}
+
+impl Expr {
+    pub(crate) fn pos(&self) -> Option<Pos> {
+        match self {
+            Expr::Literal(_) => None,
+            Expr::Path { pos, .. } | Expr::Binary { pos, .. } | Expr::Filter { pos, .. } => {
+                Some(*pos)
+            }
+            Expr::Not(inner) => inner.pos(),
+            Expr::Defined { expr, .. } => expr.pos(),
+        }
+    }
+
+    /// 还原变量路径的文本形式（用于错误信息）
+    pub(crate) fn path_text(root: &str, segments: &[Segment]) -> String {
+        let mut text = root.to_string();
+        for seg in segments {
+            match seg {
+                Segment::Key(k) => {
+                    text.push('.');
+                    text.push_str(k);
+                }
+                Segment::Index(i) => text.push_str(&format!("[{}]", i)),
+            }
+        }
+        text
+    }
+}
+
+/// 将字节偏移换算为行列号
+pub(crate) struct LineIndex {
+    starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub(crate) fn new(source: &str) -> Self {
+        let mut starts = vec![0];
+        starts.extend(source.match_indices('\n').map(|(i, _)| i + 1));
+        Self { starts }
+    }
+
+    pub(crate) fn pos(&self, source: &str, offset: usize) -> Pos {
+        let line = match self.starts.binary_search(&offset) {
+            Ok(i) => i,
+            Err(i) => i - 1,
+        };
+        let column = source[self.starts[line]..offset].chars().count() + 1;
+        Pos {
+            line: line + 1,
+            column,
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// 第一阶段：切分片段
+// ---------------------------------------------------------------------------
+
+#[derive(Debug)]
+enum Piece<'a> {
+    Text(&'a str),
+    Expr { body: &'a str, offset: usize },
+    Stmt { body: &'a str, offset: usize },
+}
+
+/// 切分源文本，同时处理 `{{-` / `-}}` 形式的空白裁剪
+fn split_pieces<'a>(source: &'a str, lines: &LineIndex) -> Result<Vec<Piece<'a>>, TemplateError> {
+    let mut pieces: Vec<Piece<'a>> = Vec::new();
+    let mut rest_start = 0;
+    let mut trim_next = false;
+
+    while let Some(found) = find_open(&source[rest_start..]) {
+        let (rel, open) = found;
+        let tag_start = rest_start + rel;
+
+        let mut text = &source[rest_start..tag_start];
+        if trim_next {
+            text = text.trim_start();
+        }
+
+        let close = match open {
+            "{{" => "}}",
+            "{%" => "%}",
+            _ => "#}",
+        };
+        let mut body_start = tag_start + 2;
+        if source[body_start..].starts_with('-') {
+            text = text.trim_end();
+            body_start += 1;
+        }
+        if !text.is_empty() {
+            pieces.push(Piece::Text(text));
+        }
+
+        let close_rel = source[body_start..].find(close).ok_or_else(|| {
+            TemplateError::new(
+                TemplateErrorKind::Syntax,
+                format!("标签 `{}` 缺少对应的 `{}`", open, close),
+                lines.pos(source, tag_start),
+            )
+        })?;
+        let mut body_end = body_start + close_rel;
+        trim_next = false;
+        if body_end > body_start && source[..body_end].ends_with('-') {
+            body_end -= 1;
+            trim_next = true;
+        }
+        let body = &source[body_start..body_end];
+
+        match open {
+            "{{" => pieces.push(Piece::Expr {
+                body,
+                offset: body_start,
+            }),
+            "{%" => pieces.push(Piece::Stmt {
+                body,
+                offset: body_start,
+            }),
+            _ => {} // 注释直接丢弃
+        }
+        rest_start = body_start + close_rel + 2;
+    }
+
+    let mut tail = &source[rest_start..];
+    if trim_next {
+        tail = tail.trim_start();
+    }
+    if !tail.is_empty() {
+        pieces.push(Piece::Text(tail));
+    }
+    Ok(pieces)
+}
+
+fn find_open(s: &str) -> Option<(usize, &'static str)> {
+    let bytes = s.as_bytes();
+    let mut i = 0;
+    while i + 1 < bytes.len() {
+        if bytes[i] == b'{' {
+            match bytes[i + 1] {
+                b'{' => return Some((i, "{{")),
+                b'%' => return Some((i, "{%")),
+                b'#' => return Some((i, "{#")),
+                _ => {}
+            }
+        }
+        i += 1;
+    }
+    None
+}
+
+// ---------------------------------------------------------------------------
+// 第二阶段：表达式词法与语法
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Ident(String),
+    Str(String),
+    Int(i64),
+    Float(f64),
+    Dot,
+    Comma,
+    Pipe,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Op(&'static str),
+}
+
+struct Lexed {
+    tok: Tok,
+    offset: usize,
+}
+
+fn lex_expr(
+    body: &str,
+    base: usize,
+    source: &str,
+    lines: &LineIndex,
+) -> Result<Vec<Lexed>, TemplateError> {
+    let mut out = Vec::new();
+    let chars: Vec<(usize, char)> = body.char_indices().collect();
+    let mut i = 0;
+    let err = |at: usize, msg: String| {
+        TemplateError::new(TemplateErrorKind::Syntax, msg, lines.pos(source, base + at))
+    };
+
+    while i < chars.len() {
+        let (off, c) = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let tok = match c {
+            '.' => Tok::Dot,
+            ',' => Tok::Comma,
+            '(' => Tok::LParen,
+            ')' => Tok::RParen,
+            '[' => Tok::LBracket,
+            ']' => Tok::RBracket,
+            '|' => Tok::Pipe,
+            '=' | '!' | '<' | '>' => {
+                let next = chars.get(i + 1).map(|(_, c)| *c);
+                let op = match (c, next) {
+                    ('=', Some('=')) => "==",
+                    ('!', Some('=')) => "!=",
+                    ('<', Some('=')) => "<=",
+                    ('>', Some('=')) => ">=",
+                    ('<', _) => "<",
+                    ('>', _) => ">",
+                    _ => return Err(err(off, format!("无法识别的运算符 `{}`", c))),
+                };
+                i += op.len();
+                out.push(Lexed {
+                    tok: Tok::Op(op),
+                    offset: base + off,
+                });
+                continue;
+            }
+            '"' | '\'' => {
+                let quote = c;
+                let mut s = String::new();
+                let mut j = i + 1;
+                loop {
+                    let Some(&(_, ch)) = chars.get(j) else {
+                        return Err(err(off, "字符串字面量未闭合".to_string()));
+                    };
+                    if ch == quote {
+                        break;
+                    }
+                    if ch == '\\' {
+                        j += 1;
+                        let Some(&(_, esc)) = chars.get(j) else {
+                            return Err(err(off, "字符串字面量未闭合".to_string()));
+                        };
+                        s.push(match esc {
+                            'n' => '\n',
+                            't' => '\t',
+                            'r' => '\r',
+                            other => other,
+                        });
+                    } else {
+                        s.push(ch);
+                    }
+                    j += 1;
+                }
+                i = j + 1;
+                out.push(Lexed {
+                    tok: Tok::Str(s),
+                    offset: base + off,
+                });
+                continue;
+            }
+            c if c.is_ascii_digit()
+                || (c == '-' && chars.get(i + 1).is_some_and(|(_, n)| n.is_ascii_digit())) =>
+            {
+                let mut j = i + 1;
+                let mut is_float = false;
+                while let Some(&(_, ch)) = chars.get(j) {
+                    if ch.is_ascii_digit() {
+                        j += 1;
+                    } else if ch == '.'
+                        && !is_float
+                        && chars.get(j + 1).is_some_and(|(_, n)| n.is_ascii_digit())
+                    {
+                        is_float = true;
+                        j += 1;
+                    } else {
+                        break;
+                    }
+                }
+                let end = chars.get(j).map(|(o, _)| *o).unwrap_or(body.len());
+                let text = &body[off..end];
+                let tok = if is_float {
+                    Tok::Float(
+                        text.parse()
+                            .map_err(|_| err(off, format!("非法数字 `{}`", text)))?,
+                    )
+                } else {
+                    Tok::Int(
+                        text.parse()
+                            .map_err(|_| err(off, format!("非法数字 `{}`", text)))?,
+                    )
+                };
+                i = j;
+                out.push(Lexed {
+                    tok,
+                    offset: base + off,
+                });
+                continue;
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut j = i + 1;
+                while chars
+                    .get(j)
+                    .is_some_and(|(_, ch)| ch.is_alphanumeric() || *ch == '_' || *ch == '-')
+                {
+                    j += 1;
+                }
+                let end = chars.get(j).map(|(o, _)| *o).unwrap_or(body.len());
+                i = j;
+                out.push(Lexed {
+                    tok: Tok::Ident(body[off..end].to_string()),
+                    offset: base + off,
+                });
+                continue;
+            }
+            other => return Err(err(off, format!("无法识别的字符 `{}`", other))),
+        };
+        out.push(Lexed {
+            tok,
+            offset: base + off,
+        });
+        i += 1;
+    }
+    Ok(out)
+}
+
+struct ExprParser<'s> {
+    toks: Vec<Lexed>,
+    cur: usize,
+    /// 片段结束处的偏移，用于“意外结束”类错误定位
+    end: usize,
+    source: &'s str,
+    lines: &'s LineIndex,
+}
+
+impl<'s> ExprParser<'s> {
+    fn new(
+        body: &str,
+        base: usize,
+        source: &'s str,
+        lines: &'s LineIndex,
+    ) -> Result<Self, TemplateError> {
+        Ok(Self {
+            toks: lex_expr(body, base, source, lines)?,
+            cur: 0,
+            end: base + body.len(),
+            source,
+            lines,
+        })
+    }
+
+    fn pos_at(&self, offset: usize) -> Pos {
+        self.lines.pos(self.source, offset)
+    }
+
+    fn here(&self) -> Pos {
+        self.pos_at(
+            self.toks
+                .get(self.cur)
+                .map(|t| t.offset)
+                .unwrap_or(self.end),
+        )
+    }
+
+    fn error(&self, msg: impl Into<String>) -> TemplateError {
+        TemplateError::new(TemplateErrorKind::Syntax, msg, self.here())
+    }
+
+    fn peek(&self) -> Option<&Tok> {
+        self.toks.get(self.cur).map(|t| &t.tok)
+    }
+
+    fn bump(&mut self) -> Option<Tok> {
+        let t = self.toks.get(self.cur).map(|t| t.tok.clone());
+        self.cur += 1;
+        t
+    }
+
+    fn eat_ident(&mut self, word: &str) -> bool {
+        if matches!(self.peek(), Some(Tok::Ident(w)) if w == word) {
+            self.cur += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, tok: Tok, what: &str) -> Result<(), TemplateError> {
+        if self.peek() == Some(&tok) {
+            self.cur += 1;
+            Ok(())
+        } else {
+            Err(self.error(format!("此处应为 {}", what)))
+        }
+    }
+
+    fn ident(&mut self, what: &str) -> Result<String, TemplateError> {
+        match self.peek() {
+            Some(Tok::Ident(w)) => {
+                let w = w.clone();
+                self.cur += 1;
+                Ok(w)
+            }
+            _ => Err(self.error(format!("此处应为{}", what))),
+        }
+    }
+
+    fn finish(&self) -> Result<(), TemplateError> {
+        if self.cur < self.toks.len() {
+            Err(self.error("表达式后存在多余内容"))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, TemplateError> {
+        self.or_expr()
+    }
+
+    fn or_expr(&mut self) -> Result<Expr, TemplateError> {
+        let mut lhs = self.and_expr()?;
+        loop {
+            let pos = self.here();
+            if !self.eat_ident("or") {
+                return Ok(lhs);
+            }
+            let rhs = self.and_expr()?;
+            lhs = Expr::Binary {
+                op: BinOp::Or,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+                pos,
+            };
+        }
+    }
+
+    fn and_expr(&mut self) -> Result<Expr, TemplateError> {
+        let mut lhs = self.not_expr()?;
+        loop {
+            let pos = self.here();
+            if !self.eat_ident("and") {
+                return Ok(lhs);
+            }
+            let rhs = self.not_expr()?;
+            lhs = Expr::Binary {
+                op: BinOp::And,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+                pos,
+            };
+        }
+    }
+
+    fn not_expr(&mut self) -> Result<Expr, TemplateError> {
+        if self.eat_ident("not") {
+            return Ok(Expr::Not(Box::new(self.not_expr()?)));
+        }
+        self.cmp_expr()
+    }
+
+    fn cmp_expr(&mut self) -> Result<Expr, TemplateError> {
+        let lhs = self.filtered()?;
+        let pos = self.here();
+
+        if self.eat_ident("is") {
+            let negated = self.eat_ident("not");
+            let test = self.ident("测试名称")?;
+            if test != "defined" {
+                return Err(TemplateError::new(
+                    TemplateErrorKind::Syntax,
+                    format!("未知的测试 `{}`，目前仅支持 `defined`", test),
+                    pos,
+                ));
+            }
+            return Ok(Expr::Defined {
+                expr: Box::new(lhs),
+                negated,
+            });
+        }
+
+        let op = match self.peek() {
+            Some(Tok::Op("==")) => BinOp::Eq,
+            Some(Tok::Op("!=")) => BinOp::Ne,
+            Some(Tok::Op("<")) => BinOp::Lt,
+            Some(Tok::Op("<=")) => BinOp::Le,
+            Some(Tok::Op(">")) => BinOp::Gt,
+            Some(Tok::Op(">=")) => BinOp::Ge,
+            Some(Tok::Ident(w)) if w == "in" => BinOp::In,
+            _ => return Ok(lhs),
+        };
+        self.cur += 1;
+        let rhs = self.filtered()?;
+        Ok(Expr::Binary {
+            op,
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+            pos,
+        })
+    }
+
+    fn filtered(&mut self) -> Result<Expr, TemplateError> {
+        let mut expr = self.primary()?;
+        while self.peek() == Some(&Tok::Pipe) {
+            self.cur += 1;
+            let pos = self.here();
+            let name = self.ident("过滤器名称")?;
+            if !filters::is_known(&name) {
+                return Err(TemplateError::new(
+                    TemplateErrorKind::UnknownFilter,
+                    format!("未知的过滤器 `{}`", name),
+                    pos,
+                ));
+            }
+            let mut args = Vec::new();
+            if self.peek() == Some(&Tok::LParen) {
+                self.cur += 1;
+                if self.peek() != Some(&Tok::RParen) {
+                    loop {
+                        args.push(self.expr()?);
+                        if self.peek() == Some(&Tok::Comma) {
+                            self.cur += 1;
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                self.expect(Tok::RParen, "`)`")?;
+            }
+            expr = Expr::Filter {
+                expr: Box::new(expr),
+                name,
+                args,
+                pos,
+            };
+        }
+        Ok(expr)
+    }
+
+    fn primary(&mut self) -> Result<Expr, TemplateError> {
+        let pos = self.here();
+        match self.bump() {
+            Some(Tok::Str(s)) => Ok(Expr::Literal(Value::String(s))),
+            Some(Tok::Int(n)) => Ok(Expr::Literal(Value::from(n))),
+            Some(Tok::Float(f)) => Ok(Expr::Literal(Value::from(f))),
+            Some(Tok::LParen) => {
+                let inner = self.expr()?;
+                self.expect(Tok::RParen, "`)`")?;
+                Ok(inner)
+            }
+            Some(Tok::Ident(word)) => match word.as_str() {
+                "true" => Ok(Expr::Literal(Value::Bool(true))),
+                "false" => Ok(Expr::Literal(Value::Bool(false))),
+                "null" | "none" => Ok(Expr::Literal(Value::Null)),
+                _ => self.path_tail(word, pos),
+            },
+            Some(_) => {
+                self.cur -= 1;
+                Err(self.error("此处应为变量或字面量"))
+            }
+            None => Err(self.error("表达式意外结束")),
+        }
+    }
+
+    fn path_tail(&mut self, root: String, pos: Pos) -> Result<Expr, TemplateError> {
+        let mut segments = Vec::new();
+        loop {
+            match self.peek() {
+                Some(Tok::Dot) => {
+                    self.cur += 1;
+                    match self.bump() {
+                        Some(Tok::Ident(k)) => segments.push(Segment::Key(k)),
+                        Some(Tok::Int(i)) if i >= 0 => segments.push(Segment::Index(i as usize)),
+                        _ => {
+                            self.cur -= 1;
+                            return Err(self.error("`.` 之后应为属性名或下标"));
+                        }
+                    }
+                }
+                Some(Tok::LBracket) => {
+                    self.cur += 1;
+                    match self.bump() {
+                        Some(Tok::Str(k)) => segments.push(Segment::Key(k)),
+                        Some(Tok::Int(i)) if i >= 0 => segments.push(Segment::Index(i as usize)),
+                        _ => {
+                            self.cur -= 1;
+                            return Err(self.error("`[` 之内应为字符串或非负整数"));
+                        }
+                    }
+                    self.expect(Tok::RBracket, "`]`")?;
+                }
+                _ => break,
+            }
+        }
+        Ok(Expr::Path {
+            root,
+            segments,
+            pos,
+        })
+    }
+}
+
+// ---------------------------------------------------------------------------
+// 第三阶段：语句块
+// ---------------------------------------------------------------------------
+
+/// 块结束时遇到的终止语句
+enum Terminator {
+    Eof,
+    Elif(Expr),
+    Else,
+    EndIf,
+    EndFor,
+}
+
+struct BlockParser<'a> {
+    pieces: std::vec::IntoIter<Piece<'a>>,
+    source: &'a str,
+    lines: &'a LineIndex,
+}
+
+impl<'a> BlockParser<'a> {
+    fn stmt_keyword(body: &str) -> (&str, &str) {
+        let trimmed = body.trim_start();
+        let end = trimmed
+            .find(|c: char| c.is_whitespace())
+            .unwrap_or(trimmed.len());
+        (&trimmed[..end], &trimmed[end..])
+    }
+
+    /// 解析直到遇到终止语句，返回块内节点及终止语句
+    fn block(&mut self) -> Result<(Vec<Node>, Terminator, Pos), TemplateError> {
+        let mut nodes = Vec::new();
+        while let Some(piece) = self.pieces.next() {
+            match piece {
+                Piece::Text(t) => nodes.push(Node::Text(t.to_string())),
+                Piece::Expr { body, offset } => {
+                    let mut p = ExprParser::new(body, offset, self.source, self.lines)?;
+                    if p.peek().is_none() {
+                        return Err(p.error("`{{ }}` 中缺少表达式"));
+                    }
+                    let expr = p.expr()?;
+                    p.finish()?;
+                    nodes.push(Node::Output(expr));
+                }
+                Piece::Stmt { body, offset } => {
+                    let (keyword, rest) = Self::stmt_keyword(body);
+                    let rest_offset = offset + (body.len() - rest.len());
+                    let pos = self.lines.pos(self.source, offset);
+                    match keyword {
+                        "if" => nodes.push(self.if_block(rest, rest_offset)?),
+                        "for" => nodes.push(self.for_block(rest, rest_offset, pos)?),
+                        "elif" => {
+                            let mut p =
+                                ExprParser::new(rest, rest_offset, self.source, self.lines)?;
+                            let cond = p.expr()?;
+                            p.finish()?;
+                            return Ok((nodes, Terminator::Elif(cond), pos));
+                        }
+                        "else" => {
+                            self.expect_empty(rest, rest_offset, "else")?;
+                            return Ok((nodes, Terminator::Else, pos));
+                        }
+                        "endif" => {
+                            self.expect_empty(rest, rest_offset, "endif")?;
+                            return Ok((nodes, Terminator::EndIf, pos));
+                        }
+                        "endfor" => {
+                            self.expect_empty(rest, rest_offset, "endfor")?;
+                            return Ok((nodes, Terminator::EndFor, pos));
+                        }
+                        "" => {
+                            return Err(TemplateError::new(
+                                TemplateErrorKind::Syntax,
+                                "`{% %}` 中缺少语句",
+                                pos,
+                            ))
+                        }
+                        other => {
+                            return Err(TemplateError::new(
+                                TemplateErrorKind::Syntax,
+                                format!("未知的语句 `{}`", other),
+                                pos,
+                            ))
+                        }
+                    }
+                }
+            }
+        }
+        let end = self.lines.pos(self.source, self.source.len());
+        Ok((nodes, Terminator::Eof, end))
+    }
+
+    fn expect_empty(&self, rest: &str, offset: usize, keyword: &str) -> Result<(), TemplateError> {
+        if rest.trim().is_empty() {
+            Ok(())
+        } else {
+            Err(TemplateError::new(
+                TemplateErrorKind::Syntax,
+                format!("`{}` 之后不应有其它内容", keyword),
+                self.lines.pos(self.source, offset),
+            ))
+        }
+    }
+
+    fn if_block(&mut self, cond_src: &str, offset: usize) -> Result<Node, TemplateError> {
+        let mut p = ExprParser::new(cond_src, offset, self.source, self.lines)?;
+        let mut cond = p.expr()?;
+        p.finish()?;
+
+        let mut branches = Vec::new();
+        loop {
+            let (body, term, pos) = self.block()?;
+            match term {
+                Terminator::Elif(next) => {
+                    branches.push((cond, body));
+                    cond = next;
+                }
+                Terminator::Else => {
+                    branches.push((cond, body));
+                    let (otherwise, term, pos) = self.block()?;
+                    if !matches!(term, Terminator::EndIf) {
+                        return Err(self.unexpected(term, pos, "endif"));
+                    }
+                    return Ok(Node::If {
+                        branches,
+                        otherwise: Some(otherwise),
+                    });
+                }
+                Terminator::EndIf => {
+                    branches.push((cond, body));
+                    return Ok(Node::If {
+                        branches,
+                        otherwise: None,
+                    });
+                }
+                other => return Err(self.unexpected(other, pos, "endif")),
+            }
+        }
+    }
+
+    fn for_block(&mut self, head: &str, offset: usize, pos: Pos) -> Result<Node, TemplateError> {
+        let mut p = ExprParser::new(head, offset, self.source, self.lines)?;
+        let first = p.ident("循环变量名")?;
+        let (key_var, var) = if p.peek() == Some(&Tok::Comma) {
+            p.cur += 1;
+            (Some(first), p.ident("循环变量名")?)
+        } else {
+            (None, first)
+        };
+        if !p.eat_ident("in") {
+            return Err(p.error("`for` 语句中应为 `in`"));
+        }
+        let iter = p.expr()?;
+        p.finish()?;
+
+        let (body, term, end_pos) = self.block()?;
+        let otherwise = match term {
+            Terminator::EndFor => None,
+            Terminator::Else => {
+                let (otherwise, term, end_pos) = self.block()?;
+                if !matches!(term, Terminator::EndFor) {
+                    return Err(self.unexpected(term, end_pos, "endfor"));
+                }
+                Some(otherwise)
+            }
+            Terminator::Eof => {
+                return Err(TemplateError::new(
+                    TemplateErrorKind::Syntax,
+                    "`for` 语句缺少对应的 `endfor`",
+                    pos,
+                ))
+            }
+            other => return Err(self.unexpected(other, end_pos, "endfor")),
+        };
+        Ok(Node::For {
+            key_var,
+            var,
+            iter,
+            body,
+            otherwise,
+        })
+    }
+
+    fn unexpected(&self, term: Terminator, pos: Pos, expected: &str) -> TemplateError {
+        let found = match term {
+            Terminator::Eof => "模板结尾",
+            Terminator::Elif(_) => "`elif`",
+            Terminator::Else => "`else`",
+            Terminator::EndIf => "`endif`",
+            Terminator::EndFor => "`endfor`",
+        };
+        TemplateError::new(
+            TemplateErrorKind::Syntax,
+            format!("此处应为 `{}`，实际遇到{}", expected, found),
+            pos,
+        )
+    }
+}
+
+/// 解析独立表达式（不含 `{{ }}`）
+pub(crate) fn parse_expr(source: &str) -> Result<Expr, TemplateError> {
+    let lines = LineIndex::new(source);
+    let mut parser = ExprParser::new(source, 0, source, &lines)?;
+    if parser.peek().is_none() {
+        return Err(parser.error("缺少表达式"));
+    }
+    let expr = parser.expr()?;
+    parser.finish()?;
+    Ok(expr)
+}
+
+/// 解析完整模板
+pub(crate) fn parse(source: &str) -> Result<Vec<Node>, TemplateError> {
+    let lines = LineIndex::new(source);
+    let pieces = split_pieces(source, &lines)?;
+    let mut parser = BlockParser {
+        pieces: pieces.into_iter(),
+        source,
+        lines: &lines,
+    };
+    let (nodes, term, pos) = parser.block()?;
+    match term {
+        Terminator::Eof => Ok(nodes),
+        Terminator::Elif(_) | Terminator::Else | Terminator::EndIf | Terminator::EndFor => {
+            Err(TemplateError::new(
+                TemplateErrorKind::Syntax,
+                "多余的块结束语句，找不到与之匹配的 `if` / `for`",
+                pos,
+            ))
+        }
+    }
+}
@@ -0,0 +1,290 @@
+//! 模板渲染：在 JSON 数据上求值语法树
+//!
+//! 变量缺失时严格报错（带位置），只有 `default` 过滤器与 `is defined` 测试可以容忍缺失。
+
+use std::cmp::Ordering;
+
+use serde_json::{Map, Value};
+
+use super::filters::{self, type_name};
+use super::parser::{BinOp, Expr, Node, Segment};
+use super::{TemplateError, TemplateErrorKind};
+
+pub(crate) struct Renderer<'d> {
+    data: &'d Value,
+    /// 循环变量作用域栈，内层优先
+    scopes: Vec<Map<String, Value>>,
+}
+
+impl<'d> Renderer<'d> {
+    pub(crate) fn new(data: &'d Value) -> Self {
+        Self {
+            data,
+            scopes: Vec::new(),
+        }
+    }
+
+    pub(crate) fn render(&mut self, nodes: &[Node], out: &mut String) -> Result<(), TemplateError> {
+        for node in nodes {
+            match node {
+                Node::Text(t) => out.push_str(t),
+                Node::Output(expr) => {
+                    let value = self.eval_strict(expr)?;
+                    out.push_str(&filters::to_text(&value));
+                }
+                Node::If {
+                    branches,
+                    otherwise,
+                } => {
+                    let mut taken = false;
+                    for (cond, body) in branches {
+                        if truthy(&self.eval_strict(cond)?) {
+                            self.render(body, out)?;
+                            taken = true;
+                            break;
+                        }
+                    }
+                    if !taken {
+                        if let Some(body) = otherwise {
+                            self.render(body, out)?;
+                        }
+                    }
+                }
+                Node::For {
+                    key_var,
+                    var,
+                    iter,
+                    body,
+                    otherwise,
+                } => {
+                    let items: Vec<(Value, Value)> = match self.eval_strict(iter)? {
+                        Value::Array(items) => items
+                            .into_iter()
+                            .enumerate()
+                            .map(|(i, v)| (Value::from(i), v))
+                            .collect(),
+                        Value::Object(map) => map
+                            .into_iter()
+                            .map(|(k, v)| (Value::String(k), v))
+                            .collect(),
+                        Value::Null => Vec::new(),
+                        other => {
+                            return Err(TemplateError::new(
+                                TemplateErrorKind::Type,
+                                format!("无法遍历{}", type_name(&other)),
+                                iter.pos().unwrap_or_default(),
+                            ))
+                        }
+                    };
+
+                    if items.is_empty() {
+                        if let Some(body) = otherwise {
+                            self.render(body, out)?;
+                        }
+                        continue;
+                    }
+
+                    let length = items.len();
+                    for (index, (key, value)) in items.into_iter().enumerate() {
+                        let mut scope = Map::new();
+                        if let Some(k) = key_var {
+                            scope.insert(k.clone(), key);
+                        }
+                        scope.insert(var.clone(), value);
+                        scope.insert(
+                            "loop".to_string(),
+                            serde_json::json!({
+                                "index": index + 1,
+                                "index0": index,
+                                "first": index == 0,
+                                "last": index + 1 == length,
+                                "length": length,
+                            }),
+                        );
+                        self.scopes.push(scope);
+                        let result = self.render(body, out);
+                        self.scopes.pop();
+                        result?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn lookup_root(&self, root: &str) -> Option<&Value> {
+        for scope in self.scopes.iter().rev() {
+            if let Some(v) = scope.get(root) {
+                return Some(v);
+            }
+        }
+        self.data.get(root)
+    }
+
+    /// 宽松求值：变量缺失返回 `Ok(None)`
+    fn eval(&self, expr: &Expr) -> Result<Option<Value>, TemplateError> {
+        match expr {
+            Expr::Literal(v) => Ok(Some(v.clone())),
+            Expr::Path { root, segments, .. } => {
+                let mut cur = match self.lookup_root(root) {
+                    Some(v) => v,
+                    None => return Ok(None),
+                };
+                for seg in segments {
+                    let next = match (seg, cur) {
+                        (Segment::Key(k), Value::Object(map)) => map.get(k),
+                        (Segment::Index(i), Value::Array(items)) => items.get(*i),
+                        (Segment::Key(k), Value::Array(items)) => {
+                            k.parse::<usize>().ok().and_then(|i| items.get(i))
+                        }
+                        (Segment::Index(i), Value::Object(map)) => map.get(&i.to_string()),
+                        _ => None,
+                    };
+                    match next {
+                        Some(v) => cur = v,
+                        None => return Ok(None),
+                    }
+                }
+                Ok(Some(cur.clone()))
+            }
+            Expr::Not(inner) => Ok(Some(Value::Bool(!truthy(&self.eval_strict(inner)?)))),
+            Expr::Defined { expr, negated } => {
+                let defined = self.eval(expr)?.is_some();
+                Ok(Some(Value::Bool(defined != *negated)))
+            }
+            Expr::Binary { op, lhs, rhs, pos } => {
+                let l = self.eval_strict(lhs)?;
+                // and/or 短路求值
+                match op {
+                    BinOp::And if !truthy(&l) => return Ok(Some(l)),
+                    BinOp::Or if truthy(&l) => return Ok(Some(l)),
+                    BinOp::And | BinOp::Or => return self.eval_strict(rhs).map(Some),
+                    _ => {}
+                }
+                let r = self.eval_strict(rhs)?;
+                let result = match op {
+                    BinOp::Eq => values_eq(&l, &r),
+                    BinOp::Ne => !values_eq(&l, &r),
+                    BinOp::In => contains(&r, &l)
+                        .map_err(|msg| TemplateError::new(TemplateErrorKind::Type, msg, *pos))?,
+                    BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => {
+                        let ord = compare(&l, &r).ok_or_else(|| {
+                            TemplateError::new(
+                                TemplateErrorKind::Type,
+                                format!("无法比较{}与{}", type_name(&l), type_name(&r)),
+                                *pos,
+                            )
+                        })?;
+                        match op {
+                            BinOp::Lt => ord == Ordering::Less,
+                            BinOp::Le => ord != Ordering::Greater,
+                            BinOp::Gt => ord == Ordering::Greater,
+                            _ => ord != Ordering::Less,
+                        }
+                    }
+                    BinOp::And | BinOp::Or => unreachable!(),
+                };
+                Ok(Some(Value::Bool(result)))
+            }
+            Expr::Filter {
+                expr,
+                name,
+                args,
+                pos,
+            } => {
+                if name == "default" {
+                    if args.len() > 1 {
+                        return Err(TemplateError::new(
+                            TemplateErrorKind::Type,
+                            "过滤器 `default` 最多接受 1 个参数",
+                            *pos,
+                        ));
+                    }
+                    return match self.eval(expr)? {
+                        Some(v) if !v.is_null() => Ok(Some(v)),
+                        _ => match args.first() {
+                            Some(arg) => self.eval_strict(arg).map(Some),
+                            None => Ok(Some(Value::String(String::new()))),
+                        },
+                    };
+                }
+                let input = self.eval_strict(expr)?;
+                let args = args
+                    .iter()
+                    .map(|a| self.eval_strict(a))
+                    .collect::<Result<Vec<_>, _>>()?;
+                filters::apply(name, input, &args)
+                    .map(Some)
+                    .map_err(|msg| TemplateError::new(TemplateErrorKind::Type, msg, *pos))
+            }
+        }
+    }
+
+    /// 对单个表达式严格求值（供 `Template::render_value` 使用）
+    pub(crate) fn eval_value(&self, expr: &Expr) -> Result<Value, TemplateError> {
+        self.eval_strict(expr)
+    }
+
+    /// 严格求值：变量缺失时返回带位置的错误
+    fn eval_strict(&self, expr: &Expr) -> Result<Value, TemplateError> {
+        match self.eval(expr)? {
+            Some(v) => Ok(v),
+            None => Err(undefined_error(expr)),
+        }
+    }
+}
+
+fn undefined_error(expr: &Expr) -> TemplateError {
+    let (text, pos) = match expr {
+        Expr::Path {
+            root,
+            segments,
+            pos,
+        } => (Expr::path_text(root, segments), *pos),
+        other => (String::from("表达式"), other.pos().unwrap_or_default()),
+    };
+    TemplateError::new(
+        TemplateErrorKind::UndefinedVariable,
+        format!("变量 `{}` 未定义", text),
+        pos,
+    )
+}
+
+/// 真值判断：null/false/0/空串/空数组/空对象为假
+pub(crate) fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().is_some_and(|f| f != 0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(a) => !a.is_empty(),
+        Value::Object(o) => !o.is_empty(),
+    }
+}
+
+fn values_eq(l: &Value, r: &Value) -> bool {
+    match (l, r) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+        _ => l == r,
+    }
+}
+
+fn compare(l: &Value, r: &Value) -> Option<Ordering> {
+    match (l, r) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+fn contains(haystack: &Value, needle: &Value) -> Result<bool, String> {
+    match haystack {
+        Value::Array(items) => Ok(items.iter().any(|v| values_eq(v, needle))),
+        Value::Object(map) => Ok(needle.as_str().is_some_and(|k| map.contains_key(k))),
+        Value::String(s) => match needle {
+            Value::String(n) => Ok(s.contains(n.as_str())),
+            other => Err(format!("无法在字符串中查找{}", type_name(other))),
+        },
+        other => Err(format!("`in` 的右侧不能是{}", type_name(other))),
+    }
+}
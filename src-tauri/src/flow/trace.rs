@@ -0,0 +1,301 @@
+//! 运行轨迹：记录每个节点的输入、输出与耗时，子流程与循环体的轨迹嵌套在对应节点中
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::human::HumanInput;
+use crate::llm::guard::GuardReport;
+use crate::llm::{ChatResponse, ToolSpec, Usage};
+
+/// 当前 Unix 时间戳（毫秒）
+pub fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TraceStatus {
+    Pending,
+    Running,
+    Succeeded,
+    Failed,
+    /// 因上游失败或分支未命中而未执行
+    Skipped,
+    /// 被用户取消
+    Cancelled,
+    /// 运行被暂停，可从已完成的节点继续
+    Paused,
+    /// 等待人工输入，答复后继续
+    Waiting,
+}
+
+impl TraceStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TraceStatus::Pending => "pending",
+            TraceStatus::Running => "running",
+            TraceStatus::Succeeded => "succeeded",
+            TraceStatus::Failed => "failed",
+            TraceStatus::Skipped => "skipped",
+            TraceStatus::Cancelled => "cancelled",
+            TraceStatus::Paused => "paused",
+            TraceStatus::Waiting => "waiting",
+        }
+    }
+}
+
+/// 运行失败的类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// 流程定义或输入无效，未开始执行
+    Invalid,
+    /// 节点执行失败
+    Node,
+    /// 调用模型失败
+    Provider,
+    /// 超出预算
+    Budget,
+}
+
+/// 一次流程执行的轨迹
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlowTrace {
+    /// 运行 id（仅根流程），与事件及运行历史中的 id 一致
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
+    pub flow_id: String,
+    pub flow_version: String,
+    pub status: TraceStatus,
+    pub inputs: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outputs: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_kind: Option<ErrorKind>,
+    pub started_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<i64>,
+    pub nodes: Vec<NodeTrace>,
+}
+
+impl FlowTrace {
+    pub fn new(flow_id: &str, flow_version: &str, inputs: Value) -> Self {
+        Self {
+            run_id: None,
+            flow_id: flow_id.to_string(),
+            flow_version: flow_version.to_string(),
+            status: TraceStatus::Running,
+            inputs,
+            outputs: None,
+            error: None,
+            error_kind: None,
+            started_at: now_ms(),
+            finished_at: None,
+            nodes: Vec::new(),
+        }
+    }
+
+    pub fn succeed(&mut self, outputs: Value) {
+        self.status = TraceStatus::Succeeded;
+        self.outputs = Some(outputs);
+        self.finished_at = Some(now_ms());
+    }
+
+    pub fn fail(&mut self, error: impl Into<String>) {
+        self.status = TraceStatus::Failed;
+        self.error = Some(error.into());
+        self.finished_at = Some(now_ms());
+    }
+
+    pub fn cancel(&mut self) {
+        self.status = TraceStatus::Cancelled;
+        self.finished_at = Some(now_ms());
+    }
+
+    /// 暂停不算结束，`finished_at` 保持为空
+    pub fn pause(&mut self) {
+        self.status = TraceStatus::Paused;
+    }
+
+    /// 等待人工输入同样不算结束
+    pub fn wait(&mut self) {
+        self.status = TraceStatus::Waiting;
+    }
+
+    /// 所有节点（含子流程、循环与 map 元素）的费用合计
+    pub fn cost(&self) -> f64 {
+        self.nodes.iter().map(NodeTrace::total_cost).sum()
+    }
+}
+
+/// 单个节点的执行轨迹
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeTrace {
+    pub node_id: String,
+    pub kind: String,
+    pub status: TraceStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<i64>,
+    /// 渲染后的提示词
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rendered_prompt: Option<String>,
+    /// 模型原始响应
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+    /// 按价格表计算的费用，模型不在价格表中时为空
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost: Option<f64>,
+    /// 模型调用记录（按调用顺序），用于回放
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub calls: Vec<ProviderCall>,
+    /// 提供给模型的工具声明，回放时据此构建请求
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_specs: Vec<ToolSpec>,
+    /// 工具调用记录（按调用顺序）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCallTrace>,
+    /// 注入系统提示词的用户记忆 id
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub memories: Vec<String>,
+    /// 结构化输出的调用次数（含重试）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attempts: Option<u32>,
+    /// 输出来自节点缓存，本次未调用模型
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cached: bool,
+    /// 子流程节点的嵌套轨迹
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subflow: Option<Box<FlowTrace>>,
+    /// 循环节点每一轮的轨迹
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub iterations: Vec<FlowTrace>,
+    /// map 节点每个元素的轨迹（按输入顺序）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub items: Vec<NodeTrace>,
+    /// input 节点的请求与答复
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input: Option<HumanInput>,
+}
+
+impl NodeTrace {
+    pub fn new(node_id: &str, kind: &str) -> Self {
+        Self {
+            node_id: node_id.to_string(),
+            kind: kind.to_string(),
+            status: TraceStatus::Pending,
+            started_at: None,
+            finished_at: None,
+            rendered_prompt: None,
+            response: None,
+            output: None,
+            error: None,
+            usage: None,
+            cost: None,
+            calls: Vec::new(),
+            tool_specs: Vec::new(),
+            tool_calls: Vec::new(),
+            memories: Vec::new(),
+            attempts: None,
+            cached: false,
+            subflow: None,
+            iterations: Vec::new(),
+            items: Vec::new(),
+            input: None,
+        }
+    }
+
+    pub fn start(&mut self) {
+        self.status = TraceStatus::Running;
+        self.started_at = Some(now_ms());
+    }
+
+    pub fn succeed(&mut self, output: Value) {
+        self.status = TraceStatus::Succeeded;
+        self.output = Some(output);
+        self.finished_at = Some(now_ms());
+    }
+
+    pub fn fail(&mut self, error: impl Into<String>) {
+        self.status = TraceStatus::Failed;
+        self.error = Some(error.into());
+        self.finished_at = Some(now_ms());
+    }
+
+    pub fn cancel(&mut self) {
+        self.status = TraceStatus::Cancelled;
+        self.finished_at = Some(now_ms());
+    }
+
+    pub fn wait(&mut self) {
+        self.status = TraceStatus::Waiting;
+    }
+
+    /// 暂停时节点回到待执行，继续执行时重新运行；已产生的调用与费用保留，继续时计入预算
+    pub fn pause(&mut self) {
+        self.status = TraceStatus::Pending;
+        self.started_at = None;
+    }
+
+    pub fn skip(&mut self) {
+        self.status = TraceStatus::Skipped;
+        self.finished_at = Some(now_ms());
+    }
+
+    /// 本节点及其嵌套轨迹的费用合计
+    pub fn total_cost(&self) -> f64 {
+        self.cost.unwrap_or_default()
+            + self.nested().iter().map(|(_, t)| t.cost()).sum::<f64>()
+            + self.items.iter().map(NodeTrace::total_cost).sum::<f64>()
+    }
+
+    /// 嵌套轨迹及其相对路径前缀：子流程为空，循环第 n 轮为 `n/`
+    pub fn nested(&self) -> Vec<(String, &FlowTrace)> {
+        let subflow = self.subflow.iter().map(|t| (String::new(), &**t));
+        let iterations =
+            (self.iterations.iter().enumerate()).map(|(i, t)| (format!("{}/", i + 1), t));
+        subflow.chain(iterations).collect()
+    }
+}
+
+/// 一次模型调用的录制
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProviderCall {
+    /// 请求哈希，见 [`crate::llm::ChatRequest::hash`]
+    pub request_hash: String,
+    /// 调用失败时为空
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<ChatResponse>,
+    /// 限流等待与重试记录
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guard: Option<GuardReport>,
+}
+
+/// 一次工具调用
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCallTrace {
+    /// 模型生成的调用 id
+    pub id: String,
+    pub name: String,
+    /// 模型生成的原始参数
+    pub arguments: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub started_at: i64,
+    pub finished_at: i64,
+}
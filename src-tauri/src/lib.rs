@@ -1,6 +1,16 @@
+mod api;
+mod cli;
 mod commands;
+mod flow;
+mod llm;
+mod mcp;
+mod memory;
+mod project;
 mod state;
+mod tools;
+mod trigger;
 mod utils;
+mod vault;
 
 use state::GlobalState;
 use tauri::Emitter;
@@ -8,6 +18,11 @@ use utils::file_watcher;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // 带子命令时以命令行模式运行，不创建窗口
+    if let Some(command) = state::args::Args::new().command {
+        std::process::exit(cli::main(command));
+    }
+
     tauri::Builder::default()
         .plugin(utils::sql::init_sql_plugin().build())
         .plugin(tauri_plugin_notification::init())
@@ -68,6 +83,18 @@ async fn initialize_app_async(app_handle: tauri::AppHandle) {
     }
     tracing::info!("✅ SQL 后置初始化成功");
 
+    // 等待人工输入的运行在重启后重新计时
+    commands::flow::restore_waiting_runs(app_handle.clone()).await;
+
+    // 按配置开启本地 HTTP API
+    api::start_from_config().await;
+
+    // 执行到期的定时计划
+    state.scheduler.start();
+
+    // 监听目录中新增或修改的文件
+    state.watches.start_all().await;
+
     // 3. 其他初始化任务
     // if let Err(e) = warm_up_cache().await {
     //     tracing::warn!("⚠️  缓存预热失败: {}", e);
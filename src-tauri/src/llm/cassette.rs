@@ -0,0 +1,52 @@
+//! 录制回放（cassette）
+//!
+//! 按请求哈希返回事先录制的响应，不访问真实模型。相同请求出现多次时按录制顺序依次返回。
+
+use async_trait::async_trait;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use super::{ChatRequest, ChatResponse, LlmError, LlmErrorKind, Provider};
+
+#[derive(Debug, Default)]
+pub struct Cassette {
+    responses: Mutex<HashMap<String, VecDeque<ChatResponse>>>,
+}
+
+impl Cassette {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 追加一条录制的响应
+    pub fn insert(&self, request_hash: impl Into<String>, response: ChatResponse) {
+        self.responses
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(request_hash.into())
+            .or_default()
+            .push_back(response);
+    }
+}
+
+#[async_trait]
+impl Provider for Cassette {
+    fn name(&self) -> &str {
+        "cassette"
+    }
+
+    async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, LlmError> {
+        let hash = request.hash();
+        self.responses
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get_mut(&hash)
+            .and_then(VecDeque::pop_front)
+            .ok_or_else(|| {
+                LlmError::new(
+                    LlmErrorKind::Replay,
+                    format!("没有与请求 {} 匹配的录制响应", &hash[..12]),
+                )
+            })
+    }
+}
@@ -0,0 +1,449 @@
+//! Provider 中间件：限流、重试与熔断
+//!
+//! 注册表中的每个 provider 都包在 [`Guarded`] 中：
+//! - 按 provider 密钥（地址 + API Key）限制每分钟请求数与 token 数，同一密钥的所有运行共享额度；
+//! - 429、5xx 与超时按带抖动的指数退避重试，服务端给出 `Retry-After` 时按其等待；
+//! - 连续失败达到阈值后熔断，冷却期内直接拒绝请求，冷却结束后放行一个试探请求。
+//!
+//! 等待与重试情况记录在 [`GuardReport`] 中，随响应或错误返回并写入运行轨迹。
+
+use async_trait::async_trait;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::{ChatRequest, ChatResponse, LlmError, LlmErrorKind, OnDelta, Provider};
+
+/// 限流统计窗口
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// 退避等待上限
+const MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// 中间件策略，作为 provider 配置的一部分
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct GuardPolicy {
+    /// 每分钟请求数上限
+    pub rpm: Option<u32>,
+    /// 每分钟 token 数上限（请求前按提示词长度估算，响应后按实际用量修正）
+    pub tpm: Option<u64>,
+    /// 失败后最多重试次数
+    pub max_retries: u32,
+    /// 首次重试的基础等待时间（毫秒），之后每次翻倍
+    pub retry_base_ms: u64,
+    /// 连续失败多少次后熔断，0 表示不熔断
+    pub breaker_threshold: u32,
+    /// 熔断持续时间（秒）
+    pub breaker_cooldown_secs: u64,
+}
+
+impl Default for GuardPolicy {
+    fn default() -> Self {
+        Self {
+            rpm: None,
+            tpm: None,
+            max_retries: 3,
+            retry_base_ms: 500,
+            breaker_threshold: 5,
+            breaker_cooldown_secs: 30,
+        }
+    }
+}
+
+impl GuardPolicy {
+    /// 第 `attempt` 次重试（从 0 开始）的等待时间：指数退避，在 [一半, 全部] 之间随机抖动
+    fn backoff(&self, attempt: u32) -> Duration {
+        let delay = Duration::from_millis(self.retry_base_ms)
+            .saturating_mul(1 << attempt.min(16))
+            .min(MAX_DELAY);
+        delay.mul_f64(rand::rng().random_range(0.5..=1.0))
+    }
+}
+
+/// 一次重试
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GuardRetry {
+    pub error: String,
+    /// 重试前等待的时间（毫秒）
+    pub delay_ms: u64,
+}
+
+/// 中间件对一次调用的处理记录
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GuardReport {
+    /// 因限流等待的总时长（毫秒）
+    #[serde(default)]
+    pub throttled_ms: u64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub retries: Vec<GuardRetry>,
+}
+
+impl GuardReport {
+    /// 没有等待也没有重试时不记录
+    fn into_option(self) -> Option<Self> {
+        (self.throttled_ms > 0 || !self.retries.is_empty()).then_some(self)
+    }
+}
+
+/// 限流窗口中的一次请求
+#[derive(Debug)]
+struct Slot {
+    id: u64,
+    at: Instant,
+    tokens: u64,
+}
+
+#[derive(Debug, Default)]
+struct StateInner {
+    window: VecDeque<Slot>,
+    next_id: u64,
+    /// 连续失败次数
+    failures: u32,
+    /// 熔断结束时间
+    open_until: Option<Instant>,
+    /// 冷却结束后是否已有试探请求在进行
+    probing: bool,
+}
+
+/// 同一密钥共享的限流与熔断状态
+#[derive(Debug, Default)]
+pub struct GuardState {
+    inner: Mutex<StateInner>,
+}
+
+impl GuardState {
+    fn lock(&self) -> std::sync::MutexGuard<'_, StateInner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 熔断检查：冷却期内拒绝，冷却结束后只放行一个试探请求
+    fn admit(&self, policy: &GuardPolicy) -> Result<(), LlmError> {
+        if policy.breaker_threshold == 0 {
+            return Ok(());
+        }
+        let mut state = self.lock();
+        match state.open_until {
+            None => Ok(()),
+            Some(until) if Instant::now() < until => Err(LlmError::new(
+                LlmErrorKind::CircuitOpen,
+                format!(
+                    "provider 连续失败已熔断，约 {} 秒后恢复",
+                    until.saturating_duration_since(Instant::now()).as_secs() + 1
+                ),
+            )),
+            Some(_) if state.probing => Err(LlmError::new(
+                LlmErrorKind::CircuitOpen,
+                "provider 熔断恢复中，等待试探请求结果",
+            )),
+            Some(_) => {
+                state.probing = true;
+                Ok(())
+            }
+        }
+    }
+
+    /// 记录调用结果；`healthy` 为 false 表示 429 / 5xx / 超时等服务端问题
+    fn record(&self, policy: &GuardPolicy, healthy: bool) {
+        let mut state = self.lock();
+        if healthy {
+            state.failures = 0;
+            state.open_until = None;
+            state.probing = false;
+            return;
+        }
+        state.failures += 1;
+        let threshold = policy.breaker_threshold;
+        if threshold > 0 && (state.probing || state.failures >= threshold) {
+            tracing::warn!(
+                "⚠️  provider 连续失败 {} 次，熔断 {} 秒",
+                state.failures,
+                policy.breaker_cooldown_secs
+            );
+            state.open_until =
+                Some(Instant::now() + Duration::from_secs(policy.breaker_cooldown_secs));
+            state.probing = false;
+        }
+    }
+
+    /// 等待限流额度，返回窗口中的请求 id 与等待时长
+    async fn acquire(&self, policy: &GuardPolicy, tokens: u64) -> (Option<u64>, Duration) {
+        if policy.rpm.is_none() && policy.tpm.is_none() {
+            return (None, Duration::ZERO);
+        }
+        let start = Instant::now();
+        loop {
+            let wait = {
+                let mut state = self.lock();
+                let now = Instant::now();
+                while state
+                    .window
+                    .front()
+                    .is_some_and(|slot| now.duration_since(slot.at) >= WINDOW)
+                {
+                    state.window.pop_front();
+                }
+                let used: u64 = state.window.iter().map(|slot| slot.tokens).sum();
+                let rpm_ok = policy
+                    .rpm
+                    .is_none_or(|rpm| state.window.len() < rpm as usize);
+                // 单个请求超过 tpm 时在窗口清空后放行，避免永远等待
+                let tpm_ok = policy
+                    .tpm
+                    .is_none_or(|tpm| state.window.is_empty() || used + tokens <= tpm);
+                if rpm_ok && tpm_ok {
+                    let id = state.next_id;
+                    state.next_id += 1;
+                    state.window.push_back(Slot {
+                        id,
+                        at: now,
+                        tokens,
+                    });
+                    return (Some(id), start.elapsed());
+                }
+                match state.window.front() {
+                    Some(oldest) => (oldest.at + WINDOW).saturating_duration_since(now),
+                    None => Duration::ZERO,
+                }
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// 用实际用量替换请求前的估算（服务端未返回用量时保留估算）
+    fn settle(&self, id: Option<u64>, tokens: u64) {
+        let Some(id) = id.filter(|_| tokens > 0) else {
+            return;
+        };
+        if let Some(slot) = self.lock().window.iter_mut().find(|slot| slot.id == id) {
+            slot.tokens = tokens;
+        }
+    }
+}
+
+/// 按密钥索引的共享状态，保存在全局状态中，跨运行共享
+#[derive(Debug, Default)]
+pub struct GuardStates {
+    states: Mutex<HashMap<String, Arc<GuardState>>>,
+}
+
+impl GuardStates {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, key: &str) -> Arc<GuardState> {
+        self.states
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(key.to_string())
+            .or_default()
+            .clone()
+    }
+}
+
+/// 带限流、重试与熔断的 provider
+pub struct Guarded {
+    inner: Arc<dyn Provider>,
+    policy: GuardPolicy,
+    state: Arc<GuardState>,
+}
+
+impl Guarded {
+    pub fn new(inner: Arc<dyn Provider>, policy: GuardPolicy, state: Arc<GuardState>) -> Self {
+        Self {
+            inner,
+            policy,
+            state,
+        }
+    }
+
+    async fn call<F, Fut>(
+        &self,
+        request: &ChatRequest,
+        mut send: F,
+    ) -> Result<ChatResponse, LlmError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = (Result<ChatResponse, LlmError>, bool)>,
+    {
+        let estimate = estimate_tokens(request);
+        let mut report = GuardReport::default();
+        let mut attempt = 0;
+        loop {
+            if let Err(mut e) = self.state.admit(&self.policy) {
+                e.guard = report.into_option();
+                return Err(e);
+            }
+            let (slot, waited) = self.state.acquire(&self.policy, estimate).await;
+            report.throttled_ms += waited.as_millis() as u64;
+
+            let (result, retryable) = send().await;
+            match result {
+                Ok(mut response) => {
+                    self.state.settle(slot, response.usage.total());
+                    self.state.record(&self.policy, true);
+                    response.guard = report.into_option();
+                    return Ok(response);
+                }
+                Err(mut e) => {
+                    self.state.record(&self.policy, !e.is_retryable());
+                    if !retryable || !e.is_retryable() || attempt >= self.policy.max_retries {
+                        e.guard = report.into_option();
+                        return Err(e);
+                    }
+                    let delay = e
+                        .retry_after
+                        .map(|d| d.min(MAX_DELAY))
+                        .unwrap_or_else(|| self.policy.backoff(attempt));
+                    tracing::warn!(
+                        "⚠️  provider {} 调用失败，{} 毫秒后重试: {}",
+                        self.inner.name(),
+                        delay.as_millis(),
+                        e
+                    );
+                    report.retries.push(GuardRetry {
+                        error: e.to_string(),
+                        delay_ms: delay.as_millis() as u64,
+                    });
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for Guarded {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, LlmError> {
+        self.call(request, || async { (self.inner.chat(request).await, true) })
+            .await
+    }
+
+    async fn chat_stream(
+        &self,
+        request: &ChatRequest,
+        on_delta: &OnDelta<'_>,
+    ) -> Result<ChatResponse, LlmError> {
+        self.call(request, || async {
+            // 已经输出过片段时不再重试，避免重复的流式文本
+            let emitted = std::sync::atomic::AtomicBool::new(false);
+            let forward = |delta: &str| {
+                emitted.store(true, std::sync::atomic::Ordering::Relaxed);
+                on_delta(delta);
+            };
+            let result = self.inner.chat_stream(request, &forward).await;
+            (result, !emitted.load(std::sync::atomic::Ordering::Relaxed))
+        })
+        .await
+    }
+}
+
+/// 粗略估算请求的 token 数：约 4 个字符一个 token，再加上输出上限
+fn estimate_tokens(request: &ChatRequest) -> u64 {
+    let chars: usize = request
+        .messages
+        .iter()
+        .map(|m| m.content.chars().count())
+        .sum();
+    (chars as u64).div_ceil(4) + u64::from(request.max_tokens.unwrap_or(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::ChatMessage;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// 前 `fail` 次返回 503，之后成功
+    struct Flaky {
+        fail: u32,
+        calls: AtomicU32,
+    }
+
+    #[async_trait]
+    impl Provider for Flaky {
+        fn name(&self) -> &str {
+            "flaky"
+        }
+
+        async fn chat(&self, _request: &ChatRequest) -> Result<ChatResponse, LlmError> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) < self.fail {
+                let mut e = LlmError::http(503, "overloaded");
+                e.retry_after = Some(Duration::from_millis(1));
+                return Err(e);
+            }
+            Ok(ChatResponse {
+                content: "ok".to_string(),
+                ..Default::default()
+            })
+        }
+    }
+
+    fn request() -> ChatRequest {
+        ChatRequest {
+            model: "m".to_string(),
+            messages: vec![ChatMessage::user("hi")],
+            temperature: None,
+            max_tokens: None,
+            response_format: None,
+            tools: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_and_circuit_breaker() {
+        let policy = GuardPolicy {
+            max_retries: 2,
+            breaker_threshold: 3,
+            breaker_cooldown_secs: 60,
+            ..Default::default()
+        };
+        let flaky = Arc::new(Flaky {
+            fail: 2,
+            calls: AtomicU32::new(0),
+        });
+        let guarded = Guarded::new(flaky.clone(), policy.clone(), Arc::default());
+        let response = guarded.chat(&request()).await.unwrap();
+        assert_eq!(response.content, "ok");
+        assert_eq!(response.guard.unwrap().retries.len(), 2);
+
+        let flaky = Arc::new(Flaky {
+            fail: u32::MAX,
+            calls: AtomicU32::new(0),
+        });
+        let guarded = Guarded::new(flaky.clone(), policy, Arc::default());
+        let error = guarded.chat(&request()).await.unwrap_err();
+        assert_eq!(error.status, Some(503));
+        assert_eq!(flaky.calls.load(Ordering::SeqCst), 3);
+        // 连续失败 3 次后熔断，不再调用 provider
+        let error = guarded.chat(&request()).await.unwrap_err();
+        assert_eq!(error.kind, LlmErrorKind::CircuitOpen);
+        assert_eq!(flaky.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_window() {
+        let state = GuardState::default();
+        let policy = GuardPolicy {
+            rpm: Some(2),
+            tpm: Some(100),
+            ..Default::default()
+        };
+        let (first, _) = state.acquire(&policy, 60).await;
+        state.settle(first, 10);
+        let (_, waited) = state.acquire(&policy, 60).await;
+        assert_eq!(waited.as_secs(), 0);
+        // 第三个请求超过 rpm，需要等到窗口中最早的请求过期
+        let third = tokio::time::timeout(Duration::from_millis(50), state.acquire(&policy, 1));
+        assert!(third.await.is_err());
+    }
+}
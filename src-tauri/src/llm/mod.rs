@@ -0,0 +1,266 @@
+//! 大模型服务（Provider）抽象
+//!
+//! 执行器只依赖 [`Provider`] trait；具体实现（如 OpenAI 兼容接口）在子模块中，
+//! 由 [`registry::ProviderRegistry`] 按配置创建。
+
+pub mod cassette;
+pub mod guard;
+pub mod openai;
+pub mod pricing;
+pub mod registry;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::time::Duration;
+
+use self::guard::GuardReport;
+
+/// 对话消息角色
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+    /// 工具调用结果
+    Tool,
+}
+
+/// 对话消息
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: Role,
+    pub content: String,
+    /// assistant 消息中模型请求的工具调用
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    /// tool 消息对应的工具调用 id
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+}
+
+impl ChatMessage {
+    fn new(role: Role, content: impl Into<String>) -> Self {
+        Self {
+            role,
+            content: content.into(),
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+        }
+    }
+
+    pub fn system(content: impl Into<String>) -> Self {
+        Self::new(Role::System, content)
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self::new(Role::User, content)
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self::new(Role::Assistant, content)
+    }
+
+    /// 模型发起工具调用的 assistant 消息
+    pub fn assistant_tool_calls(content: impl Into<String>, tool_calls: Vec<ToolCall>) -> Self {
+        Self {
+            tool_calls,
+            ..Self::new(Role::Assistant, content)
+        }
+    }
+
+    /// 工具调用结果
+    pub fn tool(tool_call_id: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            tool_call_id: Some(tool_call_id.into()),
+            ..Self::new(Role::Tool, content)
+        }
+    }
+}
+
+/// 模型请求的一次工具调用
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    /// 模型生成的参数（JSON 字符串，可能不合法）
+    pub arguments: String,
+}
+
+/// 提供给模型的工具声明
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolSpec {
+    pub name: String,
+    pub description: String,
+    /// 参数的 JSON Schema
+    pub parameters: Value,
+}
+
+/// 期望的响应格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseFormat {
+    /// 要求模型只输出一个 JSON 对象（JSON mode）
+    Json,
+}
+
+/// 一次对话补全请求
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChatRequest {
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+    /// 允许模型调用的工具
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<ToolSpec>,
+}
+
+impl ChatRequest {
+    /// 请求内容的 SHA-256（十六进制），用于录制与回放时匹配请求
+    pub fn hash(&self) -> String {
+        let json = serde_json::to_vec(self).unwrap_or_default();
+        format!("{:x}", Sha256::digest(json))
+    }
+}
+
+/// Token 用量
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Usage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+impl Usage {
+    pub fn total(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+}
+
+impl std::ops::AddAssign for Usage {
+    fn add_assign(&mut self, other: Self) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+    }
+}
+
+/// 对话补全响应
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChatResponse {
+    pub content: String,
+    #[serde(default)]
+    pub usage: Usage,
+    /// 服务端实际使用的模型名
+    #[serde(default)]
+    pub model: String,
+    /// 模型请求的工具调用，不为空时 `content` 通常为空
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    /// 限流、重试等中间件处理记录
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guard: Option<GuardReport>,
+}
+
+/// Provider 错误类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LlmErrorKind {
+    /// 配置错误（缺少 provider、地址非法等）
+    Config,
+    /// 网络错误
+    Network,
+    /// 请求超时
+    Timeout,
+    /// 服务端返回非成功状态码
+    Http,
+    /// 响应格式无法解析
+    Decode,
+    /// 回放时没有匹配的录制响应
+    Replay,
+    /// provider 连续失败后熔断，暂时拒绝请求
+    CircuitOpen,
+}
+
+/// Provider 调用错误
+#[derive(Debug, Clone)]
+pub struct LlmError {
+    pub kind: LlmErrorKind,
+    pub message: String,
+    /// HTTP 状态码（仅 `Http` 类别）
+    pub status: Option<u16>,
+    /// 服务端要求的重试等待时间（`Retry-After`）
+    pub retry_after: Option<Duration>,
+    /// 限流、重试等中间件处理记录
+    pub guard: Option<GuardReport>,
+}
+
+impl LlmError {
+    pub fn new(kind: LlmErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            status: None,
+            retry_after: None,
+            guard: None,
+        }
+    }
+
+    pub fn http(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status: Some(status),
+            ..Self::new(LlmErrorKind::Http, message)
+        }
+    }
+
+    /// 是否值得重试：429、5xx 与超时
+    pub fn is_retryable(&self) -> bool {
+        match self.kind {
+            LlmErrorKind::Timeout => true,
+            LlmErrorKind::Http => self.status.is_some_and(|s| s == 429 || s >= 500),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for LlmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.status {
+            Some(status) => write!(f, "[{:?} {}] {}", self.kind, status, self.message),
+            None => write!(f, "[{:?}] {}", self.kind, self.message),
+        }
+    }
+}
+
+impl std::error::Error for LlmError {}
+
+/// 流式输出回调，参数为新收到的文本片段
+pub type OnDelta<'a> = dyn Fn(&str) + Send + Sync + 'a;
+
+/// 大模型服务
+#[async_trait]
+pub trait Provider: Send + Sync {
+    /// 配置中的 provider 名称
+    fn name(&self) -> &str;
+
+    /// 发起一次对话补全
+    async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, LlmError>;
+
+    /// 流式对话补全，每收到一段文本调用一次 `on_delta`，结束后返回完整响应。
+    /// 默认实现退化为一次性补全。
+    async fn chat_stream(
+        &self,
+        request: &ChatRequest,
+        on_delta: &OnDelta<'_>,
+    ) -> Result<ChatResponse, LlmError> {
+        let response = self.chat(request).await?;
+        on_delta(&response.content);
+        Ok(response)
+    }
+}
@@ -0,0 +1,283 @@
+//! OpenAI 兼容接口（`/chat/completions`）
+//!
+//! OpenAI、DeepSeek、Ollama、vLLM 等服务均提供该接口，因此作为默认实现。
+
+use async_trait::async_trait;
+use reqwest::header::HeaderMap;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use super::{
+    ChatMessage, ChatRequest, ChatResponse, LlmError, LlmErrorKind, OnDelta, Provider,
+    ResponseFormat, ToolCall, Usage,
+};
+
+pub struct OpenAiProvider {
+    name: String,
+    base_url: String,
+    api_key: Option<String>,
+    client: reqwest::Client,
+}
+
+impl OpenAiProvider {
+    pub fn new(
+        name: impl Into<String>,
+        base_url: impl Into<String>,
+        api_key: Option<String>,
+        timeout: Duration,
+    ) -> Result<Self, LlmError> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(|e| LlmError::new(LlmErrorKind::Config, e.to_string()))?;
+        Ok(Self {
+            name: name.into(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            api_key,
+            client,
+        })
+    }
+
+    fn request_body(request: &ChatRequest) -> Value {
+        let messages: Vec<Value> = request.messages.iter().map(message_json).collect();
+        let mut body = json!({
+            "model": request.model,
+            "messages": messages,
+        });
+        if let Some(t) = request.temperature {
+            body["temperature"] = json!(t);
+        }
+        if let Some(m) = request.max_tokens {
+            body["max_tokens"] = json!(m);
+        }
+        if let Some(ResponseFormat::Json) = request.response_format {
+            body["response_format"] = json!({ "type": "json_object" });
+        }
+        if !request.tools.is_empty() {
+            let tools: Vec<Value> = request
+                .tools
+                .iter()
+                .map(|tool| {
+                    json!({
+                        "type": "function",
+                        "function": {
+                            "name": tool.name,
+                            "description": tool.description,
+                            "parameters": tool.parameters,
+                        }
+                    })
+                })
+                .collect();
+            body["tools"] = json!(tools);
+        }
+        body
+    }
+
+    /// 发送请求，非成功状态码转换为 `Http` 错误
+    async fn send(&self, body: &Value) -> Result<reqwest::Response, LlmError> {
+        let url = format!("{}/chat/completions", self.base_url);
+        let mut builder = self.client.post(&url).json(body);
+        if let Some(key) = &self.api_key {
+            builder = builder.bearer_auth(key);
+        }
+
+        let response = builder.send().await.map_err(transport_error)?;
+        let status = response.status();
+        if !status.is_success() {
+            let retry_after = retry_after(response.headers());
+            let text = response.text().await.unwrap_or_default();
+            let mut error = LlmError::http(status.as_u16(), text);
+            error.retry_after = retry_after;
+            return Err(error);
+        }
+        Ok(response)
+    }
+}
+
+/// 解析 `Retry-After`（秒），也支持 OpenAI 的 `retry-after-ms`
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let parse = |name: &str| headers.get(name)?.to_str().ok()?.trim().parse::<f64>().ok();
+    if let Some(ms) = parse("retry-after-ms") {
+        return Some(Duration::from_secs_f64(ms.max(0.0) / 1000.0));
+    }
+    parse("retry-after").map(|secs| Duration::from_secs_f64(secs.max(0.0)))
+}
+
+fn transport_error(e: reqwest::Error) -> LlmError {
+    let kind = if e.is_timeout() {
+        LlmErrorKind::Timeout
+    } else if e.is_decode() {
+        LlmErrorKind::Decode
+    } else {
+        LlmErrorKind::Network
+    };
+    LlmError::new(kind, e.to_string())
+}
+
+fn message_json(message: &ChatMessage) -> Value {
+    let mut value = json!({ "role": message.role, "content": message.content });
+    if !message.tool_calls.is_empty() {
+        let calls: Vec<Value> = message
+            .tool_calls
+            .iter()
+            .map(|call| {
+                json!({
+                    "id": call.id,
+                    "type": "function",
+                    "function": { "name": call.name, "arguments": call.arguments },
+                })
+            })
+            .collect();
+        value["tool_calls"] = json!(calls);
+    }
+    if let Some(id) = &message.tool_call_id {
+        value["tool_call_id"] = json!(id);
+    }
+    value
+}
+
+fn parse_tool_call(call: &Value) -> Option<ToolCall> {
+    let text = |pointer: &str| call.pointer(pointer).and_then(Value::as_str);
+    Some(ToolCall {
+        id: text("/id")?.to_string(),
+        name: text("/function/name")?.to_string(),
+        arguments: text("/function/arguments").unwrap_or("{}").to_string(),
+    })
+}
+
+fn parse_usage(usage: &Value) -> Usage {
+    Usage {
+        prompt_tokens: usage
+            .get("prompt_tokens")
+            .and_then(Value::as_u64)
+            .unwrap_or(0),
+        completion_tokens: usage
+            .get("completion_tokens")
+            .and_then(Value::as_u64)
+            .unwrap_or(0),
+    }
+}
+
+#[async_trait]
+impl Provider for OpenAiProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, LlmError> {
+        let response = self.send(&Self::request_body(request)).await?;
+        let body: Value = response.json().await.map_err(transport_error)?;
+
+        let message = body.pointer("/choices/0/message").ok_or_else(|| {
+            LlmError::new(
+                LlmErrorKind::Decode,
+                format!("响应中缺少 choices[0].message: {}", body),
+            )
+        })?;
+        let tool_calls: Vec<ToolCall> = message
+            .get("tool_calls")
+            .and_then(Value::as_array)
+            .map(|calls| calls.iter().filter_map(parse_tool_call).collect())
+            .unwrap_or_default();
+        let content = match message.get("content").and_then(Value::as_str) {
+            Some(content) => content.to_string(),
+            None if !tool_calls.is_empty() => String::new(),
+            None => {
+                return Err(LlmError::new(
+                    LlmErrorKind::Decode,
+                    format!("响应中缺少 choices[0].message.content: {}", body),
+                ))
+            }
+        };
+        let usage = body.get("usage").map(parse_usage).unwrap_or_default();
+        let model = body
+            .get("model")
+            .and_then(Value::as_str)
+            .unwrap_or(&request.model)
+            .to_string();
+
+        Ok(ChatResponse {
+            content,
+            usage,
+            model,
+            tool_calls,
+            guard: None,
+        })
+    }
+
+    async fn chat_stream(
+        &self,
+        request: &ChatRequest,
+        on_delta: &OnDelta<'_>,
+    ) -> Result<ChatResponse, LlmError> {
+        let mut body = Self::request_body(request);
+        body["stream"] = json!(true);
+        body["stream_options"] = json!({ "include_usage": true });
+        let mut response = self.send(&body).await?;
+
+        let mut result = ChatResponse {
+            model: request.model.clone(),
+            ..Default::default()
+        };
+        // SSE：逐行读取 `data: {...}`，行以换行分隔，按字节缓冲避免截断多字节字符
+        let mut buffer: Vec<u8> = Vec::new();
+        // 工具调用按 index 分片到达，参数逐段拼接
+        let mut calls: Vec<ToolCall> = Vec::new();
+        'read: while let Some(chunk) = response.chunk().await.map_err(transport_error)? {
+            buffer.extend_from_slice(&chunk);
+            while let Some(end) = buffer.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line);
+                let Some(data) = line.trim().strip_prefix("data:") else {
+                    continue;
+                };
+                let data = data.trim();
+                if data == "[DONE]" {
+                    break 'read;
+                }
+                let event: Value = serde_json::from_str(data).map_err(|e| {
+                    LlmError::new(LlmErrorKind::Decode, format!("无法解析流式响应: {}", e))
+                })?;
+                if let Some(delta) = event
+                    .pointer("/choices/0/delta/content")
+                    .and_then(Value::as_str)
+                {
+                    if !delta.is_empty() {
+                        result.content.push_str(delta);
+                        on_delta(delta);
+                    }
+                }
+                let deltas = event.pointer("/choices/0/delta/tool_calls");
+                for delta in deltas.and_then(Value::as_array).into_iter().flatten() {
+                    let index = delta.get("index").and_then(Value::as_u64).unwrap_or(0) as usize;
+                    if calls.len() <= index {
+                        calls.resize_with(index + 1, || ToolCall {
+                            id: String::new(),
+                            name: String::new(),
+                            arguments: String::new(),
+                        });
+                    }
+                    let call = &mut calls[index];
+                    if let Some(id) = delta.get("id").and_then(Value::as_str) {
+                        call.id = id.to_string();
+                    }
+                    if let Some(name) = delta.pointer("/function/name").and_then(Value::as_str) {
+                        call.name.push_str(name);
+                    }
+                    if let Some(args) = delta.pointer("/function/arguments").and_then(Value::as_str)
+                    {
+                        call.arguments.push_str(args);
+                    }
+                }
+                if let Some(usage) = event.get("usage").filter(|u| !u.is_null()) {
+                    result.usage = parse_usage(usage);
+                }
+                if let Some(model) = event.get("model").and_then(Value::as_str) {
+                    result.model = model.to_string();
+                }
+            }
+        }
+        result.tool_calls = calls.into_iter().filter(|c| !c.name.is_empty()).collect();
+        Ok(result)
+    }
+}
@@ -0,0 +1,110 @@
+//! 模型价格表
+//!
+//! 价格配置保存在 app.db 的 config 表中（key = `model_price`），值形如：
+//! `{ "model": "gpt-4o", "provider": "openai", "prompt": 2.5, "completion": 10 }`，
+//! 单位为每百万 token 的价格。`model` 以 `*` 结尾时按前缀匹配，省略 `provider` 时对所有 provider 生效。
+
+use serde::Deserialize;
+
+use super::Usage;
+use crate::utils::appdb::AppDb;
+
+/// config 表中价格配置的 key
+pub const CONFIG_KEY: &str = "model_price";
+
+/// 单个模型的价格
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ModelPrice {
+    pub model: String,
+    #[serde(default)]
+    pub provider: Option<String>,
+    /// 每百万输入 token 的价格
+    pub prompt: f64,
+    /// 每百万输出 token 的价格
+    pub completion: f64,
+}
+
+impl ModelPrice {
+    /// 匹配优先级：精确匹配优先于前缀匹配，前缀越长越优先，指定 provider 的优先
+    fn rank(&self, provider: &str, model: &str) -> Option<(bool, usize, bool)> {
+        if self.provider.as_deref().is_some_and(|p| p != provider) {
+            return None;
+        }
+        let (exact, len) = match self.model.strip_suffix('*') {
+            Some(prefix) if model.starts_with(prefix) => (false, prefix.len()),
+            Some(_) => return None,
+            None if self.model == model => (true, model.len()),
+            None => return None,
+        };
+        Some((exact, len, self.provider.is_some()))
+    }
+}
+
+/// 价格表
+#[derive(Debug, Clone, Default)]
+pub struct PriceTable {
+    prices: Vec<ModelPrice>,
+}
+
+impl PriceTable {
+    pub fn new(prices: Vec<ModelPrice>) -> Self {
+        Self { prices }
+    }
+
+    /// 从 app.db 读取全部价格配置
+    pub async fn load(app_db: &AppDb) -> Result<Self, String> {
+        let items = app_db.get_configs_by_key(CONFIG_KEY).await?;
+        let mut prices = Vec::with_capacity(items.len());
+        for item in items {
+            match serde_json::from_value(item.value) {
+                Ok(price) => prices.push(price),
+                Err(e) => tracing::warn!("⚠️  价格配置 {} 格式错误，已忽略: {}", item.id, e),
+            }
+        }
+        Ok(Self::new(prices))
+    }
+
+    pub fn find(&self, provider: &str, model: &str) -> Option<&ModelPrice> {
+        self.prices
+            .iter()
+            .filter_map(|p| p.rank(provider, model).map(|rank| (rank, p)))
+            .max_by_key(|(rank, _)| *rank)
+            .map(|(_, p)| p)
+    }
+
+    /// 计算一次调用的费用，价格表中没有该模型时返回 `None`
+    pub fn cost(&self, provider: &str, model: &str, usage: &Usage) -> Option<f64> {
+        let price = self.find(provider, model)?;
+        Some(
+            (usage.prompt_tokens as f64 * price.prompt
+                + usage.completion_tokens as f64 * price.completion)
+                / 1_000_000.0,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_price_matching() {
+        let prices: Vec<ModelPrice> = serde_json::from_value(json!([
+            { "model": "gpt-4o*", "prompt": 2.5, "completion": 10 },
+            { "model": "gpt-4o-mini*", "prompt": 0.15, "completion": 0.6 },
+            { "model": "gpt-4o-mini", "provider": "azure", "prompt": 0.2, "completion": 0.8 }
+        ]))
+        .unwrap();
+        let table = PriceTable::new(prices);
+        let usage = Usage {
+            prompt_tokens: 1_000_000,
+            completion_tokens: 500_000,
+        };
+
+        assert_eq!(table.cost("openai", "gpt-4o-2024-08-06", &usage), Some(7.5));
+        assert_eq!(table.cost("openai", "gpt-4o-mini", &usage), Some(0.45));
+        assert_eq!(table.cost("azure", "gpt-4o-mini", &usage), Some(0.6));
+        assert_eq!(table.cost("openai", "claude", &usage), None);
+    }
+}
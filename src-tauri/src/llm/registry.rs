@@ -0,0 +1,216 @@
+//! Provider 注册表
+//!
+//! Provider 配置保存在 app.db 的 config 表中（key = `provider`），值形如：
+//! `{ "name": "openai", "kind": "openai", "base_url": "https://api.openai.com/v1", "api_key": "...", "default": true }`
+//!
+//! 可同时配置限流、重试与熔断策略（见 [`GuardPolicy`]），如 `"rpm": 60, "tpm": 90000, "max_retries": 3`。
+//!
+//! API Key 应保存在密钥库中，配置里以 `"api_key_secret": "<密钥名>"` 引用；密钥库未解锁时
+//! 引用了密钥的 provider 不可用。明文 `api_key` 仍然兼容，但会在加载时给出警告。
+
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::guard::{GuardPolicy, GuardStates, Guarded};
+use super::openai::OpenAiProvider;
+use super::Provider;
+use crate::utils::appdb::AppDb;
+use crate::vault::Vault;
+
+/// config 表中 provider 配置的 key
+pub const CONFIG_KEY: &str = "provider";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderKind {
+    /// OpenAI 兼容的 `/chat/completions` 接口
+    #[default]
+    Openai,
+}
+
+/// 单个 provider 的配置
+#[derive(Clone, Deserialize)]
+pub struct ProviderConfig {
+    pub name: String,
+    #[serde(default)]
+    pub kind: ProviderKind,
+    pub base_url: String,
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// 密钥库中保存 API Key 的密钥名
+    #[serde(default)]
+    pub api_key_secret: Option<String>,
+    /// 请求超时（秒）
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// 节点未指定 provider 时使用默认 provider
+    #[serde(default)]
+    pub default: bool,
+    #[serde(flatten)]
+    pub guard: GuardPolicy,
+}
+
+impl std::fmt::Debug for ProviderConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProviderConfig")
+            .field("name", &self.name)
+            .field("kind", &self.kind)
+            .field("base_url", &self.base_url)
+            .field("api_key", &self.api_key.as_ref().map(|_| "<redacted>"))
+            .field("api_key_secret", &self.api_key_secret)
+            .field("timeout_secs", &self.timeout_secs)
+            .field("default", &self.default)
+            .field("guard", &self.guard)
+            .finish()
+    }
+}
+
+impl ProviderConfig {
+    /// 限流与熔断状态按密钥共享：地址与 API Key 相同的配置共用额度
+    fn guard_key(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.base_url.trim_end_matches('/').as_bytes());
+        hasher.update([0]);
+        hasher.update(self.api_key.as_deref().unwrap_or_default().as_bytes());
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+fn default_timeout_secs() -> u64 {
+    120
+}
+
+/// 按名称索引的 provider 集合
+#[derive(Default, Clone)]
+pub struct ProviderRegistry {
+    providers: HashMap<String, Arc<dyn Provider>>,
+    default: Option<String>,
+    /// 设置后所有名称都解析到该 provider
+    single: Option<Arc<dyn Provider>>,
+    /// 已注册的配置，用于以其它 API Key 创建 provider
+    configs: HashMap<String, ProviderConfig>,
+    guards: Arc<GuardStates>,
+}
+
+impl ProviderRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 所有 provider 名称都解析为同一个实例（用于回放）
+    pub fn single(provider: Arc<dyn Provider>) -> Self {
+        Self {
+            single: Some(provider),
+            ..Self::default()
+        }
+    }
+
+    /// 从 app.db 读取全部 provider 配置并创建实例，`guards` 为跨运行共享的限流与熔断状态，
+    /// 引用的 API Key 从 `vault` 中读取
+    pub async fn load(
+        app_db: &AppDb,
+        guards: &Arc<GuardStates>,
+        vault: &Vault,
+    ) -> Result<Self, String> {
+        let items = app_db.get_configs_by_key(CONFIG_KEY).await?;
+        let mut registry = Self::new();
+        registry.guards = guards.clone();
+        for item in items {
+            let mut config: ProviderConfig = match serde_json::from_value(item.value) {
+                Ok(c) => c,
+                Err(e) => {
+                    tracing::warn!("⚠️  provider 配置 {} 格式错误，已忽略: {}", item.id, e);
+                    continue;
+                }
+            };
+            if let Some(secret) = &config.api_key_secret {
+                match vault.get(secret) {
+                    Ok(key) => config.api_key = Some(key.to_string()),
+                    Err(e) => {
+                        tracing::warn!(
+                            "⚠️  provider {} 引用的密钥 {} 不可用，已忽略: {}",
+                            config.name,
+                            secret,
+                            e
+                        );
+                        continue;
+                    }
+                }
+            } else if config.api_key.is_some() {
+                tracing::warn!(
+                    "⚠️  provider {} 的 API Key 以明文保存在配置中，建议改用 api_key_secret 引用密钥库",
+                    config.name
+                );
+            }
+            if let Err(e) = registry.add_config(&config, guards) {
+                tracing::warn!("⚠️  创建 provider {} 失败: {}", config.name, e);
+            }
+        }
+        Ok(registry)
+    }
+
+    /// 按配置创建 provider（外包限流、重试与熔断中间件）并注册
+    pub fn add_config(
+        &mut self,
+        config: &ProviderConfig,
+        guards: &GuardStates,
+    ) -> Result<(), String> {
+        let provider = build(config, guards)?;
+        if config.default || self.default.is_none() {
+            self.default = Some(config.name.clone());
+        }
+        self.providers.insert(config.name.clone(), provider);
+        self.configs.insert(config.name.clone(), config.clone());
+        Ok(())
+    }
+
+    /// 按名称获取 provider，名称为空时返回默认 provider
+    pub fn get(&self, name: Option<&str>) -> Option<Arc<dyn Provider>> {
+        if let Some(provider) = &self.single {
+            return Some(provider.clone());
+        }
+        let name = name.or(self.default.as_deref())?;
+        self.providers.get(name).cloned()
+    }
+
+    /// 以指定 API Key 创建 provider（其余配置不变），限流额度按该 Key 单独计算
+    pub fn with_api_key(
+        &self,
+        name: Option<&str>,
+        api_key: &str,
+    ) -> Option<Result<Arc<dyn Provider>, String>> {
+        if let Some(provider) = &self.single {
+            return Some(Ok(provider.clone()));
+        }
+        let name = name.or(self.default.as_deref())?;
+        let config = ProviderConfig {
+            api_key: Some(api_key.to_string()),
+            ..self.configs.get(name)?.clone()
+        };
+        Some(build(&config, &self.guards))
+    }
+}
+
+/// 按配置创建 provider（外包限流、重试与熔断中间件）
+fn build(config: &ProviderConfig, guards: &GuardStates) -> Result<Arc<dyn Provider>, String> {
+    let provider: Arc<dyn Provider> = match config.kind {
+        ProviderKind::Openai => Arc::new(
+            OpenAiProvider::new(
+                config.name.clone(),
+                config.base_url.clone(),
+                config.api_key.clone(),
+                Duration::from_secs(config.timeout_secs),
+            )
+            .map_err(|e| e.to_string())?,
+        ),
+    };
+    let provider = Arc::new(Guarded::new(
+        provider,
+        config.guard.clone(),
+        guards.get(&config.guard_key()),
+    ));
+    Ok(provider)
+}
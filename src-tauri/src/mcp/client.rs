@@ -0,0 +1,461 @@
+//! MCP 客户端：以子进程启动 stdio MCP 服务并调用其工具
+//!
+//! 服务在首次使用时启动并完成握手（`initialize` → `notifications/initialized`）。进程退出后在下次使用时
+//! 重启，短时间内反复退出时按指数退避（最长 60 秒）拒绝使用。服务的 stderr 逐行写入日志；
+//! 客户端被丢弃时结束子进程。
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStderr, ChildStdin, ChildStdout, Command};
+use tokio::sync::oneshot;
+use zeroize::Zeroizing;
+
+use super::{codes, Message, RpcError, ToolInfo, PROTOCOL_VERSION, SUPPORTED_VERSIONS};
+
+/// 握手超时
+const INIT_TIMEOUT: Duration = Duration::from_secs(30);
+/// 运行超过该时长后退出视为正常，不计入连续失败
+const HEALTHY_AFTER: Duration = Duration::from_secs(60);
+const MAX_BACKOFF_SECS: u64 = 60;
+
+/// 启动服务的命令
+#[derive(Clone)]
+pub struct Launch {
+    pub command: String,
+    pub args: Vec<String>,
+    pub env: Vec<(String, Zeroizing<String>)>,
+    pub cwd: PathBuf,
+}
+
+type Pending = HashMap<u64, oneshot::Sender<Result<Value, RpcError>>>;
+
+/// 一个已启动的服务进程
+struct Connection {
+    child: Mutex<Child>,
+    stdin: tokio::sync::Mutex<ChildStdin>,
+    pending: Mutex<Pending>,
+    alive: AtomicBool,
+    /// 服务通知工具列表有变化
+    tools_changed: AtomicBool,
+    tools: Mutex<Option<Vec<ToolInfo>>>,
+    started: Instant,
+}
+
+impl Connection {
+    fn pending(&self) -> std::sync::MutexGuard<'_, Pending> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    async fn send(&self, message: &Message) -> Result<(), String> {
+        let mut line = serde_json::to_string(message).map_err(|e| e.to_string())?;
+        line.push('\n');
+        let mut stdin = self.stdin.lock().await;
+        let written = stdin.write_all(line.as_bytes()).await;
+        if let Err(e) = written.and(stdin.flush().await) {
+            self.close();
+            return Err(e.to_string());
+        }
+        Ok(())
+    }
+
+    /// 标记为已退出，未完成的请求随之失败
+    fn close(&self) {
+        self.alive.store(false, Ordering::SeqCst);
+        self.pending().clear();
+    }
+
+    async fn dispatch(&self, name: &str, message: Message) {
+        match (message.method, message.id) {
+            (Some(method), Some(id)) => {
+                let reply = if method == "ping" {
+                    Message::response(id, json!({}))
+                } else {
+                    let message = format!("不支持的方法 `{}`", method);
+                    Message::error(id, RpcError::new(codes::METHOD_NOT_FOUND, message))
+                };
+                let _ = self.send(&reply).await;
+            }
+            (Some(method), None) => match method.as_str() {
+                "notifications/tools/list_changed" => {
+                    self.tools_changed.store(true, Ordering::SeqCst);
+                }
+                "notifications/message" => {
+                    let data = message.params.unwrap_or_default();
+                    tracing::info!("MCP {}: {}", name, data["data"]);
+                }
+                _ => {}
+            },
+            (None, Some(id)) => {
+                let sender = id.as_u64().and_then(|id| self.pending().remove(&id));
+                if let Some(sender) = sender {
+                    let _ = sender.send(match message.error {
+                        Some(error) => Err(error),
+                        None => Ok(message.result.unwrap_or(Value::Null)),
+                    });
+                }
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+/// 进程状态与重启退避
+#[derive(Default)]
+struct Slot {
+    connection: Option<Arc<Connection>>,
+    /// 连续启动失败或启动后很快退出的次数
+    failures: u32,
+    last_start: Option<Instant>,
+}
+
+/// 一个 MCP 服务
+pub struct McpClient {
+    name: String,
+    launch: Launch,
+    /// 单次请求的超时
+    timeout: Duration,
+    next_id: AtomicU64,
+    slot: tokio::sync::Mutex<Slot>,
+}
+
+impl McpClient {
+    pub fn new(name: &str, launch: Launch, timeout: Duration) -> Self {
+        Self {
+            name: name.to_string(),
+            launch,
+            timeout,
+            next_id: AtomicU64::new(1),
+            slot: tokio::sync::Mutex::new(Slot::default()),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 服务进程是否在运行
+    pub async fn is_running(&self) -> bool {
+        let slot = self.slot.lock().await;
+        (slot.connection.as_ref()).is_some_and(|conn| conn.alive.load(Ordering::SeqCst))
+    }
+
+    /// 结束服务进程并清除退避，下次使用时重新启动
+    pub async fn restart(&self) {
+        let mut slot = self.slot.lock().await;
+        if slot.connection.take().is_some() {
+            tracing::info!("⏹️  MCP 服务 {} 已停止", self.name);
+        }
+        *slot = Slot::default();
+    }
+
+    /// 服务提供的工具（缓存到服务通知工具列表变化或进程重启）
+    pub async fn tools(&self) -> Result<Vec<ToolInfo>, String> {
+        let conn = self.connection().await?;
+        let changed = conn.tools_changed.swap(false, Ordering::SeqCst);
+        if !changed {
+            if let Some(tools) = conn.tools.lock().unwrap_or_else(|e| e.into_inner()).clone() {
+                return Ok(tools);
+            }
+        }
+        let tools = self.list_tools(&conn).await?;
+        *conn.tools.lock().unwrap_or_else(|e| e.into_inner()) = Some(tools.clone());
+        Ok(tools)
+    }
+
+    /// 调用工具，返回 [`tool_output`] 的结果
+    pub async fn call_tool(&self, name: &str, arguments: Value) -> Result<Value, String> {
+        let conn = self.connection().await?;
+        let params = json!({ "name": name, "arguments": arguments });
+        let result = self
+            .request(&conn, "tools/call", params, self.timeout)
+            .await?;
+        tool_output(result)
+    }
+
+    /// 正在运行的进程；未运行或已退出时（在退避允许时）启动
+    async fn connection(&self) -> Result<Arc<Connection>, String> {
+        let mut slot = self.slot.lock().await;
+        if let Some(conn) = &slot.connection {
+            if conn.alive.load(Ordering::SeqCst) {
+                return Ok(conn.clone());
+            }
+            let status = conn
+                .child
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .try_wait();
+            let status = match status {
+                Ok(Some(status)) => status.to_string(),
+                _ => "输出已关闭".to_string(),
+            };
+            if conn.started.elapsed() >= HEALTHY_AFTER {
+                slot.failures = 0;
+            } else {
+                slot.failures += 1;
+            }
+            tracing::warn!(
+                "⚠️  MCP 服务 {} 已退出（{}），将重新启动",
+                self.name,
+                status
+            );
+            slot.connection = None;
+        }
+
+        if let (Some(last), true) = (slot.last_start, slot.failures > 0) {
+            let backoff = 1u64 << (slot.failures - 1).min(6);
+            let backoff = Duration::from_secs(backoff.min(MAX_BACKOFF_SECS));
+            if last.elapsed() < backoff {
+                return Err(format!(
+                    "MCP 服务 {} 反复退出，{} 秒后再重试",
+                    self.name,
+                    (backoff - last.elapsed()).as_secs() + 1
+                ));
+            }
+        }
+        slot.last_start = Some(Instant::now());
+        match self.start().await {
+            Ok(conn) => {
+                slot.connection = Some(conn.clone());
+                Ok(conn)
+            }
+            Err(e) => {
+                slot.failures += 1;
+                Err(e)
+            }
+        }
+    }
+
+    async fn start(&self) -> Result<Arc<Connection>, String> {
+        let mut command = Command::new(&self.launch.command);
+        command
+            .args(&self.launch.args)
+            .current_dir(&self.launch.cwd)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        for (key, value) in &self.launch.env {
+            command.env(key, value.as_str());
+        }
+        let mut child = command.spawn().map_err(|e| {
+            format!(
+                "启动 MCP 服务 {}（{}）失败: {}",
+                self.name, self.launch.command, e
+            )
+        })?;
+        let pid = child.id();
+        let (Some(stdin), Some(stdout), Some(stderr)) =
+            (child.stdin.take(), child.stdout.take(), child.stderr.take())
+        else {
+            return Err(format!("MCP 服务 {} 的标准输入输出不可用", self.name));
+        };
+        tokio::spawn(log_stderr(self.name.clone(), stderr));
+        let conn = Arc::new(Connection {
+            child: Mutex::new(child),
+            stdin: tokio::sync::Mutex::new(stdin),
+            pending: Mutex::new(HashMap::new()),
+            alive: AtomicBool::new(true),
+            tools_changed: AtomicBool::new(false),
+            tools: Mutex::new(None),
+            started: Instant::now(),
+        });
+        tokio::spawn(read_messages(
+            self.name.clone(),
+            stdout,
+            Arc::downgrade(&conn),
+        ));
+
+        let params = json!({
+            "protocolVersion": PROTOCOL_VERSION,
+            "capabilities": {},
+            "clientInfo": { "name": "vlogi", "version": env!("CARGO_PKG_VERSION") },
+        });
+        let result = self
+            .request(&conn, "initialize", params, INIT_TIMEOUT)
+            .await?;
+        let version = result["protocolVersion"].as_str().unwrap_or_default();
+        if !SUPPORTED_VERSIONS.contains(&version) {
+            return Err(format!(
+                "MCP 服务 {} 使用不支持的协议版本 `{}`",
+                self.name, version
+            ));
+        }
+        conn.send(&Message::notification(
+            "notifications/initialized",
+            json!({}),
+        ))
+        .await
+        .map_err(|e| format!("MCP 服务 {} 握手失败: {}", self.name, e))?;
+        tracing::info!(
+            "✅ MCP 服务 {} 已启动（pid {}，{}，协议 {}）",
+            self.name,
+            pid.unwrap_or_default(),
+            result["serverInfo"]["name"].as_str().unwrap_or("未知"),
+            version
+        );
+        Ok(conn)
+    }
+
+    async fn list_tools(&self, conn: &Connection) -> Result<Vec<ToolInfo>, String> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Page {
+            #[serde(default)]
+            tools: Vec<ToolInfo>,
+            #[serde(default)]
+            next_cursor: Option<String>,
+        }
+
+        let mut tools = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let params = match &cursor {
+                Some(cursor) => json!({ "cursor": cursor }),
+                None => json!({}),
+            };
+            let result = self
+                .request(conn, "tools/list", params, self.timeout)
+                .await?;
+            let page: Page = serde_json::from_value(result)
+                .map_err(|e| format!("MCP 服务 {} 的工具列表格式错误: {}", self.name, e))?;
+            tools.extend(page.tools);
+            match page.next_cursor {
+                Some(next) if !next.is_empty() => cursor = Some(next),
+                _ => return Ok(tools),
+            }
+        }
+    }
+
+    async fn request(
+        &self,
+        conn: &Connection,
+        method: &str,
+        params: Value,
+        timeout: Duration,
+    ) -> Result<Value, String> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = oneshot::channel();
+        conn.pending().insert(id, sender);
+        if let Err(e) = conn
+            .send(&Message::request(json!(id), method, params))
+            .await
+        {
+            conn.pending().remove(&id);
+            return Err(format!("向 MCP 服务 {} 发送请求失败: {}", self.name, e));
+        }
+        match tokio::time::timeout(timeout, receiver).await {
+            Ok(Ok(Ok(result))) => Ok(result),
+            Ok(Ok(Err(error))) => Err(format!(
+                "MCP 服务 {} 返回错误（{}）: {}",
+                self.name, error.code, error.message
+            )),
+            Ok(Err(_)) => Err(format!("MCP 服务 {} 已退出", self.name)),
+            Err(_) => {
+                conn.pending().remove(&id);
+                let cancel = json!({ "requestId": id, "reason": "timeout" });
+                let _ = conn
+                    .send(&Message::notification("notifications/cancelled", cancel))
+                    .await;
+                Err(format!(
+                    "MCP 服务 {} 在 {} 秒内未响应 {}",
+                    self.name,
+                    timeout.as_secs(),
+                    method
+                ))
+            }
+        }
+    }
+}
+
+async fn read_messages(name: String, stdout: ChildStdout, conn: Weak<Connection>) {
+    let mut lines = BufReader::new(stdout).lines();
+    loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => break,
+            Err(e) => {
+                tracing::warn!("⚠️  读取 MCP 服务 {} 的输出失败: {}", name, e);
+                break;
+            }
+        };
+        // 连接已被丢弃（服务被替换或重启）
+        let Some(conn) = conn.upgrade() else {
+            return;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<Message>(&line) {
+            Ok(message) => conn.dispatch(&name, message).await,
+            Err(e) => tracing::warn!("⚠️  MCP 服务 {} 输出了无法解析的消息: {}", name, e),
+        }
+    }
+    if let Some(conn) = conn.upgrade() {
+        conn.close();
+    }
+}
+
+async fn log_stderr(name: String, stderr: ChildStderr) {
+    let mut lines = BufReader::new(stderr).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if !line.trim().is_empty() {
+            tracing::info!("MCP {} stderr: {}", name, line);
+        }
+    }
+}
+
+/// `tools/call` 的结果：`isError` 时为错误文本，否则优先使用 `structuredContent`，
+/// 其次是合并后的文本内容，含非文本内容时原样返回 `content`
+pub fn tool_output(result: Value) -> Result<Value, String> {
+    let content = result.get("content").and_then(Value::as_array);
+    let texts: Vec<&str> = content
+        .into_iter()
+        .flatten()
+        .filter(|item| item["type"] == "text")
+        .filter_map(|item| item["text"].as_str())
+        .collect();
+    if result["isError"].as_bool() == Some(true) {
+        return Err(if texts.is_empty() {
+            "工具执行失败".to_string()
+        } else {
+            texts.join("\n")
+        });
+    }
+    if let Some(structured) = result.get("structuredContent").filter(|v| !v.is_null()) {
+        return Ok(structured.clone());
+    }
+    match content {
+        Some(items) if items.len() == texts.len() => Ok(Value::String(texts.join("\n"))),
+        Some(items) => Ok(Value::Array(items.clone())),
+        None => Ok(Value::Null),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_output() {
+        let text = |text: &str| json!({ "type": "text", "text": text });
+        let result = json!({ "content": [text("a"), text("b")] });
+        assert_eq!(tool_output(result), Ok(json!("a\nb")));
+
+        let result = json!({ "content": [text("{}")], "structuredContent": { "n": 1 } });
+        assert_eq!(tool_output(result), Ok(json!({ "n": 1 })));
+
+        let image = json!({ "type": "image", "data": "", "mimeType": "image/png" });
+        let result = json!({ "content": [text("a"), image.clone()] });
+        assert_eq!(tool_output(result), Ok(json!([text("a"), image])));
+
+        let result = json!({ "content": [text("not found")], "isError": true });
+        assert_eq!(tool_output(result), Err("not found".to_string()));
+    }
+}
@@ -0,0 +1,134 @@
+//! Model Context Protocol
+//!
+//! 只实现 stdio 传输：每行一条 JSON-RPC 2.0 消息。[`server`] 把项目中的流程作为工具提供给其它 agent，
+//! [`client`] 启动外部 MCP 服务并调用其工具。
+
+pub mod client;
+pub mod server;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// 首选的协议版本
+pub const PROTOCOL_VERSION: &str = "2025-06-18";
+/// 支持的协议版本（新到旧）
+pub const SUPPORTED_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// JSON-RPC 错误码
+pub mod codes {
+    pub const PARSE_ERROR: i64 = -32700;
+    pub const INVALID_REQUEST: i64 = -32600;
+    pub const METHOD_NOT_FOUND: i64 = -32601;
+    pub const INVALID_PARAMS: i64 = -32602;
+}
+
+/// JSON-RPC 消息：有 `method` 与 `id` 为请求，只有 `method` 为通知，其余为响应
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Message {
+    pub jsonrpc: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+impl Message {
+    fn new() -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn request(id: Value, method: &str, params: Value) -> Self {
+        Self {
+            id: Some(id),
+            method: Some(method.to_string()),
+            params: Some(params),
+            ..Self::new()
+        }
+    }
+
+    pub fn notification(method: &str, params: Value) -> Self {
+        Self {
+            method: Some(method.to_string()),
+            params: Some(params),
+            ..Self::new()
+        }
+    }
+
+    pub fn response(id: Value, result: Value) -> Self {
+        Self {
+            id: Some(id),
+            result: Some(result),
+            ..Self::new()
+        }
+    }
+
+    pub fn error(id: Value, error: RpcError) -> Self {
+        Self {
+            id: Some(id),
+            error: Some(error),
+            ..Self::new()
+        }
+    }
+}
+
+/// JSON-RPC 错误
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+}
+
+/// `tools/list` 中的工具
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolInfo {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub input_schema: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<Value>,
+}
+
+/// 工具名只能包含字母、数字、`_` 与 `-`，最长 64 个字符
+pub fn tool_name(raw: &str) -> String {
+    let name: String = raw
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(64)
+        .collect();
+    if name.is_empty() {
+        "tool".to_string()
+    } else {
+        name
+    }
+}
@@ -0,0 +1,377 @@
+//! MCP 服务：把项目中的流程作为工具
+//!
+//! 每个流程是一个工具，参数为流程输入（[`FlowDef::input_schema`]），成功时以文本与
+//! `structuredContent` 返回流程输出；执行失败、被取消或等待人工输入时返回 `isError`。
+//! 调用方发送 `notifications/cancelled` 时取消对应的运行；请求带 `progressToken` 时
+//! 每完成一个节点发送一次 `notifications/progress`。
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio::task::JoinSet;
+
+use super::{codes, tool_name, Message, RpcError, ToolInfo, PROTOCOL_VERSION, SUPPORTED_VERSIONS};
+use crate::commands::flow::{record_start, run_target, Start, Target};
+use crate::flow::events::{EventSink, FlowEvent, NoopSink};
+use crate::flow::exec::ExecOptions;
+use crate::flow::model::FlowDef;
+use crate::flow::store::FlowStore;
+use crate::flow::trace::{FlowTrace, TraceStatus};
+use crate::state::GlobalState;
+
+/// 以流程为工具的 MCP 服务
+pub struct McpServer {
+    store: FlowStore,
+    project: String,
+    tools: Vec<(ToolInfo, FlowDef)>,
+    options: ExecOptions,
+    /// 是否把运行登记到运行历史
+    record: bool,
+    /// 进行中的调用：请求 id → 运行 id
+    calls: Mutex<HashMap<String, String>>,
+}
+
+impl McpServer {
+    pub fn new(store: FlowStore, flows: Vec<FlowDef>, options: ExecOptions, record: bool) -> Self {
+        let mut used = HashSet::new();
+        let tools = flows
+            .into_iter()
+            .map(|flow| {
+                let base = tool_name(&flow.id);
+                let mut name = base.clone();
+                let mut n = 2;
+                while !used.insert(name.clone()) {
+                    let suffix = format!("_{}", n);
+                    name = format!("{}{}", &base[..base.len().min(64 - suffix.len())], suffix);
+                    n += 1;
+                }
+                let description = [&flow.description, &flow.name, &flow.id]
+                    .into_iter()
+                    .find(|text| !text.is_empty())
+                    .cloned();
+                let info = ToolInfo {
+                    name,
+                    title: (!flow.name.is_empty()).then(|| flow.name.clone()),
+                    description,
+                    input_schema: flow.input_schema(),
+                    output_schema: (!flow.outputs.is_empty()).then(|| flow.output_schema()),
+                };
+                (info, flow)
+            })
+            .collect();
+        Self {
+            project: store.project().root().to_string_lossy().into_owned(),
+            store,
+            tools,
+            options,
+            record,
+            calls: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn tools(&self) -> impl Iterator<Item = &ToolInfo> {
+        self.tools.iter().map(|(info, _)| info)
+    }
+
+    /// 处理输入中的消息直到输入关闭；关闭时取消进行中的运行
+    pub async fn serve<R, W>(self: Arc<Self>, input: R, output: W) -> Result<(), String>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin + Send + 'static,
+    {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let writer = tokio::spawn(write_messages(rx, output));
+        let mut lines = BufReader::new(input).lines();
+        let mut calls = JoinSet::new();
+        while let Some(line) = lines
+            .next_line()
+            .await
+            .map_err(|e| format!("读取 MCP 消息失败: {}", e))?
+        {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let message: Message = match serde_json::from_str(&line) {
+                Ok(message) => message,
+                Err(e) => {
+                    let error = RpcError::new(codes::PARSE_ERROR, e.to_string());
+                    let _ = tx.send(Message::error(Value::Null, error));
+                    continue;
+                }
+            };
+            let params = message.params.unwrap_or(Value::Null);
+            match (message.method, message.id) {
+                (Some(method), Some(id)) if method == "tools/call" => {
+                    let server = self.clone();
+                    let tx = tx.clone();
+                    calls.spawn(async move {
+                        let reply = match server.call(&id, params, &tx).await {
+                            Ok(result) => Message::response(id, result),
+                            Err(error) => Message::error(id, error),
+                        };
+                        let _ = tx.send(reply);
+                    });
+                }
+                (Some(method), Some(id)) => {
+                    let reply = match self.handle(&method, params) {
+                        Ok(result) => Message::response(id, result),
+                        Err(error) => Message::error(id, error),
+                    };
+                    let _ = tx.send(reply);
+                }
+                (Some(method), None) => self.notify(&method, &params),
+                // 服务不发出请求，收到的响应直接忽略
+                (None, Some(_)) => {}
+                (None, None) => {
+                    let error = RpcError::new(codes::INVALID_REQUEST, "缺少 method");
+                    let _ = tx.send(Message::error(Value::Null, error));
+                }
+            }
+            while calls.try_join_next().is_some() {}
+        }
+
+        let running: Vec<String> = self.lock_calls().values().cloned().collect();
+        for run_id in running {
+            cancel_run(&run_id);
+        }
+        while calls.join_next().await.is_some() {}
+        drop(tx);
+        let _ = writer.await;
+        Ok(())
+    }
+
+    fn lock_calls(&self) -> std::sync::MutexGuard<'_, HashMap<String, String>> {
+        self.calls.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn handle(&self, method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            "initialize" => {
+                let requested = params.get("protocolVersion").and_then(Value::as_str);
+                let version = requested
+                    .filter(|version| SUPPORTED_VERSIONS.contains(version))
+                    .unwrap_or(PROTOCOL_VERSION);
+                if let Some(client) = params.get("clientInfo") {
+                    tracing::info!("✅ MCP 客户端已连接: {}（协议 {}）", client, version);
+                }
+                Ok(json!({
+                    "protocolVersion": version,
+                    "capabilities": { "tools": { "listChanged": false } },
+                    "serverInfo": { "name": "vlogi", "version": env!("CARGO_PKG_VERSION") },
+                }))
+            }
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": self.tools().collect::<Vec<_>>() })),
+            _ => Err(RpcError::new(
+                codes::METHOD_NOT_FOUND,
+                format!("不支持的方法 `{}`", method),
+            )),
+        }
+    }
+
+    fn notify(&self, method: &str, params: &Value) {
+        if method != "notifications/cancelled" {
+            return;
+        }
+        let Some(request) = params.get("requestId") else {
+            return;
+        };
+        if let Some(run_id) = self.lock_calls().get(&request.to_string()) {
+            tracing::info!("⏹️  MCP 客户端取消了运行 {}", run_id);
+            cancel_run(run_id);
+        }
+    }
+
+    async fn call(
+        &self,
+        id: &Value,
+        params: Value,
+        tx: &UnboundedSender<Message>,
+    ) -> Result<Value, RpcError> {
+        let params: CallParams = serde_json::from_value(params)
+            .map_err(|e| RpcError::new(codes::INVALID_PARAMS, e.to_string()))?;
+        let Some((_, def)) = self.tools.iter().find(|(info, _)| info.name == params.name) else {
+            return Err(RpcError::new(
+                codes::INVALID_PARAMS,
+                format!("未知工具 `{}`", params.name),
+            ));
+        };
+        let inputs = params.arguments.unwrap_or_else(|| json!({}));
+        let run_id = uuid::Uuid::new_v4().to_string();
+        let recorded = self.record && record_start(&run_id, &self.project, def, &inputs).await;
+
+        let events: Arc<dyn EventSink> = match params.meta.and_then(|meta| meta.progress_token) {
+            Some(token) => Arc::new(Progress {
+                token,
+                tx: tx.clone(),
+                finished: AtomicU64::new(0),
+            }),
+            None => Arc::new(NoopSink),
+        };
+        let key = id.to_string();
+        self.lock_calls().insert(key.clone(), run_id.clone());
+        let target = Target {
+            run_id,
+            project: self.project.clone(),
+            store: self.store.clone(),
+            def: def.clone(),
+        };
+        let result = run_target(
+            target,
+            self.options.clone(),
+            Start::New(inputs),
+            events,
+            recorded,
+        )
+        .await;
+        self.lock_calls().remove(&key);
+        Ok(tool_result(result))
+    }
+}
+
+#[derive(Deserialize)]
+struct CallParams {
+    name: String,
+    #[serde(default)]
+    arguments: Option<Value>,
+    #[serde(default, rename = "_meta")]
+    meta: Option<CallMeta>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CallMeta {
+    #[serde(default)]
+    progress_token: Option<Value>,
+}
+
+/// 把完成的节点作为进度通知发出
+struct Progress {
+    token: Value,
+    tx: UnboundedSender<Message>,
+    finished: AtomicU64,
+}
+
+impl EventSink for Progress {
+    fn emit(&self, event: FlowEvent) {
+        let FlowEvent::NodeFinished { path, .. } = event else {
+            return;
+        };
+        let progress = self.finished.fetch_add(1, Ordering::Relaxed) + 1;
+        let params = json!({ "progressToken": self.token, "progress": progress, "message": path });
+        let _ = self
+            .tx
+            .send(Message::notification("notifications/progress", params));
+    }
+}
+
+fn cancel_run(run_id: &str) {
+    if let Some(control) = GlobalState::get().runs.get(run_id) {
+        control.cancel();
+    }
+}
+
+async fn write_messages<W: AsyncWrite + Unpin>(
+    mut rx: mpsc::UnboundedReceiver<Message>,
+    mut output: W,
+) {
+    while let Some(message) = rx.recv().await {
+        let mut line = match serde_json::to_string(&message) {
+            Ok(line) => line,
+            Err(e) => {
+                tracing::warn!("⚠️  序列化 MCP 消息失败: {}", e);
+                continue;
+            }
+        };
+        line.push('\n');
+        let written = output.write_all(line.as_bytes()).await;
+        if let Err(e) = written.and(output.flush().await) {
+            tracing::warn!("⚠️  写入 MCP 消息失败: {}", e);
+            break;
+        }
+    }
+}
+
+/// `tools/call` 的结果
+fn tool_result(result: Result<FlowTrace, String>) -> Value {
+    let error =
+        |text: String| json!({ "content": [{ "type": "text", "text": text }], "isError": true });
+    let trace = match result {
+        Ok(trace) => trace,
+        Err(e) => return error(e),
+    };
+    match trace.status {
+        TraceStatus::Succeeded => {
+            let outputs = trace.outputs.unwrap_or_else(|| json!({}));
+            let text = serde_json::to_string_pretty(&outputs).unwrap_or_default();
+            json!({
+                "content": [{ "type": "text", "text": text }],
+                "structuredContent": outputs,
+                "isError": false,
+            })
+        }
+        TraceStatus::Waiting => error(format!(
+            "运行 {} 等待人工输入，可在应用中答复后继续",
+            trace.run_id.unwrap_or_default()
+        )),
+        status => error(match trace.error {
+            Some(e) => format!("运行结束（{}）: {}", status.as_str(), e),
+            None => format!("运行结束（{}）", status.as_str()),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::Project;
+    use tokio::io::AsyncReadExt;
+
+    #[tokio::test]
+    async fn test_handshake_list_and_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let meta = dir.path().join("vlogi/meta.json5");
+        std::fs::create_dir_all(meta.parent().unwrap()).unwrap();
+        std::fs::write(meta, r#"{ "name": "test" }"#).unwrap();
+        let store = FlowStore::new(Project::open(dir.path()).unwrap());
+        let flows: Vec<FlowDef> = ["daily.report", "daily_report"]
+            .iter()
+            .map(|id| serde_json::from_value(json!({ "id": id, "description": "日报" })).unwrap())
+            .collect();
+        let server = Arc::new(McpServer::new(store, flows, ExecOptions::default(), false));
+
+        let requests = [
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize",
+                "params": { "protocolVersion": "2025-03-26", "capabilities": {} } }),
+            json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" }),
+            json!({ "jsonrpc": "2.0", "id": 3, "method": "tools/call",
+                "params": { "name": "missing" } }),
+            json!({ "jsonrpc": "2.0", "id": 4, "method": "resources/list" }),
+        ];
+        let input: String = requests.iter().map(|r| format!("{}\n", r)).collect();
+        let (output, mut reader) = tokio::io::duplex(64 * 1024);
+        server.serve(input.as_bytes(), output).await.unwrap();
+
+        let mut text = String::new();
+        reader.read_to_string(&mut text).await.unwrap();
+        let mut replies: Vec<Value> = text
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        replies.sort_by_key(|reply| reply["id"].as_i64());
+        assert_eq!(replies.len(), 4);
+        assert_eq!(replies[0]["result"]["protocolVersion"], "2025-03-26");
+        let tools = &replies[1]["result"]["tools"];
+        assert_eq!(tools[0]["name"], "daily_report");
+        assert_eq!(tools[1]["name"], "daily_report_2");
+        assert_eq!(tools[0]["description"], "日报");
+        assert_eq!(tools[0]["inputSchema"]["type"], "object");
+        assert_eq!(replies[2]["error"]["code"], codes::INVALID_PARAMS);
+        assert_eq!(replies[3]["error"]["code"], codes::METHOD_NOT_FOUND);
+    }
+}
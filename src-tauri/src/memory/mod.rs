@@ -0,0 +1,323 @@
+//! 用户记忆：关于用户的事实、偏好与过往交互，供 agent 个性化处理
+//!
+//! 记忆保存在 engine.db 的 `memories` 表中，按用户档案（profile）与项目划分，项目为空的记忆对档案下的全部项目生效。
+//! 当前档案来自 app.db 的 config 表（key = `memory`，值形如 `{ "profile": "alice" }`），未配置时为 `default`。
+//!
+//! - agent 节点声明 `memory` 后，执行时按提示词检索相关记忆，在 token 上限内注入系统提示词
+//! - agent 节点可以通过 `memory_search`、`memory_save`、`memory_forget` 工具读写记忆（见 [`crate::tools::memory`]）
+//! - 每次写入都记录在 `memory_changes` 表中（agent 的写入注明运行与节点），可逐条或按运行撤销，见 [`store`]
+//!
+//! 检索不依赖向量模型：按查询与记忆共有的词计分（英文等按单词，中文等按单字与相邻两字），分数相同时较新的优先。
+
+pub mod store;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use self::store::{MemoryFilter, MemoryStore};
+use crate::utils::appdb::AppDb;
+use crate::utils::enginedb::EngineDb;
+
+/// config 表中记忆配置的 key
+pub const CONFIG_KEY: &str = "memory";
+pub const DEFAULT_PROFILE: &str = "default";
+/// 注入前参与排序的候选记忆数
+const RECALL_CANDIDATES: usize = 50;
+
+/// 记忆配置
+#[derive(Debug, Clone, Deserialize)]
+pub struct MemoryConfig {
+    /// 当前用户档案
+    #[serde(default = "default_profile")]
+    pub profile: String,
+}
+
+fn default_profile() -> String {
+    DEFAULT_PROFILE.to_string()
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self {
+            profile: default_profile(),
+        }
+    }
+}
+
+impl MemoryConfig {
+    pub async fn load(app_db: &AppDb) -> Result<Self, String> {
+        let items = app_db.get_configs_by_key(CONFIG_KEY).await?;
+        for item in items {
+            match serde_json::from_value(item.value) {
+                Ok(config) => return Ok(config),
+                Err(e) => tracing::warn!("⚠️  记忆配置 {} 格式错误，已忽略: {}", item.id, e),
+            }
+        }
+        Ok(Self::default())
+    }
+}
+
+/// 记忆类别
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryKind {
+    /// 关于用户的事实
+    #[default]
+    Fact,
+    /// 用户的偏好
+    Preference,
+    /// 过往交互的要点
+    Interaction,
+}
+
+impl MemoryKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MemoryKind::Fact => "fact",
+            MemoryKind::Preference => "preference",
+            MemoryKind::Interaction => "interaction",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            MemoryKind::Fact => "事实",
+            MemoryKind::Preference => "偏好",
+            MemoryKind::Interaction => "交互",
+        }
+    }
+}
+
+/// 一条记忆
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Memory {
+    /// 为空时保存为新记忆
+    #[serde(default)]
+    pub id: String,
+    /// 用户档案，为空时使用当前档案
+    #[serde(default)]
+    pub profile: String,
+    /// 项目根目录，为空时对档案下的全部项目生效
+    #[serde(default)]
+    pub project: String,
+    #[serde(default)]
+    pub kind: MemoryKind,
+    pub content: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub created_at: i64,
+    #[serde(default)]
+    pub updated_at: i64,
+    /// 被遗忘的时间；遗忘的记忆不参与检索，可撤销
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forgotten_at: Option<i64>,
+}
+
+/// 写入的发起者：运行中的 agent 节点，或（均为空时）应用中的用户
+#[derive(Debug, Clone, Default)]
+pub struct Origin {
+    pub run_id: Option<String>,
+    pub node_id: Option<String>,
+}
+
+impl Origin {
+    pub fn agent(run_id: &str, node_id: &str) -> Self {
+        Self {
+            run_id: Some(run_id.to_string()),
+            node_id: Some(node_id.to_string()),
+        }
+    }
+}
+
+/// 执行器使用的记忆：限定在一次运行的档案与项目中
+#[async_trait]
+pub trait AgentMemory: Send + Sync {
+    /// 范围内未遗忘的记忆，不存在时报错
+    async fn get(&self, id: &str) -> Result<Memory, String>;
+
+    /// 与查询相关的记忆，按相关度排序
+    async fn search(&self, query: &str, limit: usize) -> Result<Vec<Memory>, String>;
+
+    /// 新增（`id` 为空）或修改记忆；`project` 非空时记忆只对当前项目生效
+    async fn save(&self, memory: Memory, origin: &Origin) -> Result<Memory, String>;
+
+    /// 遗忘记忆，返回记忆是否存在
+    async fn forget(&self, id: &str, origin: &Origin) -> Result<bool, String>;
+}
+
+/// 运行中可见的记忆：当前档案下对全部项目生效或属于当前项目的记忆
+pub struct ScopedMemory<'a> {
+    store: MemoryStore<'a>,
+    profile: String,
+    project: String,
+    read_only: bool,
+}
+
+impl<'a> ScopedMemory<'a> {
+    pub fn new(db: &'a EngineDb, profile: impl Into<String>, project: impl Into<String>) -> Self {
+        Self {
+            store: MemoryStore::new(db),
+            profile: profile.into(),
+            project: project.into(),
+            read_only: false,
+        }
+    }
+
+    /// 只读（回放时使用）：检索正常，写入报错
+    pub fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    fn writable(&self) -> Result<(), String> {
+        match self.read_only {
+            true => Err("回放时不写入记忆".to_string()),
+            false => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl AgentMemory for ScopedMemory<'static> {
+    async fn get(&self, id: &str) -> Result<Memory, String> {
+        match self.store.get(id).await? {
+            Some(memory)
+                if memory.profile == self.profile
+                    && (memory.project.is_empty() || memory.project == self.project)
+                    && memory.forgotten_at.is_none() =>
+            {
+                Ok(memory)
+            }
+            _ => Err(format!("记忆 {} 不存在", id)),
+        }
+    }
+
+    async fn search(&self, query: &str, limit: usize) -> Result<Vec<Memory>, String> {
+        let filter = MemoryFilter {
+            profile: Some(self.profile.clone()),
+            project: Some(self.project.clone()),
+            query: Some(query.to_string()),
+            limit: Some(limit as i64),
+            ..Default::default()
+        };
+        self.store.list(&filter).await
+    }
+
+    async fn save(&self, mut memory: Memory, origin: &Origin) -> Result<Memory, String> {
+        self.writable()?;
+        if !memory.id.is_empty() {
+            self.get(&memory.id).await?;
+        }
+        memory.profile = self.profile.clone();
+        if !memory.project.is_empty() {
+            memory.project = self.project.clone();
+        }
+        self.store.save(&memory, origin).await
+    }
+
+    async fn forget(&self, id: &str, origin: &Origin) -> Result<bool, String> {
+        self.writable()?;
+        if self.get(id).await.is_err() {
+            return Ok(false);
+        }
+        self.store.forget(id, origin).await
+    }
+}
+
+/// 与查询相关、合计不超过 `budget` 个 token 的记忆；检索失败时只记录警告
+pub async fn recall(memory: &dyn AgentMemory, query: &str, budget: u32) -> Vec<Memory> {
+    let candidates = match memory.search(query, RECALL_CANDIDATES).await {
+        Ok(candidates) => candidates,
+        Err(e) => {
+            tracing::warn!("⚠️  检索用户记忆失败: {}", e);
+            return Vec::new();
+        }
+    };
+    let mut used = 0;
+    let mut recalled = Vec::new();
+    for memory in candidates {
+        let tokens = estimate_tokens(&line(&memory));
+        if used + tokens <= budget {
+            used += tokens;
+            recalled.push(memory);
+        }
+    }
+    recalled
+}
+
+/// 注入系统提示词的内容
+pub fn render(memories: &[Memory]) -> String {
+    let lines: Vec<String> = memories.iter().map(line).collect();
+    format!(
+        "以下是关于用户的已知信息，仅在与当前任务相关时参考：\n{}",
+        lines.join("\n")
+    )
+}
+
+fn line(memory: &Memory) -> String {
+    format!("- [{}] {}", memory.kind.label(), memory.content)
+}
+
+/// 粗略估算 token 数：中日韩文字约一字一个 token，其它约 4 个字符一个 token
+pub fn estimate_tokens(text: &str) -> u32 {
+    let (cjk, other) = text
+        .chars()
+        .fold((0u32, 0u32), |(cjk, other), c| match store::is_cjk(c) {
+            true => (cjk + 1, other),
+            false => (cjk, other + 1),
+        });
+    cjk + other.div_ceil(4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory(content: &str, project: &str) -> Memory {
+        Memory {
+            id: String::new(),
+            profile: String::new(),
+            project: project.into(),
+            kind: MemoryKind::Preference,
+            content: content.into(),
+            tags: Vec::new(),
+            created_at: 0,
+            updated_at: 0,
+            forgotten_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scope_and_recall_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        let db: &'static EngineDb =
+            Box::leak(Box::new(EngineDb::with_path(dir.path().join("engine.db"))));
+        let scoped = ScopedMemory::new(db, "alice", "/work/a");
+        let user = Origin::default();
+
+        let short = scoped.save(memory("报告用中文", ""), &user).await.unwrap();
+        assert_eq!(short.profile, "alice");
+        let long = memory("报告的结构：先写结论，再列出数据来源与计算方法", "x");
+        let long = scoped.save(long, &user).await.unwrap();
+        assert_eq!(long.project, "/work/a");
+
+        // 其它项目看不到只对 /work/a 生效的记忆，也不能修改它
+        let other = ScopedMemory::new(db, "alice", "/work/b");
+        let found = other.search("报告", 10).await.unwrap();
+        assert_eq!(found, vec![short.clone()]);
+        assert!(other.save(long.clone(), &user).await.is_err());
+        assert!(!other.forget(&long.id, &user).await.unwrap());
+
+        // 放不下的记忆被跳过，之后更短的仍可加入
+        let all = recall(&scoped, "报告", 1000).await;
+        assert_eq!(all.len(), 2);
+        let budget = estimate_tokens(&line(&short)) + 2;
+        assert_eq!(recall(&scoped, "报告", budget).await, vec![short.clone()]);
+        assert!(render(&all).contains("- [偏好] 报告用中文"));
+
+        let replay = ScopedMemory::new(db, "alice", "/work/a").read_only();
+        assert_eq!(replay.search("报告", 10).await.unwrap().len(), 2);
+        assert!(replay.forget(&short.id, &user).await.is_err());
+    }
+}
@@ -0,0 +1,642 @@
+//! engine.db 中的记忆与修改记录
+//!
+//! `memories` 表保存记忆当前的内容，遗忘只做标记；`memory_changes` 表按顺序记录每次新增、修改与遗忘前后的完整记忆。
+//! 撤销一条修改即恢复修改之前的内容（撤销新增会删除记忆），只能撤销记忆最近一次未撤销的修改，
+//! 撤销后修改记录保留并标记撤销时间。彻底删除（[`MemoryStore::purge`]）同时删除修改记录，不可撤销。
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::sqlite::SqliteRow;
+use sqlx::{QueryBuilder, Row, Sqlite, SqliteConnection};
+use std::collections::HashSet;
+
+use super::{Memory, MemoryKind, Origin};
+use crate::flow::trace::now_ms;
+use crate::utils::enginedb::EngineDb;
+
+const DEFAULT_LIMIT: i64 = 100;
+
+/// 记忆列表过滤条件，字段均可省略
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct MemoryFilter {
+    pub profile: Option<String>,
+    /// 对该项目生效的记忆（含对全部项目生效的记忆）；为空字符串时只列出对全部项目生效的记忆
+    pub project: Option<String>,
+    pub kind: Option<MemoryKind>,
+    /// 按相关度排序并只保留相关的记忆
+    pub query: Option<String>,
+    /// 包含已遗忘的记忆
+    pub include_forgotten: bool,
+    pub limit: Option<i64>,
+}
+
+/// 修改类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeAction {
+    Add,
+    Update,
+    Forget,
+}
+
+impl ChangeAction {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ChangeAction::Add => "add",
+            ChangeAction::Update => "update",
+            ChangeAction::Forget => "forget",
+        }
+    }
+}
+
+/// 一次修改
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryChange {
+    pub id: i64,
+    pub memory_id: String,
+    pub profile: String,
+    pub action: ChangeAction,
+    /// 修改之前的记忆，新增时为空
+    pub before: Option<Memory>,
+    pub after: Option<Memory>,
+    /// 发起修改的运行与节点，用户在应用中修改时为空
+    pub run_id: Option<String>,
+    pub node_id: Option<String>,
+    pub at: i64,
+    pub reverted_at: Option<i64>,
+}
+
+/// 修改记录过滤条件，字段均可省略
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ChangeFilter {
+    pub profile: Option<String>,
+    pub memory_id: Option<String>,
+    pub run_id: Option<String>,
+    /// 只列出 agent 的修改
+    pub agent_only: bool,
+    pub limit: Option<i64>,
+}
+
+/// engine.db 中的记忆
+pub struct MemoryStore<'a> {
+    db: &'a EngineDb,
+}
+
+const SELECT: &str = "SELECT id, profile, project, kind, content, tags, created_at, updated_at,
+     forgotten_at
+ FROM memories";
+
+const CHANGE_SELECT: &str = "SELECT id, memory_id, profile, action, before, after, run_id, node_id,
+     at, reverted_at
+ FROM memory_changes";
+
+impl<'a> MemoryStore<'a> {
+    pub fn new(db: &'a EngineDb) -> Self {
+        Self { db }
+    }
+
+    pub async fn get(&self, id: &str) -> Result<Option<Memory>, String> {
+        let mut conn = self.connection().await?;
+        get(&mut conn, id).await
+    }
+
+    /// 按条件列出记忆：指定 `query` 时按相关度排序，否则按修改时间倒序
+    pub async fn list(&self, filter: &MemoryFilter) -> Result<Vec<Memory>, String> {
+        let mut query: QueryBuilder<Sqlite> = QueryBuilder::new(SELECT);
+        query.push(" WHERE 1 = 1");
+        if let Some(profile) = &filter.profile {
+            query.push(" AND profile = ").push_bind(profile);
+        }
+        if let Some(project) = &filter.project {
+            query
+                .push(" AND project IN ('', ")
+                .push_bind(project)
+                .push(")");
+        }
+        if let Some(kind) = filter.kind {
+            query.push(" AND kind = ").push_bind(kind.as_str());
+        }
+        if !filter.include_forgotten {
+            query.push(" AND forgotten_at IS NULL");
+        }
+        query.push(" ORDER BY updated_at DESC, id");
+        let limit = filter.limit.unwrap_or(DEFAULT_LIMIT).max(0) as usize;
+        let search = filter.query.as_deref().map(terms).unwrap_or_default();
+        if search.is_empty() {
+            query.push(" LIMIT ").push_bind(limit as i64);
+        }
+
+        let rows = query
+            .build()
+            .fetch_all(self.db.pool().await?)
+            .await
+            .map_err(|e| format!("读取记忆失败: {}", e))?;
+        let memories = rows.iter().map(from_row).collect::<Result<Vec<_>, _>>()?;
+        let mut memories = rank(memories, &search);
+        memories.truncate(limit);
+        Ok(memories)
+    }
+
+    /// 新增（`id` 为空）或修改记忆，并记录修改；修改已遗忘的记忆会恢复它
+    pub async fn save(&self, memory: &Memory, origin: &Origin) -> Result<Memory, String> {
+        let content = memory.content.trim();
+        if content.is_empty() {
+            return Err("记忆内容不能为空".to_string());
+        }
+        if memory.profile.is_empty() {
+            return Err("记忆必须属于一个用户档案".to_string());
+        }
+        let mut tx = self.begin().await?;
+        let before = match memory.id.is_empty() {
+            true => None,
+            false => Some(
+                get(&mut tx, &memory.id)
+                    .await?
+                    .ok_or_else(|| format!("记忆 {} 不存在", memory.id))?,
+            ),
+        };
+        let now = now_ms();
+        let after = Memory {
+            id: match &before {
+                Some(before) => before.id.clone(),
+                None => uuid::Uuid::new_v4().to_string(),
+            },
+            profile: memory.profile.clone(),
+            project: memory.project.clone(),
+            kind: memory.kind,
+            content: content.to_string(),
+            tags: memory.tags.clone(),
+            created_at: before.as_ref().map_or(now, |before| before.created_at),
+            updated_at: now,
+            forgotten_at: None,
+        };
+        if let Some(before) = &before {
+            let unchanged = Memory {
+                updated_at: before.updated_at,
+                ..after.clone()
+            };
+            if &unchanged == before {
+                return Ok(unchanged);
+            }
+        }
+        let action = match before {
+            Some(_) => ChangeAction::Update,
+            None => ChangeAction::Add,
+        };
+        write(&mut tx, &after).await?;
+        record(&mut tx, action, before.as_ref(), &after, origin, now).await?;
+        commit(tx).await?;
+        Ok(after)
+    }
+
+    /// 遗忘记忆并记录修改，返回记忆是否存在（已遗忘的记忆返回 `false`）
+    pub async fn forget(&self, id: &str, origin: &Origin) -> Result<bool, String> {
+        let mut tx = self.begin().await?;
+        let Some(before) = get(&mut tx, id).await? else {
+            return Ok(false);
+        };
+        if before.forgotten_at.is_some() {
+            return Ok(false);
+        }
+        let now = now_ms();
+        let after = Memory {
+            forgotten_at: Some(now),
+            ..before.clone()
+        };
+        write(&mut tx, &after).await?;
+        record(
+            &mut tx,
+            ChangeAction::Forget,
+            Some(&before),
+            &after,
+            origin,
+            now,
+        )
+        .await?;
+        commit(tx).await?;
+        Ok(true)
+    }
+
+    /// 彻底删除记忆及其修改记录（不可撤销），返回记忆是否存在
+    pub async fn purge(&self, id: &str) -> Result<bool, String> {
+        let mut tx = self.begin().await?;
+        let deleted = sqlx::query("DELETE FROM memories WHERE id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("删除记忆失败: {}", e))?
+            .rows_affected();
+        sqlx::query("DELETE FROM memory_changes WHERE memory_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("删除记忆的修改记录失败: {}", e))?;
+        commit(tx).await?;
+        Ok(deleted > 0)
+    }
+
+    /// 按条件列出修改记录，最新的在前
+    pub async fn changes(&self, filter: &ChangeFilter) -> Result<Vec<MemoryChange>, String> {
+        let mut query: QueryBuilder<Sqlite> = QueryBuilder::new(CHANGE_SELECT);
+        query.push(" WHERE 1 = 1");
+        if let Some(profile) = &filter.profile {
+            query.push(" AND profile = ").push_bind(profile);
+        }
+        if let Some(memory_id) = &filter.memory_id {
+            query.push(" AND memory_id = ").push_bind(memory_id);
+        }
+        if let Some(run_id) = &filter.run_id {
+            query.push(" AND run_id = ").push_bind(run_id);
+        }
+        if filter.agent_only {
+            query.push(" AND run_id IS NOT NULL");
+        }
+        query
+            .push(" ORDER BY id DESC LIMIT ")
+            .push_bind(filter.limit.unwrap_or(DEFAULT_LIMIT));
+        let rows = query
+            .build()
+            .fetch_all(self.db.pool().await?)
+            .await
+            .map_err(|e| format!("读取记忆的修改记录失败: {}", e))?;
+        rows.iter().map(change_from_row).collect()
+    }
+
+    /// 撤销一次修改：恢复修改之前的记忆，返回被撤销的修改
+    ///
+    /// 记忆在这之后还有未撤销的修改时报错，需先撤销之后的修改。
+    pub async fn revert(&self, change_id: i64) -> Result<MemoryChange, String> {
+        let mut tx = self.begin().await?;
+        let row = sqlx::query(&format!("{} WHERE id = ?", CHANGE_SELECT))
+            .bind(change_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| format!("读取记忆的修改记录失败: {}", e))?;
+        let mut change = match row {
+            Some(row) => change_from_row(&row)?,
+            None => return Err(format!("修改记录 {} 不存在", change_id)),
+        };
+        if change.reverted_at.is_some() {
+            return Err(format!("修改记录 {} 已撤销", change_id));
+        }
+        let latest: Option<i64> = sqlx::query_scalar(
+            "SELECT id FROM memory_changes WHERE memory_id = ? AND reverted_at IS NULL
+             ORDER BY id DESC LIMIT 1",
+        )
+        .bind(&change.memory_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| format!("读取记忆的修改记录失败: {}", e))?;
+        if latest != Some(change_id) {
+            return Err(format!(
+                "记忆 {} 在这之后还有修改，需先撤销之后的修改",
+                change.memory_id
+            ));
+        }
+
+        match &change.before {
+            Some(before) => write(&mut tx, before).await?,
+            None => {
+                sqlx::query("DELETE FROM memories WHERE id = ?")
+                    .bind(&change.memory_id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|e| format!("删除记忆失败: {}", e))?;
+            }
+        }
+        let now = now_ms();
+        sqlx::query("UPDATE memory_changes SET reverted_at = ? WHERE id = ?")
+            .bind(now)
+            .bind(change_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("更新记忆的修改记录失败: {}", e))?;
+        commit(tx).await?;
+        change.reverted_at = Some(now);
+        Ok(change)
+    }
+
+    /// 按从新到旧的顺序撤销运行中 agent 的全部修改，返回撤销的修改数
+    ///
+    /// 遇到之后被其它运行或用户修改过的记忆时停止并报错，已撤销的修改保持撤销。
+    pub async fn revert_run(&self, run_id: &str) -> Result<u64, String> {
+        let ids: Vec<i64> = sqlx::query_scalar(
+            "SELECT id FROM memory_changes WHERE run_id = ? AND reverted_at IS NULL
+             ORDER BY id DESC",
+        )
+        .bind(run_id)
+        .fetch_all(self.db.pool().await?)
+        .await
+        .map_err(|e| format!("读取记忆的修改记录失败: {}", e))?;
+        let mut reverted = 0;
+        for id in ids {
+            self.revert(id)
+                .await
+                .map_err(|e| format!("已撤销 {} 条修改后停止: {}", reverted, e))?;
+            reverted += 1;
+        }
+        Ok(reverted)
+    }
+
+    async fn connection(&self) -> Result<sqlx::pool::PoolConnection<Sqlite>, String> {
+        self.db
+            .pool()
+            .await?
+            .acquire()
+            .await
+            .map_err(|e| format!("连接 engine.db 失败: {}", e))
+    }
+
+    async fn begin(&self) -> Result<sqlx::Transaction<'static, Sqlite>, String> {
+        self.db
+            .pool()
+            .await?
+            .begin()
+            .await
+            .map_err(|e| format!("开启事务失败: {}", e))
+    }
+}
+
+async fn commit(tx: sqlx::Transaction<'static, Sqlite>) -> Result<(), String> {
+    tx.commit()
+        .await
+        .map_err(|e| format!("提交事务失败: {}", e))
+}
+
+async fn get(conn: &mut SqliteConnection, id: &str) -> Result<Option<Memory>, String> {
+    let row = sqlx::query(&format!("{} WHERE id = ?", SELECT))
+        .bind(id)
+        .fetch_optional(conn)
+        .await
+        .map_err(|e| format!("读取记忆失败: {}", e))?;
+    row.as_ref().map(from_row).transpose()
+}
+
+async fn write(conn: &mut SqliteConnection, memory: &Memory) -> Result<(), String> {
+    sqlx::query(
+        "INSERT OR REPLACE INTO memories (id, profile, project, kind, content, tags, created_at,
+             updated_at, forgotten_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&memory.id)
+    .bind(&memory.profile)
+    .bind(&memory.project)
+    .bind(memory.kind.as_str())
+    .bind(&memory.content)
+    .bind(serde_json::json!(memory.tags).to_string())
+    .bind(memory.created_at)
+    .bind(memory.updated_at)
+    .bind(memory.forgotten_at)
+    .execute(conn)
+    .await
+    .map(|_| ())
+    .map_err(|e| format!("保存记忆失败: {}", e))
+}
+
+async fn record(
+    conn: &mut SqliteConnection,
+    action: ChangeAction,
+    before: Option<&Memory>,
+    after: &Memory,
+    origin: &Origin,
+    at: i64,
+) -> Result<(), String> {
+    let snapshot = |memory: &Memory| serde_json::to_string(memory).unwrap_or_default();
+    sqlx::query(
+        "INSERT INTO memory_changes (memory_id, profile, action, before, after, run_id, node_id, at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&after.id)
+    .bind(&after.profile)
+    .bind(action.as_str())
+    .bind(before.map(snapshot))
+    .bind(snapshot(after))
+    .bind(&origin.run_id)
+    .bind(&origin.node_id)
+    .bind(at)
+    .execute(conn)
+    .await
+    .map(|_| ())
+    .map_err(|e| format!("记录记忆的修改失败: {}", e))
+}
+
+fn from_row(row: &SqliteRow) -> Result<Memory, String> {
+    let kind = Value::String(row.get("kind"));
+    Ok(Memory {
+        id: row.get("id"),
+        profile: row.get("profile"),
+        project: row.get("project"),
+        kind: serde_json::from_value(kind).map_err(|e| format!("未知的记忆类别: {}", e))?,
+        content: row.get("content"),
+        tags: serde_json::from_str(row.get("tags")).unwrap_or_default(),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        forgotten_at: row.get("forgotten_at"),
+    })
+}
+
+fn change_from_row(row: &SqliteRow) -> Result<MemoryChange, String> {
+    let snapshot = |column: &str| -> Result<Option<Memory>, String> {
+        let value: Option<String> = row.get(column);
+        (value.as_deref())
+            .map(serde_json::from_str)
+            .transpose()
+            .map_err(|e| format!("修改记录中的记忆格式错误: {}", e))
+    };
+    let action = Value::String(row.get("action"));
+    Ok(MemoryChange {
+        id: row.get("id"),
+        memory_id: row.get("memory_id"),
+        profile: row.get("profile"),
+        action: serde_json::from_value(action).map_err(|e| format!("未知的修改类别: {}", e))?,
+        before: snapshot("before")?,
+        after: snapshot("after")?,
+        run_id: row.get("run_id"),
+        node_id: row.get("node_id"),
+        at: row.get("at"),
+        reverted_at: row.get("reverted_at"),
+    })
+}
+
+/// 中日韩文字（不以空格分词）
+pub(super) fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}'
+        | '\u{3400}'..='\u{4dbf}'
+        | '\u{4e00}'..='\u{9fff}'
+        | '\u{f900}'..='\u{faff}'
+        | '\u{ac00}'..='\u{d7af}')
+}
+
+/// 检索用的词：英文等按单词（小写，至少两个字符），中日韩文字取单字与相邻两字，
+/// 相邻两字同时出现时计分更高
+fn terms(text: &str) -> HashSet<String> {
+    let mut terms = HashSet::new();
+    let mut word = String::new();
+    let mut prev: Option<char> = None;
+    let flush = |word: &mut String, terms: &mut HashSet<String>| {
+        if word.chars().count() >= 2 {
+            terms.insert(word.to_lowercase());
+        }
+        word.clear();
+    };
+    for c in text.chars() {
+        if is_cjk(c) {
+            flush(&mut word, &mut terms);
+            terms.insert(c.to_string());
+            if let Some(prev) = prev {
+                terms.insert([prev, c].iter().collect());
+            }
+            prev = Some(c);
+            continue;
+        }
+        prev = None;
+        if c.is_alphanumeric() {
+            word.push(c);
+        } else {
+            flush(&mut word, &mut terms);
+        }
+    }
+    flush(&mut word, &mut terms);
+    terms
+}
+
+/// 按与查询共有的词数排序（保持原有顺序作为次序），只保留相关的记忆；查询为空时原样返回
+fn rank(memories: Vec<Memory>, search: &HashSet<String>) -> Vec<Memory> {
+    if search.is_empty() {
+        return memories;
+    }
+    let mut scored: Vec<(usize, Memory)> = memories
+        .into_iter()
+        .map(|memory| {
+            let text = format!("{} {}", memory.content, memory.tags.join(" "));
+            (terms(&text).intersection(search).count(), memory)
+        })
+        .filter(|(score, _)| *score > 0)
+        .collect();
+    scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    scored.into_iter().map(|(_, memory)| memory).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory(content: &str, kind: MemoryKind) -> Memory {
+        Memory {
+            id: String::new(),
+            profile: "alice".into(),
+            project: String::new(),
+            kind,
+            content: content.into(),
+            tags: Vec::new(),
+            created_at: 0,
+            updated_at: 0,
+            forgotten_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_changes_and_revert() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = EngineDb::with_path(dir.path().join("engine.db"));
+        let store = MemoryStore::new(&db);
+        let user = Origin::default();
+        let agent = Origin::agent("run-1", "summarize");
+
+        let tea = (store.save(
+            &memory("喜欢喝绿茶，不喝咖啡", MemoryKind::Preference),
+            &user,
+        ))
+        .await
+        .unwrap();
+        let mut scoped = memory("Reports go to the finance team", MemoryKind::Fact);
+        scoped.project = "/work/reports".into();
+        scoped.tags = vec!["weekly".into()];
+        let report = store.save(&scoped, &agent).await.unwrap();
+        let mut other = memory("Prefers dark mode", MemoryKind::Preference);
+        other.profile = "bob".into();
+        store.save(&other, &user).await.unwrap();
+        assert!(store
+            .save(&memory("  ", MemoryKind::Fact), &user)
+            .await
+            .is_err());
+
+        let search = |query: &str, project: &str| MemoryFilter {
+            profile: Some("alice".into()),
+            project: Some(project.into()),
+            query: Some(query.into()),
+            ..Default::default()
+        };
+        let found = store
+            .list(&search("早上喝什么茶？", "/other"))
+            .await
+            .unwrap();
+        assert_eq!(found, vec![tea.clone()]);
+        let found = store
+            .list(&search("weekly REPORTS", "/work/reports"))
+            .await
+            .unwrap();
+        assert_eq!(found, vec![report.clone()]);
+        // 只对其它项目生效的记忆不可见
+        assert!(store
+            .list(&search("reports", "/other"))
+            .await
+            .unwrap()
+            .is_empty());
+
+        // agent 修改、遗忘后按运行撤销
+        let mut edited = tea.clone();
+        edited.content = "喜欢喝红茶".into();
+        let edited = store.save(&edited, &agent).await.unwrap();
+        assert_eq!(edited.created_at, tea.created_at);
+        assert!(store.forget(&tea.id, &agent).await.unwrap());
+        assert!(!store.forget(&tea.id, &agent).await.unwrap());
+        assert!(store.list(&search("红茶", "")).await.unwrap().is_empty());
+
+        let changes = (store.changes(&ChangeFilter {
+            run_id: Some("run-1".into()),
+            ..Default::default()
+        }))
+        .await
+        .unwrap();
+        let actions: Vec<_> = changes.iter().map(|c| c.action).collect();
+        assert_eq!(
+            actions,
+            [
+                ChangeAction::Forget,
+                ChangeAction::Update,
+                ChangeAction::Add
+            ]
+        );
+        assert_eq!(
+            changes[1].before.as_ref().unwrap().content,
+            "喜欢喝绿茶，不喝咖啡"
+        );
+        assert_eq!(changes[0].node_id.as_deref(), Some("summarize"));
+
+        // 只能撤销最近一次修改
+        assert!(store.revert(changes[1].id).await.is_err());
+        assert_eq!(store.revert_run("run-1").await.unwrap(), 3);
+        assert_eq!(store.get(&tea.id).await.unwrap().unwrap(), tea);
+        assert_eq!(store.get(&report.id).await.unwrap(), None);
+        assert!(store.revert(changes[0].id).await.is_err());
+
+        // 用户之后的修改使撤销停止
+        let report = store.save(&scoped, &agent).await.unwrap();
+        let mut renamed = report.clone();
+        renamed.content = "Reports go to the CFO".into();
+        store.save(&renamed, &user).await.unwrap();
+        assert!(store.revert_run("run-1").await.is_err());
+        assert!(store.purge(&report.id).await.unwrap());
+        let remaining = (store.changes(&ChangeFilter {
+            memory_id: Some(report.id.clone()),
+            ..Default::default()
+        }))
+        .await
+        .unwrap();
+        assert!(remaining.is_empty());
+    }
+}
@@ -0,0 +1,101 @@
+//! 项目归档：把项目数据（`vlogi/` 与 `gitdata/`）打包为 tar.gz，用于在其它机器上恢复项目
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs::File;
+use std::path::{Component, Path};
+
+use super::{Project, GITDATA_DIR, VLOGI_DIR};
+
+/// 导出项目到归档文件
+pub fn export(project: &Project, dest: &Path) -> Result<(), String> {
+    // 归档文件不能位于被打包的目录中
+    let dest_dir = match dest.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.canonicalize(),
+        _ => std::env::current_dir(),
+    }
+    .map_err(|e| format!("归档目录无效: {}", e))?;
+    if dest_dir.starts_with(project.vlogi_dir()) || dest_dir.starts_with(project.gitdata_dir()) {
+        return Err(format!("归档文件 {:?} 不能位于项目数据目录中", dest));
+    }
+
+    let file = File::create(dest).map_err(|e| format!("创建归档 {:?} 失败: {}", dest, e))?;
+    let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    builder.follow_symlinks(false);
+    for (name, dir) in [
+        (VLOGI_DIR, project.vlogi_dir()),
+        (GITDATA_DIR, project.gitdata_dir()),
+    ] {
+        if dir.is_dir() {
+            builder
+                .append_dir_all(name, &dir)
+                .map_err(|e| format!("打包 {:?} 失败: {}", dir, e))?;
+        }
+    }
+    builder
+        .into_inner()
+        .and_then(|encoder| encoder.finish())
+        .map_err(|e| format!("写入归档 {:?} 失败: {}", dest, e))?;
+    Ok(())
+}
+
+/// 把归档解包到目录（目录须不存在或为空），只恢复项目数据目录中的条目
+pub fn import(archive: &Path, root: &Path) -> Result<Project, String> {
+    if root.exists() {
+        let non_empty = std::fs::read_dir(root)
+            .map_err(|e| format!("读取目录 {:?} 失败: {}", root, e))?
+            .next()
+            .is_some();
+        if non_empty {
+            return Err(format!("目标目录 {:?} 非空", root));
+        }
+    }
+    std::fs::create_dir_all(root).map_err(|e| format!("创建目录 {:?} 失败: {}", root, e))?;
+
+    let file = File::open(archive).map_err(|e| format!("打开归档 {:?} 失败: {}", archive, e))?;
+    let mut entries = tar::Archive::new(GzDecoder::new(file));
+    let read_err = |e: std::io::Error| format!("读取归档 {:?} 失败: {}", archive, e);
+    for entry in entries.entries().map_err(read_err)? {
+        let mut entry = entry.map_err(read_err)?;
+        let path = entry.path().map_err(read_err)?.into_owned();
+        let top = match path.components().next() {
+            Some(Component::Normal(top)) => top.to_string_lossy().into_owned(),
+            _ => String::new(),
+        };
+        if top != VLOGI_DIR && top != GITDATA_DIR {
+            tracing::warn!("⚠️  忽略归档中的条目 {:?}", path);
+            continue;
+        }
+        // unpack_in 会拒绝包含 `..` 等越出目标目录的路径
+        entry
+            .unpack_in(root)
+            .map_err(|e| format!("解包 {:?} 失败: {}", path, e))?;
+    }
+    Project::open(root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_import_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let (project, meta) = Project::init(dir.path().join("src"), Some("demo"), false).unwrap();
+        std::fs::create_dir_all(project.flows_dir()).unwrap();
+        std::fs::write(project.flows_dir().join("a.json5"), "{id: 'a'}").unwrap();
+
+        let archive = dir.path().join("demo.tar.gz");
+        export(&project, &archive).unwrap();
+        assert!(export(&project, &project.vlogi_dir().join("x.tar.gz")).is_err());
+
+        let restored = import(&archive, &dir.path().join("dst")).unwrap();
+        assert_eq!(restored.meta().unwrap().id, meta.id);
+        assert!(restored.flows_dir().join("a.json5").is_file());
+        assert!(restored.gitdata_dir().is_dir());
+
+        // 目标目录非空时拒绝覆盖
+        assert!(import(&archive, &dir.path().join("dst")).is_err());
+    }
+}
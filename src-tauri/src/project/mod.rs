@@ -0,0 +1,136 @@
+//! 项目目录布局
+//!
+//! 与前端 `projectStore.loadPath` 创建的结构一致：
+//! ```text
+//! <root>/
+//! ├── gitdata/
+//! └── vlogi/
+//!     ├── meta.json5          项目元信息（Repository）
+//!     ├── mcp.json5           项目使用的 MCP 服务（可选）
+//!     ├── flows/<id>.json5    本项目的 PromptFlow
+//!     └── packages/<package>/<version>/<id>.json5   已安装的 PromptFlow 包
+//! ```
+
+pub mod archive;
+pub mod registry;
+
+use std::path::{Path, PathBuf};
+
+use self::registry::Repository;
+
+pub const GITDATA_DIR: &str = "gitdata";
+pub const VLOGI_DIR: &str = "vlogi";
+pub const META_FILE: &str = "meta.json5";
+pub const MCP_FILE: &str = "mcp.json5";
+pub const FLOWS_DIR: &str = "flows";
+pub const PACKAGES_DIR: &str = "packages";
+
+/// 已打开的项目
+#[derive(Debug, Clone)]
+pub struct Project {
+    root: PathBuf,
+}
+
+impl Project {
+    /// 打开项目目录，要求 `vlogi/meta.json5` 已存在
+    pub fn open(root: impl AsRef<Path>) -> Result<Self, String> {
+        let root = root
+            .as_ref()
+            .canonicalize()
+            .map_err(|e| format!("项目目录 {:?} 无效: {}", root.as_ref(), e))?;
+        let project = Self { root };
+        if !project.meta_path().is_file() {
+            return Err(format!(
+                "{:?} 不是 vlogi 项目（缺少 {}/{}）",
+                project.root, VLOGI_DIR, META_FILE
+            ));
+        }
+        Ok(project)
+    }
+
+    /// 在目录中创建项目布局与元信息（目录不存在时创建），返回项目与元信息
+    ///
+    /// 与前端 `loadPath` 一致：非空目录需调用方确认（`force`），已是项目时直接返回。
+    pub fn init(
+        root: impl AsRef<Path>,
+        name: Option<&str>,
+        force: bool,
+    ) -> Result<(Self, Repository), String> {
+        let root = root.as_ref();
+        if let Ok(project) = Self::open(root) {
+            let meta = project.meta()?;
+            return Ok((project, meta));
+        }
+        std::fs::create_dir_all(root)
+            .map_err(|e| format!("创建项目目录 {:?} 失败: {}", root, e))?;
+        let non_empty = std::fs::read_dir(root)
+            .map_err(|e| format!("读取目录 {:?} 失败: {}", root, e))?
+            .next()
+            .is_some();
+        if non_empty && !force {
+            return Err(format!(
+                "目录 {:?} 非空，确认在此创建项目请加 --force",
+                root
+            ));
+        }
+        let root = root
+            .canonicalize()
+            .map_err(|e| format!("项目目录 {:?} 无效: {}", root, e))?;
+        let project = Self { root };
+        for dir in [project.flows_dir(), project.gitdata_dir()] {
+            std::fs::create_dir_all(&dir).map_err(|e| format!("创建目录 {:?} 失败: {}", dir, e))?;
+        }
+        let name = match name {
+            Some(name) => name.to_string(),
+            None => (project.root.file_name())
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        };
+        let meta = Repository::new(name, &project.root);
+        project.write_meta(&meta)?;
+        Ok((project, meta))
+    }
+
+    /// 读取项目元信息
+    pub fn meta(&self) -> Result<Repository, String> {
+        let path = self.meta_path();
+        let content =
+            std::fs::read_to_string(&path).map_err(|e| format!("读取 {:?} 失败: {}", path, e))?;
+        json5::from_str(&content).map_err(|e| format!("{:?} 格式错误: {}", path, e))
+    }
+
+    /// 写入项目元信息（与前端一致使用 JSON）
+    pub fn write_meta(&self, meta: &Repository) -> Result<(), String> {
+        let path = self.meta_path();
+        let content = serde_json::to_string(meta).map_err(|e| e.to_string())?;
+        std::fs::write(&path, content).map_err(|e| format!("写入 {:?} 失败: {}", path, e))
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn gitdata_dir(&self) -> PathBuf {
+        self.root.join(GITDATA_DIR)
+    }
+
+    pub fn vlogi_dir(&self) -> PathBuf {
+        self.root.join(VLOGI_DIR)
+    }
+
+    pub fn meta_path(&self) -> PathBuf {
+        self.vlogi_dir().join(META_FILE)
+    }
+
+    pub fn mcp_path(&self) -> PathBuf {
+        self.vlogi_dir().join(MCP_FILE)
+    }
+
+    pub fn flows_dir(&self) -> PathBuf {
+        self.vlogi_dir().join(FLOWS_DIR)
+    }
+
+    pub fn packages_dir(&self) -> PathBuf {
+        self.vlogi_dir().join(PACKAGES_DIR)
+    }
+}
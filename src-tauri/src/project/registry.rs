@@ -0,0 +1,141 @@
+//! 项目登记
+//!
+//! 与前端 `repositoryStore` 共用 app.db 中 key 为 `repository` 的配置：
+//! 记录 id 即项目 id，值为 `{ name, path, ver, owner }`，`owner` 为打开该项目的进程 pid（0 表示未打开）。
+//! 项目目录中的 `vlogi/meta.json5` 保存同样的信息。
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::Path;
+
+use super::Project;
+use crate::flow::trace::now_ms;
+use crate::utils::appdb::AppDb;
+
+/// app.db 中的配置 key
+pub const CONFIG_KEY: &str = "repository";
+
+/// 项目元信息 / 登记记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Repository {
+    pub id: String,
+    pub name: String,
+    pub path: String,
+    /// 创建项目时的应用版本
+    #[serde(default)]
+    pub ver: String,
+    /// 创建时间（秒）
+    #[serde(default)]
+    pub ctime: i64,
+    /// 打开此项目的进程 pid，0 表示未打开
+    #[serde(default)]
+    pub owner: u32,
+}
+
+impl Repository {
+    pub fn new(name: String, root: &Path) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            name,
+            path: root.to_string_lossy().into_owned(),
+            ver: env!("CARGO_PKG_VERSION").to_string(),
+            ctime: now_ms() / 1000,
+            owner: 0,
+        }
+    }
+
+    /// 登记记录中的值（id 与创建时间由记录本身保存）
+    fn value(&self) -> Value {
+        json!({
+            "name": self.name,
+            "path": self.path,
+            "ver": self.ver,
+            "owner": self.owner,
+        })
+    }
+}
+
+/// app.db 中的项目登记
+pub struct Registry<'a> {
+    db: &'a AppDb,
+}
+
+impl<'a> Registry<'a> {
+    pub fn new(db: &'a AppDb) -> Self {
+        Self { db }
+    }
+
+    /// 全部已登记的项目（按登记时间）
+    pub async fn list(&self) -> Result<Vec<Repository>, String> {
+        let items = self.db.get_configs_by_key(CONFIG_KEY).await?;
+        Ok(items
+            .into_iter()
+            .map(|item| {
+                let field = |name: &str| item.value.get(name).and_then(Value::as_str);
+                Repository {
+                    name: field("name").unwrap_or_default().to_string(),
+                    path: field("path").unwrap_or_default().to_string(),
+                    ver: field("ver").unwrap_or_default().to_string(),
+                    ctime: item.created_at,
+                    owner: (item.value.get("owner").and_then(Value::as_u64))
+                        .and_then(|pid| u32::try_from(pid).ok())
+                        .unwrap_or(0),
+                    id: item.id,
+                }
+            })
+            .collect())
+    }
+
+    /// 按 id 或项目目录查找
+    pub async fn find(&self, target: &str) -> Result<Option<Repository>, String> {
+        let path = Path::new(target).canonicalize().ok();
+        Ok(self.list().await?.into_iter().find(|repo| {
+            repo.id == target
+                || repo.path == target
+                || path
+                    .as_deref()
+                    .is_some_and(|path| Path::new(&repo.path) == path)
+        }))
+    }
+
+    /// 按 id 或目录打开项目（未登记的目录直接打开）
+    pub async fn open(&self, target: &str) -> Result<Project, String> {
+        match self.find(target).await? {
+            Some(repo) => Project::open(&repo.path),
+            None => Project::open(target),
+        }
+    }
+
+    /// 新增或更新登记
+    pub async fn save(&self, repo: &Repository) -> Result<(), String> {
+        self.db
+            .upsert_by_id(&repo.id, CONFIG_KEY, &repo.value())
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_registry_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = AppDb::with_path(dir.path().join("app.db"));
+        let registry = Registry::new(&db);
+
+        let mut repo = Repository::new("demo".to_string(), dir.path());
+        registry.save(&repo).await.unwrap();
+        repo.owner = 42;
+        registry.save(&repo).await.unwrap();
+
+        let list = registry.list().await.unwrap();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].owner, 42);
+        assert_eq!(list[0].name, "demo");
+
+        let by_path = registry.find(&repo.path).await.unwrap().unwrap();
+        assert_eq!(by_path.id, repo.id);
+        assert!(registry.find("missing").await.unwrap().is_none());
+    }
+}
@@ -24,6 +24,11 @@ impl AppHandleState {
         Ok(())
     }
 
+    /// 获取 AppHandle（命令行模式下为空）
+    pub async fn get(&self) -> Option<AppHandle> {
+        self.inner.lock().await.clone()
+    }
+
     // /// 检查是否已初始化
     // pub async fn is_initialized(&self) -> bool {
     //     let guard = self.inner.lock().await;
@@ -1,3 +1,4 @@
+use crate::cli::Command;
 use clap::Parser;
 use std::fs;
 use std::path::PathBuf;
@@ -9,7 +10,8 @@ use tracing::Level;
     name = env!("CARGO_PKG_NAME"),
     version = env!("CARGO_PKG_VERSION"),
     about = env!("CARGO_PKG_DESCRIPTION"),
-    long_about = r#"vlogi.cc is an all-in-one multi-agent automation platform..."#
+    long_about = r#"vlogi.cc is an all-in-one multi-agent automation platform..."#,
+    args_conflicts_with_subcommands = true
 )]
 pub struct Args {
     /// Project directory path
@@ -19,7 +21,7 @@ pub struct Args {
     pub project: Option<PathBuf>,
 
     /// Enable debug mode (sets log level to DEBUG)
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     pub debug: bool,
 
     /// Log level: TRACE, DEBUG, INFO, WARN, ERROR
@@ -30,7 +32,8 @@ pub struct Args {
         short = 'l',
         long = "log-level",
         default_value_t = Self::default_log_level(),
-        value_name = "LEVEL"
+        value_name = "LEVEL",
+        global = true
     )]
     pub log_level: Level,
 
@@ -40,9 +43,14 @@ pub struct Args {
     #[arg(
         long = "log-file",
         value_name = "FILE",
-        value_parser = parse_log_file_path
+        value_parser = parse_log_file_path,
+        global = true
     )]
     pub log_file: Option<PathBuf>,
+
+    /// Run a command without opening a window
+    #[command(subcommand)]
+    pub command: Option<Command>,
 }
 
 impl Args {
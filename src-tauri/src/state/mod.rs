@@ -3,9 +3,20 @@ pub mod app_states;
 pub mod args;
 
 use self::app_handle::AppHandleState;
+use crate::api::ApiServer;
 use self::app_states::AppStates;
+use crate::flow::control::ActiveRuns;
+use crate::llm::guard::GuardStates;
+use crate::tools::mcp::McpHub;
+use crate::tools::{self, ToolRegistry};
+use crate::trigger::schedule::Scheduler;
+use crate::trigger::watch::FolderWatchers;
+use crate::utils::appdb::AppDb;
+use crate::utils::enginedb::EngineDb;
+use crate::vault::Vault;
 use notify::RecommendedWatcher;
 use notify_debouncer_full::{Debouncer, NoCache};  // 改为 NoCache
+use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::OnceLock;
 
@@ -28,6 +39,36 @@ pub struct GlobalState {
 
     /// 文件监听器（使用 Mutex 保护可变访问，设置为None会停止监听－－可以重新调用setup_config_watcher再次监听．）
     pub config_watcher: Mutex<Option<Debouncer<RecommendedWatcher, NoCache>>>,  // 改为 NoCache
+
+    /// app.db 连接池（后端只读访问 provider 等配置，首次使用时连接）
+    pub app_db: AppDb,
+
+    /// engine.db 连接池（运行历史等后端数据，首次使用时连接并迁移）
+    pub engine_db: EngineDb,
+
+    /// 正在执行的流程运行（用于取消 / 暂停）
+    pub runs: ActiveRuns,
+
+    /// provider 的限流与熔断状态（按密钥共享，跨运行保留）
+    pub provider_guards: Arc<GuardStates>,
+
+    /// agent 节点可调用的后端工具（启动时注册，之后只读）
+    pub tools: ToolRegistry,
+
+    /// 项目中声明的 MCP 服务（按需启动，跨运行复用）
+    pub mcp: McpHub,
+
+    /// 加密密钥库（解锁后密钥明文只保存在这里）
+    pub vault: Arc<Vault>,
+
+    /// 本地 HTTP API 服务（按配置开启）
+    pub api: ApiServer,
+
+    /// 定时计划调度器（应用启动后在后台运行）
+    pub scheduler: Scheduler,
+
+    /// 目录监听触发（应用启动后建立监听）
+    pub watches: FolderWatchers,
     // 其他字段示例：
     // pub db: Arc<DbPool>,           // Arc 包装的数据库连接池
     // pub config: RwLock<Config>,    // RwLock 保护的可变配置
@@ -49,6 +90,16 @@ impl GlobalState {
             args: args::Args::new(),
             app_states: AppStates::new(),
             config_watcher: Mutex::new(None),
+            app_db: AppDb::new(),
+            engine_db: EngineDb::new(),
+            runs: ActiveRuns::new(),
+            provider_guards: Arc::new(GuardStates::new()),
+            tools: tools::builtin(),
+            mcp: McpHub::new(),
+            vault: Arc::new(Vault::new()),
+            api: ApiServer::new(),
+            scheduler: Scheduler::new(),
+            watches: FolderWatchers::new(),
         }
     }
 
@@ -0,0 +1,401 @@
+//! 项目文件工具：读取、列目录、搜索与写入
+//!
+//! 所有路径都相对于项目根目录，解析时拒绝绝对路径与 `..`，并对已存在的部分做规范化，
+//! 防止通过符号链接逃出项目。写入需要流程开启 `permissions.fs_write`，且不能写入 `vlogi/` 目录。
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::io::ErrorKind;
+use std::path::{Component, Path, PathBuf};
+
+use super::{Tool, ToolContext};
+use crate::project::VLOGI_DIR;
+
+/// 单次读取的最大字节数，超出部分截断
+const MAX_READ_BYTES: usize = 256 * 1024;
+/// 搜索时跳过超过该大小的文件
+const MAX_SEARCH_FILE_BYTES: u64 = 1024 * 1024;
+const DEFAULT_SEARCH_RESULTS: usize = 50;
+
+/// 把相对路径解析为项目内的绝对路径
+///
+/// 路径不存在时只规范化最近的已存在上级目录，剩余部分原样拼接（写入新文件时使用）。
+pub fn resolve(root: &Path, path: &str) -> Result<PathBuf, String> {
+    let mut relative = PathBuf::new();
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(name) => relative.push(name),
+            Component::CurDir => {}
+            Component::ParentDir => return Err(format!("路径 `{}` 不能包含 `..`", path)),
+            Component::RootDir | Component::Prefix(_) => {
+                return Err(format!("路径 `{}` 必须是相对于项目根目录的路径", path))
+            }
+        }
+    }
+
+    let full = root.join(&relative);
+    let mut existing = full.as_path();
+    let mut rest = Vec::new();
+    while existing.symlink_metadata().is_err() {
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name.to_owned());
+                existing = parent;
+            }
+            _ => break,
+        }
+    }
+    let mut resolved = existing
+        .canonicalize()
+        .map_err(|e| format!("无法解析路径 `{}`: {}", path, e))?;
+    if !resolved.starts_with(root) {
+        return Err(format!("路径 `{}` 指向项目目录之外", path));
+    }
+    resolved.extend(rest.into_iter().rev());
+    Ok(resolved)
+}
+
+/// 项目内路径的显示形式（`/` 分隔，根目录为 `.`）
+fn display(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    let parts: Vec<_> = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect();
+    if parts.is_empty() {
+        ".".to_string()
+    } else {
+        parts.join("/")
+    }
+}
+
+fn str_arg<'a>(args: &'a Value, name: &str) -> &'a str {
+    args.get(name).and_then(Value::as_str).unwrap_or_default()
+}
+
+fn io_error(path: &str, e: std::io::Error) -> String {
+    match e.kind() {
+        ErrorKind::NotFound => format!("`{}` 不存在", path),
+        _ => format!("访问 `{}` 失败: {}", path, e),
+    }
+}
+
+/// 读取文本文件
+pub struct ReadFile;
+
+#[async_trait]
+impl Tool for ReadFile {
+    fn name(&self) -> &str {
+        "read_file"
+    }
+
+    fn description(&self) -> &str {
+        "读取项目中的文本文件。path 为相对于项目根目录的路径，过长的内容会被截断。"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": { "path": { "type": "string" } },
+            "required": ["path"]
+        })
+    }
+
+    async fn invoke(&self, args: Value, ctx: &ToolContext<'_>) -> Result<Value, String> {
+        let path = str_arg(&args, "path");
+        let full = resolve(ctx.project.root(), path)?;
+        let mut bytes = std::fs::read(&full).map_err(|e| io_error(path, e))?;
+        let truncated = bytes.len() > MAX_READ_BYTES;
+        if truncated {
+            bytes.truncate(MAX_READ_BYTES);
+        }
+        let content = match String::from_utf8(bytes) {
+            Ok(content) => content,
+            // 截断可能切在多字节字符中间
+            Err(e) if truncated && e.utf8_error().error_len().is_none() => {
+                let valid = e.utf8_error().valid_up_to();
+                let mut bytes = e.into_bytes();
+                bytes.truncate(valid);
+                String::from_utf8(bytes).unwrap_or_default()
+            }
+            Err(_) => return Err(format!("`{}` 不是 UTF-8 文本文件", path)),
+        };
+        Ok(json!({ "content": content, "truncated": truncated }))
+    }
+}
+
+/// 列出目录内容
+pub struct ListDir;
+
+#[async_trait]
+impl Tool for ListDir {
+    fn name(&self) -> &str {
+        "list_dir"
+    }
+
+    fn description(&self) -> &str {
+        "列出项目中某个目录的内容。path 为相对于项目根目录的路径，省略时为根目录。"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": { "path": { "type": "string" } }
+        })
+    }
+
+    async fn invoke(&self, args: Value, ctx: &ToolContext<'_>) -> Result<Value, String> {
+        let path = str_arg(&args, "path");
+        let full = resolve(ctx.project.root(), path)?;
+        let mut entries = Vec::new();
+        for entry in std::fs::read_dir(&full).map_err(|e| io_error(path, e))? {
+            let entry = entry.map_err(|e| io_error(path, e))?;
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            let kind = if meta.is_dir() {
+                "dir"
+            } else if meta.is_symlink() {
+                "symlink"
+            } else {
+                "file"
+            };
+            entries.push(json!({
+                "name": entry.file_name().to_string_lossy(),
+                "kind": kind,
+                "size": meta.len(),
+            }));
+        }
+        entries.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+        Ok(json!({ "path": display(ctx.project.root(), &full), "entries": entries }))
+    }
+}
+
+/// 在项目文件中搜索文本
+pub struct SearchFiles;
+
+#[async_trait]
+impl Tool for SearchFiles {
+    fn name(&self) -> &str {
+        "search_files"
+    }
+
+    fn description(&self) -> &str {
+        "在项目文本文件中搜索包含 query 的行（不区分大小写）。path 限定搜索的目录，省略时搜索整个项目。"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "query": { "type": "string", "minLength": 1 },
+                "path": { "type": "string" },
+                "max_results": { "type": "integer", "minimum": 1, "maximum": 500 }
+            },
+            "required": ["query"]
+        })
+    }
+
+    async fn invoke(&self, args: Value, ctx: &ToolContext<'_>) -> Result<Value, String> {
+        let root = ctx.project.root().to_path_buf();
+        let start = resolve(&root, str_arg(&args, "path"))?;
+        let query = str_arg(&args, "query").to_lowercase();
+        let limit = args
+            .get("max_results")
+            .and_then(Value::as_u64)
+            .map_or(DEFAULT_SEARCH_RESULTS, |n| n as usize);
+        tokio::task::spawn_blocking(move || search(&root, &start, &query, limit))
+            .await
+            .map_err(|e| format!("搜索失败: {}", e))
+    }
+}
+
+fn search(root: &Path, start: &Path, query: &str, limit: usize) -> Value {
+    let mut matches = Vec::new();
+    let mut truncated = false;
+    let mut pending = vec![start.to_path_buf()];
+    'walk: while let Some(path) = pending.pop() {
+        // 不跟随符号链接，避免搜索到项目之外
+        let Ok(meta) = path.symlink_metadata() else {
+            continue;
+        };
+        if meta.is_dir() {
+            let Ok(entries) = std::fs::read_dir(&path) else {
+                continue;
+            };
+            let mut children: Vec<PathBuf> = entries
+                .filter_map(Result::ok)
+                .filter(|e| !e.file_name().to_string_lossy().starts_with('.'))
+                .map(|e| e.path())
+                .collect();
+            children.sort_by(|a, b| b.cmp(a));
+            pending.extend(children);
+            continue;
+        }
+        if !meta.is_file() || meta.len() > MAX_SEARCH_FILE_BYTES {
+            continue;
+        }
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        for (index, line) in content.lines().enumerate() {
+            if !line.to_lowercase().contains(query) {
+                continue;
+            }
+            if matches.len() >= limit {
+                truncated = true;
+                break 'walk;
+            }
+            matches.push(json!({
+                "path": display(root, &path),
+                "line": index + 1,
+                "text": line.trim(),
+            }));
+        }
+    }
+    json!({ "matches": matches, "truncated": truncated })
+}
+
+/// 写入文本文件
+pub struct WriteFile;
+
+#[async_trait]
+impl Tool for WriteFile {
+    fn name(&self) -> &str {
+        "write_file"
+    }
+
+    fn description(&self) -> &str {
+        "把文本写入项目中的文件，自动创建上级目录。默认覆盖已有内容，append 为 true 时追加到末尾。"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": { "type": "string", "minLength": 1 },
+                "content": { "type": "string" },
+                "append": { "type": "boolean" }
+            },
+            "required": ["path", "content"]
+        })
+    }
+
+    fn writes(&self) -> bool {
+        true
+    }
+
+    async fn invoke(&self, args: Value, ctx: &ToolContext<'_>) -> Result<Value, String> {
+        let root = ctx.project.root();
+        let path = str_arg(&args, "path");
+        let full = resolve(root, path)?;
+        if full == root || full.starts_with(root.join(VLOGI_DIR)) {
+            return Err(format!("不允许写入 `{}`", path));
+        }
+        if full.is_dir() {
+            return Err(format!("`{}` 是目录", path));
+        }
+        if let Some(parent) = full.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| io_error(path, e))?;
+        }
+        let content = str_arg(&args, "content");
+        let append = args.get("append").and_then(Value::as_bool).unwrap_or(false);
+        if append {
+            use std::io::Write;
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&full)
+                .and_then(|mut file| file.write_all(content.as_bytes()))
+        } else {
+            std::fs::write(&full, content)
+        }
+        .map_err(|e| io_error(path, e))?;
+        tracing::info!("✅ 运行 {} 写入文件 {}", ctx.run_id, full.display());
+        Ok(json!({ "path": display(root, &full), "bytes": content.len() }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::Project;
+
+    fn project() -> (tempfile::TempDir, Project) {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("vlogi")).unwrap();
+        std::fs::write(dir.path().join("vlogi/meta.json5"), "{}").unwrap();
+        std::fs::create_dir_all(dir.path().join("docs")).unwrap();
+        std::fs::write(dir.path().join("docs/a.md"), "# Title\nhello World\n").unwrap();
+        let project = Project::open(dir.path()).unwrap();
+        (dir, project)
+    }
+
+    #[test]
+    fn test_resolve_rejects_escapes() {
+        let (_dir, project) = project();
+        let root = project.root();
+        assert_eq!(
+            resolve(root, "docs/./a.md").unwrap(),
+            root.join("docs/a.md")
+        );
+        assert_eq!(resolve(root, "new/b.md").unwrap(), root.join("new/b.md"));
+        assert!(resolve(root, "docs/../../etc").unwrap_err().contains(".."));
+        assert!(resolve(root, "/etc/passwd").is_err());
+
+        #[cfg(unix)]
+        {
+            let outside = tempfile::tempdir().unwrap();
+            std::os::unix::fs::symlink(outside.path(), root.join("link")).unwrap();
+            assert!(resolve(root, "link/x.md")
+                .unwrap_err()
+                .contains("项目目录之外"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fs_tools() {
+        let (_dir, project) = project();
+        let ctx = ToolContext {
+            project: &project,
+            run_id: "r",
+            allow_write: true,
+            mcp: &[],
+            node_id: "n",
+            memory: None,
+        };
+
+        let found = SearchFiles
+            .invoke(json!({ "query": "world" }), &ctx)
+            .await
+            .unwrap();
+        assert_eq!(
+            found["matches"],
+            json!([{ "path": "docs/a.md", "line": 2, "text": "hello World" }])
+        );
+
+        let written = WriteFile
+            .invoke(json!({ "path": "out/b.md", "content": "hi" }), &ctx)
+            .await
+            .unwrap();
+        assert_eq!(written["path"], "out/b.md");
+        let read = ReadFile
+            .invoke(json!({ "path": "out/b.md" }), &ctx)
+            .await
+            .unwrap();
+        assert_eq!(read["content"], "hi");
+        assert!(WriteFile
+            .invoke(json!({ "path": "vlogi/meta.json5", "content": "" }), &ctx)
+            .await
+            .is_err());
+
+        let listed = ListDir.invoke(json!({}), &ctx).await.unwrap();
+        let names: Vec<_> = listed["entries"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["docs", "out", "vlogi"]);
+    }
+}
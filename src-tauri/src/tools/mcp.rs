@@ -0,0 +1,316 @@
+//! MCP 工具：项目 `vlogi/mcp.json5` 中声明的 MCP 服务提供的工具
+//!
+//! ```json5
+//! {
+//!   servers: {
+//!     github: {
+//!       command: "npx",
+//!       args: ["-y", "@modelcontextprotocol/server-github"],
+//!       // 从密钥库读取的环境变量：变量名 → 密钥名
+//!       secrets: { GITHUB_PERSONAL_ACCESS_TOKEN: "github-token" },
+//!     },
+//!   },
+//! }
+//! ```
+//!
+//! 工具以 `<服务名>__<工具名>` 注册，agent 节点在 `tools` 中按此名称引用，流程还需在 `permissions.mcp`
+//! 中允许该服务。服务在第一个允许它的运行开始时启动，之后保持运行供后续运行复用；声明变化后重新启动。
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use zeroize::Zeroizing;
+
+use super::{Tool, ToolContext, ToolRegistry};
+use crate::flow::model::mcp_allowed;
+use crate::mcp::client::{Launch, McpClient};
+use crate::mcp::tool_name;
+use crate::project::Project;
+use crate::vault::Vault;
+
+/// `vlogi/mcp.json5`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct McpConfig {
+    /// 服务名 → 服务声明（兼容其它应用使用的 `mcpServers`）
+    #[serde(default, alias = "mcpServers")]
+    pub servers: BTreeMap<String, ServerConfig>,
+}
+
+/// 以子进程启动的 stdio 服务
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ServerConfig {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// 从密钥库读取的环境变量：变量名 → 密钥名
+    #[serde(default)]
+    pub secrets: BTreeMap<String, String>,
+    /// 工作目录（相对于项目根目录），默认为项目根目录
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// 单次请求的超时（秒）
+    #[serde(default = "default_timeout")]
+    pub timeout_secs: u64,
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+fn default_timeout() -> u64 {
+    120
+}
+
+impl McpConfig {
+    /// 读取项目中的声明，文件不存在时为空
+    pub fn load(project: &Project) -> Result<Self, String> {
+        let path = project.mcp_path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content =
+            std::fs::read_to_string(&path).map_err(|e| format!("读取 {:?} 失败: {}", path, e))?;
+        json5::from_str(&content).map_err(|e| format!("{:?} 格式错误: {}", path, e))
+    }
+}
+
+impl ServerConfig {
+    fn launch(&self, name: &str, project: &Project, vault: &Vault) -> Result<Launch, String> {
+        let mut env: Vec<(String, Zeroizing<String>)> = (self.env.iter())
+            .map(|(key, value)| (key.clone(), Zeroizing::new(value.clone())))
+            .collect();
+        for (key, secret) in &self.secrets {
+            let value = vault
+                .get(secret)
+                .map_err(|e| format!("MCP 服务 {} 引用的密钥 {} 不可用: {}", name, secret, e))?;
+            env.push((key.clone(), value));
+        }
+        let cwd = match &self.cwd {
+            Some(cwd) => project.root().join(cwd),
+            None => project.root().to_path_buf(),
+        };
+        Ok(Launch {
+            command: self.command.clone(),
+            args: self.args.clone(),
+            env,
+            cwd,
+        })
+    }
+}
+
+/// MCP 服务提供的工具
+struct McpTool {
+    name: String,
+    server: String,
+    /// 服务中的工具名
+    remote: String,
+    description: String,
+    parameters: Value,
+    client: Arc<McpClient>,
+}
+
+#[async_trait]
+impl Tool for McpTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn parameters(&self) -> Value {
+        self.parameters.clone()
+    }
+
+    fn server(&self) -> Option<&str> {
+        Some(&self.server)
+    }
+
+    async fn invoke(&self, args: Value, _ctx: &ToolContext<'_>) -> Result<Value, String> {
+        self.client.call_tool(&self.remote, args).await
+    }
+}
+
+/// 项目中声明的服务及其状态
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerStatus {
+    pub name: String,
+    pub command: String,
+    pub disabled: bool,
+    pub running: bool,
+}
+
+/// (项目根目录, 服务名) → 启动时的声明与服务
+type Clients = HashMap<(PathBuf, String), (ServerConfig, Arc<McpClient>)>;
+
+/// 全部项目中已启动的 MCP 服务
+#[derive(Default)]
+pub struct McpHub {
+    clients: tokio::sync::Mutex<Clients>,
+}
+
+impl std::fmt::Debug for McpHub {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("McpHub").finish_non_exhaustive()
+    }
+}
+
+impl McpHub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 在 `base` 的基础上注册 `allowed` 中的服务提供的工具
+    ///
+    /// 服务在此时启动；声明有误或启动失败的服务只记录警告，其工具在调用时报未注册。
+    pub async fn tools(
+        &self,
+        project: &Project,
+        allowed: &[String],
+        base: &ToolRegistry,
+        vault: &Vault,
+    ) -> ToolRegistry {
+        if allowed.is_empty() {
+            return base.clone();
+        }
+        let config = match McpConfig::load(project) {
+            Ok(config) => config,
+            Err(e) => {
+                tracing::warn!("⚠️  {}", e);
+                return base.clone();
+            }
+        };
+        let mut registry = base.clone();
+        for (name, server) in &config.servers {
+            if server.disabled || !mcp_allowed(allowed, name) {
+                continue;
+            }
+            let registered = match self.client(project, name, server, vault).await {
+                Ok(client) => register(&mut registry, name, client).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = registered {
+                tracing::warn!("⚠️  {}", e);
+            }
+        }
+        registry
+    }
+
+    /// 项目中声明的服务及其状态
+    pub async fn status(&self, project: &Project) -> Result<Vec<ServerStatus>, String> {
+        let config = McpConfig::load(project)?;
+        let clients = self.clients.lock().await;
+        let mut list = Vec::new();
+        for (name, server) in config.servers {
+            let key = (project.root().to_path_buf(), name.clone());
+            let running = match clients.get(&key) {
+                Some((_, client)) => client.is_running().await,
+                None => false,
+            };
+            list.push(ServerStatus {
+                name,
+                command: server.command,
+                disabled: server.disabled,
+                running,
+            });
+        }
+        Ok(list)
+    }
+
+    /// 重新启动服务，返回其工具注册后的名称
+    pub async fn restart(
+        &self,
+        project: &Project,
+        name: &str,
+        vault: &Vault,
+    ) -> Result<Vec<String>, String> {
+        let config = McpConfig::load(project)?;
+        let server = (config.servers.get(name))
+            .ok_or_else(|| format!("项目中没有声明 MCP 服务 `{}`", name))?;
+        let client = self.client(project, name, server, vault).await?;
+        client.restart().await;
+        let mut registry = ToolRegistry::new();
+        register(&mut registry, name, client).await?;
+        Ok(registry.list().into_iter().map(|spec| spec.name).collect())
+    }
+
+    /// 已启动的服务；声明变化时替换（旧进程随之结束）
+    async fn client(
+        &self,
+        project: &Project,
+        name: &str,
+        server: &ServerConfig,
+        vault: &Vault,
+    ) -> Result<Arc<McpClient>, String> {
+        let key = (project.root().to_path_buf(), name.to_string());
+        let mut clients = self.clients.lock().await;
+        if let Some((config, client)) = clients.get(&key) {
+            if config == server {
+                return Ok(client.clone());
+            }
+            tracing::info!("MCP 服务 {} 的声明已修改，将重新启动", name);
+        }
+        let launch = server.launch(name, project, vault)?;
+        let timeout = Duration::from_secs(server.timeout_secs.max(1));
+        let client = Arc::new(McpClient::new(name, launch, timeout));
+        clients.insert(key, (server.clone(), client.clone()));
+        Ok(client)
+    }
+}
+
+/// 列出服务的工具并注册为 `<服务名>__<工具名>`
+async fn register(
+    registry: &mut ToolRegistry,
+    server: &str,
+    client: Arc<McpClient>,
+) -> Result<(), String> {
+    for tool in client.tools().await? {
+        let description = [&tool.description, &tool.title]
+            .into_iter()
+            .flatten()
+            .find(|text| !text.is_empty())
+            .cloned()
+            .unwrap_or_else(|| tool.name.clone());
+        let mcp_tool = McpTool {
+            name: tool_name(&format!("{}__{}", server, tool.name)),
+            server: server.to_string(),
+            remote: tool.name,
+            description,
+            parameters: tool.input_schema,
+            client: client.clone(),
+        };
+        if let Err(e) = registry.register(Arc::new(mcp_tool)) {
+            tracing::warn!("⚠️  跳过 MCP 服务 {} 的工具: {}", client.name(), e);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_accepts_mcp_servers_alias() {
+        let config: McpConfig = json5::from_str(
+            r#"{
+                // 其它应用的格式
+                mcpServers: {
+                    files: { command: "mcp-files", args: ["--root", "."], timeout_secs: 5 },
+                    off: { command: "x", disabled: true },
+                },
+            }"#,
+        )
+        .unwrap();
+        let files = &config.servers["files"];
+        assert_eq!(files.args, ["--root", "."]);
+        assert_eq!(files.timeout_secs, 5);
+        assert!(config.servers["off"].disabled);
+        assert_eq!(config.servers["off"].timeout_secs, 120);
+    }
+}
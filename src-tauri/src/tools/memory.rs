@@ -0,0 +1,156 @@
+//! 用户记忆工具：检索、保存与遗忘
+//!
+//! 只能访问当前运行的用户档案与项目范围内的记忆（见 [`crate::memory`]），运行未启用记忆时报错。
+//! 写入记录发起的运行与节点，可在应用中逐条或按运行撤销。
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use super::{Tool, ToolContext};
+use crate::memory::{AgentMemory, Memory, MemoryKind, Origin};
+
+const DEFAULT_SEARCH_RESULTS: usize = 10;
+
+fn memory<'a>(ctx: &ToolContext<'a>) -> Result<&'a dyn AgentMemory, String> {
+    ctx.memory.ok_or_else(|| "未启用用户记忆".to_string())
+}
+
+fn origin(ctx: &ToolContext<'_>) -> Origin {
+    Origin::agent(ctx.run_id, ctx.node_id)
+}
+
+fn summary(memory: &Memory) -> Value {
+    json!({
+        "id": memory.id,
+        "kind": memory.kind,
+        "content": memory.content,
+        "tags": memory.tags,
+        "project_only": !memory.project.is_empty(),
+    })
+}
+
+/// 检索用户记忆
+pub struct SearchMemory;
+
+#[async_trait]
+impl Tool for SearchMemory {
+    fn name(&self) -> &str {
+        "memory_search"
+    }
+
+    fn description(&self) -> &str {
+        "检索关于用户的记忆（事实、偏好与过往交互），按与 query 的相关度排序。"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "query": { "type": "string" },
+                "limit": { "type": "integer", "minimum": 1, "maximum": 50 }
+            },
+            "required": ["query"]
+        })
+    }
+
+    async fn invoke(&self, args: Value, ctx: &ToolContext<'_>) -> Result<Value, String> {
+        let query = args["query"].as_str().unwrap_or_default();
+        let limit = args["limit"]
+            .as_u64()
+            .map_or(DEFAULT_SEARCH_RESULTS, |n| n as usize);
+        let found = memory(ctx)?.search(query, limit).await?;
+        Ok(json!({ "memories": found.iter().map(summary).collect::<Vec<_>>() }))
+    }
+}
+
+/// 新增或修改用户记忆
+pub struct SaveMemory;
+
+#[async_trait]
+impl Tool for SaveMemory {
+    fn name(&self) -> &str {
+        "memory_save"
+    }
+
+    fn description(&self) -> &str {
+        "记住关于用户的信息。省略 id 时新增记忆，指定 id 时修改该记忆（省略的字段保持不变）。\
+         kind 为 fact（事实）、preference（偏好）或 interaction（过往交互的要点）；\
+         project_only 为 true 时记忆只在当前项目中生效。"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "string" },
+                "content": { "type": "string", "minLength": 1 },
+                "kind": { "type": "string", "enum": ["fact", "preference", "interaction"] },
+                "tags": { "type": "array", "items": { "type": "string" } },
+                "project_only": { "type": "boolean" }
+            },
+            "required": ["content"]
+        })
+    }
+
+    async fn invoke(&self, args: Value, ctx: &ToolContext<'_>) -> Result<Value, String> {
+        let memory = memory(ctx)?;
+        let id = args["id"].as_str().unwrap_or_default();
+        let mut target = match id.is_empty() {
+            true => Memory {
+                id: String::new(),
+                profile: String::new(),
+                project: String::new(),
+                kind: MemoryKind::default(),
+                content: String::new(),
+                tags: Vec::new(),
+                created_at: 0,
+                updated_at: 0,
+                forgotten_at: None,
+            },
+            false => memory.get(id).await?,
+        };
+        target.content = args["content"].as_str().unwrap_or_default().to_string();
+        if let Some(kind) = args.get("kind") {
+            target.kind = serde_json::from_value(kind.clone()).map_err(|e| e.to_string())?;
+        }
+        if let Some(tags) = args.get("tags") {
+            target.tags = serde_json::from_value(tags.clone()).map_err(|e| e.to_string())?;
+        }
+        if let Some(project_only) = args["project_only"].as_bool() {
+            target.project = match project_only {
+                true => ctx.project.root().to_string_lossy().into_owned(),
+                false => String::new(),
+            };
+        }
+        let saved = memory.save(target, &origin(ctx)).await?;
+        Ok(summary(&saved))
+    }
+}
+
+/// 遗忘用户记忆
+pub struct ForgetMemory;
+
+#[async_trait]
+impl Tool for ForgetMemory {
+    fn name(&self) -> &str {
+        "memory_forget"
+    }
+
+    fn description(&self) -> &str {
+        "遗忘一条过时或错误的用户记忆。"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": { "id": { "type": "string" } },
+            "required": ["id"]
+        })
+    }
+
+    async fn invoke(&self, args: Value, ctx: &ToolContext<'_>) -> Result<Value, String> {
+        let id = args["id"].as_str().unwrap_or_default();
+        let forgotten = memory(ctx)?.forget(id, &origin(ctx)).await?;
+        Ok(json!({ "forgotten": forgotten }))
+    }
+}
@@ -0,0 +1,319 @@
+//! 后端工具：agent 节点可以调用的函数
+//!
+//! 工具实现 [`Tool`] 并注册到 [`ToolRegistry`]。agent 节点在 `tools` 中列出允许使用的工具，
+//! 执行器把工具声明交给模型，模型发起调用时由注册表校验参数并执行，结果再反馈给模型。
+//! 内置工具见 [`builtin`]（项目文件见 [`fs`]，用户记忆见 [`memory`]），项目中声明的 MCP 服务提供的工具见 [`mcp`]。
+
+use async_trait::async_trait;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::flow::model::mcp_allowed;
+use crate::flow::structured::{self, OutputSchema};
+use crate::llm::ToolSpec;
+use crate::memory::AgentMemory;
+use crate::project::Project;
+
+pub mod fs;
+pub mod mcp;
+pub mod memory;
+
+/// 工具执行时的上下文
+pub struct ToolContext<'a> {
+    pub project: &'a Project,
+    pub run_id: &'a str,
+    /// 当前流程是否开启了 `permissions.fs_write`
+    pub allow_write: bool,
+    /// 当前流程允许的 MCP 服务（`permissions.mcp`）
+    pub mcp: &'a [String],
+    /// 发起调用的节点
+    pub node_id: &'a str,
+    /// 当前运行的用户记忆，未启用时为空
+    pub memory: Option<&'a dyn AgentMemory>,
+}
+
+/// 后端工具
+#[async_trait]
+pub trait Tool: Send + Sync {
+    /// 工具名称，只能包含字母、数字、`_` 与 `-`
+    fn name(&self) -> &str;
+
+    /// 提供给模型的说明
+    fn description(&self) -> &str;
+
+    /// 参数的 JSON Schema
+    fn parameters(&self) -> Value;
+
+    /// 是否会修改项目文件（需要流程开启写入权限）
+    fn writes(&self) -> bool {
+        false
+    }
+
+    /// 提供此工具的 MCP 服务（需要流程在 `permissions.mcp` 中允许）
+    fn server(&self) -> Option<&str> {
+        None
+    }
+
+    /// 执行工具，参数已通过 Schema 校验
+    async fn invoke(&self, args: Value, ctx: &ToolContext<'_>) -> Result<Value, String>;
+}
+
+struct Entry {
+    tool: Arc<dyn Tool>,
+    schema: Arc<OutputSchema>,
+}
+
+impl Clone for Entry {
+    fn clone(&self) -> Self {
+        Self {
+            tool: self.tool.clone(),
+            schema: self.schema.clone(),
+        }
+    }
+}
+
+/// 按名称索引的工具集合
+#[derive(Clone, Default)]
+pub struct ToolRegistry {
+    tools: BTreeMap<String, Entry>,
+}
+
+impl std::fmt::Debug for ToolRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.tools.keys()).finish()
+    }
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 注册工具，同名工具会被替换
+    pub fn register(&mut self, tool: Arc<dyn Tool>) -> Result<(), String> {
+        let name = tool.name().to_string();
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid {
+            return Err(format!("工具名称 `{}` 无效", name));
+        }
+        let schema = OutputSchema::compile(&tool.parameters())
+            .map_err(|e| format!("工具 `{}` 的参数声明无效: {}", name, e))?;
+        self.tools.insert(
+            name,
+            Entry {
+                tool,
+                schema: Arc::new(schema),
+            },
+        );
+        Ok(())
+    }
+
+    /// 全部工具的声明（按名称排序）
+    pub fn list(&self) -> Vec<ToolSpec> {
+        self.tools
+            .values()
+            .map(|entry| spec(&*entry.tool))
+            .collect()
+    }
+
+    /// 取出指定工具的声明，存在未注册或无权使用的工具时报错
+    pub fn specs(&self, names: &[String], ctx: &ToolContext<'_>) -> Result<Vec<ToolSpec>, String> {
+        names
+            .iter()
+            .map(|name| Ok(spec(&*self.entry(name, ctx)?.tool)))
+            .collect()
+    }
+
+    fn entry(&self, name: &str, ctx: &ToolContext<'_>) -> Result<&Entry, String> {
+        let entry = self
+            .tools
+            .get(name)
+            .ok_or_else(|| format!("未注册的工具 `{}`", name))?;
+        if entry.tool.writes() && !ctx.allow_write {
+            return Err(format!(
+                "工具 `{}` 会写入文件，当前流程未开启 permissions.fs_write",
+                name
+            ));
+        }
+        if let Some(server) = entry.tool.server() {
+            if !mcp_allowed(ctx.mcp, server) {
+                return Err(format!(
+                    "工具 `{}` 由 MCP 服务 `{}` 提供，当前流程未在 permissions.mcp 中允许",
+                    name, server
+                ));
+            }
+        }
+        Ok(entry)
+    }
+
+    /// 解析并校验模型生成的参数后执行工具
+    pub async fn invoke(
+        &self,
+        name: &str,
+        arguments: &str,
+        ctx: &ToolContext<'_>,
+    ) -> Result<Value, String> {
+        let entry = self.entry(name, ctx)?;
+        let args = if arguments.trim().is_empty() {
+            Value::Object(Default::default())
+        } else {
+            structured::parse_lenient(arguments)
+                .map_err(|e| format!("参数不是合法的 JSON: {}", e))?
+        };
+        let errors = entry.schema.check(&args);
+        if !errors.is_empty() {
+            return Err(format!("参数不符合声明: {}", errors.join("; ")));
+        }
+        entry.tool.invoke(args, ctx).await
+    }
+}
+
+/// 内置工具
+pub fn builtin() -> ToolRegistry {
+    let mut registry = ToolRegistry::new();
+    let tools: [Arc<dyn Tool>; 7] = [
+        Arc::new(fs::ReadFile),
+        Arc::new(fs::ListDir),
+        Arc::new(fs::SearchFiles),
+        Arc::new(fs::WriteFile),
+        Arc::new(memory::SearchMemory),
+        Arc::new(memory::SaveMemory),
+        Arc::new(memory::ForgetMemory),
+    ];
+    for tool in tools {
+        registry.register(tool).expect("内置工具声明无效");
+    }
+    registry
+}
+
+fn spec(tool: &dyn Tool) -> ToolSpec {
+    ToolSpec {
+        name: tool.name().to_string(),
+        description: tool.description().to_string(),
+        parameters: tool.parameters(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow::exec::{ExecOptions, Executor};
+    use crate::flow::store::FlowStore;
+    use crate::flow::trace::TraceStatus;
+    use crate::llm::registry::ProviderRegistry;
+    use crate::llm::{ChatRequest, ChatResponse, LlmError, Provider, Role, ToolCall};
+    use serde_json::json;
+
+    struct Add;
+
+    #[async_trait]
+    impl Tool for Add {
+        fn name(&self) -> &str {
+            "add"
+        }
+
+        fn description(&self) -> &str {
+            "两数相加"
+        }
+
+        fn parameters(&self) -> Value {
+            json!({
+                "type": "object",
+                "properties": { "a": { "type": "number" }, "b": { "type": "number" } },
+                "required": ["a", "b"]
+            })
+        }
+
+        async fn invoke(&self, args: Value, _ctx: &ToolContext<'_>) -> Result<Value, String> {
+            Ok(json!(
+                args["a"].as_f64().unwrap_or(0.0) + args["b"].as_f64().unwrap_or(0.0)
+            ))
+        }
+    }
+
+    /// 收到工具结果前一直请求调用 `add`，之后返回工具结果
+    struct Caller;
+
+    #[async_trait]
+    impl Provider for Caller {
+        fn name(&self) -> &str {
+            "caller"
+        }
+
+        async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, LlmError> {
+            let last = request.messages.last().unwrap();
+            if last.role == Role::Tool {
+                return Ok(ChatResponse {
+                    content: format!(
+                        "结果 {}",
+                        request.messages[request.messages.len() - 2].content
+                    ),
+                    ..Default::default()
+                });
+            }
+            let call = |id: &str, arguments: &str| ToolCall {
+                id: id.to_string(),
+                name: "add".to_string(),
+                arguments: arguments.to_string(),
+            };
+            Ok(ChatResponse {
+                tool_calls: vec![call("c1", r#"{"a": 2, "b": 3}"#), call("c2", r#"{"a": 1}"#)],
+                ..Default::default()
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_agent_calls_tools() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("vlogi")).unwrap();
+        std::fs::write(dir.path().join("vlogi/meta.json5"), "{}").unwrap();
+        let mut tools = ToolRegistry::new();
+        tools.register(Arc::new(Add)).unwrap();
+        tools.register(Arc::new(fs::ReadFile)).unwrap();
+        let exec = Executor::new(
+            FlowStore::new(Project::open(dir.path()).unwrap()),
+            ProviderRegistry::single(Arc::new(Caller)),
+            ExecOptions::default(),
+        )
+        .with_tools(tools);
+
+        let flow = |tools: Value| {
+            serde_json::from_value(json!({
+                "id": "f",
+                "nodes": [{ "id": "ask", "type": "agent", "model": "m", "prompt": "2+3", "tools": tools }]
+            }))
+            .unwrap()
+        };
+        let trace = exec.run("r1", flow(json!(["add"])), json!({})).await;
+        assert_eq!(trace.status, TraceStatus::Succeeded, "{:?}", trace.error);
+        let node = &trace.nodes[0];
+        assert_eq!(node.output, Some(json!("结果 5.0")));
+        assert_eq!(node.calls.len(), 2);
+        assert_eq!(node.tool_calls[0].output, Some(json!(5.0)));
+        assert!(node.tool_calls[1]
+            .error
+            .as_ref()
+            .unwrap()
+            .contains("参数不符合声明"));
+
+        let trace = exec.run("r2", flow(json!(["sub"])), json!({})).await;
+        assert!(trace.error.unwrap().contains("未注册的工具 `sub`"));
+
+        // 模型请求节点未声明的工具时不执行，作为工具错误反馈
+        let trace = exec.run("r3", flow(json!(["read_file"])), json!({})).await;
+        assert_eq!(trace.status, TraceStatus::Succeeded, "{:?}", trace.error);
+        let node = &trace.nodes[0];
+        assert_eq!(node.tool_calls.len(), 2);
+        assert!(node.tool_calls.iter().all(|call| call.output.is_none()
+            && call
+                .error
+                .as_ref()
+                .unwrap()
+                .contains("未在节点的 tools 中声明")));
+    }
+}
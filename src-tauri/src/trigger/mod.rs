@@ -0,0 +1,53 @@
+//! 触发器：无需人工操作、自动启动项目中的流程
+//!
+//! [`schedule`] 按 cron 表达式定时执行流程，[`watch`] 在目录中出现新文件或文件修改后执行流程。触发的运行与 HTTP API 发起的运行一样登记运行历史，
+//! 应用运行时执行事件同时转发给前端。
+
+pub mod schedule;
+pub mod watch;
+
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::commands::flow::{exec_options, record_start, run_external, Start, Target};
+use crate::flow::events::NoopSink;
+use crate::flow::store::FlowStore;
+use crate::flow::trace::{FlowTrace, TraceStatus};
+use crate::project::Project;
+
+/// 在后台启动一次运行，运行结束后返回轨迹，运行失败或被取消时返回错误
+///
+/// 流程无法读取时直接返回错误，不登记运行。
+pub(crate) fn launch(
+    project: &str,
+    flow: &str,
+    inputs: Value,
+    run_id: &str,
+) -> Result<tokio::task::JoinHandle<Result<FlowTrace, String>>, String> {
+    let store = FlowStore::new(Project::open(project)?);
+    let def = store.load_local(flow)?;
+    let target = Target {
+        run_id: run_id.to_string(),
+        project: project.to_string(),
+        store,
+        def,
+    };
+    Ok(tokio::spawn(async move {
+        let recorded = record_start(&target.run_id, &target.project, &target.def, &inputs).await;
+        let (options, events) = (exec_options(None), Arc::new(NoopSink));
+        let trace = run_external(target, options, Start::New(inputs), events, recorded).await?;
+        match trace.status {
+            TraceStatus::Failed => Err(trace.error.unwrap_or_else(|| "运行失败".into())),
+            TraceStatus::Cancelled => Err("运行已取消".into()),
+            _ => Ok(trace),
+        }
+    }))
+}
+
+fn empty_inputs() -> Value {
+    json!({})
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
@@ -0,0 +1,542 @@
+//! 定时触发：按 cron 表达式以固定输入执行流程
+//!
+//! 计划保存在 engine.db 的 `schedules` 表中，应用启动后由 [`Scheduler`] 在后台按 `next_run_at` 触发。
+//! cron 表达式按本地时间计算，支持 5 段（分 时 日 月 周）或带秒的 6 段，如 `0 2 * * *` 为每天凌晨两点。
+//!
+//! - 错过的执行（应用未运行或休眠期间到期）按 [`MissedPolicy`] 跳过或补执行一次
+//! - 上一次触发的运行仍在执行时按 [`ConcurrencyPolicy`] 处理
+//! - 触发时先以 `next_run_at` 为条件更新记录，多个进程共用 engine.db 时同一次执行只会触发一次
+
+use chrono::{Local, TimeZone};
+use croner::Cron;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::sqlite::SqliteRow;
+use sqlx::Row;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+use super::{empty_inputs, enabled_by_default, launch};
+use crate::flow::store::FlowStore;
+use crate::flow::trace::now_ms;
+use crate::project::Project;
+use crate::state::GlobalState;
+use crate::utils::enginedb::EngineDb;
+
+/// 到期超过该时长（毫秒）才算错过，避免调度延迟被当作错过
+const GRACE_MS: i64 = 60_000;
+/// 最长休眠时长，期间系统时间变化也能及时发现到期的计划
+const MAX_SLEEP: Duration = Duration::from_secs(60);
+
+/// 错过执行时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MissedPolicy {
+    /// 跳过错过的执行，等待下一次
+    #[default]
+    Skip,
+    /// 补执行一次（错过多次也只执行一次）
+    CatchUp,
+}
+
+/// 上一次触发的运行仍在执行时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConcurrencyPolicy {
+    /// 同时执行
+    Allow,
+    /// 跳过本次执行
+    #[default]
+    Forbid,
+    /// 取消上一次运行后执行
+    Replace,
+}
+
+impl MissedPolicy {
+    fn as_str(&self) -> &'static str {
+        match self {
+            MissedPolicy::Skip => "skip",
+            MissedPolicy::CatchUp => "catch_up",
+        }
+    }
+}
+
+impl ConcurrencyPolicy {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ConcurrencyPolicy::Allow => "allow",
+            ConcurrencyPolicy::Forbid => "forbid",
+            ConcurrencyPolicy::Replace => "replace",
+        }
+    }
+}
+
+/// 一个定时计划
+///
+/// `last_*` 与 `next_run_at` 由调度器维护，保存时忽略传入的值。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Schedule {
+    /// 为空时保存为新计划
+    #[serde(default)]
+    pub id: String,
+    /// 项目根目录（与运行历史中一致）
+    pub project: String,
+    pub flow_id: String,
+    pub cron: String,
+    /// 每次执行使用的流程输入
+    #[serde(default = "empty_inputs")]
+    pub inputs: Value,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub missed: MissedPolicy,
+    #[serde(default)]
+    pub concurrency: ConcurrencyPolicy,
+    #[serde(default)]
+    pub last_run_at: Option<i64>,
+    #[serde(default)]
+    pub last_run_id: Option<String>,
+    /// 上一次触发的运行未能启动或失败时的错误
+    #[serde(default)]
+    pub last_error: Option<String>,
+    /// 下一次执行时间，停用时为空
+    #[serde(default)]
+    pub next_run_at: Option<i64>,
+    #[serde(default)]
+    pub created_at: i64,
+    #[serde(default)]
+    pub updated_at: i64,
+}
+
+/// 解析 cron 表达式（秒可省略）
+pub fn parse_cron(expr: &str) -> Result<Cron, String> {
+    Cron::new(expr.trim())
+        .with_seconds_optional()
+        .parse()
+        .map_err(|e| format!("cron 表达式 `{}` 无效: {}", expr, e))
+}
+
+/// `after`（毫秒时间戳）之后的下一次执行时间
+pub fn next_after(cron: &Cron, after: i64) -> Option<i64> {
+    // 按整秒查找，避免得到带毫秒的执行时间
+    let after = Local
+        .timestamp_millis_opt(after.div_euclid(1000) * 1000)
+        .single()?;
+    let next = cron.find_next_occurrence(&after, false).ok()?;
+    Some(next.timestamp_millis())
+}
+
+/// engine.db 中的定时计划
+pub struct ScheduleStore<'a> {
+    db: &'a EngineDb,
+}
+
+const SELECT: &str = "SELECT id, project, flow_id, cron, inputs, enabled, missed, concurrency,
+     last_run_at, last_run_id, last_error, next_run_at, created_at, updated_at
+ FROM schedules";
+
+impl<'a> ScheduleStore<'a> {
+    pub fn new(db: &'a EngineDb) -> Self {
+        Self { db }
+    }
+
+    /// 全部计划（可按项目过滤），按创建时间排序
+    pub async fn list(&self, project: Option<&str>) -> Result<Vec<Schedule>, String> {
+        let rows = match project {
+            Some(project) => {
+                sqlx::query(&format!("{} WHERE project = ? ORDER BY created_at", SELECT))
+                    .bind(project)
+                    .fetch_all(self.db.pool().await?)
+                    .await
+            }
+            None => {
+                sqlx::query(&format!("{} ORDER BY created_at", SELECT))
+                    .fetch_all(self.db.pool().await?)
+                    .await
+            }
+        }
+        .map_err(|e| format!("读取定时计划失败: {}", e))?;
+        rows.iter().map(from_row).collect()
+    }
+
+    pub async fn get(&self, id: &str) -> Result<Option<Schedule>, String> {
+        let row = sqlx::query(&format!("{} WHERE id = ?", SELECT))
+            .bind(id)
+            .fetch_optional(self.db.pool().await?)
+            .await
+            .map_err(|e| format!("读取定时计划失败: {}", e))?;
+        row.as_ref().map(from_row).transpose()
+    }
+
+    /// 新增或修改计划，返回保存后的计划
+    ///
+    /// 只校验 cron 表达式与输入，引用的流程见 [`validate`]。
+    /// 下一次执行时间从现在起重新计算，上一次执行的记录保持不变。
+    pub async fn save(&self, schedule: &Schedule) -> Result<Schedule, String> {
+        let cron = parse_cron(&schedule.cron)?;
+        if !schedule.inputs.is_object() {
+            return Err("流程输入必须是对象".to_string());
+        }
+        let now = now_ms();
+        let next_run_at = match schedule.enabled {
+            true => Some(next_after(&cron, now).ok_or("cron 表达式没有下一次执行时间")?),
+            false => None,
+        };
+        let id = match schedule.id.is_empty() {
+            true => uuid::Uuid::new_v4().to_string(),
+            false => schedule.id.clone(),
+        };
+        sqlx::query(
+            "INSERT INTO schedules (id, project, flow_id, cron, inputs, enabled, missed, concurrency,
+                 next_run_at, created_at, updated_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT(id) DO UPDATE SET
+                 project = excluded.project,
+                 flow_id = excluded.flow_id,
+                 cron = excluded.cron,
+                 inputs = excluded.inputs,
+                 enabled = excluded.enabled,
+                 missed = excluded.missed,
+                 concurrency = excluded.concurrency,
+                 next_run_at = excluded.next_run_at,
+                 updated_at = excluded.updated_at",
+        )
+        .bind(&id)
+        .bind(&schedule.project)
+        .bind(&schedule.flow_id)
+        .bind(schedule.cron.trim())
+        .bind(schedule.inputs.to_string())
+        .bind(schedule.enabled)
+        .bind(schedule.missed.as_str())
+        .bind(schedule.concurrency.as_str())
+        .bind(next_run_at)
+        .bind(now)
+        .bind(now)
+        .execute(self.db.pool().await?)
+        .await
+        .map_err(|e| format!("保存定时计划失败: {}", e))?;
+        self.get(&id)
+            .await?
+            .ok_or_else(|| format!("定时计划 {} 不存在", id))
+    }
+
+    /// 删除计划，返回是否存在
+    pub async fn delete(&self, id: &str) -> Result<bool, String> {
+        sqlx::query("DELETE FROM schedules WHERE id = ?")
+            .bind(id)
+            .execute(self.db.pool().await?)
+            .await
+            .map(|r| r.rows_affected() > 0)
+            .map_err(|e| format!("删除定时计划失败: {}", e))
+    }
+
+    /// 已到期的启用计划
+    pub async fn due(&self, now: i64) -> Result<Vec<Schedule>, String> {
+        let rows = sqlx::query(&format!(
+            "{} WHERE enabled = 1 AND next_run_at <= ? ORDER BY next_run_at",
+            SELECT
+        ))
+        .bind(now)
+        .fetch_all(self.db.pool().await?)
+        .await
+        .map_err(|e| format!("读取定时计划失败: {}", e))?;
+        rows.iter().map(from_row).collect()
+    }
+
+    /// 最早的下一次执行时间
+    pub async fn next_due(&self) -> Result<Option<i64>, String> {
+        sqlx::query_scalar("SELECT MIN(next_run_at) FROM schedules WHERE enabled = 1")
+            .fetch_one(self.db.pool().await?)
+            .await
+            .map_err(|e| format!("读取定时计划失败: {}", e))
+    }
+
+    /// 把下一次执行时间从 `due` 推进到 `next`（不执行），返回是否由本次调用推进
+    pub async fn advance(&self, id: &str, due: i64, next: Option<i64>) -> Result<bool, String> {
+        sqlx::query("UPDATE schedules SET next_run_at = ? WHERE id = ? AND next_run_at = ?")
+            .bind(next)
+            .bind(id)
+            .bind(due)
+            .execute(self.db.pool().await?)
+            .await
+            .map(|r| r.rows_affected() > 0)
+            .map_err(|e| format!("更新定时计划失败: {}", e))
+    }
+
+    /// 认领 `due` 这次执行：推进下一次执行时间并记录本次运行，返回是否认领成功
+    ///
+    /// 其它进程已认领（`next_run_at` 已变化）时返回 `false`。
+    pub async fn claim(
+        &self,
+        id: &str,
+        due: i64,
+        next: Option<i64>,
+        run_id: &str,
+        at: i64,
+    ) -> Result<bool, String> {
+        sqlx::query(
+            "UPDATE schedules
+             SET next_run_at = ?, last_run_at = ?, last_run_id = ?, last_error = NULL
+             WHERE id = ? AND next_run_at = ?",
+        )
+        .bind(next)
+        .bind(at)
+        .bind(run_id)
+        .bind(id)
+        .bind(due)
+        .execute(self.db.pool().await?)
+        .await
+        .map(|r| r.rows_affected() > 0)
+        .map_err(|e| format!("更新定时计划失败: {}", e))
+    }
+
+    /// 记录运行的错误（计划已再次触发时不覆盖）
+    pub async fn record_error(&self, id: &str, run_id: &str, error: &str) -> Result<(), String> {
+        sqlx::query("UPDATE schedules SET last_error = ? WHERE id = ? AND last_run_id = ?")
+            .bind(error)
+            .bind(id)
+            .bind(run_id)
+            .execute(self.db.pool().await?)
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("更新定时计划失败: {}", e))
+    }
+}
+
+fn from_row(row: &SqliteRow) -> Result<Schedule, String> {
+    let policy = |column: &str| Value::String(row.get(column));
+    Ok(Schedule {
+        id: row.get("id"),
+        project: row.get("project"),
+        flow_id: row.get("flow_id"),
+        cron: row.get("cron"),
+        inputs: serde_json::from_str(row.get("inputs")).unwrap_or_else(|_| empty_inputs()),
+        enabled: row.get("enabled"),
+        missed: serde_json::from_value(policy("missed"))
+            .map_err(|e| format!("未知的错过执行策略: {}", e))?,
+        concurrency: serde_json::from_value(policy("concurrency"))
+            .map_err(|e| format!("未知的并发策略: {}", e))?,
+        last_run_at: row.get("last_run_at"),
+        last_run_id: row.get("last_run_id"),
+        last_error: row.get("last_error"),
+        next_run_at: row.get("next_run_at"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    })
+}
+
+/// 对到期计划的处理
+#[derive(Debug, PartialEq)]
+enum Action {
+    /// 不执行，只推进下一次执行时间
+    Skip(&'static str),
+    /// 执行（`replace` 为是否先取消上一次运行）
+    Run { replace: bool },
+}
+
+/// 按策略决定到期计划如何处理；`previous_running` 为上一次触发的运行是否仍在执行
+fn decide(schedule: &Schedule, due: i64, now: i64, previous_running: bool) -> Action {
+    if now - due > GRACE_MS && schedule.missed == MissedPolicy::Skip {
+        return Action::Skip("已错过执行时间");
+    }
+    match (schedule.concurrency, previous_running) {
+        (ConcurrencyPolicy::Forbid, true) => Action::Skip("上一次运行仍在执行"),
+        (ConcurrencyPolicy::Replace, true) => Action::Run { replace: true },
+        _ => Action::Run { replace: false },
+    }
+}
+
+/// 后台调度器：休眠到最早的执行时间，触发到期的计划
+#[derive(Debug, Default)]
+pub struct Scheduler {
+    wake: Notify,
+    started: AtomicBool,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 启动后台任务（只启动一次）
+    pub fn start(&'static self) {
+        if self.started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        tokio::spawn(async move {
+            tracing::info!("✅ 定时计划调度器已启动");
+            loop {
+                let next = match self.tick().await {
+                    Ok(next) => next,
+                    Err(e) => {
+                        tracing::warn!("⚠️  处理定时计划失败: {}", e);
+                        None
+                    }
+                };
+                let delay = match next {
+                    Some(at) => Duration::from_millis((at - now_ms()).max(0) as u64).min(MAX_SLEEP),
+                    None => MAX_SLEEP,
+                };
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = self.wake.notified() => {}
+                }
+            }
+        });
+    }
+
+    /// 计划变化后立即重新计算休眠时长
+    pub fn wake(&self) {
+        self.wake.notify_one();
+    }
+
+    /// 触发到期的计划，返回最早的下一次执行时间
+    async fn tick(&self) -> Result<Option<i64>, String> {
+        let store = ScheduleStore::new(&GlobalState::get().engine_db);
+        let now = now_ms();
+        for schedule in store.due(now).await? {
+            if let Err(e) = fire(&store, &schedule, now).await {
+                tracing::warn!("⚠️  定时计划 {} 触发失败: {}", schedule.id, e);
+            }
+        }
+        store.next_due().await
+    }
+}
+
+async fn fire(store: &ScheduleStore<'_>, schedule: &Schedule, now: i64) -> Result<(), String> {
+    let Some(due) = schedule.next_run_at else {
+        return Ok(());
+    };
+    // 表达式在保存时已校验，无法计算下一次时也要推进，避免反复触发
+    let next = parse_cron(&schedule.cron)
+        .ok()
+        .and_then(|cron| next_after(&cron, now));
+    let state = GlobalState::get();
+    let previous = (schedule.last_run_id.as_deref()).and_then(|id| state.runs.get(id));
+
+    let replace = match decide(schedule, due, now, previous.is_some()) {
+        Action::Skip(reason) => {
+            if store.advance(&schedule.id, due, next).await? {
+                tracing::info!("⏭️  定时计划 {} {}，跳过本次执行", schedule.id, reason);
+            }
+            return Ok(());
+        }
+        Action::Run { replace } => replace,
+    };
+    let run_id = uuid::Uuid::new_v4().to_string();
+    if !store.claim(&schedule.id, due, next, &run_id, now).await? {
+        return Ok(());
+    }
+    if let (true, Some(control)) = (replace, previous) {
+        tracing::info!("⏹️  定时计划 {} 取消上一次运行", schedule.id);
+        control.cancel();
+    }
+
+    tracing::info!(
+        "⏰ 定时计划 {} 执行流程 {}（运行 {}）",
+        schedule.id,
+        schedule.flow_id,
+        run_id
+    );
+    let handle = match launch(
+        &schedule.project,
+        &schedule.flow_id,
+        schedule.inputs.clone(),
+        &run_id,
+    ) {
+        Ok(handle) => handle,
+        Err(e) => return store.record_error(&schedule.id, &run_id, &e).await,
+    };
+    let id = schedule.id.clone();
+    tokio::spawn(async move {
+        let error = match handle.await {
+            Ok(result) => result.err(),
+            Err(e) => Some(e.to_string()),
+        };
+        if let Some(error) = error {
+            let store = ScheduleStore::new(&GlobalState::get().engine_db);
+            if let Err(e) = store.record_error(&id, &run_id, &error).await {
+                tracing::warn!("⚠️  {}", e);
+            }
+        }
+    });
+    Ok(())
+}
+
+/// 校验计划引用的项目与流程
+pub fn validate(schedule: &Schedule) -> Result<(), String> {
+    let store = FlowStore::new(Project::open(&schedule.project)?);
+    store.load_local(&schedule.flow_id).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schedule(cron: &str) -> Schedule {
+        serde_json::from_value(json!({
+            "project": "/tmp/reports",
+            "flow_id": "nightly",
+            "cron": cron,
+            "inputs": { "days": 1 },
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_save_claim_and_policies() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = EngineDb::with_path(dir.path().join("engine.db"));
+        let store = ScheduleStore::new(&db);
+
+        assert!(store.save(&schedule("not a cron")).await.is_err());
+        let saved = store.save(&schedule("0 2 * * *")).await.unwrap();
+        assert!(!saved.id.is_empty());
+        assert_eq!(saved.missed, MissedPolicy::Skip);
+        assert_eq!(saved.concurrency, ConcurrencyPolicy::Forbid);
+        let due = saved.next_run_at.unwrap();
+        assert!(due > now_ms() && due - now_ms() <= 24 * 3600 * 1000);
+        assert!(store.due(due - 1).await.unwrap().is_empty());
+        assert_eq!(store.next_due().await.unwrap(), Some(due));
+
+        // 同一次执行只能认领一次
+        let next = next_after(&parse_cron(&saved.cron).unwrap(), due);
+        assert!(next.unwrap() > due);
+        assert!(store.claim(&saved.id, due, next, "r1", due).await.unwrap());
+        assert!(!store.claim(&saved.id, due, next, "r2", due).await.unwrap());
+        store.record_error(&saved.id, "r0", "stale").await.unwrap();
+        store.record_error(&saved.id, "r1", "boom").await.unwrap();
+        let claimed = store.get(&saved.id).await.unwrap().unwrap();
+        assert_eq!(claimed.last_run_id.as_deref(), Some("r1"));
+        assert_eq!(claimed.last_error.as_deref(), Some("boom"));
+        assert_eq!(claimed.next_run_at, next);
+
+        // 修改时保留上一次执行的记录，停用后不再到期
+        let mut edited = claimed.clone();
+        edited.enabled = false;
+        edited.missed = MissedPolicy::CatchUp;
+        let edited = store.save(&edited).await.unwrap();
+        assert_eq!(edited.last_run_id.as_deref(), Some("r1"));
+        assert_eq!(edited.next_run_at, None);
+        assert_eq!(edited.missed, MissedPolicy::CatchUp);
+        assert!(store.due(i64::MAX).await.unwrap().is_empty());
+        assert!(store.delete(&saved.id).await.unwrap());
+        assert!(store.list(None).await.unwrap().is_empty());
+
+        let late = due + GRACE_MS + 1;
+        let mut s = schedule("0 2 * * *");
+        assert_eq!(decide(&s, due, late, false), Action::Skip("已错过执行时间"));
+        assert_eq!(
+            decide(&s, due, due + 1000, true),
+            Action::Skip("上一次运行仍在执行")
+        );
+        s.missed = MissedPolicy::CatchUp;
+        assert_eq!(decide(&s, due, late, false), Action::Run { replace: false });
+        s.concurrency = ConcurrencyPolicy::Replace;
+        assert_eq!(decide(&s, due, late, true), Action::Run { replace: true });
+        s.concurrency = ConcurrencyPolicy::Allow;
+        assert_eq!(decide(&s, due, late, true), Action::Run { replace: false });
+    }
+}
@@ -0,0 +1,762 @@
+//! 目录监听触发：目录中出现新文件或文件被修改后，以文件路径与内容为输入执行流程
+//!
+//! 监听保存在 engine.db 的 `watches` 表中，应用启动后由 [`FolderWatchers`] 为启用的监听建立文件监听
+//! （与配置文件监听相同的 notify 防抖机制），每个监听按顺序逐个处理文件。
+//!
+//! - `patterns` 为相对于监听目录的 glob（如 `*.txt`、`**/*.md`），为空时匹配全部文件
+//! - 按内容哈希去重：处理过的内容记录在 `watch_files` 表中，内容相同的文件（包括改回之前的内容）不再执行；
+//!   运行失败时移除记录，文件再次修改后重试
+//! - 应用未运行期间新增或修改的文件在启动时补处理（只包括监听创建之后修改的文件）
+//! - 设置 `output` 时把流程输出写入输出目录，相对路径与源文件相同、扩展名替换
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use notify::event::ModifyKind;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode};
+use notify_debouncer_full::{new_debouncer, DebounceEventResult, Debouncer, RecommendedCache};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use sqlx::sqlite::SqliteRow;
+use sqlx::Row;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use super::{empty_inputs, enabled_by_default, launch};
+use crate::flow::store::FlowStore;
+use crate::flow::trace::{now_ms, FlowTrace, TraceStatus};
+use crate::project::Project;
+use crate::state::GlobalState;
+use crate::utils::enginedb::EngineDb;
+
+/// 防抖时长：文件写入完成后再处理
+const DEBOUNCE: Duration = Duration::from_secs(2);
+/// 超过该大小的文件不处理
+const MAX_FILE_BYTES: u64 = 8 * 1024 * 1024;
+
+/// 流程输出写入的位置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatchOutput {
+    /// 输出目录；相对路径相对于监听目录的上级目录，即与监听目录同级
+    pub dir: String,
+    /// 写入的输出字段；默认写入唯一的输出字段，有多个输出时写入全部输出
+    #[serde(default)]
+    pub key: Option<String>,
+    /// 输出文件的扩展名；默认文本为 `md`，其它为 `json`（格式化的 JSON）
+    #[serde(default)]
+    pub extension: Option<String>,
+}
+
+/// 一个目录监听
+///
+/// `last_*` 由监听维护，保存时忽略传入的值。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FolderWatch {
+    /// 为空时保存为新监听
+    #[serde(default)]
+    pub id: String,
+    /// 项目根目录（与运行历史中一致）
+    pub project: String,
+    pub flow_id: String,
+    /// 监听的目录（绝对路径）
+    pub dir: String,
+    #[serde(default)]
+    pub patterns: Vec<String>,
+    /// 同时监听子目录
+    #[serde(default)]
+    pub recursive: bool,
+    /// 每次执行使用的其它流程输入
+    #[serde(default = "empty_inputs")]
+    pub inputs: Value,
+    /// 文件路径对应的流程输入
+    #[serde(default = "default_path_input")]
+    pub path_input: String,
+    /// 文件内容对应的流程输入
+    #[serde(default = "default_content_input")]
+    pub content_input: String,
+    #[serde(default)]
+    pub output: Option<WatchOutput>,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub last_run_at: Option<i64>,
+    #[serde(default)]
+    pub last_run_id: Option<String>,
+    /// 上一次处理失败或无法监听目录时的错误
+    #[serde(default)]
+    pub last_error: Option<String>,
+    #[serde(default)]
+    pub created_at: i64,
+    #[serde(default)]
+    pub updated_at: i64,
+}
+
+fn default_path_input() -> String {
+    "path".to_string()
+}
+
+fn default_content_input() -> String {
+    "content".to_string()
+}
+
+impl FolderWatch {
+    fn matcher(&self) -> Result<GlobSet, String> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in &self.patterns {
+            let glob = Glob::new(pattern).map_err(|e| format!("glob `{}` 无效: {}", pattern, e))?;
+            builder.add(glob);
+        }
+        builder.build().map_err(|e| format!("glob 无效: {}", e))
+    }
+
+    fn output_dir(&self) -> Option<PathBuf> {
+        let output = self.output.as_ref()?;
+        let dir = Path::new(&output.dir);
+        if dir.is_absolute() {
+            return Some(dir.to_path_buf());
+        }
+        let watched = Path::new(&self.dir);
+        Some(watched.parent().unwrap_or(watched).join(dir))
+    }
+
+    /// 需要处理的文件返回其相对于监听目录的路径
+    fn accepts(&self, matcher: &GlobSet, path: &Path) -> Option<PathBuf> {
+        let relative = path.strip_prefix(&self.dir).ok()?;
+        if relative.as_os_str().is_empty()
+            || (!self.recursive && relative.components().count() != 1)
+        {
+            return None;
+        }
+        // 输出目录在监听目录中时不处理写入的输出
+        if self.output_dir().is_some_and(|dir| path.starts_with(dir)) {
+            return None;
+        }
+        if !self.patterns.is_empty() && !matcher.is_match(relative) {
+            return None;
+        }
+        Some(relative.to_path_buf())
+    }
+
+    fn run_inputs(&self, path: &Path, content: String) -> Value {
+        let mut inputs = self.inputs.as_object().cloned().unwrap_or_default();
+        inputs.insert(self.path_input.clone(), json!(path.to_string_lossy()));
+        inputs.insert(self.content_input.clone(), Value::String(content));
+        Value::Object(inputs)
+    }
+
+    /// 源文件（相对路径）对应的输出文件及其内容，未设置 `output` 时为 `None`
+    fn output_file(
+        &self,
+        relative: &Path,
+        outputs: &Value,
+    ) -> Result<Option<(PathBuf, String)>, String> {
+        let (Some(output), Some(dir)) = (&self.output, self.output_dir()) else {
+            return Ok(None);
+        };
+        let value = match (&output.key, outputs.as_object()) {
+            (Some(key), _) => {
+                (outputs.get(key).cloned()).ok_or_else(|| format!("流程输出中没有 `{}`", key))?
+            }
+            (None, Some(map)) if map.len() == 1 => map.values().next().cloned().unwrap_or_default(),
+            (None, _) => outputs.clone(),
+        };
+        let (content, extension) = match value {
+            Value::String(text) => (text, "md"),
+            other => (
+                serde_json::to_string_pretty(&other).map_err(|e| e.to_string())?,
+                "json",
+            ),
+        };
+        let extension = match &output.extension {
+            Some(extension) => extension.trim_start_matches('.'),
+            None => extension,
+        };
+        Ok(Some((
+            dir.join(relative).with_extension(extension),
+            content,
+        )))
+    }
+}
+
+/// engine.db 中的目录监听与已处理的文件
+pub struct WatchStore<'a> {
+    db: &'a EngineDb,
+}
+
+const SELECT: &str = "SELECT id, project, flow_id, dir, patterns, recursive, inputs, path_input,
+     content_input, output, enabled, last_run_at, last_run_id, last_error, created_at, updated_at
+ FROM watches";
+
+impl<'a> WatchStore<'a> {
+    pub fn new(db: &'a EngineDb) -> Self {
+        Self { db }
+    }
+
+    /// 全部监听（可按项目过滤），按创建时间排序
+    pub async fn list(&self, project: Option<&str>) -> Result<Vec<FolderWatch>, String> {
+        let rows = match project {
+            Some(project) => {
+                sqlx::query(&format!("{} WHERE project = ? ORDER BY created_at", SELECT))
+                    .bind(project)
+                    .fetch_all(self.db.pool().await?)
+                    .await
+            }
+            None => {
+                sqlx::query(&format!("{} ORDER BY created_at", SELECT))
+                    .fetch_all(self.db.pool().await?)
+                    .await
+            }
+        }
+        .map_err(|e| format!("读取目录监听失败: {}", e))?;
+        rows.iter().map(from_row).collect()
+    }
+
+    pub async fn get(&self, id: &str) -> Result<Option<FolderWatch>, String> {
+        let row = sqlx::query(&format!("{} WHERE id = ?", SELECT))
+            .bind(id)
+            .fetch_optional(self.db.pool().await?)
+            .await
+            .map_err(|e| format!("读取目录监听失败: {}", e))?;
+        row.as_ref().map(from_row).transpose()
+    }
+
+    /// 新增或修改监听，返回保存后的监听
+    ///
+    /// 只校验 glob 与输入，目录与引用的流程见 [`validate`]。已处理的文件记录保持不变。
+    pub async fn save(&self, watch: &FolderWatch) -> Result<FolderWatch, String> {
+        watch.matcher()?;
+        if !watch.inputs.is_object() {
+            return Err("流程输入必须是对象".to_string());
+        }
+        if watch.path_input.is_empty()
+            || watch.content_input.is_empty()
+            || watch.path_input == watch.content_input
+        {
+            return Err("文件路径与内容必须对应两个不同的流程输入".to_string());
+        }
+        let now = now_ms();
+        let id = match watch.id.is_empty() {
+            true => uuid::Uuid::new_v4().to_string(),
+            false => watch.id.clone(),
+        };
+        let output = match &watch.output {
+            Some(output) => Some(serde_json::to_string(output).map_err(|e| e.to_string())?),
+            None => None,
+        };
+        sqlx::query(
+            "INSERT INTO watches (id, project, flow_id, dir, patterns, recursive, inputs, path_input,
+                 content_input, output, enabled, created_at, updated_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT(id) DO UPDATE SET
+                 project = excluded.project,
+                 flow_id = excluded.flow_id,
+                 dir = excluded.dir,
+                 patterns = excluded.patterns,
+                 recursive = excluded.recursive,
+                 inputs = excluded.inputs,
+                 path_input = excluded.path_input,
+                 content_input = excluded.content_input,
+                 output = excluded.output,
+                 enabled = excluded.enabled,
+                 updated_at = excluded.updated_at",
+        )
+        .bind(&id)
+        .bind(&watch.project)
+        .bind(&watch.flow_id)
+        .bind(&watch.dir)
+        .bind(json!(watch.patterns).to_string())
+        .bind(watch.recursive)
+        .bind(watch.inputs.to_string())
+        .bind(&watch.path_input)
+        .bind(&watch.content_input)
+        .bind(output)
+        .bind(watch.enabled)
+        .bind(now)
+        .bind(now)
+        .execute(self.db.pool().await?)
+        .await
+        .map_err(|e| format!("保存目录监听失败: {}", e))?;
+        self.get(&id)
+            .await?
+            .ok_or_else(|| format!("目录监听 {} 不存在", id))
+    }
+
+    /// 删除监听及其已处理的文件记录，返回是否存在
+    pub async fn delete(&self, id: &str) -> Result<bool, String> {
+        sqlx::query("DELETE FROM watches WHERE id = ?")
+            .bind(id)
+            .execute(self.db.pool().await?)
+            .await
+            .map(|r| r.rows_affected() > 0)
+            .map_err(|e| format!("删除目录监听失败: {}", e))
+    }
+
+    /// 认领文件内容并记录本次运行，返回是否认领成功（内容已处理过时为 `false`）
+    pub async fn claim_file(
+        &self,
+        id: &str,
+        hash: &str,
+        path: &Path,
+        run_id: &str,
+        at: i64,
+    ) -> Result<bool, String> {
+        let claimed = sqlx::query(
+            "INSERT OR IGNORE INTO watch_files (watch_id, hash, path, run_id, processed_at)
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(id)
+        .bind(hash)
+        .bind(path.to_string_lossy())
+        .bind(run_id)
+        .bind(at)
+        .execute(self.db.pool().await?)
+        .await
+        .map(|r| r.rows_affected() > 0)
+        .map_err(|e| format!("记录已处理的文件失败: {}", e))?;
+        if claimed {
+            sqlx::query(
+                "UPDATE watches SET last_run_at = ?, last_run_id = ?, last_error = NULL
+                 WHERE id = ?",
+            )
+            .bind(at)
+            .bind(run_id)
+            .bind(id)
+            .execute(self.db.pool().await?)
+            .await
+            .map_err(|e| format!("更新目录监听失败: {}", e))?;
+        }
+        Ok(claimed)
+    }
+
+    /// 移除文件内容的处理记录，之后相同内容会再次处理
+    pub async fn release_file(&self, id: &str, hash: &str) -> Result<(), String> {
+        sqlx::query("DELETE FROM watch_files WHERE watch_id = ? AND hash = ?")
+            .bind(id)
+            .bind(hash)
+            .execute(self.db.pool().await?)
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("移除已处理的文件失败: {}", e))
+    }
+
+    /// 记录错误
+    pub async fn record_error(&self, id: &str, error: &str) -> Result<(), String> {
+        sqlx::query("UPDATE watches SET last_error = ? WHERE id = ?")
+            .bind(error)
+            .bind(id)
+            .execute(self.db.pool().await?)
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("更新目录监听失败: {}", e))
+    }
+}
+
+fn from_row(row: &SqliteRow) -> Result<FolderWatch, String> {
+    let output: Option<String> = row.get("output");
+    Ok(FolderWatch {
+        id: row.get("id"),
+        project: row.get("project"),
+        flow_id: row.get("flow_id"),
+        dir: row.get("dir"),
+        patterns: serde_json::from_str(row.get("patterns"))
+            .map_err(|e| format!("glob 列表格式错误: {}", e))?,
+        recursive: row.get("recursive"),
+        inputs: serde_json::from_str(row.get("inputs")).unwrap_or_else(|_| empty_inputs()),
+        path_input: row.get("path_input"),
+        content_input: row.get("content_input"),
+        output: match output {
+            Some(output) => Some(
+                serde_json::from_str(&output).map_err(|e| format!("输出设置格式错误: {}", e))?,
+            ),
+            None => None,
+        },
+        enabled: row.get("enabled"),
+        last_run_at: row.get("last_run_at"),
+        last_run_id: row.get("last_run_id"),
+        last_error: row.get("last_error"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    })
+}
+
+/// 校验监听的目录与引用的项目、流程
+pub fn validate(watch: &FolderWatch) -> Result<(), String> {
+    let dir = Path::new(&watch.dir);
+    if !dir.is_absolute() || !dir.is_dir() {
+        return Err(format!("监听的目录 {:?} 不存在", dir));
+    }
+    if watch.output_dir().is_some_and(|output| output == dir) {
+        return Err("输出目录不能是监听的目录".to_string());
+    }
+    let store = FlowStore::new(Project::open(&watch.project)?);
+    store.load_local(&watch.flow_id).map(|_| ())
+}
+
+/// 已建立的监听：防抖器被 drop 时停止监听
+type Active = HashMap<
+    String,
+    (
+        Debouncer<RecommendedWatcher, RecommendedCache>,
+        JoinHandle<()>,
+    ),
+>;
+
+/// 全部已建立的目录监听
+#[derive(Default)]
+pub struct FolderWatchers {
+    active: Mutex<Active>,
+}
+
+impl std::fmt::Debug for FolderWatchers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FolderWatchers").finish_non_exhaustive()
+    }
+}
+
+impl FolderWatchers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 为全部启用的监听建立文件监听（应用启动时调用）
+    pub async fn start_all(&self) {
+        let store = WatchStore::new(&GlobalState::get().engine_db);
+        let watches = match store.list(None).await {
+            Ok(watches) => watches,
+            Err(e) => {
+                tracing::warn!("⚠️  {}", e);
+                return;
+            }
+        };
+        for watch in watches.into_iter().filter(|watch| watch.enabled) {
+            let id = watch.id.clone();
+            if let Err(e) = self.start(watch) {
+                tracing::warn!("⚠️  目录监听 {} 启动失败: {}", id, e);
+                if let Err(e) = store.record_error(&id, &e).await {
+                    tracing::warn!("⚠️  {}", e);
+                }
+            }
+        }
+    }
+
+    /// 按保存的设置重新建立监听；已停用或删除的监听只停止
+    pub async fn reload(&self, id: &str) -> Result<(), String> {
+        self.stop(id);
+        let store = WatchStore::new(&GlobalState::get().engine_db);
+        match store.get(id).await? {
+            Some(watch) if watch.enabled => self.start(watch),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn stop(&self, id: &str) {
+        let removed = self.lock().remove(id);
+        if let Some((_, task)) = removed {
+            task.abort();
+            tracing::info!("⏹️  目录监听 {} 已停止", id);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Active> {
+        self.active.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn start(&self, watch: FolderWatch) -> Result<(), String> {
+        let matcher = watch.matcher()?;
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut debouncer = new_debouncer(DEBOUNCE, None, move |result: DebounceEventResult| {
+            match result {
+                Ok(events) => {
+                    for event in events {
+                        // 只处理新建、写入与移入，读取文件产生的访问事件会被忽略
+                        let changed = match event.kind {
+                            EventKind::Create(_) => true,
+                            EventKind::Modify(kind) => !matches!(kind, ModifyKind::Metadata(_)),
+                            _ => false,
+                        };
+                        if changed {
+                            for path in &event.paths {
+                                let _ = tx.send(path.clone());
+                            }
+                        }
+                    }
+                }
+                Err(errors) => {
+                    for e in errors {
+                        tracing::error!("目录监听错误: {:?}", e);
+                    }
+                }
+            }
+        })
+        .map_err(|e| format!("创建目录监听失败: {}", e))?;
+        let mode = match watch.recursive {
+            true => RecursiveMode::Recursive,
+            false => RecursiveMode::NonRecursive,
+        };
+        debouncer
+            .watch(Path::new(&watch.dir), mode)
+            .map_err(|e| format!("监听目录 {} 失败: {}", watch.dir, e))?;
+
+        tracing::info!("✅ 目录监听 {} 开始监听 {}", watch.id, watch.dir);
+        let id = watch.id.clone();
+        let task = tokio::spawn(process(watch, matcher, rx));
+        if let Some((_, previous)) = self.lock().insert(id, (debouncer, task)) {
+            previous.abort();
+        }
+        Ok(())
+    }
+}
+
+/// 按顺序处理监听到的文件：先补处理应用未运行期间修改的文件
+async fn process(watch: FolderWatch, matcher: GlobSet, mut rx: mpsc::UnboundedReceiver<PathBuf>) {
+    let mut pending = Vec::new();
+    modified_since(
+        Path::new(&watch.dir),
+        watch.recursive,
+        watch.created_at,
+        &mut pending,
+    );
+    pending.sort();
+    for path in pending {
+        handle(&watch, &matcher, &path).await;
+    }
+    while let Some(path) = rx.recv().await {
+        handle(&watch, &matcher, &path).await;
+    }
+}
+
+/// 目录中修改时间不早于 `since`（毫秒时间戳）的文件
+fn modified_since(dir: &Path, recursive: bool, since: i64, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        if meta.is_dir() {
+            if recursive {
+                modified_since(&entry.path(), recursive, since, files);
+            }
+            continue;
+        }
+        let modified = (meta.modified().ok())
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|time| time.as_millis() as i64);
+        if modified.is_some_and(|modified| modified >= since) {
+            files.push(entry.path());
+        }
+    }
+}
+
+async fn handle(watch: &FolderWatch, matcher: &GlobSet, path: &Path) {
+    let Some(relative) = watch.accepts(matcher, path) else {
+        return;
+    };
+    if let Err(e) = process_file(watch, path, &relative).await {
+        tracing::warn!("⚠️  目录监听 {} 处理 {:?} 失败: {}", watch.id, path, e);
+    }
+}
+
+async fn process_file(watch: &FolderWatch, path: &Path, relative: &Path) -> Result<(), String> {
+    // 已删除、目录或正在创建的空文件
+    let Ok(meta) = std::fs::metadata(path) else {
+        return Ok(());
+    };
+    if !meta.is_file() || meta.len() == 0 {
+        return Ok(());
+    }
+    if meta.len() > MAX_FILE_BYTES {
+        return Err(format!("文件超过 {} 字节，已跳过", MAX_FILE_BYTES));
+    }
+    let bytes = std::fs::read(path).map_err(|e| format!("读取文件失败: {}", e))?;
+    let hash = format!("{:x}", Sha256::digest(&bytes));
+    let content = String::from_utf8(bytes).map_err(|_| "文件不是 UTF-8 文本，已跳过")?;
+
+    let store = WatchStore::new(&GlobalState::get().engine_db);
+    let run_id = uuid::Uuid::new_v4().to_string();
+    if !store
+        .claim_file(&watch.id, &hash, path, &run_id, now_ms())
+        .await?
+    {
+        tracing::debug!("目录监听 {} 已处理过 {:?} 的内容", watch.id, path);
+        return Ok(());
+    }
+    tracing::info!(
+        "📂 目录监听 {} 执行流程 {}: {:?}（运行 {}）",
+        watch.id,
+        watch.flow_id,
+        path,
+        run_id
+    );
+    let inputs = watch.run_inputs(path, content);
+    let result = match launch(&watch.project, &watch.flow_id, inputs, &run_id) {
+        Ok(handle) => handle.await.unwrap_or_else(|e| Err(e.to_string())),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result.and_then(|trace| write_output(watch, relative, &trace)) {
+        // 失败的文件再次修改后重试
+        store.release_file(&watch.id, &hash).await?;
+        store.record_error(&watch.id, &e).await?;
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// 把成功结束的运行的输出写入输出目录
+///
+/// 暂停或等待人工输入的运行没有输出，按失败处理，文件再次修改后重新运行。
+fn write_output(watch: &FolderWatch, relative: &Path, trace: &FlowTrace) -> Result<(), String> {
+    if trace.status != TraceStatus::Succeeded {
+        return Err(format!(
+            "运行以 {} 状态结束，未写入输出",
+            trace.status.as_str()
+        ));
+    }
+    let outputs = trace.outputs.clone().unwrap_or_else(empty_inputs);
+    let Some((file, content)) = watch.output_file(relative, &outputs)? else {
+        return Ok(());
+    };
+    if let Some(dir) = file.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("创建目录 {:?} 失败: {}", dir, e))?;
+    }
+    std::fs::write(&file, content).map_err(|e| format!("写入 {:?} 失败: {}", file, e))?;
+    tracing::info!("✅ 目录监听 {} 已写入 {:?}", watch.id, file);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn watch(dir: &Path) -> FolderWatch {
+        serde_json::from_value(json!({
+            "project": "/tmp/notes",
+            "flow_id": "summarize",
+            "dir": dir.join("transcripts"),
+            "patterns": ["*.txt", "*.vtt"],
+            "inputs": { "language": "zh" },
+            "output": { "dir": "summaries" },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_accepts_inputs_and_output_file() {
+        let root = Path::new("/data");
+        let mut watch = watch(root);
+        let matcher = watch.matcher().unwrap();
+        let dir = root.join("transcripts");
+
+        assert_eq!(
+            watch.accepts(&matcher, &dir.join("a.txt")),
+            Some(PathBuf::from("a.txt"))
+        );
+        assert_eq!(watch.accepts(&matcher, &dir.join("a.md")), None);
+        assert_eq!(watch.accepts(&matcher, &dir.join("sub/b.txt")), None);
+        assert_eq!(watch.accepts(&matcher, &root.join("other/a.txt")), None);
+        watch.recursive = true;
+        assert_eq!(
+            watch.accepts(&matcher, &dir.join("sub/b.txt")),
+            Some(PathBuf::from("sub/b.txt"))
+        );
+        // 监听目录中的输出目录
+        watch.output = Some(WatchOutput {
+            dir: dir.join("out").to_string_lossy().into_owned(),
+            key: None,
+            extension: None,
+        });
+        assert_eq!(watch.accepts(&matcher, &dir.join("out/a.txt")), None);
+
+        let inputs = watch.run_inputs(&dir.join("a.txt"), "hello".into());
+        assert_eq!(inputs["language"], "zh");
+        assert_eq!(inputs["content"], "hello");
+        assert!(inputs["path"].as_str().unwrap().ends_with("a.txt"));
+
+        let watch = self::watch(root);
+        let (file, content) = (watch
+            .output_file(Path::new("sub/a.txt"), &json!({ "summary": "# 摘要" })))
+        .unwrap()
+        .unwrap();
+        assert_eq!(file, root.join("summaries/sub/a.md"));
+        assert_eq!(content, "# 摘要");
+        let (file, content) = (watch.output_file(Path::new("a.txt"), &json!({ "a": 1, "b": 2 })))
+            .unwrap()
+            .unwrap();
+        assert_eq!(file, root.join("summaries/a.json"));
+        assert_eq!(serde_json::from_str::<Value>(&content).unwrap()["b"], 2);
+
+        let mut keyed = watch.clone();
+        keyed.output = Some(WatchOutput {
+            dir: "summaries".into(),
+            key: Some("missing".into()),
+            extension: Some(".txt".into()),
+        });
+        assert!(keyed.output_file(Path::new("a.txt"), &json!({})).is_err());
+        let (file, _) = (keyed.output_file(Path::new("a.txt"), &json!({ "missing": "x" })))
+            .unwrap()
+            .unwrap();
+        assert_eq!(file, root.join("summaries/a.txt"));
+    }
+
+    #[test]
+    fn test_unfinished_run_writes_no_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let watch = watch(dir.path());
+        let mut trace = FlowTrace::new("summarize", "", json!({}));
+        trace.pause();
+        let error = write_output(&watch, Path::new("a.txt"), &trace).unwrap_err();
+        assert!(error.contains("paused"));
+        assert!(!dir.path().join("summaries").exists());
+
+        trace.succeed(json!({ "summary": "# 摘要" }));
+        write_output(&watch, Path::new("a.txt"), &trace).unwrap();
+        let written = std::fs::read_to_string(dir.path().join("summaries/a.md")).unwrap();
+        assert_eq!(written, "# 摘要");
+    }
+
+    #[tokio::test]
+    async fn test_files_deduplicated_by_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = EngineDb::with_path(dir.path().join("engine.db"));
+        let store = WatchStore::new(&db);
+
+        let mut invalid = watch(dir.path());
+        invalid.patterns = vec!["[".into()];
+        assert!(store.save(&invalid).await.is_err());
+        let saved = store.save(&watch(dir.path())).await.unwrap();
+        assert_eq!(saved.patterns, ["*.txt", "*.vtt"]);
+        assert_eq!(saved.path_input, "path");
+        assert_eq!(saved.output.as_ref().unwrap().dir, "summaries");
+
+        let path = Path::new("/data/transcripts/a.txt");
+        assert!(store
+            .claim_file(&saved.id, "h1", path, "r1", 1)
+            .await
+            .unwrap());
+        assert!(!store
+            .claim_file(&saved.id, "h1", path, "r2", 2)
+            .await
+            .unwrap());
+        store.record_error(&saved.id, "boom").await.unwrap();
+        let claimed = store.get(&saved.id).await.unwrap().unwrap();
+        assert_eq!(claimed.last_run_id.as_deref(), Some("r1"));
+        assert_eq!(claimed.last_error.as_deref(), Some("boom"));
+
+        // 失败后移除记录，相同内容再次处理
+        store.release_file(&saved.id, "h1").await.unwrap();
+        assert!(store
+            .claim_file(&saved.id, "h1", path, "r3", 3)
+            .await
+            .unwrap());
+        assert!(store.delete(&saved.id).await.unwrap());
+        let saved = store.save(&watch(dir.path())).await.unwrap();
+        assert!(store
+            .claim_file(&saved.id, "h1", path, "r4", 4)
+            .await
+            .unwrap());
+    }
+}
@@ -0,0 +1,167 @@
+//! Rust 侧访问 app.db
+//!
+//! 与前端（tauri-plugin-sql）共用 `<app_config_dir>/app.db`，表结构由 `utils::sql` 中的迁移维护。
+//! 后端以只读查询为主；命令行模式下需要维护项目登记，因此也提供与前端 `appDB` 一致的写入
+//! （写入后记录到 change 表，由调用方触发变更信号通知正在运行的实例）。
+
+use serde_json::Value;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::Row;
+use std::path::PathBuf;
+use tokio::sync::OnceCell;
+
+/// 与 tauri.conf.json 中的 identifier 保持一致
+pub const APP_IDENTIFIER: &str = "vlogi.cc";
+
+/// 应用配置目录（与 Tauri 的 `app_config_dir()` 一致，无需 AppHandle 即可获取）
+pub fn app_config_dir() -> Option<PathBuf> {
+    directories::BaseDirs::new().map(|dirs| dirs.config_dir().join(APP_IDENTIFIER))
+}
+
+/// 与 `utils::sql` 中的迁移一致；应用从未启动过时（如新机器上直接使用命令行）由后端建表
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS config (
+        id TEXT PRIMARY KEY NOT NULL,
+        key TEXT NOT NULL,
+        value TEXT NOT NULL,
+        created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+        updated_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+    );
+    CREATE INDEX IF NOT EXISTS idx_config_key ON config(key);
+    CREATE TRIGGER IF NOT EXISTS update_config_timestamp
+    AFTER UPDATE ON config
+    FOR EACH ROW
+    BEGIN
+        UPDATE config SET updated_at = strftime('%s', 'now')
+        WHERE id = NEW.id;
+    END;
+    CREATE TABLE IF NOT EXISTS change (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        key TEXT NOT NULL,
+        cfgid TEXT,
+        ctime INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+    );
+    CREATE INDEX IF NOT EXISTS idx_change_key ON change(key);
+    CREATE INDEX IF NOT EXISTS idx_change_ctime ON change(ctime);
+";
+
+/// config 表中的一条记录
+#[derive(Debug, Clone)]
+pub struct ConfigItem {
+    pub id: String,
+    pub value: Value,
+    /// 创建时间（秒）
+    pub created_at: i64,
+}
+
+/// app.db 连接池（首次使用时才建立连接）
+#[derive(Debug, Default)]
+pub struct AppDb {
+    /// 数据库文件路径，为空时使用 `<app_config_dir>/app.db`
+    path: Option<PathBuf>,
+    pool: OnceCell<SqlitePool>,
+}
+
+impl AppDb {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 使用指定的数据库文件
+    #[cfg(test)]
+    pub fn with_path(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Some(path.into()),
+            pool: OnceCell::new(),
+        }
+    }
+
+    /// 获取连接池
+    pub async fn pool(&self) -> Result<&SqlitePool, String> {
+        self.pool
+            .get_or_try_init(|| async {
+                let path = match &self.path {
+                    Some(path) => path.clone(),
+                    None => app_config_dir()
+                        .ok_or("无法确定应用配置目录")?
+                        .join("app.db"),
+                };
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir)
+                        .map_err(|e| format!("创建配置目录 {:?} 失败: {}", dir, e))?;
+                }
+                let options = SqliteConnectOptions::new()
+                    .filename(&path)
+                    .create_if_missing(true);
+                let pool = SqlitePoolOptions::new()
+                    .max_connections(4)
+                    .connect_with(options)
+                    .await
+                    .map_err(|e| format!("打开 app.db 失败: {}", e))?;
+                sqlx::raw_sql(SCHEMA)
+                    .execute(&pool)
+                    .await
+                    .map_err(|e| format!("初始化 app.db 失败: {}", e))?;
+                Ok(pool)
+            })
+            .await
+    }
+
+    /// 按 key 读取配置（value 与前端一致按 JSON5 解析）
+    pub async fn get_configs_by_key(&self, key: &str) -> Result<Vec<ConfigItem>, String> {
+        let pool = self.pool().await?;
+        let rows = sqlx::query(
+            "SELECT id, value, created_at FROM config WHERE key = ? ORDER BY created_at",
+        )
+        .bind(key)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("查询配置 {} 失败: {}", key, e))?;
+
+        let mut items = Vec::with_capacity(rows.len());
+        for row in rows {
+            let raw: String = row.get("value");
+            let value = match json5::from_str::<Value>(&raw) {
+                Ok(v) => v,
+                Err(e) => {
+                    tracing::warn!("⚠️  配置 {} 的值无法解析，已忽略: {}", key, e);
+                    continue;
+                }
+            };
+            items.push(ConfigItem {
+                id: row.get("id"),
+                value,
+                created_at: row.get("created_at"),
+            });
+        }
+        Ok(items)
+    }
+
+    /// 按 id 新增或替换配置，并记录变更
+    pub async fn upsert_by_id(&self, id: &str, key: &str, value: &Value) -> Result<(), String> {
+        let pool = self.pool().await?;
+        sqlx::query(
+            "INSERT INTO config (id, key, value) VALUES (?, ?, ?)
+             ON CONFLICT(id) DO UPDATE SET key = excluded.key, value = excluded.value",
+        )
+        .bind(id)
+        .bind(key)
+        .bind(value.to_string())
+        .execute(pool)
+        .await
+        .map_err(|e| format!("写入配置 {} 失败: {}", key, e))?;
+        self.record_change(key, id).await
+    }
+
+    /// 记录变更，前端据此只重新加载变化的 key
+    async fn record_change(&self, key: &str, id: &str) -> Result<(), String> {
+        let pool = self.pool().await?;
+        sqlx::query("INSERT INTO change (key, cfgid) VALUES (?, ?)")
+            .bind(key)
+            .bind(id)
+            .execute(pool)
+            .await
+            .map_err(|e| format!("记录配置变更失败: {}", e))?;
+        Ok(())
+    }
+}
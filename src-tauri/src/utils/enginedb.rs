@@ -0,0 +1,262 @@
+//! 后端专用数据库 engine.db
+//!
+//! app.db 的表结构由前端插件迁移维护，后端写入的运行数据（运行历史、节点输出缓存等）
+//! 放在独立的 `<app_config_dir>/engine.db` 中，由这里的迁移列表维护，版本号记录在 `PRAGMA user_version`。
+
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions};
+use std::path::PathBuf;
+use tokio::sync::OnceCell;
+
+use super::appdb::app_config_dir;
+
+/// 迁移：(版本, 说明, SQL)，版本号必须递增
+const MIGRATIONS: &[(i64, &str, &str)] = &[
+    (
+        1,
+        "create runs and run_nodes tables",
+        "
+        CREATE TABLE IF NOT EXISTS runs (
+            id TEXT PRIMARY KEY NOT NULL,
+            project TEXT NOT NULL,
+            flow_id TEXT NOT NULL,
+            flow_version TEXT NOT NULL,
+            status TEXT NOT NULL,
+            inputs TEXT NOT NULL,
+            outputs TEXT,
+            error TEXT,
+            started_at INTEGER NOT NULL,
+            finished_at INTEGER,
+            prompt_tokens INTEGER NOT NULL DEFAULT 0,
+            completion_tokens INTEGER NOT NULL DEFAULT 0,
+            trace TEXT
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_runs_project ON runs(project, started_at);
+        CREATE INDEX IF NOT EXISTS idx_runs_flow ON runs(flow_id, started_at);
+
+        CREATE TABLE IF NOT EXISTS run_nodes (
+            run_id TEXT NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+            path TEXT NOT NULL,
+            seq INTEGER NOT NULL,
+            node_id TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            status TEXT NOT NULL,
+            rendered_prompt TEXT,
+            response TEXT,
+            output TEXT,
+            error TEXT,
+            started_at INTEGER,
+            finished_at INTEGER,
+            prompt_tokens INTEGER NOT NULL DEFAULT 0,
+            completion_tokens INTEGER NOT NULL DEFAULT 0,
+            attempts INTEGER,
+            PRIMARY KEY (run_id, path)
+        );
+    ",
+    ),
+    (
+        2,
+        "create node_cache table",
+        "
+        CREATE TABLE IF NOT EXISTS node_cache (
+            project TEXT NOT NULL,
+            key TEXT NOT NULL,
+            node_id TEXT NOT NULL,
+            entry TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            created_at INTEGER NOT NULL,
+            expires_at INTEGER NOT NULL,
+            last_hit_at INTEGER NOT NULL,
+            hits INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (project, key)
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_node_cache_expires ON node_cache(expires_at);
+    ",
+    ),
+    (
+        3,
+        "add cost columns",
+        "
+        ALTER TABLE runs ADD COLUMN cost REAL NOT NULL DEFAULT 0.0;
+        ALTER TABLE run_nodes ADD COLUMN cost REAL;
+    ",
+    ),
+    (
+        4,
+        "create schedules table",
+        "
+        CREATE TABLE IF NOT EXISTS schedules (
+            id TEXT PRIMARY KEY NOT NULL,
+            project TEXT NOT NULL,
+            flow_id TEXT NOT NULL,
+            cron TEXT NOT NULL,
+            inputs TEXT NOT NULL,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            missed TEXT NOT NULL,
+            concurrency TEXT NOT NULL,
+            last_run_at INTEGER,
+            last_run_id TEXT,
+            last_error TEXT,
+            next_run_at INTEGER,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_schedules_next ON schedules(enabled, next_run_at);
+    ",
+    ),
+    (
+        5,
+        "create watches and watch_files tables",
+        "
+        CREATE TABLE IF NOT EXISTS watches (
+            id TEXT PRIMARY KEY NOT NULL,
+            project TEXT NOT NULL,
+            flow_id TEXT NOT NULL,
+            dir TEXT NOT NULL,
+            patterns TEXT NOT NULL,
+            recursive INTEGER NOT NULL DEFAULT 0,
+            inputs TEXT NOT NULL,
+            path_input TEXT NOT NULL,
+            content_input TEXT NOT NULL,
+            output TEXT,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            last_run_at INTEGER,
+            last_run_id TEXT,
+            last_error TEXT,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS watch_files (
+            watch_id TEXT NOT NULL REFERENCES watches(id) ON DELETE CASCADE,
+            hash TEXT NOT NULL,
+            path TEXT NOT NULL,
+            run_id TEXT,
+            processed_at INTEGER NOT NULL,
+            PRIMARY KEY (watch_id, hash)
+        );
+    ",
+    ),
+    (
+        6,
+        "create memories and memory_changes tables",
+        "
+        CREATE TABLE IF NOT EXISTS memories (
+            id TEXT PRIMARY KEY NOT NULL,
+            profile TEXT NOT NULL,
+            project TEXT NOT NULL DEFAULT '',
+            kind TEXT NOT NULL,
+            content TEXT NOT NULL,
+            tags TEXT NOT NULL DEFAULT '[]',
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL,
+            forgotten_at INTEGER
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_memories_profile ON memories(profile, project);
+
+        CREATE TABLE IF NOT EXISTS memory_changes (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            memory_id TEXT NOT NULL,
+            profile TEXT NOT NULL,
+            action TEXT NOT NULL,
+            before TEXT,
+            after TEXT,
+            run_id TEXT,
+            node_id TEXT,
+            at INTEGER NOT NULL,
+            reverted_at INTEGER
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_memory_changes_memory ON memory_changes(memory_id, id);
+        CREATE INDEX IF NOT EXISTS idx_memory_changes_run ON memory_changes(run_id);
+    ",
+    ),
+];
+
+/// engine.db 连接池（首次使用时建立连接并执行迁移）
+#[derive(Debug, Default)]
+pub struct EngineDb {
+    /// 数据库文件路径，为空时使用 `<app_config_dir>/engine.db`
+    path: Option<PathBuf>,
+    pool: OnceCell<SqlitePool>,
+}
+
+impl EngineDb {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 使用指定的数据库文件
+    #[cfg(test)]
+    pub fn with_path(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Some(path.into()),
+            pool: OnceCell::new(),
+        }
+    }
+
+    /// 获取连接池
+    pub async fn pool(&self) -> Result<&SqlitePool, String> {
+        self.pool
+            .get_or_try_init(|| async {
+                let path = match &self.path {
+                    Some(path) => path.clone(),
+                    None => app_config_dir()
+                        .ok_or("无法确定应用配置目录")?
+                        .join("engine.db"),
+                };
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir)
+                        .map_err(|e| format!("创建目录 {:?} 失败: {}", dir, e))?;
+                }
+                let options = SqliteConnectOptions::new()
+                    .filename(&path)
+                    .create_if_missing(true)
+                    .journal_mode(SqliteJournalMode::Wal)
+                    .foreign_keys(true);
+                let pool = SqlitePoolOptions::new()
+                    .max_connections(4)
+                    .connect_with(options)
+                    .await
+                    .map_err(|e| format!("打开 engine.db 失败: {}", e))?;
+                migrate(&pool).await?;
+                Ok(pool)
+            })
+            .await
+    }
+}
+
+/// 执行尚未应用的迁移
+async fn migrate(pool: &SqlitePool) -> Result<(), String> {
+    let current: i64 = sqlx::query_scalar("PRAGMA user_version")
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("读取 engine.db 版本失败: {}", e))?;
+
+    for &(version, description, sql) in MIGRATIONS {
+        if version <= current {
+            continue;
+        }
+        let mut tx = pool
+            .begin()
+            .await
+            .map_err(|e| format!("开启迁移事务失败: {}", e))?;
+        sqlx::query(sql)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("engine.db 迁移 {} ({}) 失败: {}", version, description, e))?;
+        // PRAGMA 不支持参数绑定，版本号来自常量
+        sqlx::query(&format!("PRAGMA user_version = {}", version))
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("更新 engine.db 版本失败: {}", e))?;
+        tx.commit()
+            .await
+            .map_err(|e| format!("提交迁移事务失败: {}", e))?;
+        tracing::info!("✅ engine.db 已迁移到版本 {}: {}", version, description);
+    }
+    Ok(())
+}
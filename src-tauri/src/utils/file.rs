@@ -0,0 +1,32 @@
+//! 文件写入工具
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// 原子地写入只有当前用户可读写的文件（unix 下权限为 0600），父目录不存在时创建
+///
+/// 先写入同目录下的 `<文件名>.tmp` 再重命名。临时文件创建时即设置权限，写入期间其他用户也无法读取；
+/// 上次写入中断留下的临时文件先删除。
+pub fn write_private(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    let failed = |e: std::io::Error| format!("写入 {:?} 失败: {}", path, e);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("创建目录 {:?} 失败: {}", dir, e))?;
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    let _ = std::fs::remove_file(&tmp);
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(&tmp)
+        .and_then(|mut f| f.write_all(bytes))
+        .map_err(failed)?;
+    std::fs::rename(&tmp, path).map_err(failed)
+}
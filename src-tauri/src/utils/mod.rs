@@ -2,6 +2,9 @@
 
 // use crate::state::{self, GlobalState};
 
+pub mod appdb;
+pub mod enginedb;
+pub mod file;
 pub mod sql;
 pub mod file_watcher;
 pub mod message;
@@ -0,0 +1,442 @@
+//! 加密密钥库：保存 provider API Key 等敏感值
+//!
+//! 密钥保存在 `<app_config_dir>/vault.json`，整体以 XChaCha20-Poly1305 加密，
+//! 密钥由用户口令经 Argon2id 派生；口令错误或文件被篡改时解密失败。
+//! 解锁后明文只保存在后端内存中（锁定时清零），前端只能拿到打码后的值，日志中只出现密钥名。
+//! provider 配置与 agent 节点通过 `api_key_secret` 按名称引用密钥。
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use zeroize::Zeroizing;
+
+use crate::flow::trace::now_ms;
+use crate::utils::appdb::app_config_dir;
+use crate::utils::file::write_private;
+
+/// 文件格式版本
+const VERSION: u32 = 1;
+/// 附加认证数据，绑定文件格式
+const AAD: &[u8] = b"vlogi-vault-v1";
+
+/// 列表与前端展示用的密钥信息（值已打码）
+#[derive(Debug, Clone, Serialize)]
+pub struct SecretInfo {
+    pub name: String,
+    pub masked: String,
+    pub updated_at: i64,
+}
+
+/// 密钥库状态
+#[derive(Debug, Clone, Serialize)]
+pub struct VaultStatus {
+    /// 密钥库文件是否已创建
+    pub exists: bool,
+    pub unlocked: bool,
+}
+
+/// Argon2id 参数，随文件保存，便于以后调整强度
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct KdfParams {
+    /// 内存（KiB）
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+    salt: String,
+}
+
+impl KdfParams {
+    fn generate() -> Self {
+        let defaults = Params::default();
+        Self {
+            m_cost: defaults.m_cost(),
+            t_cost: defaults.t_cost(),
+            p_cost: defaults.p_cost(),
+            salt: BASE64.encode(rand::random::<[u8; 16]>()),
+        }
+    }
+
+    fn derive(&self, passphrase: &str) -> Result<Zeroizing<[u8; 32]>, String> {
+        let salt = BASE64
+            .decode(&self.salt)
+            .map_err(|e| format!("密钥库文件已损坏: {}", e))?;
+        let params = Params::new(self.m_cost, self.t_cost, self.p_cost, Some(32))
+            .map_err(|e| format!("密钥库参数无效: {}", e))?;
+        let mut key = Zeroizing::new([0u8; 32]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut *key)
+            .map_err(|e| format!("派生密钥失败: {}", e))?;
+        Ok(key)
+    }
+}
+
+/// 密钥库文件
+#[derive(Debug, Serialize, Deserialize)]
+struct VaultFile {
+    version: u32,
+    kdf: KdfParams,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// 加密前的密钥条目
+#[derive(Clone, Serialize, Deserialize)]
+struct Secret {
+    value: Zeroizing<String>,
+    updated_at: i64,
+}
+
+/// 解锁后的内存状态
+#[derive(Clone)]
+struct Unlocked {
+    key: Zeroizing<[u8; 32]>,
+    kdf: KdfParams,
+    secrets: BTreeMap<String, Secret>,
+}
+
+/// 密钥库（解锁状态在进程内共享）
+pub struct Vault {
+    /// 为空表示无法确定应用配置目录
+    path: Option<PathBuf>,
+    unlocked: RwLock<Option<Unlocked>>,
+}
+
+impl std::fmt::Debug for Vault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Vault")
+            .field("path", &self.path)
+            .field("unlocked", &self.is_unlocked())
+            .finish()
+    }
+}
+
+impl Default for Vault {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Vault {
+    /// 位于 `<app_config_dir>/vault.json` 的密钥库
+    pub fn new() -> Self {
+        Self {
+            path: app_config_dir().map(|dir| dir.join("vault.json")),
+            unlocked: RwLock::new(None),
+        }
+    }
+
+    /// 使用指定的密钥库文件
+    #[cfg(test)]
+    pub fn with_path(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Some(path.into()),
+            unlocked: RwLock::new(None),
+        }
+    }
+
+    fn path(&self) -> Result<&Path, String> {
+        self.path
+            .as_deref()
+            .ok_or_else(|| "无法确定应用配置目录".to_string())
+    }
+
+    pub fn status(&self) -> VaultStatus {
+        VaultStatus {
+            exists: self.path.as_deref().is_some_and(Path::exists),
+            unlocked: self.is_unlocked(),
+        }
+    }
+
+    pub fn is_unlocked(&self) -> bool {
+        self.unlocked
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_some()
+    }
+
+    /// 用口令解锁；密钥库不存在时以该口令创建空密钥库
+    pub fn unlock(&self, passphrase: &str) -> Result<(), String> {
+        if passphrase.is_empty() {
+            return Err("口令不能为空".to_string());
+        }
+        let path = self.path()?;
+        let unlocked = if path.exists() {
+            let raw = std::fs::read_to_string(path)
+                .map_err(|e| format!("读取密钥库 {:?} 失败: {}", path, e))?;
+            let file: VaultFile =
+                serde_json::from_str(&raw).map_err(|e| format!("密钥库文件已损坏: {}", e))?;
+            if file.version != VERSION {
+                return Err(format!("不支持的密钥库版本 {}", file.version));
+            }
+            let key = file.kdf.derive(passphrase)?;
+            let secrets = decrypt(&key, &file)?;
+            Unlocked {
+                key,
+                kdf: file.kdf,
+                secrets,
+            }
+        } else {
+            let kdf = KdfParams::generate();
+            let unlocked = Unlocked {
+                key: kdf.derive(passphrase)?,
+                kdf,
+                secrets: BTreeMap::new(),
+            };
+            save(path, &unlocked)?;
+            tracing::info!("✅ 已创建密钥库 {:?}", path);
+            unlocked
+        };
+        tracing::info!("✅ 密钥库已解锁（{} 个密钥）", unlocked.secrets.len());
+        *self.unlocked.write().unwrap_or_else(|e| e.into_inner()) = Some(unlocked);
+        Ok(())
+    }
+
+    /// 锁定并清除内存中的明文
+    pub fn lock(&self) {
+        if self
+            .unlocked
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+            .is_some()
+        {
+            tracing::info!("密钥库已锁定");
+        }
+    }
+
+    /// 新增或替换密钥
+    pub fn set(&self, name: &str, value: &str) -> Result<SecretInfo, String> {
+        check_name(name)?;
+        if value.is_empty() {
+            return Err("密钥值不能为空".to_string());
+        }
+        let info = self.update(|unlocked| {
+            let secret = Secret {
+                value: Zeroizing::new(value.to_string()),
+                updated_at: now_ms(),
+            };
+            let info = info(name, &secret);
+            unlocked.secrets.insert(name.to_string(), secret);
+            Ok(info)
+        })?;
+        tracing::info!("✅ 已保存密钥 {}", name);
+        Ok(info)
+    }
+
+    /// 删除密钥，返回是否存在
+    pub fn delete(&self, name: &str) -> Result<bool, String> {
+        let removed = self.update(|unlocked| Ok(unlocked.secrets.remove(name).is_some()))?;
+        if removed {
+            tracing::info!("已删除密钥 {}", name);
+        }
+        Ok(removed)
+    }
+
+    /// 更换口令：以新的盐与口令重新派生密钥并重新加密
+    pub fn rotate(&self, passphrase: &str, new_passphrase: &str) -> Result<(), String> {
+        if new_passphrase.is_empty() {
+            return Err("新口令不能为空".to_string());
+        }
+        self.update(|unlocked| {
+            if *unlocked.kdf.derive(passphrase)? != *unlocked.key {
+                return Err("口令错误".to_string());
+            }
+            let kdf = KdfParams::generate();
+            unlocked.key = kdf.derive(new_passphrase)?;
+            unlocked.kdf = kdf;
+            Ok(())
+        })?;
+        tracing::info!("✅ 密钥库口令已更换");
+        Ok(())
+    }
+
+    /// 全部密钥（按名称排序，值已打码）
+    pub fn list(&self) -> Result<Vec<SecretInfo>, String> {
+        self.read(|unlocked| {
+            (unlocked.secrets.iter())
+                .map(|(name, secret)| info(name, secret))
+                .collect()
+        })
+    }
+
+    /// 打码后的密钥
+    pub fn masked(&self, name: &str) -> Result<SecretInfo, String> {
+        self.read(|unlocked| unlocked.secrets.get(name).map(|secret| info(name, secret)))?
+            .ok_or_else(|| format!("密钥 `{}` 不存在", name))
+    }
+
+    /// 密钥明文，只供后端使用（不得返回给前端或写入日志）
+    pub fn get(&self, name: &str) -> Result<Zeroizing<String>, String> {
+        self.read(|unlocked| {
+            (unlocked.secrets.get(name)).map(|secret| Zeroizing::new(secret.value.to_string()))
+        })?
+        .ok_or_else(|| format!("密钥 `{}` 不存在", name))
+    }
+
+    fn read<T>(&self, f: impl FnOnce(&Unlocked) -> T) -> Result<T, String> {
+        let guard = self.unlocked.read().unwrap_or_else(|e| e.into_inner());
+        guard.as_ref().map(f).ok_or_else(locked)
+    }
+
+    /// 修改后立即写回文件；`f` 返回错误时不写入
+    ///
+    /// 在副本上修改，写入成功后才替换内存状态，避免写入失败后内存与文件不一致（如口令已更换）
+    fn update<T>(&self, f: impl FnOnce(&mut Unlocked) -> Result<T, String>) -> Result<T, String> {
+        let path = self.path()?;
+        let mut guard = self.unlocked.write().unwrap_or_else(|e| e.into_inner());
+        let mut unlocked = guard.as_ref().ok_or_else(locked)?.clone();
+        let value = f(&mut unlocked)?;
+        save(path, &unlocked)?;
+        *guard = Some(unlocked);
+        Ok(value)
+    }
+}
+
+fn locked() -> String {
+    "密钥库未解锁".to_string()
+}
+
+/// 密钥名只能包含字母、数字、`_`、`-` 与 `.`
+fn check_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("密钥名 `{}` 无效", name))
+    }
+}
+
+fn info(name: &str, secret: &Secret) -> SecretInfo {
+    SecretInfo {
+        name: name.to_string(),
+        masked: mask(&secret.value),
+        updated_at: secret.updated_at,
+    }
+}
+
+/// 较长的值保留前 3 位与后 4 位，其余（及较短的值）全部隐藏，不暴露长度
+pub fn mask(value: &str) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    if chars.len() < 16 {
+        return "••••••".to_string();
+    }
+    let head: String = chars[..3].iter().collect();
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}••••••{}", head, tail)
+}
+
+fn decrypt(key: &[u8; 32], file: &VaultFile) -> Result<BTreeMap<String, Secret>, String> {
+    let corrupted = |e: base64::DecodeError| format!("密钥库文件已损坏: {}", e);
+    let nonce = BASE64.decode(&file.nonce).map_err(corrupted)?;
+    let ciphertext = BASE64.decode(&file.ciphertext).map_err(corrupted)?;
+    if nonce.len() != 24 {
+        return Err("密钥库文件已损坏: nonce 长度错误".to_string());
+    }
+    let plaintext = XChaCha20Poly1305::new(key.into())
+        .decrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: &ciphertext,
+                aad: AAD,
+            },
+        )
+        .map(Zeroizing::new)
+        .map_err(|_| "口令错误或密钥库文件已被篡改".to_string())?;
+    serde_json::from_slice(&plaintext).map_err(|e| format!("密钥库内容无法解析: {}", e))
+}
+
+/// 以新的随机 nonce 加密并原子地写入文件
+fn save(path: &Path, unlocked: &Unlocked) -> Result<(), String> {
+    let plaintext =
+        Zeroizing::new(serde_json::to_vec(&unlocked.secrets).map_err(|e| e.to_string())?);
+    let nonce = rand::random::<[u8; 24]>();
+    let ciphertext = XChaCha20Poly1305::new((&*unlocked.key).into())
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: &plaintext,
+                aad: AAD,
+            },
+        )
+        .map_err(|_| "加密密钥库失败".to_string())?;
+    let file = VaultFile {
+        version: VERSION,
+        kdf: unlocked.kdf.clone(),
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(ciphertext),
+    };
+    let json = serde_json::to_string_pretty(&file).map_err(|e| e.to_string())?;
+    write_private(path, json.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vault_roundtrip_and_rotate() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.json");
+        let vault = Vault::with_path(&path);
+        assert!(vault.set("openai", "sk-x").unwrap_err().contains("未解锁"));
+
+        vault.unlock("correct horse").unwrap();
+        let info = vault.set("openai", "sk-1234567890abcdef").unwrap();
+        assert_eq!(info.masked, "sk-••••••cdef");
+        assert!(vault.set("bad name", "x").is_err());
+        let raw = std::fs::read_to_string(&path).unwrap();
+        assert!(!raw.contains("sk-1234567890abcdef"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let reopened = Vault::with_path(&path);
+        assert!(reopened.unlock("wrong").unwrap_err().contains("口令错误"));
+        reopened.unlock("correct horse").unwrap();
+        assert_eq!(*reopened.get("openai").unwrap(), "sk-1234567890abcdef");
+
+        assert!(reopened.rotate("wrong", "new pass").is_err());
+        reopened.rotate("correct horse", "new pass").unwrap();
+        reopened.lock();
+        assert!(reopened.get("openai").is_err());
+        assert!(reopened.unlock("correct horse").is_err());
+        reopened.unlock("new pass").unwrap();
+        assert!(reopened.delete("openai").unwrap());
+        assert!(reopened.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_failed_save_keeps_unlocked_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.json");
+        let vault = Vault::with_path(&path);
+        vault.unlock("correct horse").unwrap();
+        vault.set("openai", "sk-1").unwrap();
+
+        // 临时文件位置被目录占用，写入失败
+        let tmp = dir.path().join("vault.json.tmp");
+        std::fs::create_dir(&tmp).unwrap();
+        assert!(vault.rotate("correct horse", "new pass").is_err());
+        assert!(vault.set("openai", "sk-2").is_err());
+        assert_eq!(*vault.get("openai").unwrap(), "sk-1");
+
+        // 内存中仍是旧口令派生的密钥，之后的写入用旧口令即可解锁
+        std::fs::remove_dir(&tmp).unwrap();
+        vault.set("other", "x").unwrap();
+        let reopened = Vault::with_path(&path);
+        reopened.unlock("correct horse").unwrap();
+        assert_eq!(*reopened.get("openai").unwrap(), "sk-1");
+    }
+}